@@ -0,0 +1,128 @@
+//! C API for linking in-process.
+//!
+//! Build tools and language runtimes written in other languages can call
+//! [`uld_link`] instead of running `uld`: it takes the command line as C
+//! strings, links as the binary would, and returns a status with the
+//! diagnostics of a failed link as JSON lines (see [`uld::diagnostics`]).
+//! `include/uld.h` declares it.
+//!
+//! `cargo build --workspace` builds `libuld_capi.so` and `libuld_capi.a`
+//! for C callers; a plain `cargo build` leaves them out.
+//! Each call links on a thread pool of its own, so calls may come from
+//! several threads at once. Warnings go to the host's `tracing` subscriber,
+//! so C hosts don't see them.
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+
+use anyhow::{anyhow, Context, Result};
+
+use uld::config::Config;
+use uld::diagnostics;
+use uld::driver;
+use uld::threads;
+
+/// The output was written.
+pub const ULD_OK: c_int = 0;
+/// The link failed; the diagnostics say why.
+pub const ULD_LINK_FAILED: c_int = 1;
+/// An argument was null or not UTF-8.
+pub const ULD_INVALID_ARGUMENT: c_int = 2;
+
+/// What [`uld_link`] returns.
+#[repr(C)]
+pub struct UldResult {
+    /// [`ULD_OK`], [`ULD_LINK_FAILED`] or [`ULD_INVALID_ARGUMENT`].
+    pub status: c_int,
+    /// Unless the link succeeded, one JSON object per line describing what
+    /// went wrong, to be freed with [`uld_free_diagnostics`]; null if it
+    /// succeeded.
+    pub diagnostics: *mut c_char,
+}
+
+/// Link with the `argc` command-line arguments in `argv`, without the
+/// program name: options and input files in the order `uld` would get them
+/// (such as `"-o"`, `"out"`, `"main.o"`, `"-L."`, `"-lfoo"`), as `-l`,
+/// `--as-needed` and `--start-group` depend on their place among the
+/// inputs. `argv` may be null when `argc` is 0.
+///
+/// # Safety
+///
+/// `argv` must point to `argc` valid C strings.
+#[no_mangle]
+pub unsafe extern "C" fn uld_link(argv: *const *const c_char, argc: usize) -> UldResult {
+    let args = match unsafe { arguments(argv, argc) } {
+        Ok(args) => args,
+        Err(err) => return failure(ULD_INVALID_ARGUMENT, &err),
+    };
+    // A panic must not unwind into the caller.
+    let linked = panic::catch_unwind(AssertUnwindSafe(|| link(args))).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(anyhow!("internal error: {}", message))
+    });
+    match linked {
+        Ok(()) => UldResult {
+            status: ULD_OK,
+            diagnostics: std::ptr::null_mut(),
+        },
+        Err(err) => failure(ULD_LINK_FAILED, &err),
+    }
+}
+
+/// Free the diagnostics of a [`UldResult`]; null is ignored.
+///
+/// # Safety
+///
+/// `diagnostics` must be null or come from [`uld_link`], and not have been
+/// freed already.
+#[no_mangle]
+pub unsafe extern "C" fn uld_free_diagnostics(diagnostics: *mut c_char) {
+    if !diagnostics.is_null() {
+        drop(unsafe { CString::from_raw(diagnostics) });
+    }
+}
+
+/// The arguments of the link, as the binary would get them: the program
+/// name, then `argv`.
+unsafe fn arguments(argv: *const *const c_char, argc: usize) -> Result<Vec<String>> {
+    if argc > 0 && argv.is_null() {
+        return Err(anyhow!("argv is null"));
+    }
+    let mut args = vec!["uld".to_string()];
+    for i in 0..argc {
+        let p = unsafe { *argv.add(i) };
+        if p.is_null() {
+            return Err(anyhow!("argument {} is null", i));
+        }
+        let s = unsafe { CStr::from_ptr(p) };
+        args.push(
+            s.to_str()
+                .with_context(|| format!("argument {} is not UTF-8", i))?
+                .to_string(),
+        );
+    }
+    Ok(args)
+}
+
+/// Link with the command-line arguments `args` on a thread pool of its own.
+fn link(args: Vec<String>) -> Result<()> {
+    let config = Config::from_args(args)?;
+    let (pool, _threads) = threads::pool(config.threads()?)?;
+    pool.install(|| driver::link(&config))
+}
+
+/// A failed [`UldResult`] with `status`, describing `err`.
+fn failure(status: c_int, err: &anyhow::Error) -> UldResult {
+    let mut json = Vec::new();
+    // Writing to memory can't fail, and JSON escapes any nul.
+    let _ = diagnostics::write_json(&mut json, err);
+    let text = CString::new(json).unwrap_or_default();
+    UldResult {
+        status,
+        diagnostics: text.into_raw(),
+    }
+}
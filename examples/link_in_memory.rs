@@ -0,0 +1,30 @@
+//! Link the x86_64 objects named on the command line in memory and write
+//! the executable to stdout, as a program handing it to `memfd_create`
+//! would.
+//!
+//! ```bash
+//! cargo run --example link_in_memory -- main.o > a.out
+//! ```
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use uld::arch::x86_64::X86_64;
+use uld::input;
+use uld::linker::Linker;
+
+fn main() -> Result<()> {
+    let paths: Vec<PathBuf> = std::env::args_os().skip(1).map(PathBuf::from).collect();
+    let maps = paths
+        .iter()
+        .map(|p| input::map_file(p).with_context(|| format!("open {}", p.display())))
+        .collect::<Result<Vec<_>>>()?;
+    let mut linker = Linker::new(X86_64);
+    for (path, map) in paths.iter().zip(&maps) {
+        linker.add_file(path, map)?;
+    }
+    let bytes = linker.link_to_bytes()?;
+    std::io::stdout().lock().write_all(&bytes)?;
+    Ok(())
+}
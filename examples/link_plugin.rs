@@ -0,0 +1,61 @@
+//! A link plugin that leaves out inputs named `*.veto.o`, defines `answer`
+//! as 42 when no input does, and reports the sections it sees.
+//!
+//! ```bash
+//! cargo run --example link_plugin -- a.out main.o
+//! ```
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use uld::arch::x86_64::X86_64;
+use uld::hooks::{LinkPlugin, LoadedSection, Output};
+use uld::layout::InputFile;
+use uld::linker::LinkerBuilder;
+use uld::symbol::SyntheticValue;
+
+struct Answer;
+
+impl LinkPlugin for Answer {
+    fn accept_input(&self, input: &InputFile) -> Result<bool> {
+        let veto = input.path.to_string_lossy().ends_with(".veto.o");
+        if veto {
+            eprintln!("vetoed {}", input);
+        }
+        Ok(!veto)
+    }
+
+    fn resolve_undefined(&self, name: &str) -> Option<SyntheticValue> {
+        (name == "answer").then_some(SyntheticValue::Absolute(42))
+    }
+
+    fn on_section_loaded(&self, section: &LoadedSection, data: &mut [u8]) -> Result<()> {
+        eprintln!(
+            "loaded {} into {} ({} bytes)",
+            section.name,
+            section.output,
+            data.len()
+        );
+        Ok(())
+    }
+
+    fn before_write(&self, output: &mut Output) -> Result<()> {
+        for seg in output.segments.iter().filter(|s| s.size > 0) {
+            eprintln!("writing {}", seg.name);
+        }
+        Ok(())
+    }
+}
+
+fn main() -> Result<()> {
+    let args: Vec<PathBuf> = std::env::args_os().skip(1).map(PathBuf::from).collect();
+    let (output, inputs) = args
+        .split_first()
+        .context("usage: link_plugin OUTPUT INPUT...")?;
+    let mut builder = LinkerBuilder::new(X86_64).link_plugin(Answer);
+    for input in inputs {
+        builder = builder.input_path(input)?;
+    }
+    builder.link()?.write(output)?;
+    Ok(())
+}
@@ -0,0 +1,37 @@
+//! Read the inputs into buffers the linker owns, then link on another
+//! thread: the `Linker` borrows nothing from the code that loaded it.
+//!
+//! ```bash
+//! cargo run --example owned_inputs -- a.out main.o libfoo.a
+//! ```
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use uld::arch::x86_64::X86_64;
+use uld::linker::Linker;
+
+fn load(paths: &[PathBuf]) -> Result<Linker<'static, X86_64>> {
+    let mut linker = Linker::new(X86_64);
+    for path in paths {
+        let data = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+        linker.add_owned(path, data)?;
+    }
+    Ok(linker)
+}
+
+fn main() -> Result<()> {
+    let args: Vec<PathBuf> = std::env::args_os().skip(1).map(PathBuf::from).collect();
+    let (output, inputs) = args
+        .split_first()
+        .context("usage: owned_inputs OUTPUT INPUT...")?;
+    let mut linker = load(inputs)?;
+    let output = output.clone();
+    std::thread::spawn(move || -> Result<()> {
+        linker.link()?;
+        linker.write(&output)?;
+        Ok(())
+    })
+    .join()
+    .expect("link thread panicked")
+}
@@ -0,0 +1,22 @@
+//! Link arbitrary bytes as the only input of a static x86_64 executable.
+//!
+//! Any input must give either an output or an error, never a panic, and
+//! any output must pass `validate::check_executable`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::path::Path;
+use uld::arch::x86_64::X86_64;
+use uld::linker::Linker;
+use uld::validate;
+
+fuzz_target!(|data: &[u8]| {
+    let mut linker = Linker::new(X86_64);
+    if linker.add_owned(Path::new("fuzz.o"), data.to_vec()).is_ok() {
+        if let Ok(bytes) = linker.link_to_bytes() {
+            let violations = validate::check_executable(&bytes);
+            assert!(violations.is_empty(), "{:?}", violations);
+        }
+    }
+});
@@ -0,0 +1,19 @@
+//! Parse and validate arbitrary bytes as an input object.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::path::Path;
+use uld::arch::x86_64::X86_64;
+use uld::arch::Architecture;
+use uld::input::{self, ParsedFile};
+use uld::validate;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(parsed) = input::parse_file(Path::new("fuzz.o"), data, X86_64::arch()) else {
+        return;
+    };
+    if let ParsedFile::Object(obj) = parsed {
+        let _ = validate::check_object(&obj.obj, "fuzz.o", &X86_64);
+    }
+});
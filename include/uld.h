@@ -0,0 +1,52 @@
+/*
+ * C API of the uld linker, in libuld_capi.so and libuld_capi.a from the
+ * workspace's uld-capi crate:
+ *
+ *     cargo build --release --workspace
+ *
+ * See capi/src/lib.rs for the details.
+ */
+#ifndef ULD_H
+#define ULD_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The output was written. */
+#define ULD_OK 0
+/* The link failed; the diagnostics say why. */
+#define ULD_LINK_FAILED 1
+/* An argument was null or not UTF-8. */
+#define ULD_INVALID_ARGUMENT 2
+
+struct uld_result {
+    /* ULD_OK, ULD_LINK_FAILED or ULD_INVALID_ARGUMENT. */
+    int status;
+    /*
+     * Unless the link succeeded, one JSON object per line describing what
+     * went wrong, to be freed with uld_free_diagnostics(); NULL if it
+     * succeeded.
+     */
+    char *diagnostics;
+};
+
+/*
+ * Link with the argc command-line arguments in argv, without the program
+ * name: options and input files in the order uld would get them (such as
+ * "-o", "out", "main.o", "-L.", "-lfoo"), since -l, --as-needed and
+ * --start-group apply where they appear among the inputs. argv may be NULL
+ * when argc is 0. Safe to call from several threads at once.
+ */
+struct uld_result uld_link(const char *const *argv, size_t argc);
+
+/* Free the diagnostics of a uld_result; NULL is ignored. */
+void uld_free_diagnostics(char *diagnostics);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -4,36 +4,286 @@
 //! This allows the core linker to remain generic while specific backends handle details like
 //! relocation types and ELF header formats.
 
-use anyhow::Result;
-use object::read::Relocation;
-use object::Endianness;
+use anyhow::{anyhow, bail, Result};
+use object::read::{Object, Relocation};
+use object::{Endianness, ObjectKind, RelocationKind};
+use std::fmt;
+use std::path::Path;
 
+use crate::dynamic::DynamicRelocKind;
+use crate::got::EntryKind;
+
+pub mod arm;
+pub mod i686;
 pub mod x86_64;
 
+/// A relocated value that doesn't fit in its field.
+///
+/// Backends return this from [`Architecture::apply_relocation`] so the linker
+/// can say which symbol and reference overflowed, and what to do about it.
+#[derive(Debug)]
+pub struct RelocationOverflow {
+    pub value: i64,
+    /// The range the field can hold.
+    pub min: i64,
+    pub max: i64,
+}
+
+impl fmt::Display for RelocationOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "relocation overflow: 0x{:x} is not in [{}, {}]",
+            self.value, self.min, self.max
+        )
+    }
+}
+
+impl std::error::Error for RelocationOverflow {}
+
+/// The target of a link: the `-m` emulation if given, otherwise that of the
+/// first object file or shared library, x86_64 if there is none.
+///
+/// Every object file and shared library must be built for the target; the
+/// error lists those that aren't. Archives may hold members for other
+/// targets, which are skipped when indexing them.
+pub fn detect(
+    inputs: &[(&Path, &[u8])],
+    emulation: Option<object::Architecture>,
+) -> Result<object::Architecture> {
+    let machines: Vec<_> = inputs
+        .iter()
+        .filter_map(|&(path, data)| {
+            let obj = object::File::parse(data).ok()?;
+            matches!(obj.kind(), ObjectKind::Relocatable | ObjectKind::Dynamic)
+                .then(|| (path, obj.architecture()))
+        })
+        .collect();
+    let (arch, from) = match (emulation, machines.first()) {
+        (Some(arch), _) => (arch, "-m".to_string()),
+        (None, Some(&(path, arch))) => (arch, path.display().to_string()),
+        (None, None) => return Ok(object::Architecture::X86_64),
+    };
+    let mismatched: Vec<_> = machines.iter().filter(|(_, a)| *a != arch).collect();
+    if !mismatched.is_empty() {
+        let mut msg = format!(
+            "mixed architectures: linking for {:?} (from {}), but these inputs are not:",
+            arch, from
+        );
+        for (path, a) in mismatched {
+            msg.push_str(&format!("\n  {}: {:?}", path.display(), a));
+        }
+        bail!(msg);
+    }
+    Ok(arch)
+}
+
+/// A stub that a branch jumps through to reach a target out of its range,
+/// or in an instruction set the branch can't switch to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Veneer {
+    /// Which stub the backend writes, e.g. an ARM or a Thumb one.
+    pub kind: u8,
+    /// The address the stub jumps to.
+    pub target: u64,
+}
+
 /// A trait representing a target architecture (e.g., x86_64, AArch64).
-pub trait Architecture {
+///
+/// Inputs are checked in parallel against the same architecture, so
+/// implementations must be `Sync`.
+pub trait Architecture: Sync {
     /// The object file format architecture
     fn arch() -> object::Architecture;
 
     /// The object crate's endianness for this architecture.
     fn endianness(&self) -> Endianness;
 
+    /// `e_machine` of the output.
+    fn e_machine(&self) -> u16;
+
+    /// `e_flags` of the output.
+    fn e_flags(&self) -> u32 {
+        0
+    }
+
+    /// The size of an address in bytes: 8 for ELF64 targets, 4 for ELF32
+    /// ones. GOT entries and `.ctors` words are this wide.
+    fn word_size(&self) -> u64 {
+        8
+    }
+
+    /// The generic kind of a relocation.
+    ///
+    /// The `object` crate reports some architecture-specific types as
+    /// `RelocationKind::Unknown`; backends map those onto their generic equivalent.
+    fn reloc_kind(&self, reloc: &Relocation) -> RelocationKind {
+        reloc.kind()
+    }
+
+    /// Whether the relocation's value is computed from the size of its
+    /// symbol (`st_size`, Z) instead of its address, as `R_X86_64_SIZE64` is.
+    fn is_size_reloc(&self, _reloc: &Relocation) -> bool {
+        false
+    }
+
+    /// The kind of GOT entry the relocation refers to, if it goes through
+    /// the GOT. Backends with TLS relocations add the TLS entry kinds.
+    fn got_entry_kind(&self, reloc: &Relocation) -> Option<EntryKind> {
+        matches!(
+            self.reloc_kind(reloc),
+            RelocationKind::Got | RelocationKind::GotRelative
+        )
+        .then_some(EntryKind::Address)
+    }
+
+    /// Whether the GOT load the relocation at `offset` in `code` belongs to
+    /// can be rewritten to use the symbol's address directly, for a symbol
+    /// defined in the output. `code` is the whole section, since the
+    /// instruction starts before the relocated field.
+    fn can_relax_got(&self, _reloc: &Relocation, _code: &[u8], _offset: usize) -> bool {
+        false
+    }
+
+    /// Rewrite an instruction [`can_relax_got`](Architecture::can_relax_got)
+    /// accepted. The relocation is then applied as PC-relative to the
+    /// symbol itself rather than to its GOT entry.
+    fn relax_got(&self, _reloc: &Relocation, _code: &mut [u8], _offset: usize) {}
+
+    /// Whether the relocation's S is its symbol's offset from the thread
+    /// pointer rather than its address, as for local-exec TLS accesses.
+    fn is_tp_relative(&self, _reloc: &Relocation) -> bool {
+        false
+    }
+
+    /// For the general- or local-dynamic TLS access the relocation at
+    /// `offset` in `code` belongs to, the distance from it to the relocated
+    /// field of the paired call to [`tls_get_addr`](Architecture::tls_get_addr),
+    /// if the backend can relax the access to local-exec.
+    fn tls_call_offset(&self, _reloc: &Relocation, _code: &[u8], _offset: usize) -> Option<u64> {
+        None
+    }
+
+    /// Rewrite an access [`tls_call_offset`](Architecture::tls_call_offset)
+    /// accepted, and its call, to local-exec: to find the symbol `tpoff`
+    /// bytes from the thread pointer, or the TLS block for local-dynamic.
+    fn relax_tls(
+        &self,
+        _reloc: &Relocation,
+        _code: &mut [u8],
+        _offset: usize,
+        _tpoff: i64,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// The function general- and local-dynamic TLS accesses call, which
+    /// relaxed ones no longer do.
+    fn tls_get_addr(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Whether the relocation is a branch that may need a [`Veneer`].
+    fn is_branch(&self, _reloc: &Relocation) -> bool {
+        false
+    }
+
+    /// The veneer the branch `reloc` at `p` needs to reach `s + a`, if it
+    /// can't directly.
+    fn veneer(&self, _reloc: &Relocation, _p: u64, _s: u64, _a: i64) -> Option<Veneer> {
+        None
+    }
+
+    /// The size in bytes of a veneer of kind `kind`, a multiple of 4.
+    fn veneer_size(&self, _kind: u8) -> u64 {
+        0
+    }
+
+    /// Write `veneer` at `p`, returning the address branches to it jump to.
+    fn write_veneer(&self, _veneer: &Veneer, p: u64, _data: &mut [u8]) -> u64 {
+        p
+    }
+
+    /// The size in bytes of a `.plt` entry, or 0 if the backend makes no
+    /// PLT, so that calls to functions bound at runtime are errors.
+    fn plt_entry_size(&self) -> u64 {
+        0
+    }
+
+    /// Write the `.plt` entry at `p`, which jumps to the address held in the
+    /// `.got.plt` slot at `slot`.
+    fn write_plt_entry(&self, _p: u64, _slot: u64, _data: &mut [u8]) {}
+
+    /// The name of a relocation's type for diagnostics, e.g. `R_X86_64_PC32`.
+    fn reloc_name(&self, reloc: &Relocation) -> String {
+        format!("{:?}", self.reloc_kind(reloc))
+    }
+
+    /// How to recompile code so that relocations like `reloc` reach any
+    /// address, suggested when one overflows: a larger code model, if the
+    /// target has one.
+    fn overflow_hint(&self, _reloc: &Relocation) -> Option<&'static str> {
+        None
+    }
+
+    /// The program interpreter for dynamically linked executables when
+    /// `--dynamic-linker` is not given.
+    fn default_dynamic_linker(&self) -> &'static str;
+
+    /// The ELF relocation type for a dynamic relocation of the given kind.
+    fn dynamic_reloc_type(&self, kind: DynamicRelocKind) -> u32;
+
+    /// The width of a relocation's field in bits.
+    fn reloc_size(&self, reloc: &Relocation) -> u8 {
+        reloc.size()
+    }
+
+    /// The addend stored in the relocated field, for relocations from
+    /// `SHT_REL` sections, which have no explicit one.
+    ///
+    /// `data` starts at the relocated field; the field's width comes from the
+    /// relocation and its value is sign-extended.
+    fn implicit_addend(&self, reloc: &Relocation, data: &[u8]) -> Result<i64> {
+        let bits = self.reloc_size(reloc);
+        let size = bits as usize / 8;
+        let field = data
+            .get(..size)
+            .filter(|f| (1..=8).contains(&f.len()))
+            .ok_or_else(|| anyhow!("no implicit addend for a {}-bit field", bits))?;
+        let mut bytes = [0; 8];
+        let value = if self.endianness() == Endianness::Little {
+            bytes[..size].copy_from_slice(field);
+            i64::from_le_bytes(bytes)
+        } else {
+            bytes[8 - size..].copy_from_slice(field);
+            i64::from_be_bytes(bytes)
+        };
+        // Sign-extend from the field's width.
+        let shift = 64 - 8 * size as u32;
+        Ok(value.wrapping_shl(shift).wrapping_shr(shift))
+    }
+
     /// Applies a relocation to a buffer.
     ///
     /// # Arguments
-    /// * `offset` - The offset within the buffer where the relocation should be applied.
     /// * `reloc` - The relocation entry from the input object file.
     /// * `p` - The runtime address of the location being relocated (P).
-    /// * `s` - The value of the symbol (S).
-    /// * `a` - The addend (A).
-    /// * `data` - The mutable buffer representing the section's data.
+    /// * `s` - The value of the symbol (S), or its size (Z) for
+    ///   [size relocations](Architecture::is_size_reloc).
+    /// * `a` - The addend (A): the relocation's own for `SHT_RELA` sections,
+    ///   the [implicit one](Architecture::implicit_addend) for `SHT_REL`.
+    /// * `got` - The address of the global offset table (GOT).
+    /// * `data` - The section's data, starting at the relocated field.
+    ///
+    /// A value that doesn't fit the relocated field is a [`RelocationOverflow`];
+    /// relocation types the backend doesn't implement are errors.
     fn apply_relocation(
         &self,
-        offset: u64,
         reloc: &Relocation,
         p: u64,
         s: u64,
         a: i64,
+        got: u64,
         data: &mut [u8],
     ) -> Result<()>;
 }
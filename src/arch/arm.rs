@@ -0,0 +1,381 @@
+//! ARM (AArch32) Architecture backend.
+//!
+//! Implements the `Architecture` trait for 32-bit ARMv7 systems (ELF32), in
+//! both the ARM and Thumb instruction sets. Objects use `SHT_REL` sections,
+//! so addends are decoded from the relocated instructions.
+//!
+//! Thumb functions have bit 0 of their address set. Calls between the two
+//! instruction sets become `BLX`; branches that can't switch sets or reach
+//! their target go through a [`Veneer`] that loads the target into `pc`.
+
+use super::{Architecture, RelocationOverflow, Veneer};
+use crate::dynamic::DynamicRelocKind;
+use anyhow::{anyhow, Result};
+use object::read::Relocation;
+use object::{Endianness, RelocationFlags, RelocationKind};
+
+/// The ARM architecture backend.
+#[derive(Debug, Clone, Copy)]
+pub struct Arm;
+
+/// Veneer kinds: the instruction set of the branches that use them.
+const ARM_VENEER: u8 = 0;
+const THUMB_VENEER: u8 = 1;
+
+/// `ldr pc, [pc, #-4]`, followed by the target address.
+const ARM_LDR_PC: u32 = 0xe51f_f004;
+/// `ldr.w pc, [pc, #0]`, followed by the target address.
+const THUMB_LDR_PC: [u16; 2] = [0xf8df, 0xf000];
+
+/// The range of `B` / `BL` offsets in ARM code (±32 MiB) and Thumb-2 code
+/// (±16 MiB).
+const ARM_BRANCH: (i64, i64) = (-(1 << 25), (1 << 25) - 4);
+const THUMB_BRANCH: (i64, i64) = (-(1 << 24), (1 << 24) - 2);
+
+/// `R_ARM_THM_CALL`, which the `object` crate has under its old name.
+const R_ARM_THM_CALL: u32 = object::elf::R_ARM_THM_PC22;
+
+fn r_type(reloc: &Relocation) -> u32 {
+    match reloc.flags() {
+        RelocationFlags::Elf { r_type } => r_type,
+        _ => object::elf::R_ARM_NONE,
+    }
+}
+
+fn read32(data: &[u8]) -> Result<u32> {
+    let field = data
+        .get(..4)
+        .ok_or_else(|| anyhow!("Relocation offset out of bounds"))?;
+    Ok(u32::from_le_bytes(field.try_into().unwrap()))
+}
+
+fn write32(data: &mut [u8], value: u32) -> Result<()> {
+    let field = data
+        .get_mut(..4)
+        .ok_or_else(|| anyhow!("Relocation offset out of bounds"))?;
+    field.copy_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+/// A Thumb-2 instruction as its two halfwords.
+fn read_thumb(data: &[u8]) -> Result<(u32, u32)> {
+    let w = read32(data)?;
+    Ok((w & 0xffff, w >> 16))
+}
+
+fn write_thumb(data: &mut [u8], hw1: u32, hw2: u32) -> Result<()> {
+    write32(data, (hw2 << 16) | (hw1 & 0xffff))
+}
+
+fn check(value: i64, (min, max): (i64, i64)) -> Result<()> {
+    if value < min || value > max {
+        return Err(RelocationOverflow { value, min, max }.into());
+    }
+    Ok(())
+}
+
+/// The offset of a Thumb-2 `BL` / `BLX` / `B.W`.
+fn thumb_branch_offset(hw1: u32, hw2: u32) -> i64 {
+    let s = (hw1 >> 10) & 1;
+    let i1 = !((hw2 >> 13) ^ s) & 1;
+    let i2 = !((hw2 >> 11) ^ s) & 1;
+    let imm = (s << 24) | (i1 << 23) | (i2 << 22) | ((hw1 & 0x3ff) << 12) | ((hw2 & 0x7ff) << 1);
+    // Sign-extend from 25 bits.
+    ((imm << 7) as i32 >> 7) as i64
+}
+
+/// `hw1` and `hw2` with the offset of a Thumb-2 branch replaced.
+fn set_thumb_branch_offset(hw1: u32, hw2: u32, value: i64) -> (u32, u32) {
+    let v = value as u32;
+    let s = (v >> 24) & 1;
+    let j1 = (!(v >> 23) ^ s) & 1;
+    let j2 = (!(v >> 22) ^ s) & 1;
+    let hw1 = (hw1 & 0xf800) | (s << 10) | ((v >> 12) & 0x3ff);
+    let hw2 = (hw2 & 0xd000) | (j1 << 13) | (j2 << 11) | ((v >> 1) & 0x7ff);
+    (hw1, hw2)
+}
+
+/// The 16-bit immediate of an ARM `MOVW` / `MOVT`.
+fn arm_mov_imm(insn: u32) -> u32 {
+    ((insn >> 4) & 0xf000) | (insn & 0xfff)
+}
+
+/// The 16-bit immediate of a Thumb-2 `MOVW` / `MOVT`.
+fn thumb_mov_imm(hw1: u32, hw2: u32) -> u32 {
+    ((hw1 & 0xf) << 12) | (((hw1 >> 10) & 1) << 11) | (((hw2 >> 12) & 7) << 8) | (hw2 & 0xff)
+}
+
+impl Arm {
+    /// Whether the branch at `p` needs a veneer to reach `s + a`: it is out
+    /// of range, or a plain `B` to the other instruction set.
+    fn needs_veneer(&self, r_type: u32, p: u64, s: u64, a: i64) -> bool {
+        use object::elf::*;
+        let thumb_target = s & 1 != 0;
+        let value = |p: u64| (s & !1) as i64 + a - p as i64;
+        match r_type {
+            R_ARM_CALL => check(value(p), ARM_BRANCH).is_err(),
+            R_ARM_JUMP24 => thumb_target || check(value(p), ARM_BRANCH).is_err(),
+            // A BLX to ARM code is relative to the word-aligned PC.
+            R_ARM_THM_CALL if !thumb_target => check(value(p & !3), THUMB_BRANCH).is_err(),
+            R_ARM_THM_CALL => check(value(p), THUMB_BRANCH).is_err(),
+            R_ARM_THM_JUMP24 => !thumb_target || check(value(p), THUMB_BRANCH).is_err(),
+            _ => false,
+        }
+    }
+}
+
+impl Architecture for Arm {
+    fn arch() -> object::Architecture {
+        object::Architecture::Arm
+    }
+
+    fn endianness(&self) -> Endianness {
+        Endianness::Little
+    }
+
+    fn e_machine(&self) -> u16 {
+        object::elf::EM_ARM
+    }
+
+    fn e_flags(&self) -> u32 {
+        object::elf::EF_ARM_EABI_VER5
+    }
+
+    fn word_size(&self) -> u64 {
+        4
+    }
+
+    fn reloc_kind(&self, reloc: &Relocation) -> RelocationKind {
+        use object::elf::*;
+        match r_type(reloc) {
+            R_ARM_ABS32 | R_ARM_TARGET1 => RelocationKind::Absolute,
+            R_ARM_REL32 | R_ARM_PREL31 => RelocationKind::Relative,
+            R_ARM_CALL | R_ARM_JUMP24 | R_ARM_THM_CALL | R_ARM_THM_JUMP24 => {
+                RelocationKind::PltRelative
+            }
+            _ => reloc.kind(),
+        }
+    }
+
+    fn reloc_size(&self, reloc: &Relocation) -> u8 {
+        match r_type(reloc) {
+            object::elf::R_ARM_NONE | object::elf::R_ARM_V4BX => 0,
+            _ => 32,
+        }
+    }
+
+    fn implicit_addend(&self, reloc: &Relocation, data: &[u8]) -> Result<i64> {
+        use object::elf::*;
+        Ok(match r_type(reloc) {
+            R_ARM_NONE | R_ARM_V4BX => 0,
+            R_ARM_ABS32 | R_ARM_TARGET1 | R_ARM_REL32 => read32(data)? as i32 as i64,
+            R_ARM_PREL31 => ((read32(data)? << 1) as i32 >> 1) as i64,
+            R_ARM_CALL | R_ARM_JUMP24 => {
+                let insn = read32(data)?;
+                let offset = ((insn << 8) as i32 >> 6) as i64;
+                // The H bit of a BLX gives the halfword.
+                if insn >> 28 == 0xf {
+                    offset | (((insn >> 24) & 1) << 1) as i64
+                } else {
+                    offset
+                }
+            }
+            R_ARM_THM_CALL | R_ARM_THM_JUMP24 => {
+                let (hw1, hw2) = read_thumb(data)?;
+                thumb_branch_offset(hw1, hw2)
+            }
+            R_ARM_MOVW_ABS_NC | R_ARM_MOVT_ABS => arm_mov_imm(read32(data)?) as i16 as i64,
+            R_ARM_THM_MOVW_ABS_NC | R_ARM_THM_MOVT_ABS => {
+                let (hw1, hw2) = read_thumb(data)?;
+                thumb_mov_imm(hw1, hw2) as i16 as i64
+            }
+            _ => return Err(anyhow!("unsupported relocation {}", self.reloc_name(reloc))),
+        })
+    }
+
+    fn is_branch(&self, reloc: &Relocation) -> bool {
+        use object::elf::*;
+        matches!(
+            r_type(reloc),
+            R_ARM_CALL | R_ARM_JUMP24 | R_ARM_THM_CALL | R_ARM_THM_JUMP24
+        )
+    }
+
+    fn veneer(&self, reloc: &Relocation, p: u64, s: u64, a: i64) -> Option<Veneer> {
+        use object::elf::*;
+        let r_type = r_type(reloc);
+        if !self.needs_veneer(r_type, p, s, a) {
+            return None;
+        }
+        // The addend is the target's offset from the instruction minus the
+        // PC bias: 8 bytes in ARM code, 4 in Thumb.
+        let (kind, bias) = match r_type {
+            R_ARM_THM_CALL | R_ARM_THM_JUMP24 => (THUMB_VENEER, 4),
+            _ => (ARM_VENEER, 8),
+        };
+        Some(Veneer {
+            kind,
+            target: (s as i64 + a + bias) as u64,
+        })
+    }
+
+    fn veneer_size(&self, _kind: u8) -> u64 {
+        8
+    }
+
+    fn write_veneer(&self, veneer: &Veneer, p: u64, data: &mut [u8]) -> u64 {
+        let (insn, entry) = match veneer.kind {
+            THUMB_VENEER => (
+                THUMB_LDR_PC[0] as u32 | (THUMB_LDR_PC[1] as u32) << 16,
+                p | 1,
+            ),
+            _ => (ARM_LDR_PC, p),
+        };
+        data[..4].copy_from_slice(&insn.to_le_bytes());
+        data[4..8].copy_from_slice(&(veneer.target as u32).to_le_bytes());
+        entry
+    }
+
+    fn reloc_name(&self, reloc: &Relocation) -> String {
+        use object::elf::*;
+        let RelocationFlags::Elf { r_type } = reloc.flags() else {
+            return format!("{:?}", reloc.kind());
+        };
+        let name = match r_type {
+            R_ARM_NONE => "R_ARM_NONE",
+            R_ARM_ABS32 => "R_ARM_ABS32",
+            R_ARM_REL32 => "R_ARM_REL32",
+            R_ARM_ABS16 => "R_ARM_ABS16",
+            R_ARM_ABS8 => "R_ARM_ABS8",
+            R_ARM_THM_CALL => "R_ARM_THM_CALL",
+            R_ARM_GOT32 => "R_ARM_GOT_BREL",
+            R_ARM_CALL => "R_ARM_CALL",
+            R_ARM_JUMP24 => "R_ARM_JUMP24",
+            R_ARM_THM_JUMP24 => "R_ARM_THM_JUMP24",
+            R_ARM_TARGET1 => "R_ARM_TARGET1",
+            R_ARM_V4BX => "R_ARM_V4BX",
+            R_ARM_PREL31 => "R_ARM_PREL31",
+            R_ARM_MOVW_ABS_NC => "R_ARM_MOVW_ABS_NC",
+            R_ARM_MOVT_ABS => "R_ARM_MOVT_ABS",
+            R_ARM_THM_MOVW_ABS_NC => "R_ARM_THM_MOVW_ABS_NC",
+            R_ARM_THM_MOVT_ABS => "R_ARM_THM_MOVT_ABS",
+            R_ARM_THM_PC11 => "R_ARM_THM_JUMP11",
+            R_ARM_THM_PC9 => "R_ARM_THM_JUMP8",
+            R_ARM_TLS_IE32 => "R_ARM_TLS_IE32",
+            R_ARM_TLS_LE32 => "R_ARM_TLS_LE32",
+            _ => return format!("R_ARM type {}", r_type),
+        };
+        name.to_string()
+    }
+
+    fn default_dynamic_linker(&self) -> &'static str {
+        "/lib/ld-linux-armhf.so.3"
+    }
+
+    fn dynamic_reloc_type(&self, kind: DynamicRelocKind) -> u32 {
+        match kind {
+            DynamicRelocKind::Relative => object::elf::R_ARM_RELATIVE,
+            DynamicRelocKind::GlobDat => object::elf::R_ARM_GLOB_DAT,
+            DynamicRelocKind::Absolute => object::elf::R_ARM_ABS32,
+            DynamicRelocKind::TpOff => object::elf::R_ARM_TLS_TPOFF32,
+            DynamicRelocKind::TlsDesc => object::elf::R_ARM_TLS_DESC,
+            DynamicRelocKind::IRelative => object::elf::R_ARM_IRELATIVE,
+            DynamicRelocKind::JumpSlot => object::elf::R_ARM_JUMP_SLOT,
+        }
+    }
+
+    fn apply_relocation(
+        &self,
+        reloc: &Relocation,
+        p: u64, // Place of storage (P) - The VA where the relocation is written
+        s: u64, // Symbol value (S), with bit 0 set for Thumb functions (T)
+        a: i64, // Addend (A)
+        _got: u64,
+        data: &mut [u8],
+    ) -> Result<()> {
+        use object::elf::*;
+        let r_type = r_type(reloc);
+        let thumb_target = s & 1 != 0;
+        let (s, p) = (s as i64, p as i64);
+        let name = || self.reloc_name(reloc);
+
+        match r_type {
+            R_ARM_NONE | R_ARM_V4BX => Ok(()),
+            // (S + A) | T, with T already in S
+            R_ARM_ABS32 | R_ARM_TARGET1 => write32(data, (s + a) as u32),
+            // ((S + A) | T) - P
+            R_ARM_REL32 => write32(data, (s + a - p) as u32),
+            // ((S + A) | T) - P in 31 bits, keeping the top bit
+            R_ARM_PREL31 => {
+                let value = s + a - p;
+                check(value, (-(1 << 30), (1 << 30) - 1))?;
+                let old = read32(data)?;
+                write32(data, (old & 0x8000_0000) | (value as u32 & 0x7fff_ffff))
+            }
+            // ((S + A) | T) - P; a BL to Thumb code becomes a BLX.
+            R_ARM_CALL | R_ARM_JUMP24 => {
+                let value = (s & !1) + a - p;
+                let insn = read32(data)?;
+                let insn = if thumb_target {
+                    if r_type == R_ARM_JUMP24 {
+                        return Err(anyhow!("{} to Thumb code needs a veneer", name()));
+                    }
+                    check(value, (ARM_BRANCH.0, ARM_BRANCH.1 + 2))?;
+                    0xfa00_0000 | (((value as u32 >> 1) & 1) << 24)
+                } else {
+                    check(value, ARM_BRANCH)?;
+                    // A BLX written for Thumb code becomes a BL again.
+                    if insn >> 28 == 0xf {
+                        0xeb00_0000
+                    } else {
+                        insn & 0xff00_0000
+                    }
+                };
+                write32(data, insn | ((value as u32 >> 2) & 0x00ff_ffff))
+            }
+            // ((S + A) | T) - P; a BL to ARM code becomes a BLX, relative
+            // to the word-aligned PC.
+            R_ARM_THM_CALL | R_ARM_THM_JUMP24 => {
+                let (hw1, hw2) = read_thumb(data)?;
+                let (value, hw2) = match (r_type, thumb_target) {
+                    (R_ARM_THM_JUMP24, false) => {
+                        return Err(anyhow!("{} to ARM code needs a veneer", name()));
+                    }
+                    (R_ARM_THM_CALL, false) => ((s & !1) + a - (p & !3), hw2 & !0x1000),
+                    (R_ARM_THM_CALL, true) => ((s & !1) + a - p, hw2 | 0x1000),
+                    _ => ((s & !1) + a - p, hw2),
+                };
+                check(value, THUMB_BRANCH)?;
+                let (hw1, hw2) = set_thumb_branch_offset(hw1, hw2, value);
+                write_thumb(data, hw1, hw2)
+            }
+            // (S + A) | T, low or high halfword
+            R_ARM_MOVW_ABS_NC | R_ARM_MOVT_ABS => {
+                let value = (s + a) as u32;
+                let imm = if r_type == R_ARM_MOVT_ABS {
+                    value >> 16
+                } else {
+                    value & 0xffff
+                };
+                let insn = read32(data)?;
+                write32(
+                    data,
+                    (insn & 0xfff0_f000) | ((imm & 0xf000) << 4) | (imm & 0xfff),
+                )
+            }
+            R_ARM_THM_MOVW_ABS_NC | R_ARM_THM_MOVT_ABS => {
+                let value = (s + a) as u32;
+                let imm = if r_type == R_ARM_THM_MOVT_ABS {
+                    value >> 16
+                } else {
+                    value & 0xffff
+                };
+                let (hw1, hw2) = read_thumb(data)?;
+                let hw1 = (hw1 & 0xfbf0) | ((imm >> 12) & 0xf) | (((imm >> 11) & 1) << 10);
+                let hw2 = (hw2 & 0x8f00) | (((imm >> 8) & 7) << 12) | (imm & 0xff);
+                write_thumb(data, hw1, hw2)
+            }
+            _ => Err(anyhow!("unsupported relocation {}", name())),
+        }
+    }
+}
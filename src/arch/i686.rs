@@ -0,0 +1,218 @@
+//! i386 Architecture backend.
+//!
+//! Implements the `Architecture` trait for 32-bit x86 systems (ELF32). Objects
+//! use `SHT_REL` sections, so addends are read from the relocated fields.
+
+use super::{Architecture, RelocationOverflow};
+use crate::dynamic::DynamicRelocKind;
+use anyhow::{anyhow, Result};
+use object::read::Relocation;
+use object::{Endianness, RelocationFlags, RelocationKind};
+
+/// The i386 architecture backend.
+#[derive(Debug, Clone, Copy)]
+pub struct I686;
+
+/// GOT32X: a GOT32 the linker may relax, which the `object` crate reports
+/// as `Unknown` with no size.
+fn is_got32x(reloc: &Relocation) -> bool {
+    matches!(
+        reloc.flags(),
+        RelocationFlags::Elf {
+            r_type: object::elf::R_386_GOT32X,
+        }
+    )
+}
+
+/// Opcodes of the instructions GOT32X relaxation rewrites.
+const MOV_LOAD: u8 = 0x8b;
+const LEA: u8 = 0x8d;
+
+impl Architecture for I686 {
+    fn arch() -> object::Architecture {
+        object::Architecture::I386
+    }
+
+    fn endianness(&self) -> Endianness {
+        Endianness::Little
+    }
+
+    fn e_machine(&self) -> u16 {
+        object::elf::EM_386
+    }
+
+    fn word_size(&self) -> u64 {
+        4
+    }
+
+    fn reloc_kind(&self, reloc: &Relocation) -> RelocationKind {
+        if is_got32x(reloc) {
+            RelocationKind::Got
+        } else {
+            reloc.kind()
+        }
+    }
+
+    fn can_relax_got(&self, reloc: &Relocation, code: &[u8], offset: usize) -> bool {
+        // Only loads through a base register: without one the field holds
+        // the GOT entry's absolute address, which `lea` can't express.
+        is_got32x(reloc)
+            && offset >= 2
+            && code[offset - 2] == MOV_LOAD
+            && code[offset - 1] & 0xc7 != 0x05
+    }
+
+    fn relax_got(&self, _reloc: &Relocation, code: &mut [u8], offset: usize) {
+        // mov foo@GOT(%reg), %reg -> lea foo@GOTOFF(%reg), %reg
+        if code[offset - 2] == MOV_LOAD {
+            code[offset - 2] = LEA;
+        }
+    }
+
+    fn reloc_name(&self, reloc: &Relocation) -> String {
+        use object::elf::*;
+        let RelocationFlags::Elf { r_type } = reloc.flags() else {
+            return format!("{:?}", reloc.kind());
+        };
+        let name = match r_type {
+            R_386_NONE => "R_386_NONE",
+            R_386_32 => "R_386_32",
+            R_386_PC32 => "R_386_PC32",
+            R_386_GOT32 => "R_386_GOT32",
+            R_386_PLT32 => "R_386_PLT32",
+            R_386_GOTOFF => "R_386_GOTOFF",
+            R_386_GOTPC => "R_386_GOTPC",
+            R_386_TLS_TPOFF => "R_386_TLS_TPOFF",
+            R_386_TLS_IE => "R_386_TLS_IE",
+            R_386_TLS_GOTIE => "R_386_TLS_GOTIE",
+            R_386_TLS_LE => "R_386_TLS_LE",
+            R_386_TLS_GD => "R_386_TLS_GD",
+            R_386_TLS_LDM => "R_386_TLS_LDM",
+            R_386_16 => "R_386_16",
+            R_386_PC16 => "R_386_PC16",
+            R_386_8 => "R_386_8",
+            R_386_PC8 => "R_386_PC8",
+            R_386_TLS_LDO_32 => "R_386_TLS_LDO_32",
+            R_386_TLS_IE_32 => "R_386_TLS_IE_32",
+            R_386_TLS_LE_32 => "R_386_TLS_LE_32",
+            R_386_SIZE32 => "R_386_SIZE32",
+            R_386_TLS_GOTDESC => "R_386_TLS_GOTDESC",
+            R_386_TLS_DESC_CALL => "R_386_TLS_DESC_CALL",
+            R_386_GOT32X => "R_386_GOT32X",
+            _ => return format!("R_386 type {}", r_type),
+        };
+        name.to_string()
+    }
+
+    fn is_size_reloc(&self, reloc: &Relocation) -> bool {
+        matches!(
+            reloc.flags(),
+            RelocationFlags::Elf {
+                r_type: object::elf::R_386_SIZE32,
+            }
+        )
+    }
+
+    fn default_dynamic_linker(&self) -> &'static str {
+        "/lib/ld-linux.so.2"
+    }
+
+    fn dynamic_reloc_type(&self, kind: DynamicRelocKind) -> u32 {
+        match kind {
+            DynamicRelocKind::Relative => object::elf::R_386_RELATIVE,
+            DynamicRelocKind::GlobDat => object::elf::R_386_GLOB_DAT,
+            DynamicRelocKind::Absolute => object::elf::R_386_32,
+            DynamicRelocKind::TpOff => object::elf::R_386_TLS_TPOFF,
+            DynamicRelocKind::TlsDesc => object::elf::R_386_TLS_DESC,
+            DynamicRelocKind::IRelative => object::elf::R_386_IRELATIVE,
+            DynamicRelocKind::JumpSlot => object::elf::R_386_JMP_SLOT,
+        }
+    }
+
+    fn reloc_size(&self, reloc: &Relocation) -> u8 {
+        if is_got32x(reloc) {
+            32
+        } else {
+            reloc.size()
+        }
+    }
+
+    fn apply_relocation(
+        &self,
+        reloc: &Relocation,
+        p: u64,   // Place of storage (P) - The VA where the relocation is written
+        s: u64,   // Symbol value OR GOT entry VA (S)
+        a: i64,   // Addend (A)
+        got: u64, // Address of the GOT (GOT)
+        data: &mut [u8],
+    ) -> Result<()> {
+        use object::elf::*;
+        let RelocationFlags::Elf { r_type } = reloc.flags() else {
+            return Err(anyhow!("not an ELF relocation: {:?}", reloc.flags()));
+        };
+        let (s, p, got) = (s as i64, p as i64, got as i64);
+
+        // Addresses are 32 bits, so a 32-bit field holds any of them and
+        // wraps like the CPU's arithmetic does.
+        let (value, field) = match r_type {
+            R_386_NONE => return Ok(()),
+            // S + A
+            R_386_32 => (s + a, Field::Word32),
+            R_386_16 => (s + a, Field::Bits16),
+            R_386_8 => (s + a, Field::Bits8),
+            // S + A - P. uld creates no PLT, so PLT32 calls bind to the
+            // definition directly.
+            R_386_PC32 | R_386_PLT32 => (s + a - p, Field::Word32),
+            R_386_PC16 => (s + a - p, Field::Signed16),
+            R_386_PC8 => (s + a - p, Field::Signed8),
+            // G + A - GOT with S the GOT entry, or S + A - GOT once relaxed
+            // to a `lea`; both are S + A - GOT.
+            R_386_GOT32 | R_386_GOT32X | R_386_GOTOFF => (s + a - got, Field::Word32),
+            // GOT + A - P
+            R_386_GOTPC => (got + a - p, Field::Word32),
+            // Z + A, with the symbol's size passed as S.
+            R_386_SIZE32 => (s + a, Field::Word32),
+            _ => return Err(anyhow!("unsupported relocation {}", self.reloc_name(reloc))),
+        };
+        field.write(value, data)
+    }
+}
+
+/// The width and overflow check of a relocated field.
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    /// A 32-bit address or offset, wrapping modulo 4 GiB.
+    Word32,
+    Signed16,
+    Signed8,
+    /// Either signed or unsigned, as for `R_386_16`.
+    Bits16,
+    /// Either signed or unsigned, as for `R_386_8`.
+    Bits8,
+}
+
+impl Field {
+    /// The field's size in bytes and the range of values it holds.
+    fn layout(self) -> (usize, i64, i64) {
+        match self {
+            Field::Word32 => (4, i64::MIN, i64::MAX),
+            Field::Signed16 => (2, i16::MIN as i64, i16::MAX as i64),
+            Field::Signed8 => (1, i8::MIN as i64, i8::MAX as i64),
+            Field::Bits16 => (2, i16::MIN as i64, u16::MAX as i64),
+            Field::Bits8 => (1, i8::MIN as i64, u8::MAX as i64),
+        }
+    }
+
+    /// Write the low bytes of `value`, which must be in range.
+    fn write(self, value: i64, data: &mut [u8]) -> Result<()> {
+        let (size, min, max) = self.layout();
+        if value < min || value > max {
+            return Err(RelocationOverflow { value, min, max }.into());
+        }
+        let Some(out) = data.get_mut(..size) else {
+            return Err(anyhow!("Relocation offset out of bounds"));
+        };
+        out.copy_from_slice(&value.to_le_bytes()[..size]);
+        Ok(())
+    }
+}
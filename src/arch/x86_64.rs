@@ -2,14 +2,44 @@
 //!
 //! Implements the `Architecture` trait for 64-bit x86 systems (ELF64).
 
-use super::Architecture;
+use super::{Architecture, RelocationOverflow};
+use crate::dynamic::DynamicRelocKind;
+use crate::got::EntryKind;
 use anyhow::{anyhow, Result};
 use object::read::Relocation;
-use object::{Endianness, RelocationKind};
+use object::{Endianness, RelocationFlags, RelocationKind};
+use std::num::Wrapping;
 
 /// The x86_64 architecture backend.
+#[derive(Debug, Clone, Copy)]
 pub struct X86_64;
 
+/// GOTPCRELX / REX_GOTPCRELX: a GOTPCREL the linker may relax, which the
+/// `object` crate reports as `Unknown` with no size.
+fn is_gotpcrelx(reloc: &Relocation) -> bool {
+    matches!(
+        reloc.flags(),
+        RelocationFlags::Elf {
+            r_type: object::elf::R_X86_64_GOTPCRELX | object::elf::R_X86_64_REX_GOTPCRELX,
+        }
+    )
+}
+
+/// Opcodes of the instructions GOTPCRELX relaxation rewrites.
+const MOV_LOAD: u8 = 0x8b;
+const LEA: u8 = 0x8d;
+const GROUP5: u8 = 0xff;
+/// ModRM bytes of `call *disp(%rip)` and `jmp *disp(%rip)` under [`GROUP5`].
+const CALL_RIP: u8 = 0x15;
+const JMP_RIP: u8 = 0x25;
+
+/// `data16 lea x@tlsgd(%rip), %rdi`, up to the relocated field.
+const TLSGD_LEA: [u8; 4] = [0x66, 0x48, 0x8d, 0x3d];
+/// `lea x@tlsld(%rip), %rdi`, up to the relocated field.
+const TLSLD_LEA: [u8; 3] = [0x48, 0x8d, 0x3d];
+/// `mov %fs:0, %rax`, which loads the thread pointer.
+const LOAD_TP: [u8; 9] = [0x64, 0x48, 0x8b, 0x04, 0x25, 0, 0, 0, 0];
+
 impl Architecture for X86_64 {
     fn arch() -> object::Architecture {
         object::Architecture::X86_64
@@ -19,74 +49,345 @@ impl Architecture for X86_64 {
         Endianness::Little
     }
 
-    fn apply_relocation(
+    fn e_machine(&self) -> u16 {
+        object::elf::EM_X86_64
+    }
+
+    fn reloc_kind(&self, reloc: &Relocation) -> RelocationKind {
+        if is_gotpcrelx(reloc) {
+            RelocationKind::GotRelative
+        } else {
+            reloc.kind()
+        }
+    }
+
+    fn got_entry_kind(&self, reloc: &Relocation) -> Option<EntryKind> {
+        use object::elf::*;
+        match reloc.flags() {
+            RelocationFlags::Elf {
+                r_type: R_X86_64_GOTTPOFF,
+            } => Some(EntryKind::TlsIe),
+            RelocationFlags::Elf {
+                r_type: R_X86_64_GOTPC32_TLSDESC,
+            } => Some(EntryKind::TlsDesc),
+            _ => matches!(
+                self.reloc_kind(reloc),
+                RelocationKind::Got | RelocationKind::GotRelative
+            )
+            .then_some(EntryKind::Address),
+        }
+    }
+
+    fn can_relax_got(&self, reloc: &Relocation, code: &[u8], offset: usize) -> bool {
+        if !is_gotpcrelx(reloc) || offset < 2 {
+            return false;
+        }
+        matches!(
+            (code[offset - 2], code[offset - 1]),
+            (MOV_LOAD, _) | (GROUP5, CALL_RIP | JMP_RIP)
+        )
+    }
+
+    fn relax_got(&self, _reloc: &Relocation, code: &mut [u8], offset: usize) {
+        match (code[offset - 2], code[offset - 1]) {
+            // mov foo@GOTPCREL(%rip), %reg -> lea foo(%rip), %reg
+            (MOV_LOAD, _) => code[offset - 2] = LEA,
+            // call *foo@GOTPCREL(%rip) -> addr32 call foo
+            (GROUP5, CALL_RIP) => code[offset - 2..offset].copy_from_slice(&[0x67, 0xe8]),
+            // jmp *foo@GOTPCREL(%rip) -> nop; jmp foo
+            (GROUP5, JMP_RIP) => code[offset - 2..offset].copy_from_slice(&[0x90, 0xe9]),
+            _ => {}
+        }
+    }
+
+    fn is_tp_relative(&self, reloc: &Relocation) -> bool {
+        use object::elf::*;
+        // After local-dynamic relaxation, offsets within the TLS block are
+        // from the thread pointer too.
+        matches!(
+            reloc.flags(),
+            RelocationFlags::Elf {
+                r_type: R_X86_64_TPOFF32 | R_X86_64_TPOFF64 | R_X86_64_DTPOFF32 | R_X86_64_DTPOFF64,
+            }
+        )
+    }
+
+    fn tls_call_offset(&self, reloc: &Relocation, code: &[u8], offset: usize) -> Option<u64> {
+        use object::elf::*;
+        let RelocationFlags::Elf { r_type } = reloc.flags() else {
+            return None;
+        };
+        let call = code.get(offset + 4..offset + 8)?;
+        match r_type {
+            // data16 data16 rex64 call __tls_get_addr@PLT, or
+            // data16 rex64 call *__tls_get_addr@GOTPCREL(%rip)
+            R_X86_64_TLSGD
+                if code.get(offset.checked_sub(4)?..offset)? == TLSGD_LEA
+                    && matches!(
+                        call,
+                        [0x66, 0x66, 0x48, 0xe8] | [0x66, 0x48, GROUP5, CALL_RIP]
+                    ) =>
+            {
+                Some(8)
+            }
+            R_X86_64_TLSLD if code.get(offset.checked_sub(3)?..offset)? == TLSLD_LEA => {
+                match call {
+                    [0xe8, ..] => Some(5),
+                    [GROUP5, CALL_RIP, ..] => Some(6),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn relax_tls(
         &self,
-        offset: u64,
         reloc: &Relocation,
-        p: u64, // Place of storage (P) - The VA where the relocation is written
-        s: u64, // Symbol value OR GOT entry VA (S)
-        a: i64, // Addend (A)
-        data: &mut [u8],
+        code: &mut [u8],
+        offset: usize,
+        tpoff: i64,
     ) -> Result<()> {
-        let offset = offset as usize;
-
-        // x86_64 often uses SHT_RELA (explicit addends), but we should check
-        // if the addend is 0 and try to read from the buffer to support implicit addends
-        // which can sometimes appear in specific toolchains or object formats.
-        let mut final_addend = a;
-        if final_addend == 0 && reloc.size() == 32 && offset + 4 <= data.len() {
-            let existing = i32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
-            if existing != 0 {
-                final_addend = existing as i64;
+        use object::elf::*;
+        match reloc.flags() {
+            // mov %fs:0, %rax; lea x@tpoff(%rax), %rax
+            RelocationFlags::Elf {
+                r_type: R_X86_64_TLSGD,
+            } => {
+                let at = offset - TLSGD_LEA.len();
+                code[at..at + LOAD_TP.len()].copy_from_slice(&LOAD_TP);
+                code[at + 9..at + 12].copy_from_slice(&[0x48, 0x8d, 0x80]);
+                Field::Signed32.write(tpoff, &mut code[at + 12..])
+            }
+            // The block starts at the thread pointer less its size, which the
+            // @dtpoff offsets that follow now include: just load it, padded
+            // with prefixes to the length of the sequence.
+            _ => {
+                let at = offset - TLSLD_LEA.len();
+                let prefixes = if code[offset + 4] == GROUP5 { 4 } else { 3 };
+                code[at..at + prefixes].fill(0x66);
+                code[at + prefixes..at + prefixes + LOAD_TP.len()].copy_from_slice(&LOAD_TP);
+                Ok(())
             }
         }
+    }
 
-        let val: u64 = match reloc.kind() {
-            // R_X86_64_64: S + A
-            RelocationKind::Absolute => (s as i64 + final_addend) as u64,
-
-            // R_X86_64_PC32 / PLT32 / GOTPCREL / GOTPCRELX: S + A - P
-            RelocationKind::Relative
-            | RelocationKind::PltRelative
-            | RelocationKind::GotRelative => (s as i64 + final_addend - p as i64) as u64,
+    fn tls_get_addr(&self) -> Option<&'static str> {
+        Some("__tls_get_addr")
+    }
 
-            _ => {
-                tracing::trace!("Unsupported relocation kind: {:?}", reloc.kind());
-                return Ok(());
+    fn is_size_reloc(&self, reloc: &Relocation) -> bool {
+        matches!(
+            reloc.flags(),
+            RelocationFlags::Elf {
+                r_type: object::elf::R_X86_64_SIZE32 | object::elf::R_X86_64_SIZE64,
             }
+        )
+    }
+
+    fn reloc_name(&self, reloc: &Relocation) -> String {
+        use object::elf::*;
+        let RelocationFlags::Elf { r_type } = reloc.flags() else {
+            return format!("{:?}", reloc.kind());
+        };
+        let name = match r_type {
+            R_X86_64_NONE => "R_X86_64_NONE",
+            R_X86_64_64 => "R_X86_64_64",
+            R_X86_64_PC32 => "R_X86_64_PC32",
+            R_X86_64_GOT32 => "R_X86_64_GOT32",
+            R_X86_64_PLT32 => "R_X86_64_PLT32",
+            R_X86_64_GOTPCREL => "R_X86_64_GOTPCREL",
+            R_X86_64_32 => "R_X86_64_32",
+            R_X86_64_32S => "R_X86_64_32S",
+            R_X86_64_16 => "R_X86_64_16",
+            R_X86_64_PC16 => "R_X86_64_PC16",
+            R_X86_64_8 => "R_X86_64_8",
+            R_X86_64_PC8 => "R_X86_64_PC8",
+            R_X86_64_TLSGD => "R_X86_64_TLSGD",
+            R_X86_64_TLSLD => "R_X86_64_TLSLD",
+            R_X86_64_DTPOFF32 => "R_X86_64_DTPOFF32",
+            R_X86_64_GOTTPOFF => "R_X86_64_GOTTPOFF",
+            R_X86_64_TPOFF32 => "R_X86_64_TPOFF32",
+            R_X86_64_PC64 => "R_X86_64_PC64",
+            R_X86_64_GOTOFF64 => "R_X86_64_GOTOFF64",
+            R_X86_64_GOTPC32 => "R_X86_64_GOTPC32",
+            R_X86_64_GOT64 => "R_X86_64_GOT64",
+            R_X86_64_GOTPCREL64 => "R_X86_64_GOTPCREL64",
+            R_X86_64_GOTPC64 => "R_X86_64_GOTPC64",
+            R_X86_64_SIZE32 => "R_X86_64_SIZE32",
+            R_X86_64_SIZE64 => "R_X86_64_SIZE64",
+            R_X86_64_GOTPC32_TLSDESC => "R_X86_64_GOTPC32_TLSDESC",
+            R_X86_64_TLSDESC_CALL => "R_X86_64_TLSDESC_CALL",
+            R_X86_64_GOTPCRELX => "R_X86_64_GOTPCRELX",
+            R_X86_64_REX_GOTPCRELX => "R_X86_64_REX_GOTPCRELX",
+            _ => return format!("R_X86_64 type {}", r_type),
         };
+        name.to_string()
+    }
 
-        // Write the value to the buffer.
-        match reloc.size() {
-            32 => {
-                // x86_64 PC-relative displacements are signed 32-bit integers.
-                let signed_val = val as i64;
-                if signed_val < i32::MIN as i64 || signed_val > i32::MAX as i64 {
-                    return Err(anyhow!(
-                        "Relocation overflow at VA 0x{:x}: displacement 0x{:x} exceeds 32-bit signed range. \
-                         Target (S) is 0x{:x}, P is 0x{:x}. Ensure segments are within 2GB of each other.",
-                        p, signed_val, s, p
-                    ));
-                }
+    fn plt_entry_size(&self) -> u64 {
+        16
+    }
 
-                let bytes = (val as u32).to_le_bytes();
-                if offset + 4 <= data.len() {
-                    data[offset..offset + 4].copy_from_slice(&bytes);
-                } else {
-                    return Err(anyhow!("Relocation offset out of bounds at 0x{:x}", offset));
-                }
-            }
-            64 => {
-                let bytes = val.to_le_bytes();
-                if offset + 8 <= data.len() {
-                    data[offset..offset + 8].copy_from_slice(&bytes);
-                } else {
-                    return Err(anyhow!("Relocation offset out of bounds at 0x{:x}", offset));
-                }
-            }
-            _ => return Err(anyhow!("Unsupported relocation size: {}", reloc.size())),
+    fn write_plt_entry(&self, p: u64, slot: u64, data: &mut [u8]) {
+        // endbr64, as the entry stands in for the function under IBT; jmp
+        // *slot(%rip); and a 6-byte nop to fill it.
+        data[..16].copy_from_slice(&[
+            0xf3, 0x0f, 0x1e, 0xfa, 0xff, 0x25, 0, 0, 0, 0, 0x66, 0x0f, 0x1f, 0x44, 0, 0,
+        ]);
+        let disp = slot.wrapping_sub(p + 10) as u32;
+        data[6..10].copy_from_slice(&disp.to_le_bytes());
+    }
+
+    fn overflow_hint(&self, _reloc: &Relocation) -> Option<&'static str> {
+        Some("recompile with -mcmodel=large")
+    }
+
+    fn default_dynamic_linker(&self) -> &'static str {
+        "/lib64/ld-linux-x86-64.so.2"
+    }
+
+    fn dynamic_reloc_type(&self, kind: DynamicRelocKind) -> u32 {
+        match kind {
+            DynamicRelocKind::Relative => object::elf::R_X86_64_RELATIVE,
+            DynamicRelocKind::GlobDat => object::elf::R_X86_64_GLOB_DAT,
+            DynamicRelocKind::Absolute => object::elf::R_X86_64_64,
+            DynamicRelocKind::TpOff => object::elf::R_X86_64_TPOFF64,
+            DynamicRelocKind::TlsDesc => object::elf::R_X86_64_TLSDESC,
+            DynamicRelocKind::IRelative => object::elf::R_X86_64_IRELATIVE,
+            DynamicRelocKind::JumpSlot => object::elf::R_X86_64_JUMP_SLOT,
+        }
+    }
+
+    fn reloc_size(&self, reloc: &Relocation) -> u8 {
+        use object::elf::*;
+        // `object` reports a size of 0 for the types below; the widths
+        // match the fields `apply_relocation` writes.
+        match reloc.flags() {
+            RelocationFlags::Elf {
+                r_type:
+                    R_X86_64_GOTPCRELX
+                    | R_X86_64_REX_GOTPCRELX
+                    | R_X86_64_GOTTPOFF
+                    | R_X86_64_GOTPC32_TLSDESC
+                    | R_X86_64_GOTPC32
+                    | R_X86_64_SIZE32
+                    | R_X86_64_TPOFF32
+                    | R_X86_64_DTPOFF32
+                    | R_X86_64_TLSGD
+                    | R_X86_64_TLSLD,
+            } => 32,
+            RelocationFlags::Elf {
+                r_type:
+                    R_X86_64_PC64 | R_X86_64_GOTPCREL64 | R_X86_64_GOTOFF64 | R_X86_64_GOTPC64
+                    | R_X86_64_SIZE64 | R_X86_64_TPOFF64 | R_X86_64_DTPOFF64,
+            } => 64,
+            _ => reloc.size(),
+        }
+    }
+
+    fn apply_relocation(
+        &self,
+        reloc: &Relocation,
+        p: u64,   // Place of storage (P) - The VA where the relocation is written
+        s: u64,   // Symbol value OR GOT entry VA (S)
+        a: i64,   // Addend (A)
+        got: u64, // Address of the GOT (GOT)
+        data: &mut [u8],
+    ) -> Result<()> {
+        use object::elf::*;
+        let RelocationFlags::Elf { r_type } = reloc.flags() else {
+            return Err(anyhow!("not an ELF relocation: {:?}", reloc.flags()));
+        };
+
+        // Addends are arbitrary 64-bit values, so the sums wrap like the
+        // CPU's address arithmetic; narrower fields still check the result.
+        let (s, a, p, got) = (
+            Wrapping(s as i64),
+            Wrapping(a),
+            Wrapping(p as i64),
+            Wrapping(got as i64),
+        );
+
+        let (value, field) = match r_type {
+            R_X86_64_NONE => return Ok(()),
+            // S + A
+            R_X86_64_64 => (s + a, Field::Word64),
+            R_X86_64_32 => (s + a, Field::Unsigned32),
+            R_X86_64_32S => (s + a, Field::Signed32),
+            R_X86_64_16 => (s + a, Field::Bits16),
+            R_X86_64_8 => (s + a, Field::Bits8),
+            // S + A - P; S is the GOT entry for the GOTPCREL family, and
+            // the `.plt` entry for PLT32 calls to functions bound at
+            // runtime. Other PLT32 calls bind to the definition directly,
+            // whatever `--no-relax` says.
+            R_X86_64_PC32
+            | R_X86_64_PLT32
+            | R_X86_64_GOTPCREL
+            | R_X86_64_GOTPCRELX
+            | R_X86_64_REX_GOTPCRELX
+            | R_X86_64_GOTTPOFF
+            | R_X86_64_GOTPC32_TLSDESC => (s + a - p, Field::Signed32),
+            // Marks the call through a TLS descriptor, which stays as is.
+            R_X86_64_TLSDESC_CALL => return Ok(()),
+            R_X86_64_PC64 | R_X86_64_GOTPCREL64 => (s + a - p, Field::Word64),
+            R_X86_64_PC16 => (s + a - p, Field::Signed16),
+            R_X86_64_PC8 => (s + a - p, Field::Signed8),
+            // S + A - GOT
+            R_X86_64_GOTOFF64 => (s + a - got, Field::Word64),
+            // GOT + A - P
+            R_X86_64_GOTPC32 => (got + a - p, Field::Signed32),
+            R_X86_64_GOTPC64 => (got + a - p, Field::Word64),
+            // Z + A, with the symbol's size passed as S.
+            R_X86_64_SIZE32 => (s + a, Field::Unsigned32),
+            R_X86_64_SIZE64 => (s + a, Field::Word64),
+            // S + A, with the symbol's offset from the thread pointer as S.
+            R_X86_64_TPOFF32 | R_X86_64_DTPOFF32 => (s + a, Field::Signed32),
+            R_X86_64_TPOFF64 | R_X86_64_DTPOFF64 => (s + a, Field::Word64),
+            _ => return Err(anyhow!("unsupported relocation {}", self.reloc_name(reloc))),
+        };
+        field.write(value.0, data)
+    }
+}
+
+/// The width and overflow check of a relocated field.
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Word64,
+    Unsigned32,
+    Signed32,
+    Signed16,
+    Signed8,
+    /// Either signed or unsigned, as for `R_X86_64_16`.
+    Bits16,
+    /// Either signed or unsigned, as for `R_X86_64_8`.
+    Bits8,
+}
+
+impl Field {
+    /// The field's size in bytes and the range of values it holds.
+    fn layout(self) -> (usize, i64, i64) {
+        match self {
+            Field::Word64 => (8, i64::MIN, i64::MAX),
+            Field::Unsigned32 => (4, 0, u32::MAX as i64),
+            Field::Signed32 => (4, i32::MIN as i64, i32::MAX as i64),
+            Field::Signed16 => (2, i16::MIN as i64, i16::MAX as i64),
+            Field::Signed8 => (1, i8::MIN as i64, i8::MAX as i64),
+            Field::Bits16 => (2, i16::MIN as i64, u16::MAX as i64),
+            Field::Bits8 => (1, i8::MIN as i64, u8::MAX as i64),
         }
+    }
 
+    /// Write the low bytes of `value`, which must be in range.
+    fn write(self, value: i64, data: &mut [u8]) -> Result<()> {
+        let (size, min, max) = self.layout();
+        if value < min || value > max {
+            return Err(RelocationOverflow { value, min, max }.into());
+        }
+        let Some(out) = data.get_mut(..size) else {
+            return Err(anyhow!("Relocation offset out of bounds"));
+        };
+        out.copy_from_slice(&value.to_le_bytes()[..size]);
         Ok(())
     }
 }
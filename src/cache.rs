@@ -0,0 +1,206 @@
+//! On-disk cache of what parsing learns about input objects
+//! (`--object-cache=DIR`).
+//!
+//! Reading an object's global symbols walks its symbol table, which only
+//! depends on the object's contents, so the cache keeps the result in a
+//! directory, one entry per object keyed by a hash of its contents:
+//! re-linking an unchanged object hashes it instead. The checks an object
+//! gets as it joins the link (see [`crate::validate::check_object`]) are
+//! never skipped.
+//!
+//! An entry records symbol names as offsets into the object, so names
+//! still borrow from the mapped input. Entries that can't be read or don't
+//! fit their object are ignored and rewritten, and none are ever removed:
+//! delete the directory to clear the cache.
+//!
+//! The key is a fast 128-bit hash, not a cryptographic one, and entries
+//! are trusted to describe their object: anyone who can write to the
+//! directory can make a link use the wrong symbols. Only share a cache
+//! directory between users who trust each other.
+
+use std::fs;
+use std::hash::BuildHasher;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Context, Result};
+use foldhash::quality::FixedState;
+use object::read::{Object, SectionIndex};
+
+use crate::input::{InputSymbol, ParsedObject};
+use crate::symbol::SymbolLocation;
+
+/// Start of every entry, changed whenever the layout of entries does.
+const MAGIC: &[u8; 8] = b"ULDOBJC3";
+
+/// Bytes of each symbol in an entry.
+const SYMBOL_LEN: usize = 8 + 4 + 1 + 8 + 8 + 8 + 1 + 1;
+
+/// Entries being written, numbered apart so threads don't share a file.
+static WRITING: AtomicUsize = AtomicUsize::new(0);
+
+/// A directory of cached object summaries.
+#[derive(Debug, Clone)]
+pub struct ObjectCache {
+    dir: PathBuf,
+}
+
+impl ObjectCache {
+    /// Use `dir` as the cache, creating it if needed.
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("creating object cache {}", dir.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    /// Parse the object `data` like [`ParsedObject::parse`], taking its
+    /// symbols from the cache when they are there, and adding them when
+    /// they aren't.
+    pub fn parse<'a>(&self, data: &'a [u8]) -> Result<ParsedObject<'a>> {
+        let path = self.entry_path(data);
+        let obj = object::File::parse(data)?;
+        if let Some(symbols) = fs::read(&path).ok().and_then(|e| decode(&e, data, &obj)) {
+            return Ok(ParsedObject { obj, symbols });
+        }
+        let parsed = ParsedObject::scan(obj)?;
+        if let Some(entry) = encode(&parsed, data) {
+            // A cache that can't be written only makes the next link slower.
+            if let Err(e) = store(&path, &entry) {
+                tracing::warn!("object cache: {}: {}", path.display(), e);
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Where the entry for an object with contents `data` is kept.
+    fn entry_path(&self, data: &[u8]) -> PathBuf {
+        let a = FixedState::with_seed(0).hash_one(data);
+        let b = FixedState::with_seed(1).hash_one(data);
+        self.dir.join(format!("{:016x}{:016x}", a, b))
+    }
+}
+
+/// Write `entry` to `path` whole, so a concurrent link never reads half
+/// of it.
+fn store(path: &Path, entry: &[u8]) -> std::io::Result<()> {
+    let n = WRITING.fetch_add(1, Ordering::Relaxed);
+    let tmp = path.with_extension(format!("{}.{}.tmp", std::process::id(), n));
+    fs::write(&tmp, entry)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// The entry for `parsed`, read from `data`; `None` if its symbol names
+/// don't lie in `data`.
+fn encode(parsed: &ParsedObject, data: &[u8]) -> Option<Vec<u8>> {
+    let version = env!("CARGO_PKG_VERSION").as_bytes();
+    let mut out = Vec::with_capacity(32 + version.len() + parsed.symbols.len() * SYMBOL_LEN);
+    out.extend_from_slice(MAGIC);
+    out.push(version.len() as u8);
+    out.extend_from_slice(version);
+    out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    out.extend_from_slice(&(parsed.symbols.len() as u32).to_le_bytes());
+    for sym in &parsed.symbols {
+        let offset = (sym.name.as_ptr() as usize).checked_sub(data.as_ptr() as usize)?;
+        if offset + sym.name.len() > data.len() {
+            return None;
+        }
+        let (tag, value) = match sym.location {
+            None => (0, 0),
+            Some(SymbolLocation::Section(i)) => (1, i.0 as u64),
+            Some(SymbolLocation::Absolute) => (2, 0),
+            Some(SymbolLocation::Common { align }) => (3, align),
+            Some(SymbolLocation::Undefined) => (4, 0),
+        };
+        out.extend_from_slice(&(offset as u64).to_le_bytes());
+        out.extend_from_slice(&(sym.name.len() as u32).to_le_bytes());
+        out.push(tag);
+        out.extend_from_slice(&value.to_le_bytes());
+        out.extend_from_slice(&sym.address.to_le_bytes());
+        out.extend_from_slice(&sym.size.to_le_bytes());
+        out.push(
+            sym.weak as u8 | (sym.optional as u8) << 1 | (sym.unique as u8) << 2 | sym.st_type << 4,
+        );
+        out.push(sym.visibility);
+    }
+    Some(out)
+}
+
+/// The symbols in `entry`, with names read from `data`, the contents of
+/// `obj`; `None` if the entry is damaged, was written by another version
+/// or names sections `obj` doesn't have.
+fn decode<'a>(entry: &[u8], data: &'a [u8], obj: &object::File) -> Option<Vec<InputSymbol<'a>>> {
+    let mut r = Reader(entry);
+    if r.take(MAGIC.len())? != MAGIC {
+        return None;
+    }
+    let version = r.take(1)?[0] as usize;
+    if r.take(version)? != env!("CARGO_PKG_VERSION").as_bytes() {
+        return None;
+    }
+    if r.u64()? != data.len() as u64 {
+        return None;
+    }
+    let count = r.u32()? as usize;
+    if r.0.len() != count.checked_mul(SYMBOL_LEN)? {
+        return None;
+    }
+    let mut symbols = Vec::with_capacity(count);
+    for _ in 0..count {
+        let offset = r.u64()? as usize;
+        let len = r.u32()? as usize;
+        let name = std::str::from_utf8(data.get(offset..offset.checked_add(len)?)?).ok()?;
+        let tag = r.take(1)?[0];
+        let value = r.u64()?;
+        let location = match tag {
+            0 => None,
+            1 => {
+                let index = SectionIndex(value as usize);
+                obj.section_by_index(index).ok()?;
+                Some(SymbolLocation::Section(index))
+            }
+            2 => Some(SymbolLocation::Absolute),
+            3 => Some(SymbolLocation::Common { align: value }),
+            4 => Some(SymbolLocation::Undefined),
+            _ => return None,
+        };
+        let address = r.u64()?;
+        let size = r.u64()?;
+        let bits = r.take(1)?[0];
+        let visibility = r.take(1)?[0];
+        symbols.push(InputSymbol {
+            name,
+            location,
+            address,
+            size,
+            weak: bits & 1 != 0,
+            optional: bits & 2 != 0,
+            visibility,
+            unique: bits & 4 != 0,
+            st_type: bits >> 4,
+        });
+    }
+    Some(symbols)
+}
+
+/// Reads an entry front to back.
+struct Reader<'e>(&'e [u8]);
+
+impl<'e> Reader<'e> {
+    fn take(&mut self, n: usize) -> Option<&'e [u8]> {
+        let (head, rest) = self.0.split_at_checked(n)?;
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+}
@@ -0,0 +1,141 @@
+//! Call-graph profile section ordering.
+//!
+//! Places hot functions next to their hottest caller using the C3 heuristic
+//! from hfsort, as lld does for `--call-graph-profile-sort`. The weighted
+//! call edges come from `.llvm.call-graph-profile` sections or a
+//! `--call-graph-ordering-file`.
+
+use anyhow::{bail, Context, Result};
+
+/// Clusters larger than this aren't merged further.
+const MAX_CLUSTER_SIZE: u64 = 1024 * 1024;
+
+/// A merge is refused if it would drop the caller's density below
+/// `1 / MAX_DENSITY_DEGRADATION` of what it was.
+const MAX_DENSITY_DEGRADATION: f64 = 8.0;
+
+struct Cluster {
+    /// Nodes in layout order; empty once merged into another cluster.
+    members: Vec<usize>,
+    size: u64,
+    weight: u64,
+    initial_weight: u64,
+    /// The heaviest incoming edge, as `(caller, weight)`.
+    best_pred: Option<(usize, u64)>,
+}
+
+impl Cluster {
+    fn density(&self) -> f64 {
+        if self.size == 0 {
+            0.0
+        } else {
+            self.weight as f64 / self.size as f64
+        }
+    }
+}
+
+/// Order the nodes of a call graph so callees follow their hottest caller.
+///
+/// `sizes` holds the size of each node (an input section) and `edges` the
+/// `(caller, callee, weight)` calls between them. Returns every node, densest
+/// cluster first.
+pub fn hfsort(sizes: &[u64], edges: &[(usize, usize, u64)]) -> Vec<usize> {
+    let mut clusters: Vec<Cluster> = sizes
+        .iter()
+        .enumerate()
+        .map(|(i, &size)| Cluster {
+            members: vec![i],
+            size,
+            weight: 0,
+            initial_weight: 0,
+            best_pred: None,
+        })
+        .collect();
+    for &(from, to, weight) in edges {
+        let c = &mut clusters[to];
+        c.weight += weight;
+        if from != to && c.best_pred.is_none_or(|(_, w)| w < weight) {
+            c.best_pred = Some((from, weight));
+        }
+    }
+    for c in &mut clusters {
+        c.initial_weight = c.weight;
+    }
+
+    let mut leaders: Vec<usize> = (0..clusters.len()).collect();
+    let mut sorted: Vec<usize> = (0..clusters.len()).collect();
+    sorted.sort_by(|&a, &b| clusters[b].density().total_cmp(&clusters[a].density()));
+
+    for l in sorted {
+        // Don't merge on an edge that is only a small share of the calls.
+        let Some((pred, weight)) = clusters[l].best_pred else {
+            continue;
+        };
+        if weight * 10 <= clusters[l].initial_weight {
+            continue;
+        }
+        let pred_l = leader(&mut leaders, pred);
+        if pred_l == l {
+            continue;
+        }
+        let (c, p) = (&clusters[l], &clusters[pred_l]);
+        if c.size + p.size > MAX_CLUSTER_SIZE {
+            continue;
+        }
+        let merged = (p.weight + c.weight) as f64 / (p.size + c.size) as f64;
+        if merged < p.density() / MAX_DENSITY_DEGRADATION {
+            continue;
+        }
+
+        leaders[l] = pred_l;
+        let members = std::mem::take(&mut clusters[l].members);
+        let (size, weight) = (clusters[l].size, clusters[l].weight);
+        let p = &mut clusters[pred_l];
+        p.members.extend(members);
+        p.size += size;
+        p.weight += weight;
+    }
+
+    let mut remaining: Vec<&Cluster> = clusters.iter().filter(|c| !c.members.is_empty()).collect();
+    remaining.sort_by(|a, b| b.density().total_cmp(&a.density()));
+    remaining
+        .into_iter()
+        .flat_map(|c| c.members.iter().copied())
+        .collect()
+}
+
+/// Find the cluster `node` was merged into, compressing the path on the way.
+fn leader(leaders: &mut [usize], node: usize) -> usize {
+    let mut root = node;
+    while leaders[root] != root {
+        root = leaders[root];
+    }
+    let mut n = node;
+    while leaders[n] != root {
+        let next = leaders[n];
+        leaders[n] = root;
+        n = next;
+    }
+    root
+}
+
+/// Parse a `--call-graph-ordering-file`: one `caller callee weight` edge per
+/// line, with `#` comments and blank lines skipped.
+pub fn parse_ordering_file(text: &str) -> Result<Vec<(String, String, u64)>> {
+    let mut edges = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [from, to, weight] = fields[..] else {
+            bail!("line {}: expected CALLER CALLEE WEIGHT", n + 1);
+        };
+        let weight = weight
+            .parse()
+            .with_context(|| format!("line {}: bad weight {}", n + 1, weight))?;
+        edges.push((from.to_string(), to.to_string(), weight));
+    }
+    Ok(edges)
+}
@@ -2,12 +2,30 @@
 //!
 //! Accepts all args positionally for clang compatibility.
 //! clang -fuse-ld= sends: -o out file1.o -L/path -lc file2.o
+//!
+//! Arguments of the form `@file` are expanded from response files before parsing.
+//! Every flag is then classified against a GNU ld compatible driver table so that
+//! flag values are never mistaken for inputs and typos are reported.
 
+use anyhow::{bail, Context, Result};
 use clap::Parser;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use tracing::{info, warn};
 
-use crate::utils::find_library;
+use crate::callgraph;
+use crate::diagnostics::DiagnosticsFormat;
+use crate::linker::{
+    FileFormat, HashStyle, LinkOptions, OrphanHandling, OutputKind, SectionPadding, SortSection,
+    UnresolvedSymbols, ZOptions,
+};
+use crate::plugin::PluginOptions;
+use crate::script::{self, InputScript};
+use crate::symbol::SyntheticValue;
+use crate::utils::{find_library, glob_match};
+use crate::version::VersionScript;
 
 #[derive(Parser)]
 #[command(author, version, about = "A minimal static linker")]
@@ -19,66 +37,1531 @@ pub struct Config {
     /// Log level
     #[arg(long, default_value = "warn")]
     pub log_level: String,
+
+    /// `args` classified against the driver flag table.
+    #[arg(skip)]
+    parsed: Vec<Arg>,
+
+    /// `@file` response files the arguments were read from.
+    #[arg(skip)]
+    response_files: Vec<PathBuf>,
+}
+
+/// How a driver flag takes its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Takes {
+    /// A plain switch.
+    Nothing,
+    /// `--flag VALUE` or `--flag=VALUE` (single letters also allow `-fVALUE`).
+    Value,
+    /// Only `--flag=VALUE`; a bare `--flag` is also valid.
+    OptionalValue,
+}
+
+/// Flags the driver understands, by canonical spelling.
+///
+/// Long options may be written with one or two dashes, as GNU ld allows.
+/// Flags after the no-op marker are accepted for compatibility with what
+/// compiler drivers pass by default and have no effect; those that would
+/// change the output (`--gc-sections`, `--eh-frame-hdr`, `--build-id`) warn
+/// that they are ignored.
+const FLAGS: &[(&str, Takes)] = &[
+    ("-o", Takes::Value),
+    ("-L", Takes::Value),
+    ("-l", Takes::Value),
+    ("-z", Takes::Value),
+    ("-M", Takes::Nothing),
+    ("--print-map", Takes::Nothing),
+    ("--Map", Takes::Value),
+    ("--pad-sections", Takes::Value),
+    ("--why-extract", Takes::Value),
+    ("--verbose", Takes::OptionalValue),
+    ("-t", Takes::Nothing),
+    ("--trace", Takes::Nothing),
+    ("-y", Takes::Value),
+    ("-u", Takes::Value),
+    ("--undefined", Takes::Value),
+    ("--require-defined", Takes::Value),
+    ("--trace-symbol", Takes::Value),
+    ("--size-report", Takes::Value),
+    ("--threads", Takes::Value),
+    ("--dependency-file", Takes::Value),
+    ("--object-cache", Takes::Value),
+    ("--stats", Takes::Nothing),
+    ("--progress", Takes::Nothing),
+    ("--time-trace", Takes::Nothing),
+    ("--time-trace-file", Takes::Value),
+    ("--reproduce", Takes::Value),
+    ("--shared", Takes::Nothing),
+    ("--Bshareable", Takes::Nothing),
+    ("--soname", Takes::Value),
+    ("-h", Takes::Value),
+    ("--emit-relocs", Takes::Nothing),
+    ("-q", Takes::Nothing),
+    ("-E", Takes::Nothing),
+    ("--export-dynamic", Takes::Nothing),
+    ("--no-export-dynamic", Takes::Nothing),
+    ("--export-dynamic-symbol", Takes::Value),
+    ("--dynamic-list", Takes::Value),
+    ("--exclude-libs", Takes::Value),
+    ("--hash-style", Takes::Value),
+    ("--version-script", Takes::Value),
+    ("--rpath", Takes::Value),
+    ("--sysroot", Takes::Value),
+    ("--no-default-libs", Takes::Nothing),
+    ("--nostdlib", Takes::Nothing),
+    ("--as-needed", Takes::Nothing),
+    ("--Bstatic", Takes::Nothing),
+    ("--static", Takes::Nothing),
+    ("--dn", Takes::Nothing),
+    ("--non_shared", Takes::Nothing),
+    ("--Bdynamic", Takes::Nothing),
+    ("--dy", Takes::Nothing),
+    ("--call_shared", Takes::Nothing),
+    ("--no-as-needed", Takes::Nothing),
+    ("--dynamic-linker", Takes::Value),
+    ("-I", Takes::Value),
+    ("-R", Takes::Value),
+    ("--defsym", Takes::Value),
+    ("--allow-multiple-definition", Takes::Nothing),
+    ("--symbol-ordering-file", Takes::Value),
+    ("--warn-symbol-ordering", Takes::Nothing),
+    ("--demangle", Takes::OptionalValue),
+    ("--no-demangle", Takes::Nothing),
+    ("--relax", Takes::Nothing),
+    ("--no-relax", Takes::Nothing),
+    ("--no-warn-symbol-ordering", Takes::Nothing),
+    ("--call-graph-ordering-file", Takes::Value),
+    ("--call-graph-profile-sort", Takes::OptionalValue),
+    ("--no-call-graph-profile-sort", Takes::Nothing),
+    ("--enable-new-dtags", Takes::Nothing),
+    ("--disable-new-dtags", Takes::Nothing),
+    ("--log-level", Takes::Value),
+    ("--ignore-unknown-flags", Takes::Nothing),
+    ("--noinhibit-exec", Takes::Nothing),
+    ("--pie", Takes::Nothing),
+    ("--no-pie", Takes::Nothing),
+    ("--static-pie", Takes::Nothing),
+    ("-m", Takes::Value),
+    ("--no-dynamic-linker", Takes::Nothing),
+    ("--plugin", Takes::Value),
+    ("--plugin-opt", Takes::Value),
+    ("--sort-section", Takes::Value),
+    ("--orphan-handling", Takes::Value),
+    ("--no-undefined", Takes::Nothing),
+    ("--unresolved-symbols", Takes::Value),
+    ("--image-base", Takes::Value),
+    ("-e", Takes::Value),
+    ("--entry", Takes::Value),
+    ("--oformat", Takes::Value),
+    ("--Ttext", Takes::Value),
+    ("--Tdata", Takes::Value),
+    ("--Tbss", Takes::Value),
+    ("--section-start", Takes::Value),
+    ("--section-lma", Takes::Value),
+    ("--diagnostics-format", Takes::Value),
+    ("--strip-debug", Takes::Nothing),
+    ("-S", Takes::Nothing),
+    ("--strip-all", Takes::Nothing),
+    ("-s", Takes::Nothing),
+    ("--gdb-index", Takes::Nothing),
+    ("--no-gdb-index", Takes::Nothing),
+    ("--no-ident", Takes::Nothing),
+    ("--check-sections", Takes::Nothing),
+    ("--no-check-sections", Takes::Nothing),
+    ("--package-metadata", Takes::Value),
+    ("-(", Takes::Nothing),
+    ("-)", Takes::Nothing),
+    ("--start-group", Takes::Nothing),
+    ("--end-group", Takes::Nothing),
+    // Compatibility no-ops.
+    ("--push-state", Takes::Nothing),
+    ("--pop-state", Takes::Nothing),
+    ("-O", Takes::Value),
+    ("--EL", Takes::Nothing),
+    ("--eh-frame-hdr", Takes::Nothing),
+    ("--build-id", Takes::OptionalValue),
+    ("--gc-sections", Takes::Nothing),
+    ("--no-gc-sections", Takes::Nothing),
+    ("--sort-common", Takes::OptionalValue),
+    ("--fatal-warnings", Takes::Nothing),
+    ("--color-diagnostics", Takes::OptionalValue),
+    ("--rpath-link", Takes::Value),
+    ("--warn-common", Takes::Nothing),
+    ("--compress-debug-sections", Takes::Value),
+    ("-X", Takes::Nothing),
+    ("-g", Takes::Nothing),
+];
+
+/// A linker argument after classification against [`FLAGS`].
+#[derive(Debug, Clone)]
+enum Arg {
+    /// A known flag by canonical spelling, with its value if it has one.
+    Flag(&'static str, Option<String>),
+    /// A positional input file.
+    Input(String),
 }
 
+/// Flags naming files read to configure the link.
+const OPTION_FILE_FLAGS: &[&str] = &[
+    "--version-script",
+    "--dynamic-list",
+    "--symbol-ordering-file",
+    "--call-graph-ordering-file",
+];
+
+/// Flags naming files the link writes besides its output.
+const REPORT_FILE_FLAGS: &[&str] = &[
+    "--Map",
+    "--why-extract",
+    "--size-report",
+    "--time-trace-file",
+    "--dependency-file",
+];
+
 impl Config {
+    /// Parse the given arguments (including the program name), expanding
+    /// any `@file` response files first. `--help`, `--version` and
+    /// unparsable arguments are returned as errors.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut response_files = Vec::new();
+        let args = expand_response_files(args, &mut response_files, 0)?;
+        Self::try_parse_from(args)?.classified(response_files)
+    }
+
+    fn classified(mut self, response_files: Vec<PathBuf>) -> Result<Self> {
+        self.parsed = classify(&self.args)?;
+        self.response_files = response_files;
+        Ok(self)
+    }
+
     pub fn output(&self) -> PathBuf {
-        let mut iter = self.args.iter();
-        while let Some(arg) = iter.next() {
-            if arg == "-o" {
-                if let Some(p) = iter.next() {
-                    return PathBuf::from(p);
+        self.flag_value("-o")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("a.out"))
+    }
+
+    /// Options that affect the link itself.
+    pub fn link_options(&self) -> Result<LinkOptions> {
+        self.warn_ignored();
+        let shared = self.has_flag("--shared") || self.has_flag("--Bshareable");
+        let z = self.z_options()?;
+        let emit_relocs = self.has_flag("--emit-relocs") || self.has_flag("-q");
+        // uld only writes .symtab for --emit-relocs, whose relocations refer to it.
+        let strip_all = self.has_flag("--strip-all") || self.has_flag("-s");
+        if strip_all && emit_relocs {
+            bail!("--strip-all and --emit-relocs may not be used together");
+        }
+        Ok(LinkOptions {
+            pad_sections: self.pad_sections()?,
+            output_kind: if shared {
+                OutputKind::SharedObject
+            } else if self.pie() {
+                OutputKind::Pie
+            } else {
+                OutputKind::Executable
+            },
+            soname: self
+                .flag_value("--soname")
+                .or(self.flag_value("-h"))
+                .map(str::to_string),
+            emit_relocs,
+            strip_debug: strip_all || self.has_flag("--strip-debug") || self.has_flag("-S"),
+            gdb_index: self.gdb_index(),
+            package_metadata: self.package_metadata()?,
+            lto_opt_level: self.lto_opt_level()?,
+            plugin: self.plugin(),
+            export_dynamic: self.export_dynamic(),
+            dynamic_exports: self.dynamic_exports()?,
+            exclude_libs: self
+                .flag_values("--exclude-libs")
+                .flat_map(|v| v.split([',', ':']))
+                .filter(|l| !l.is_empty())
+                .map(str::to_string)
+                .collect(),
+            hash_style: self.hash_style()?,
+            version_script: self.version_script()?,
+            rpath: self
+                .parsed
+                .iter()
+                .filter_map(|a| match a {
+                    Arg::Flag("--rpath" | "-R", Some(dir)) => Some(dir.clone()),
+                    _ => None,
+                })
+                .collect(),
+            new_dtags: self.new_dtags(),
+            dynamic_linker: self
+                .flag_value("--dynamic-linker")
+                .or(self.flag_value("-I"))
+                .map(str::to_string),
+            no_dynamic_linker: self.has_flag("--no-dynamic-linker")
+                || self.has_flag("--static-pie"),
+            defsyms: self.defsyms()?,
+            file_format: self.file_format()?,
+            z,
+            image_base: self.image_base(z.page_size())?,
+            entry: self.parsed.iter().rev().find_map(|a| match a {
+                Arg::Flag("-e" | "--entry", Some(v)) => Some(v.clone()),
+                _ => None,
+            }),
+            symbol_ordering: self.symbol_ordering()?,
+            warn_symbol_ordering: self.warn_symbol_ordering(),
+            demangle: self.demangle(),
+            no_relax: !self.relax(),
+            no_ident: self.has_flag("--no-ident"),
+            check_sections: self.check_sections(),
+            noinhibit_exec: self.has_flag("--noinhibit-exec"),
+            trace: self.has_flag("-t") || self.has_flag("--trace"),
+            verbose: self.verbose(),
+            trace_symbols: self
+                .flag_values("-y")
+                .chain(self.flag_values("--trace-symbol"))
+                .map(str::to_string)
+                .collect(),
+            undefined: self
+                .flag_values("-u")
+                .chain(self.flag_values("--undefined"))
+                .map(str::to_string)
+                .collect(),
+            require_defined: self
+                .flag_values("--require-defined")
+                .map(str::to_string)
+                .collect(),
+            sort_section: self.sort_section()?,
+            orphan_handling: self.orphan_handling()?,
+            unresolved_symbols: self.unresolved_symbols()?,
+            section_starts: self.section_starts()?,
+            section_lmas: self.section_lmas()?,
+            call_graph_sort: self.call_graph_sort()?,
+            call_graph_edges: self.call_graph_edges()?,
+        })
+    }
+
+    /// The `-z` keywords, with unknown ones reported and ignored as GNU ld does.
+    fn z_options(&self) -> Result<ZOptions> {
+        let mut z = ZOptions {
+            muldefs: self.has_flag("--allow-multiple-definition"),
+            defs: self.has_flag("--no-undefined"),
+            ..ZOptions::default()
+        };
+        for keyword in self.z_keywords() {
+            match keyword {
+                "relro" => z.relro = true,
+                "norelro" => z.relro = false,
+                "now" => z.now = true,
+                "lazy" => z.now = false,
+                "execstack" => z.exec_stack = true,
+                "noexecstack" => z.exec_stack = false,
+                "defs" => z.defs = true,
+                "undefs" => z.defs = false,
+                "muldefs" => z.muldefs = true,
+                "execute-only" => bail!("-z execute-only: not supported, text is always readable"),
+                "keep-text-section-prefix" => z.keep_text_section_prefix = true,
+                "ibt" => z.ibt = true,
+                "shstk" => z.shstk = true,
+                "force-ibt" => z.force_ibt = true,
+                "nokeep-text-section-prefix" => z.keep_text_section_prefix = false,
+                // Passed by compiler drivers; uld's layout does not vary with them.
+                "combreloc" | "nocombreloc" | "separate-code" | "noseparate-code" | "text"
+                | "notext" => {}
+                k if k.starts_with("max-page-size=") => {
+                    let size = parse_number(&k["max-page-size=".len()..])
+                        .with_context(|| format!("-z {}", k))?;
+                    if !size.is_power_of_two() {
+                        bail!("-z {}: not a power of two", k);
+                    }
+                    z.max_page_size = Some(size);
+                }
+                k if k.starts_with("stack-size=") => {
+                    let size = parse_number(&k["stack-size=".len()..])
+                        .with_context(|| format!("-z {}", k))?;
+                    z.stack_size = Some(size);
                 }
+                k if k.starts_with("common-page-size=") => {
+                    warn!("-z {}: not supported, ignored", k);
+                }
+                k => warn!("-z {}: unknown keyword, ignored", k),
             }
         }
-        PathBuf::from("a.out")
+        Ok(z)
     }
 
-    pub fn input_files(&self) -> Vec<PathBuf> {
-        let mut lib_paths = Vec::new();
-        let mut files = Vec::new();
-
-        let mut iter = self.args.iter();
-        while let Some(arg) = iter.next() {
-            if arg == "-o" {
-                iter.next();
-                continue;
-            }
-            if arg.starts_with("--") {
-                continue;
-            } // --start-group etc.
+    /// `--image-base=ADDRESS`, which must be page aligned.
+    fn image_base(&self, page_size: u64) -> Result<Option<u64>> {
+        let Some(v) = self.flag_value("--image-base") else {
+            return Ok(None);
+        };
+        let base = parse_number(v).with_context(|| format!("--image-base {}", v))?;
+        if base % page_size != 0 {
+            bail!(
+                "--image-base {:#x}: not a multiple of the page size {:#x}",
+                base,
+                page_size
+            );
+        }
+        Ok(Some(base))
+    }
 
-            if let Some(p) = arg.strip_prefix("-L") {
-                let path = if p.is_empty() {
-                    iter.next().map(|s| s.as_str()).unwrap_or("")
+    /// `--defsym NAME=VALUE` definitions, where `VALUE` is a number or the
+    /// name of another symbol.
+    fn defsyms(&self) -> Result<Vec<(String, SyntheticValue)>> {
+        self.flag_values("--defsym")
+            .map(|def| {
+                let (name, value) = def
+                    .split_once('=')
+                    .with_context(|| format!("--defsym {}: expected NAME=VALUE", def))?;
+                let value = if value.starts_with(|c: char| c.is_ascii_digit()) {
+                    SyntheticValue::Absolute(
+                        parse_number(value).with_context(|| format!("--defsym {}", def))?,
+                    )
                 } else {
-                    p
+                    SyntheticValue::Alias(value.to_string())
                 };
-                if !path.starts_with('-') {
-                    lib_paths.push(PathBuf::from(path));
+                Ok((name.to_string(), value))
+            })
+            .collect()
+    }
+
+    /// Output section addresses from `-Ttext`, `-Tdata`, `-Tbss` and
+    /// `--section-start=NAME=ADDRESS`; a later setting for the same section
+    /// wins.
+    fn section_starts(&self) -> Result<HashMap<String, u64>> {
+        let mut starts = HashMap::new();
+        for arg in &self.parsed {
+            let (name, addr) = match arg {
+                Arg::Flag("--Ttext", Some(v)) => (".text", v.as_str()),
+                Arg::Flag("--Tdata", Some(v)) => (".data", v.as_str()),
+                Arg::Flag("--Tbss", Some(v)) => (".bss", v.as_str()),
+                Arg::Flag("--section-start", Some(v)) => v
+                    .split_once('=')
+                    .with_context(|| format!("--section-start {}: expected NAME=ADDRESS", v))?,
+                _ => continue,
+            };
+            starts.insert(name.to_string(), parse_address(name, addr)?);
+        }
+        Ok(starts)
+    }
+
+    /// Load addresses from `--section-lma=NAME=ADDRESS`, for sections stored
+    /// somewhere other than where they run (e.g. `.data` in flash).
+    fn section_lmas(&self) -> Result<HashMap<String, u64>> {
+        let mut lmas = HashMap::new();
+        for v in self.flag_values("--section-lma") {
+            let (name, addr) = v
+                .split_once('=')
+                .with_context(|| format!("--section-lma {}: expected NAME=ADDRESS", v))?;
+            lmas.insert(name.to_string(), parse_address(name, addr)?);
+        }
+        Ok(lmas)
+    }
+
+    /// `-pie` / `-static-pie` / `--no-pie`: the last one wins.
+    fn pie(&self) -> bool {
+        self.parsed
+            .iter()
+            .rev()
+            .find_map(|a| match a {
+                Arg::Flag("--pie" | "--static-pie", _) => Some(true),
+                Arg::Flag("--no-pie", _) => Some(false),
+                _ => None,
+            })
+            .unwrap_or(false)
+    }
+
+    /// The last `-plugin-opt=O<n>`, which compiler drivers pass for `-flto`.
+    fn lto_opt_level(&self) -> Result<Option<u8>> {
+        let Some(level) = self
+            .flag_values("--plugin-opt")
+            .filter_map(|v| v.strip_prefix('O'))
+            .last()
+        else {
+            return Ok(None);
+        };
+        match level.parse() {
+            Ok(n @ 0..=3) => Ok(Some(n)),
+            _ => bail!("-plugin-opt=O{}: optimization level must be 0 to 3", level),
+        }
+    }
+
+    /// The `--package-metadata` JSON, with `%xx` escapes decoded so that
+    /// build systems can pass characters the shell or compiler driver
+    /// would mangle. An empty value asks for no note.
+    fn package_metadata(&self) -> Result<Option<String>> {
+        let Some(value) = self.flag_value("--package-metadata") else {
+            return Ok(None);
+        };
+        let mut bytes = Vec::new();
+        let mut rest = value.as_bytes();
+        while let Some((&b, tail)) = rest.split_first() {
+            let hex = tail.get(..2).and_then(|h| std::str::from_utf8(h).ok());
+            match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                Some(decoded) if b == b'%' => {
+                    bytes.push(decoded);
+                    rest = &tail[2..];
+                }
+                _ => {
+                    bytes.push(b);
+                    rest = tail;
                 }
-            } else if let Some(n) = arg.strip_prefix("-l") {
-                let name = if n.is_empty() {
-                    iter.next().map(|s| s.as_str()).unwrap_or("")
+            }
+        }
+        let Ok(json) = String::from_utf8(bytes) else {
+            bail!("--package-metadata: not valid UTF-8");
+        };
+        if json.is_empty() {
+            return Ok(None);
+        }
+        if let Err(e) = serde_json::from_str::<serde_json::Value>(&json) {
+            bail!("--package-metadata: invalid JSON: {}", e);
+        }
+        Ok(Some(json))
+    }
+
+    fn check_sections(&self) -> bool {
+        self.parsed
+            .iter()
+            .rev()
+            .find_map(|a| match a {
+                Arg::Flag("--check-sections", _) => Some(true),
+                Arg::Flag("--no-check-sections", _) => Some(false),
+                _ => None,
+            })
+            .unwrap_or(false)
+    }
+
+    fn gdb_index(&self) -> bool {
+        self.parsed
+            .iter()
+            .rev()
+            .find_map(|a| match a {
+                Arg::Flag("--gdb-index", _) => Some(true),
+                Arg::Flag("--no-gdb-index", _) => Some(false),
+                _ => None,
+            })
+            .unwrap_or(false)
+    }
+
+    /// Warn about flags that ask for output uld doesn't produce, which are
+    /// accepted because compiler drivers pass them by default.
+    fn warn_ignored(&self) {
+        let last = |on: &str, off: &str| {
+            self.parsed.iter().rev().find_map(|a| match a {
+                Arg::Flag(f, v) if *f == on => Some(v.as_deref() != Some(off)),
+                Arg::Flag(f, _) if *f == off => Some(false),
+                _ => None,
+            })
+        };
+        if last("--gc-sections", "--no-gc-sections") == Some(true) {
+            warn!("--gc-sections: not supported, ignored; unused sections are kept");
+        }
+        if self.has_flag("--eh-frame-hdr") {
+            warn!("--eh-frame-hdr: not supported, ignored; no .eh_frame_hdr is written");
+        }
+        if last("--build-id", "none") == Some(true) {
+            warn!("--build-id: not supported, ignored; no build ID note is written");
+        }
+    }
+
+    /// Whether to record the search path as `DT_RUNPATH` rather than `DT_RPATH`.
+    ///
+    /// The last of `--enable-new-dtags` and `--disable-new-dtags` wins; like GNU
+    /// ld, the default is `DT_RPATH`.
+    fn new_dtags(&self) -> bool {
+        self.parsed
+            .iter()
+            .rev()
+            .find_map(|a| match a {
+                Arg::Flag("--enable-new-dtags", _) => Some(true),
+                Arg::Flag("--disable-new-dtags", _) => Some(false),
+                _ => None,
+            })
+            .unwrap_or(false)
+    }
+
+    /// Symbol names from `--symbol-ordering-file`, one per line, with `#`
+    /// comments and blank lines skipped.
+    fn symbol_ordering(&self) -> Result<Vec<String>> {
+        let Some(path) = self.flag_value("--symbol-ordering-file") else {
+            return Ok(Vec::new());
+        };
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("read symbol ordering file {}", path))?;
+        Ok(text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Whether diagnostics demangle symbol names; on unless the last of
+    /// `--demangle[=STYLE]` and `--no-demangle` turns it off.
+    fn demangle(&self) -> bool {
+        self.parsed
+            .iter()
+            .rev()
+            .find_map(|a| match a {
+                Arg::Flag("--demangle", _) => Some(true),
+                Arg::Flag("--no-demangle", _) => Some(false),
+                _ => None,
+            })
+            .unwrap_or(true)
+    }
+
+    /// `--relax` / `--no-relax`, the last one winning; relaxation is on by
+    /// default.
+    fn relax(&self) -> bool {
+        self.parsed
+            .iter()
+            .rev()
+            .find_map(|a| match a {
+                Arg::Flag("--relax", _) => Some(true),
+                Arg::Flag("--no-relax", _) => Some(false),
+                _ => None,
+            })
+            .unwrap_or(true)
+    }
+
+    /// Whether symbols in the ordering file that can't be ordered are reported.
+    ///
+    /// The last of `--warn-symbol-ordering` and `--no-warn-symbol-ordering`
+    /// wins; like lld, the default is to warn.
+    fn warn_symbol_ordering(&self) -> bool {
+        self.parsed
+            .iter()
+            .rev()
+            .find_map(|a| match a {
+                Arg::Flag("--warn-symbol-ordering", _) => Some(true),
+                Arg::Flag("--no-warn-symbol-ordering", _) => Some(false),
+                _ => None,
+            })
+            .unwrap_or(true)
+    }
+
+    /// Whether sections are ordered by call-graph profile.
+    ///
+    /// The last of `--call-graph-profile-sort[=none|hfsort]` and
+    /// `--no-call-graph-profile-sort` wins; like lld, the default is `hfsort`.
+    fn call_graph_sort(&self) -> Result<bool> {
+        let last = self.parsed.iter().rev().find_map(|a| match a {
+            Arg::Flag("--call-graph-profile-sort", v) => Some(v.as_deref()),
+            Arg::Flag("--no-call-graph-profile-sort", _) => Some(Some("none")),
+            _ => None,
+        });
+        match last {
+            None | Some(None | Some("hfsort")) => Ok(true),
+            Some(Some("none")) => Ok(false),
+            Some(Some(v)) => bail!("--call-graph-profile-sort={}: expected none or hfsort", v),
+        }
+    }
+
+    /// The edges of `--call-graph-ordering-file`, which replace any
+    /// `.llvm.call-graph-profile` sections in the inputs.
+    fn call_graph_edges(&self) -> Result<Option<Vec<(String, String, u64)>>> {
+        let Some(path) = self.flag_value("--call-graph-ordering-file") else {
+            return Ok(None);
+        };
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("read call graph ordering file {}", path))?;
+        callgraph::parse_ordering_file(&text)
+            .map(Some)
+            .with_context(|| format!("--call-graph-ordering-file={}", path))
+    }
+
+    /// The parsed `--version-script=FILE`, if given.
+    fn version_script(&self) -> Result<Option<VersionScript>> {
+        let Some(path) = self.flag_value("--version-script") else {
+            return Ok(None);
+        };
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("read version script {}", path))?;
+        VersionScript::parse(&text)
+            .map(Some)
+            .with_context(|| format!("--version-script={}", path))
+    }
+
+    /// `--hash-style=sysv|gnu|both`.
+    fn hash_style(&self) -> Result<HashStyle> {
+        Ok(match self.flag_value("--hash-style") {
+            None | Some("sysv") => HashStyle::Sysv,
+            Some("gnu") => HashStyle::Gnu,
+            Some("both") => HashStyle::Both,
+            Some(v) => bail!(
+                "--hash-style: unknown style {} (expected sysv, gnu or both)",
+                v
+            ),
+        })
+    }
+
+    /// `--oformat=elf64-x86-64|binary|ihex|srec`.
+    fn file_format(&self) -> Result<FileFormat> {
+        Ok(match self.flag_value("--oformat") {
+            None | Some("elf64-x86-64") => FileFormat::Elf,
+            Some("binary") => FileFormat::Binary,
+            Some("ihex") => FileFormat::Ihex,
+            Some("srec") => FileFormat::Srec,
+            Some(v) => bail!(
+                "--oformat: unknown format {} (expected elf64-x86-64, binary, ihex or srec)",
+                v
+            ),
+        })
+    }
+
+    /// `--sort-section=name|alignment`.
+    fn sort_section(&self) -> Result<Option<SortSection>> {
+        Ok(match self.flag_value("--sort-section") {
+            None => None,
+            Some("name") => Some(SortSection::Name),
+            Some("alignment") => Some(SortSection::Alignment),
+            Some(v) => bail!(
+                "--sort-section: unknown sort {} (expected name or alignment)",
+                v
+            ),
+        })
+    }
+
+    /// `--unresolved-symbols=ignore-all|report-all|ignore-in-object-files|ignore-in-shared-libs`.
+    fn unresolved_symbols(&self) -> Result<Option<UnresolvedSymbols>> {
+        Ok(match self.flag_value("--unresolved-symbols") {
+            None => None,
+            Some("ignore-all") => Some(UnresolvedSymbols::IgnoreAll),
+            Some("report-all") => Some(UnresolvedSymbols::ReportAll),
+            Some("ignore-in-object-files") => Some(UnresolvedSymbols::IgnoreInObjectFiles),
+            Some("ignore-in-shared-libs") => Some(UnresolvedSymbols::IgnoreInSharedLibs),
+            Some(v) => bail!(
+                "--unresolved-symbols: unknown method {} (expected ignore-all, report-all, \
+                 ignore-in-object-files or ignore-in-shared-libs)",
+                v
+            ),
+        })
+    }
+
+    /// `--orphan-handling=place|warn|error|discard`.
+    fn orphan_handling(&self) -> Result<OrphanHandling> {
+        Ok(match self.flag_value("--orphan-handling") {
+            None | Some("place") => OrphanHandling::Place,
+            Some("warn") => OrphanHandling::Warn,
+            Some("error") => OrphanHandling::Error,
+            Some("discard") => OrphanHandling::Discard,
+            Some(v) => bail!(
+                "--orphan-handling: unknown mode {} (expected place, warn, error or discard)",
+                v
+            ),
+        })
+    }
+
+    /// Whether every defined global goes into `.dynsym` (`-E` / `--export-dynamic`).
+    ///
+    /// The last of `--export-dynamic` and `--no-export-dynamic` wins.
+    fn export_dynamic(&self) -> bool {
+        self.parsed
+            .iter()
+            .rev()
+            .find_map(|a| match a {
+                Arg::Flag("-E" | "--export-dynamic", _) => Some(true),
+                Arg::Flag("--no-export-dynamic", _) => Some(false),
+                _ => None,
+            })
+            .unwrap_or(false)
+    }
+
+    /// Glob patterns of symbols to export from `--export-dynamic-symbol=GLOB`
+    /// and `--dynamic-list=FILE`.
+    fn dynamic_exports(&self) -> Result<Vec<String>> {
+        let mut patterns: Vec<String> = self
+            .flag_values("--export-dynamic-symbol")
+            .map(str::to_string)
+            .collect();
+        for path in self.flag_values("--dynamic-list") {
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("read dynamic list {}", path))?;
+            patterns.extend(
+                parse_dynamic_list(&contents)
+                    .with_context(|| format!("--dynamic-list={}", path))?,
+            );
+        }
+        Ok(patterns)
+    }
+
+    /// `--pad-sections=ALIGN[+OFFSET]`: align each text section to `ALIGN` and
+    /// then shift it by `OFFSET` bytes.
+    fn pad_sections(&self) -> Result<Option<SectionPadding>> {
+        let Some(v) = self.flag_value("--pad-sections") else {
+            return Ok(None);
+        };
+        let (align, offset) = v.split_once('+').unwrap_or((v, "0"));
+        let align = parse_number(align).with_context(|| format!("--pad-sections={}", v))?;
+        let offset = parse_number(offset).with_context(|| format!("--pad-sections={}", v))?;
+        if !align.is_power_of_two() {
+            bail!("--pad-sections: alignment {} is not a power of two", align);
+        }
+        Ok(Some(SectionPadding { align, offset }))
+    }
+
+    /// Where to write the link map, from `-Map FILE`, `-Map=FILE` or `--Map=FILE`.
+    pub fn map_file(&self) -> Option<PathBuf> {
+        self.flag_value("--Map").map(PathBuf::from)
+    }
+
+    /// Whether the link map should be printed to stdout (`-M` / `--print-map`).
+    pub fn print_map(&self) -> bool {
+        self.has_flag("-M") || self.has_flag("--print-map")
+    }
+
+    /// Where to write the archive extraction report (`--why-extract=FILE`).
+    pub fn why_extract(&self) -> Option<PathBuf> {
+        self.flag_value("--why-extract").map(PathBuf::from)
+    }
+
+    /// Where to write the JSON size report (`--size-report=FILE`).
+    pub fn size_report(&self) -> Option<PathBuf> {
+        self.flag_value("--size-report").map(PathBuf::from)
+    }
+
+    /// Whether to print the symbol resolution log (`--verbose`).
+    pub fn verbose(&self) -> bool {
+        self.has_flag("--verbose")
+    }
+
+    /// Whether to print phase timings and counters (`--stats`).
+    pub fn stats(&self) -> bool {
+        self.has_flag("--stats")
+    }
+
+    /// Whether to show the progress of the link on stderr (`--progress`).
+    pub fn progress(&self) -> bool {
+        self.has_flag("--progress")
+    }
+
+    /// Where to write the Chrome trace requested with `--time-trace`:
+    /// `--time-trace-file=FILE`, or the output file name plus `.time-trace`.
+    pub fn time_trace(&self) -> Option<PathBuf> {
+        if !self.has_flag("--time-trace") && !self.has_flag("--time-trace-file") {
+            return None;
+        }
+        Some(self.flag_value("--time-trace-file").map_or_else(
+            || {
+                let mut path = self.output().into_os_string();
+                path.push(".time-trace");
+                PathBuf::from(path)
+            },
+            PathBuf::from,
+        ))
+    }
+
+    /// Number of worker threads requested with `--threads=N`.
+    pub fn threads(&self) -> Result<Option<usize>> {
+        let Some(v) = self.flag_value("--threads") else {
+            return Ok(None);
+        };
+        match v.parse() {
+            Ok(n) if n > 0 => Ok(Some(n)),
+            _ => bail!("--threads={}: expected a positive number", v),
+        }
+    }
+
+    /// Where to write the tarball requested with `--reproduce=FILE`.
+    pub fn reproduce(&self) -> Option<PathBuf> {
+        self.flag_value("--reproduce").map(PathBuf::from)
+    }
+
+    /// Arguments that repeat this link with the `files` it resolved, every
+    /// file read named by `rename`.
+    ///
+    /// `-l`, `-L` and `--sysroot` give way to the files they found, and the
+    /// output and reports are named without their directories, so the link
+    /// can run anywhere. `--reproduce` itself is dropped.
+    pub fn reproduce_args(&self, files: &[Input], rename: impl Fn(&Path) -> String) -> Vec<String> {
+        let mut args = Vec::new();
+        for arg in &self.parsed {
+            let Arg::Flag(flag, value) = arg else {
+                continue;
+            };
+            let value = match (*flag, value) {
+                (
+                    "--reproduce" | "-l" | "-L" | "--sysroot" | "--as-needed" | "--no-as-needed",
+                    _,
+                ) => continue,
+                (f, Some(v)) if OPTION_FILE_FLAGS.contains(&f) => Some(rename(Path::new(v))),
+                (f, Some(v)) if f == "-o" || REPORT_FILE_FLAGS.contains(&f) => Some(
+                    Path::new(v)
+                        .file_name()
+                        .map_or_else(|| v.clone(), |n| n.to_string_lossy().into_owned()),
+                ),
+                (_, v) => v.clone(),
+            };
+            match value {
+                Some(v) if flag.starts_with("--") => args.push(format!("{}={}", flag, v)),
+                Some(v) => args.extend([flag.to_string(), v]),
+                None => args.push(flag.to_string()),
+            }
+        }
+        let mut as_needed = false;
+        for input in files {
+            if input.as_needed != as_needed {
+                as_needed = input.as_needed;
+                let flag = if as_needed {
+                    "--as-needed"
                 } else {
-                    n
+                    "--no-as-needed"
                 };
-                match find_library(name, &lib_paths) {
+                args.push(flag.to_string());
+            }
+            args.push(rename(&input.path));
+        }
+        args
+    }
+
+    /// Values of every occurrence of `flag`, in command-line order.
+    fn flag_values(&self, flag: &'static str) -> impl Iterator<Item = &str> {
+        self.parsed.iter().filter_map(move |a| match a {
+            Arg::Flag(f, Some(v)) if *f == flag => Some(v.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Value of the last occurrence of `flag`.
+    fn flag_value(&self, flag: &'static str) -> Option<&str> {
+        self.flag_values(flag).last()
+    }
+
+    fn has_flag(&self, flag: &str) -> bool {
+        self.parsed
+            .iter()
+            .any(|a| matches!(a, Arg::Flag(f, _) if *f == flag))
+    }
+
+    /// Keywords passed via `-z keyword` or `-zkeyword`.
+    fn z_keywords(&self) -> impl Iterator<Item = &str> {
+        self.flag_values("-z")
+    }
+
+    /// Input files in command-line order, with `-l` resolved against `-L`.
+    ///
+    /// `--as-needed` / `--no-as-needed` and `-Bstatic` / `-Bdynamic` toggle
+    /// positionally, as compiler drivers pass them around groups of libraries.
+    /// `-l` prefers `lib{name}.so` unless `-Bstatic` (or `-static`) is in effect.
+    ///
+    /// Inputs that turn out to be linker script stubs (such as glibc's
+    /// `libc.so`) are replaced by the files they name.
+    pub fn resolve_inputs(&self) -> Result<Inputs> {
+        let mut search = self.library_search();
+        let mut inputs = Inputs::default();
+        let mut as_needed = false;
+
+        for arg in &self.parsed {
+            match arg {
+                Arg::Flag("-L", Some(path)) => {
+                    let dir = search.in_sysroot(path);
+                    search.paths.push(dir);
+                }
+                Arg::Flag("-l", Some(name)) => match search.find(name) {
                     Some(p) => {
                         info!("-l{} -> {}", name, p.display());
-                        files.push(p);
+                        add_input(p, as_needed, &search, &mut inputs, 0)?;
                     }
                     None => warn!("-l{} not found", name),
+                },
+                Arg::Flag("--as-needed", _) => as_needed = true,
+                Arg::Flag("--no-as-needed", _) => as_needed = false,
+                Arg::Flag(
+                    "--Bstatic" | "--static" | "--static-pie" | "--dn" | "--non_shared",
+                    _,
+                ) => search.dynamic = false,
+                Arg::Flag("--Bdynamic" | "--dy" | "--call_shared", _) => search.dynamic = true,
+                Arg::Flag("--start-group" | "-(", _) => {
+                    if inputs.group.is_some() {
+                        bail!("--start-group: groups may not be nested");
+                    }
+                    inputs.group = Some(inputs.files.len());
+                }
+                Arg::Flag("--end-group" | "-)", _) => {
+                    if inputs.group.take().is_none() {
+                        bail!("--end-group without --start-group");
+                    }
+                }
+                Arg::Flag(..) => {}
+                Arg::Input(path) => {
+                    let p = PathBuf::from(path);
+                    if p.exists() {
+                        add_input(p, as_needed, &search, &mut inputs, 0)?;
+                    }
                 }
-            } else if arg.starts_with('-') {
+            }
+        }
+        Ok(inputs)
+    }
+
+    /// Library search before any `-L`, `-Bstatic` or `-Bdynamic`.
+    fn library_search(&self) -> LibrarySearch {
+        let sysroot = self.flag_value("--sysroot").map(PathBuf::from);
+        let defaults = if self.has_flag("--no-default-libs") || self.has_flag("--nostdlib") {
+            Vec::new()
+        } else {
+            // A bad -m is reported when the target is chosen.
+            let arch = self.emulation().ok().flatten();
+            default_library_dirs(
+                sysroot.as_deref(),
+                arch.unwrap_or(object::Architecture::X86_64),
+            )
+        };
+        LibrarySearch {
+            paths: Vec::new(),
+            defaults,
+            dynamic: true,
+            sysroot,
+        }
+    }
+
+    /// The linker plugin (`-plugin`) and its options. Libraries it adds are
+    /// searched for as at the end of the command line.
+    fn plugin(&self) -> Option<PluginOptions> {
+        let path = self.flag_value("--plugin")?;
+        let mut search = self.library_search();
+        for arg in &self.parsed {
+            match arg {
+                Arg::Flag("-L", Some(path)) => {
+                    let dir = search.in_sysroot(path);
+                    search.paths.push(dir);
+                }
+                Arg::Flag(
+                    "--Bstatic" | "--static" | "--static-pie" | "--dn" | "--non_shared",
+                    _,
+                ) => search.dynamic = false,
+                Arg::Flag("--Bdynamic" | "--dy" | "--call_shared", _) => search.dynamic = true,
+                _ => {}
+            }
+        }
+        Some(PluginOptions {
+            path: PathBuf::from(path),
+            options: self
+                .flag_values("--plugin-opt")
+                .map(str::to_string)
+                .collect(),
+            library_dirs: search.dirs(),
+            dynamic_libraries: search.dynamic,
+        })
+    }
+
+    /// Files named by options that were read to configure the link:
+    /// response files, version scripts, dynamic lists and ordering files.
+    pub fn option_files(&self) -> Vec<PathBuf> {
+        let mut files = self.response_files.clone();
+        for flag in OPTION_FILE_FLAGS {
+            files.extend(self.flag_values(flag).map(PathBuf::from));
+        }
+        files
+    }
+
+    /// The target named by `-m EMULATION`, if given.
+    pub fn emulation(&self) -> Result<Option<object::Architecture>> {
+        Ok(match self.flag_value("-m") {
+            None => None,
+            Some("elf_x86_64") => Some(object::Architecture::X86_64),
+            Some("elf_i386") => Some(object::Architecture::I386),
+            Some("armelf_linux_eabi") => Some(object::Architecture::Arm),
+            Some(v) => bail!(
+                "-m: unknown emulation {} (expected elf_x86_64, elf_i386 or armelf_linux_eabi)",
+                v
+            ),
+        })
+    }
+
+    /// `--diagnostics-format=text|json`.
+    pub fn diagnostics_format(&self) -> Result<DiagnosticsFormat> {
+        Ok(match self.flag_value("--diagnostics-format") {
+            None | Some("text") => DiagnosticsFormat::Text,
+            Some("json") => DiagnosticsFormat::Json,
+            Some(v) => bail!(
+                "--diagnostics-format: unknown format {} (expected text or json)",
+                v
+            ),
+        })
+    }
+
+    /// Where to write the Make dependency file (`--dependency-file=FILE`).
+    pub fn dependency_file(&self) -> Option<PathBuf> {
+        self.flag_value("--dependency-file").map(PathBuf::from)
+    }
+
+    /// Directory caching what parsing learns about input objects
+    /// (`--object-cache=DIR`).
+    pub fn object_cache(&self) -> Option<PathBuf> {
+        self.flag_value("--object-cache").map(PathBuf::from)
+    }
+}
+
+/// Where `-l` looks for libraries at a given point on the command line.
+#[derive(Debug, Clone)]
+struct LibrarySearch {
+    /// `-L` directories seen so far.
+    paths: Vec<PathBuf>,
+    /// System directories searched after `paths`.
+    defaults: Vec<PathBuf>,
+    /// Whether shared libraries may satisfy `-l` (`-Bdynamic`).
+    dynamic: bool,
+    /// Target root from `--sysroot`.
+    sysroot: Option<PathBuf>,
+}
+
+impl LibrarySearch {
+    fn find(&self, name: &str) -> Option<PathBuf> {
+        find_library(name, &self.dirs(), self.dynamic)
+    }
+
+    /// Every directory searched, in order.
+    fn dirs(&self) -> Vec<PathBuf> {
+        self.paths.iter().chain(&self.defaults).cloned().collect()
+    }
+
+    /// Resolve a path that may start with `=` or `$SYSROOT`, both of which
+    /// stand for the `--sysroot` directory.
+    fn in_sysroot(&self, path: &str) -> PathBuf {
+        let Some(rest) = path
+            .strip_prefix('=')
+            .or_else(|| path.strip_prefix("$SYSROOT"))
+        else {
+            return PathBuf::from(path);
+        };
+        match &self.sysroot {
+            Some(root) => root.join(rest.trim_start_matches('/')),
+            None => PathBuf::from(rest),
+        }
+    }
+}
+
+/// Library directories searched when no `-L` directory has the library, for
+/// the `arch` the `-m` emulation selects.
+fn builtin_library_dirs(arch: object::Architecture) -> &'static [&'static str] {
+    match arch {
+        object::Architecture::I386 => &[
+            "/usr/lib/i386-linux-gnu",
+            "/lib/i386-linux-gnu",
+            "/usr/lib32",
+            "/lib32",
+            "/usr/lib",
+            "/lib",
+        ],
+        object::Architecture::Arm => &[
+            "/usr/lib/arm-linux-gnueabihf",
+            "/lib/arm-linux-gnueabihf",
+            "/usr/lib/arm-linux-gnueabi",
+            "/lib/arm-linux-gnueabi",
+            "/usr/lib",
+            "/lib",
+        ],
+        _ => &[
+            "/usr/lib/x86_64-linux-gnu",
+            "/lib/x86_64-linux-gnu",
+            "/usr/lib64",
+            "/lib64",
+            "/usr/lib",
+            "/lib",
+        ],
+    }
+}
+
+/// Whether `arch` is the one uld runs on.
+fn is_host(arch: object::Architecture) -> bool {
+    let host = match std::env::consts::ARCH {
+        "x86_64" => object::Architecture::X86_64,
+        "x86" => object::Architecture::I386,
+        "arm" => object::Architecture::Arm,
+        _ => return false,
+    };
+    arch == host
+}
+
+/// `ld.so.conf` files may include each other; stop runaway recursion.
+const MAX_LD_SO_CONF_DEPTH: usize = 8;
+
+/// The built-in search directories for `arch`, followed by those listed in
+/// `/etc/ld.so.conf`, all under `sysroot` if given. Without a sysroot,
+/// `ld.so.conf` is the host's and only read when linking for the host.
+fn default_library_dirs(sysroot: Option<&Path>, arch: object::Architecture) -> Vec<PathBuf> {
+    let rooted = |p: &str| match sysroot {
+        Some(root) => root.join(p.trim_start_matches('/')),
+        None => PathBuf::from(p),
+    };
+    let mut dirs: Vec<PathBuf> = builtin_library_dirs(arch)
+        .iter()
+        .map(|d| rooted(d))
+        .collect();
+    let mut conf = Vec::new();
+    if sysroot.is_some() || is_host(arch) {
+        read_ld_so_conf(&rooted("/etc/ld.so.conf"), &rooted, &mut conf, 0);
+    }
+    for dir in conf {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs.retain(|d| d.is_dir());
+    dirs
+}
+
+/// Append the directories listed in an `ld.so.conf` file, following
+/// `include` lines (whose file name may contain `*` and `?`).
+///
+/// A missing or unreadable file contributes nothing.
+fn read_ld_so_conf(
+    path: &Path,
+    rooted: &dyn Fn(&str) -> PathBuf,
+    out: &mut Vec<PathBuf>,
+    depth: usize,
+) {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return;
+    };
+    if depth >= MAX_LD_SO_CONF_DEPTH {
+        warn!("{}: include nesting too deep", path.display());
+        return;
+    }
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if let Some(pattern) = line.strip_prefix("include") {
+            let pattern = pattern.trim();
+            let pattern = match pattern.starts_with('/') {
+                true => rooted(pattern),
+                false => path.parent().unwrap_or(Path::new("/")).join(pattern),
+            };
+            let (Some(dir), Some(glob)) = (pattern.parent(), pattern.file_name()) else {
                 continue;
+            };
+            let glob = glob.to_string_lossy();
+            let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.file_name()
+                        .is_some_and(|f| glob_match(&glob, &f.to_string_lossy()))
+                })
+                .collect();
+            files.sort();
+            for f in files {
+                read_ld_so_conf(&f, rooted, out, depth + 1);
+            }
+        } else if line.starts_with('/') {
+            out.push(rooted(line));
+        }
+    }
+}
+
+/// Linker script stubs may name other stubs; stop runaway recursion.
+const MAX_SCRIPT_DEPTH: usize = 16;
+
+/// Add `path` to `out`, expanding it in place if it is a linker script stub.
+fn add_input(
+    path: PathBuf,
+    as_needed: bool,
+    search: &LibrarySearch,
+    out: &mut Inputs,
+    depth: usize,
+) -> Result<()> {
+    let mut head = [0u8; 8];
+    let n = File::open(&path)
+        .and_then(|mut f| f.read(&mut head))
+        .with_context(|| format!("open {}", path.display()))?;
+    out.opened.push(path.clone());
+    if !script::is_script(&head[..n]) {
+        out.files.push(Input::new(path, as_needed, out.group));
+        return Ok(());
+    }
+    if depth >= MAX_SCRIPT_DEPTH {
+        bail!("linker script nesting too deep at {}", path.display());
+    }
+
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("{}: unrecognized file format", path.display()))?;
+    let script = InputScript::parse(&text).with_context(|| format!("parse {}", path.display()))?;
+    let mut search = search.clone();
+    search
+        .paths
+        .extend(script.search_dirs.iter().map(PathBuf::from));
+    let dir = path.parent().unwrap_or(Path::new("."));
+    // Absolute paths in a script that lives inside the sysroot are relative to it.
+    let script_in_sysroot = search
+        .sysroot
+        .as_ref()
+        .is_some_and(|root| path.starts_with(root));
+
+    for input in &script.inputs {
+        let found = match input.name.strip_prefix("-l") {
+            Some(lib) => search.find(lib),
+            None => {
+                let p = search.in_sysroot(&input.name);
+                let p = match &search.sysroot {
+                    Some(root) if script_in_sysroot && p.is_absolute() && !p.starts_with(root) => {
+                        root.join(p.strip_prefix("/").unwrap_or(&p))
+                    }
+                    _ => p,
+                };
+                if p.is_absolute() {
+                    p.exists().then_some(p)
+                } else {
+                    // Relative names are tried next to the script, then on the search path.
+                    std::iter::once(dir.join(&p))
+                        .chain(search.dirs().iter().map(|d| d.join(&p)))
+                        .find(|c| c.exists())
+                }
+            }
+        };
+        let found = found
+            .with_context(|| format!("cannot find {} (named by {})", input.name, path.display()))?;
+        info!("{} -> {}", path.display(), found.display());
+        add_input(found, as_needed || input.as_needed, &search, out, depth + 1)?;
+    }
+    Ok(())
+}
+
+/// Expand `path`, a library found outside the command line, if it is a
+/// linker script stub, searching `dirs` for the libraries it names.
+pub fn expand_library(path: PathBuf, dirs: &[PathBuf], dynamic: bool) -> Result<Vec<Input>> {
+    let search = LibrarySearch {
+        paths: dirs.to_vec(),
+        defaults: Vec::new(),
+        dynamic,
+        sysroot: None,
+    };
+    let mut out = Inputs::default();
+    add_input(path, false, &search, &mut out, 0)?;
+    Ok(out.files)
+}
+
+/// An input file and the positional state it was given under.
+#[derive(Debug, Clone)]
+pub struct Input {
+    pub path: PathBuf,
+    /// Only record a shared library as `DT_NEEDED` if it resolves a reference.
+    pub as_needed: bool,
+    /// The `--start-group` group the file is in, numbered by its first file.
+    /// Archives in a group are searched again until nothing more is needed
+    /// from them.
+    pub group: Option<usize>,
+}
+
+impl Input {
+    fn new(path: PathBuf, as_needed: bool, group: Option<usize>) -> Self {
+        Self {
+            path,
+            as_needed,
+            group,
+        }
+    }
+}
+
+/// The input files of a link, as found by [`Config::resolve_inputs`].
+#[derive(Debug, Default)]
+pub struct Inputs {
+    /// Files to link, in command-line order.
+    pub files: Vec<Input>,
+    /// Every file opened to find them, including linker script stubs.
+    pub opened: Vec<PathBuf>,
+    /// The group files are being added to.
+    group: Option<usize>,
+}
+
+fn lookup(name: &str) -> Option<(&'static str, Takes)> {
+    FLAGS.iter().find(|(f, _)| *f == name).copied()
+}
+
+/// Classify raw arguments against the driver flag table.
+///
+/// Unknown flags are an error unless `--ignore-unknown-flags` is given, in
+/// which case they are skipped with a warning.
+fn classify(args: &[String]) -> Result<Vec<Arg>> {
+    let ignore_unknown = args.iter().any(|a| a == "--ignore-unknown-flags");
+    let mut out = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if !arg.starts_with('-') || arg == "-" {
+            out.push(Arg::Input(arg.clone()));
+            continue;
+        }
+
+        // Long option: `--name`, `-name`, either with `=value`.
+        let body = arg.strip_prefix("--").unwrap_or(&arg[1..]);
+        let (name, inline) = match body.split_once('=') {
+            Some((n, v)) => (n, Some(v)),
+            None => (body, None),
+        };
+        let long = lookup(&format!("--{}", name)).filter(|_| name.len() > 1);
+        // Single letter: `-x`, `-xVALUE`.
+        let short = || {
+            let flag = arg.get(..2)?;
+            let (f, takes) = lookup(flag)?;
+            let rest = &arg[2..];
+            (rest.is_empty() || takes == Takes::Value).then_some((f, takes, rest))
+        };
+
+        let (flag, value) = if let Some((flag, takes)) = long {
+            match (takes, inline) {
+                (Takes::Nothing, Some(_)) => bail!("{} does not take a value", flag),
+                (Takes::Value, None) => {
+                    let v = iter
+                        .next()
+                        .with_context(|| format!("{} expects a value", flag))?;
+                    (flag, Some(v.clone()))
+                }
+                (_, v) => (flag, v.map(str::to_string)),
+            }
+        } else if let Some((flag, takes, rest)) = (!arg.starts_with("--")).then(short).flatten() {
+            if takes == Takes::Value && rest.is_empty() {
+                let v = iter
+                    .next()
+                    .with_context(|| format!("{} expects a value", flag))?;
+                (flag, Some(v.clone()))
             } else {
-                let p = PathBuf::from(arg);
-                if p.exists() {
-                    files.push(p);
+                (flag, (!rest.is_empty()).then(|| rest.to_string()))
+            }
+        } else if ignore_unknown {
+            warn!("ignoring unknown flag: {}", arg);
+            continue;
+        } else {
+            bail!(
+                "unknown flag: {} (pass --ignore-unknown-flags to skip it)",
+                arg
+            );
+        };
+        out.push(Arg::Flag(flag, value));
+    }
+    Ok(out)
+}
+
+/// Parse the address of output section `name`, which is hexadecimal with or
+/// without a `0x` prefix.
+fn parse_address(name: &str, addr: &str) -> Result<u64> {
+    let hex = addr.strip_prefix("0x").unwrap_or(addr);
+    u64::from_str_radix(hex, 16).with_context(|| format!("{}: bad address {}", name, addr))
+}
+
+/// Parse a decimal or `0x`-prefixed hexadecimal number.
+fn parse_number(s: &str) -> Result<u64> {
+    Ok(match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16)?,
+        None => s.parse()?,
+    })
+}
+
+/// Parse a dynamic list: `{ sym; glob*; };`, with `/* */` and `#` comments.
+fn parse_dynamic_list(contents: &str) -> Result<Vec<String>> {
+    let mut text = String::new();
+    let mut rest = contents;
+    while let Some((before, after)) = rest.split_once("/*") {
+        text.push_str(before);
+        rest = after
+            .split_once("*/")
+            .context("unterminated comment in dynamic list")?
+            .1;
+    }
+    text.push_str(rest);
+    let text: String = text
+        .lines()
+        .map(|l| l.split('#').next().unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let body = text
+        .trim()
+        .strip_prefix('{')
+        .and_then(|b| b.trim_end().strip_suffix(';'))
+        .and_then(|b| b.trim_end().strip_suffix('}'))
+        .context("expected `{ symbol; ... };`")?;
+    Ok(body
+        .split(';')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Response files may include other response files; stop runaway recursion.
+const MAX_RESPONSE_FILE_DEPTH: usize = 16;
+
+/// Replace every `@file` argument with the arguments read from `file`.
+fn expand_response_files(
+    args: impl IntoIterator<Item = String>,
+    read: &mut Vec<PathBuf>,
+    depth: usize,
+) -> Result<Vec<String>> {
+    let mut out = Vec::new();
+    for arg in args {
+        let Some(path) = arg.strip_prefix('@') else {
+            out.push(arg);
+            continue;
+        };
+        if depth >= MAX_RESPONSE_FILE_DEPTH {
+            bail!("response file nesting too deep at @{}", path);
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("read response file {}", path))?;
+        read.push(PathBuf::from(path));
+        out.extend(expand_response_files(
+            split_response_file(&contents)?,
+            read,
+            depth + 1,
+        )?);
+    }
+    Ok(out)
+}
+
+/// Split response file contents into arguments using shell-style quoting.
+///
+/// Whitespace separates arguments, single quotes are literal, double quotes
+/// allow backslash escapes, and a backslash outside quotes escapes the next char.
+fn split_response_file(contents: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut cur = String::new();
+    let mut in_arg = false;
+    let mut chars = contents.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut cur));
+                    in_arg = false;
                 }
             }
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => cur.push(c),
+                        None => bail!("unterminated single quote in response file"),
+                    }
+                }
+            }
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => cur.push(c),
+                            None => bail!("unterminated double quote in response file"),
+                        },
+                        Some(c) => cur.push(c),
+                        None => bail!("unterminated double quote in response file"),
+                    }
+                }
+            }
+            '\\' => {
+                in_arg = true;
+                if let Some(c) = chars.next() {
+                    cur.push(c);
+                }
+            }
+            c => {
+                in_arg = true;
+                cur.push(c);
+            }
         }
-        files
     }
+    if in_arg {
+        args.push(cur);
+    }
+    Ok(args)
 }
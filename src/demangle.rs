@@ -0,0 +1,102 @@
+//! Symbol demangling and near-miss suggestions for diagnostics.
+//!
+//! Undefined and duplicate symbol errors show C++ (Itanium) and Rust names
+//! demangled, as `ld --demangle` does. For an undefined reference, the
+//! defined symbols are searched for the one that was probably meant: a
+//! one-character typo, the same function with a different signature, or a
+//! C/C++ linkage mismatch (a missing `extern "C"`).
+
+use std::borrow::Cow;
+
+/// The readable form of `name`, or `name` itself if it isn't mangled.
+pub fn demangle(name: &str) -> Cow<'_, str> {
+    if let Ok(rust) = rustc_demangle::try_demangle(name) {
+        // `{:#}` drops the trailing hash.
+        return Cow::Owned(format!("{:#}", rust));
+    }
+    if name.starts_with("_Z") {
+        if let Ok(symbol) = cpp_demangle::Symbol::new(name) {
+            if let Ok(s) = symbol.demangle(&Default::default()) {
+                return Cow::Owned(s);
+            }
+        }
+    }
+    Cow::Borrowed(name)
+}
+
+/// The qualified function name of a demangled C++ name, without the
+/// parameter list: `ns::foo` for `ns::foo(int)`.
+fn base_name(demangled: &str) -> &str {
+    demangled.split('(').next().unwrap_or(demangled)
+}
+
+/// Whether `a` and `b` differ by a single inserted, deleted, replaced or
+/// swapped character, or only in case.
+fn near_miss(a: &str, b: &str) -> bool {
+    if a == b {
+        return false;
+    }
+    if a.eq_ignore_ascii_case(b) {
+        return true;
+    }
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    match a.len() as isize - b.len() as isize {
+        0 => a[1..] == b[1..] || (a.len() >= 2 && a[0] == b[1] && a[1] == b[0] && a[2..] == b[2..]),
+        1 => a[1..] == *b,
+        -1 => *a == b[1..],
+        _ => false,
+    }
+}
+
+/// The defined symbols to suggest from, sorted and demangled once for all
+/// the undefined references reported together.
+pub struct Candidates<'a> {
+    defined: Vec<(&'a str, Cow<'a, str>)>,
+}
+
+impl<'a> Candidates<'a> {
+    pub fn new(defined: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut defined: Vec<&str> = defined.into_iter().collect();
+        defined.sort_unstable();
+        Self {
+            defined: defined.into_iter().map(|n| (n, demangle(n))).collect(),
+        }
+    }
+
+    /// What to suggest for an undefined reference to `name`.
+    ///
+    /// Returns the hint to print, naming the candidate with `show`, and the
+    /// candidate itself so the caller can say where it is defined.
+    pub fn suggest(&self, name: &str, show: impl Fn(&str) -> String) -> Option<(String, &'a str)> {
+        let demangled = demangle(name);
+        let mangled = demangled != name;
+
+        // Earlier kinds of near miss are the more likely ones.
+        for kind in 0..4 {
+            for (candidate, other) in &self.defined {
+                let candidate = *candidate;
+                let other_mangled = other != candidate;
+                let hint = match kind {
+                    0 if near_miss(name, candidate) => format!("did you mean: {}", show(candidate)),
+                    // The same function with different parameters.
+                    1 if mangled && other_mangled && base_name(other) == base_name(&demangled) => {
+                        format!("did you mean: {}", show(candidate))
+                    }
+                    // A C++ reference to a function defined in C.
+                    2 if mangled && !other_mangled && base_name(&demangled) == candidate => {
+                        format!("did you mean: extern \"C\" {}", candidate)
+                    }
+                    // A C reference to a function defined in C++.
+                    3 if !mangled && other_mangled && base_name(other) == name => {
+                        format!("did you mean to declare {} as extern \"C\"?", other)
+                    }
+                    _ => continue,
+                };
+                return Some((hint, candidate));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,52 @@
+//! Make dependency files (`--dependency-file`).
+//!
+//! The file names the output as depending on every file the linker read, in
+//! the format Make and Ninja read from `-MD`-style depfiles, so a build
+//! system re-links when any of them changes. Each dependency also gets an
+//! empty rule so that deleting one doesn't break the build.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Escape a path for a Make rule.
+fn escape(path: &Path) -> String {
+    let mut out = String::new();
+    for c in path.to_string_lossy().chars() {
+        match c {
+            ' ' | '#' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '$' => out.push_str("$$"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Write a rule making `output` depend on `deps` to `path`.
+///
+/// Files listed more than once are written once, where they first appear.
+pub fn write(path: &Path, output: &Path, deps: &[PathBuf]) -> Result<()> {
+    let mut unique: Vec<&PathBuf> = Vec::new();
+    for dep in deps {
+        if !unique.contains(&dep) {
+            unique.push(dep);
+        }
+    }
+
+    let f = File::create(path).with_context(|| format!("create {}", path.display()))?;
+    let mut out = BufWriter::new(f);
+    write!(out, "{}:", escape(output))?;
+    for dep in &unique {
+        write!(out, " \\\n  {}", escape(dep))?;
+    }
+    writeln!(out)?;
+    for dep in &unique {
+        writeln!(out, "\n{}:", escape(dep))?;
+    }
+    out.flush()?;
+    Ok(())
+}
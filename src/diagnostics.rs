@@ -0,0 +1,134 @@
+//! Structured link diagnostics.
+//!
+//! Errors the user has to fix in their inputs, such as undefined references,
+//! multiple definitions and relocation overflows, are built as
+//! [`Diagnostic`]s carrying the symbol, the object and the place they
+//! concern. The linker returns them inside a [`Report`], which prints as the
+//! usual text message. With
+//! `--diagnostics-format=json`, each one is written as a JSON object on a
+//! line of its own instead, so IDEs and build wrappers can show it inline.
+
+use serde_json::{json, Value};
+use std::fmt;
+use std::io::Write;
+
+/// How diagnostics are printed (`--diagnostics-format`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiagnosticsFormat {
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    UndefinedSymbol,
+    MultipleDefinition,
+    /// A relocated value that doesn't fit in its field.
+    RelocationOverflow,
+    /// An input whose headers contradict themselves, e.g. a relocation past
+    /// the end of its section.
+    MalformedInput,
+    /// Any other error, which only has a message.
+    Error,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::UndefinedSymbol => "undefined-symbol",
+            Kind::MultipleDefinition => "multiple-definition",
+            Kind::RelocationOverflow => "relocation-overflow",
+            Kind::MalformedInput => "malformed-input",
+            Kind::Error => "error",
+        }
+    }
+}
+
+/// A problem with the link, with as much of where it comes from as known.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub kind: Kind,
+    pub message: String,
+    /// The symbol concerned, as shown to the user.
+    pub symbol: Option<String>,
+    /// The input that references or defines the symbol.
+    pub file: Option<String>,
+    /// The input section of `file` and the offset in it, for a reference.
+    pub section: Option<String>,
+    pub offset: Option<u64>,
+    /// What was probably meant, e.g. a near-miss symbol name.
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(kind: Kind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            symbol: None,
+            file: None,
+            section: None,
+            offset: None,
+            suggestion: None,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut v = json!({
+            "severity": "error",
+            "kind": self.kind.name(),
+            "message": self.message,
+        });
+        let fields = [
+            ("symbol", self.symbol.clone().map(Value::from)),
+            ("file", self.file.clone().map(Value::from)),
+            ("section", self.section.clone().map(Value::from)),
+            ("offset", self.offset.map(Value::from)),
+            ("suggestion", self.suggestion.clone().map(Value::from)),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                v[key] = value;
+            }
+        }
+        v
+    }
+}
+
+/// A failed link: the text message for the terminal, and the diagnostics it
+/// is made of.
+#[derive(Debug)]
+pub struct Report {
+    text: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    pub fn new(text: String, diagnostics: Vec<Diagnostic>) -> Self {
+        Self { text, diagnostics }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl std::error::Error for Report {}
+
+/// Write `err` as JSON lines: the diagnostics of the [`Report`] it carries,
+/// or a single generic error with its message.
+pub fn write_json(out: &mut dyn Write, err: &anyhow::Error) -> anyhow::Result<()> {
+    let diagnostics = match err.chain().find_map(|e| e.downcast_ref::<Report>()) {
+        Some(report) => report.diagnostics.clone(),
+        None => vec![Diagnostic::new(Kind::Error, format!("{:#}", err))],
+    };
+    for d in diagnostics {
+        serde_json::to_writer(&mut *out, &d.to_json())?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
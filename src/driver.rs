@@ -0,0 +1,352 @@
+//! The link the command line asks for.
+//!
+//! [`main_from_args`] is the whole `uld` command, for programs that want to
+//! link in-process exactly as the binary would. [`link`] is its core: it
+//! maps the inputs named by a [`Config`], picks the backend for their
+//! target, links them and writes the output and every report the options
+//! ask for. Each link runs on a thread pool of its own (see
+//! [`threads::pool`]), so a host may link more than once.
+//!
+//! Build tools that take a C compiler as the linker, such as rustc with
+//! `linker = "uld"`, pass compiler driver arguments (`-Wl,--gc-sections`,
+//! `-nodefaultlibs`, `-fuse-ld=lld`) and leave the C runtime's start files
+//! and libraries to the compiler. Given such a line, `uld` runs the
+//! compiler (`$ULD_CC`, or `cc`) on it with itself in place of `ld`, so the
+//! compiler adds what it would for its own linker and `uld` links the
+//! result.
+
+use anyhow::{anyhow, bail, Context, Result};
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+use crate::arch::arm::Arm;
+use crate::arch::i686::I686;
+use crate::arch::x86_64::X86_64;
+use crate::arch::{self, Architecture};
+use crate::cache::ObjectCache;
+use crate::config::{Config, Input, Inputs};
+use crate::depfile;
+use crate::diagnostics::{self, DiagnosticsFormat};
+use crate::input::{self, ParsedFile};
+use crate::linker::Linker;
+use crate::progress::ProgressBar;
+use crate::report;
+use crate::reproduce;
+use crate::stats::{self, Counters, Trace};
+use crate::threads;
+
+/// Set for the compiler [`run_compiler`] starts, so the `uld` it runs as
+/// `ld` takes its arguments as a linker's.
+const IN_COMPILER: &str = "ULD_IN_COMPILER";
+
+/// Run `uld` with the command line `args` (starting with the program name)
+/// as the binary would, returning its exit status: errors are printed on
+/// stderr, and `--help` and `--version` print their text.
+pub fn main_from_args(args: &[OsString]) -> i32 {
+    match run(args) {
+        Ok(status) => status,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            1
+        }
+    }
+}
+
+fn run(args: &[OsString]) -> Result<i32> {
+    let args = args
+        .iter()
+        .map(|a| {
+            a.to_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("argument is not UTF-8: {}", a.to_string_lossy()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // `uld size-diff OLD NEW` compares two --size-report outputs.
+    if args.get(1).map(String::as_str) == Some("size-diff") {
+        let [old, new] = &args[2..] else {
+            bail!("usage: uld size-diff OLD.json NEW.json");
+        };
+        report::size_diff(
+            Path::new(old),
+            Path::new(new),
+            &mut std::io::stdout().lock(),
+        )?;
+        return Ok(0);
+    }
+    // `uld inspect REPORT` lists the placed sections of a --size-report.
+    if args.get(1).map(String::as_str) == Some("inspect") {
+        let [report] = &args[2..] else {
+            bail!("usage: uld inspect REPORT.json");
+        };
+        report::inspect(Path::new(report), &mut std::io::stdout().lock())?;
+        return Ok(0);
+    }
+    let rest = args.get(1..).unwrap_or_default();
+    if std::env::var_os(IN_COMPILER).is_none() && is_compiler_line(rest) {
+        return run_compiler(rest);
+    }
+
+    let config = match Config::from_args(args) {
+        Ok(config) => config,
+        Err(err) => match err.downcast_ref::<clap::Error>() {
+            Some(e) => {
+                let _ = e.print();
+                return Ok(e.exit_code());
+            }
+            None => return Err(err),
+        },
+    };
+
+    // A host linking in-process may have set up logging already.
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_new(&config.log_level).unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .try_init();
+
+    let format = config.diagnostics_format()?;
+    let result = threads::pool(config.threads()?).and_then(|(pool, _threads)| {
+        // The job slots are held until the link is done.
+        pool.install(|| link(&config))
+    });
+    match (result, format) {
+        (Ok(()), _) => Ok(0),
+        (Err(err), DiagnosticsFormat::Json) => {
+            diagnostics::write_json(&mut std::io::stderr().lock(), &err)?;
+            Ok(1)
+        }
+        (Err(err), DiagnosticsFormat::Text) => Err(err),
+    }
+}
+
+/// Whether `args` are a compiler driver's rather than a linker's: they pass
+/// linker flags through `-Wl,` or `-Xlinker`, or use options only a compiler
+/// takes.
+fn is_compiler_line(args: &[String]) -> bool {
+    args.iter().any(|a| {
+        a.starts_with("-Wl,")
+            || a.starts_with("-fuse-ld=")
+            || matches!(
+                a.as_str(),
+                "-Xlinker" | "-nodefaultlibs" | "-nostartfiles" | "-m64" | "-m32"
+            )
+    })
+}
+
+/// Run the compiler on the driver arguments `args` with `uld` as its `ld`,
+/// returning its exit status.
+///
+/// The compiler looks for `ld` in its `-B` directories first, so a
+/// temporary one holds a link to this program. `-fuse-ld=` is dropped: it
+/// would have the compiler look for another linker by name.
+fn run_compiler(args: &[String]) -> Result<i32> {
+    let cc = std::env::var_os("ULD_CC").unwrap_or_else(|| "cc".into());
+    let dir = ShimDir::new()?;
+    let status = Command::new(&cc)
+        .arg("-B")
+        .arg(&dir.0)
+        .args(args.iter().filter(|a| !a.starts_with("-fuse-ld=")))
+        .env(IN_COMPILER, "1")
+        .status()
+        .with_context(|| format!("running {}", cc.to_string_lossy()))?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// A temporary directory holding `ld`, a link to this program; removed
+/// when dropped.
+struct ShimDir(PathBuf);
+
+impl ShimDir {
+    /// Create a directory of its own, so no other user or link can have
+    /// put an `ld` in it first.
+    fn new() -> Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let exe = std::env::current_exe().context("finding the uld executable")?;
+        let shim = loop {
+            let dir = std::env::temp_dir().join(format!(
+                "uld-cc-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            ));
+            match std::fs::create_dir(&dir) {
+                Ok(()) => break ShimDir(dir),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e).with_context(|| format!("creating {}", dir.display())),
+            }
+        };
+        link_program(&exe, &shim.0.join("ld"))?;
+        Ok(shim)
+    }
+}
+
+impl Drop for ShimDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Make `link` run the program `exe`.
+#[cfg(unix)]
+fn link_program(exe: &Path, link: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(exe, link)
+        .with_context(|| format!("linking {} to {}", link.display(), exe.display()))
+}
+
+#[cfg(not(unix))]
+fn link_program(exe: &Path, link: &Path) -> Result<()> {
+    let _ = (exe, link);
+    bail!("compiler driver arguments are only supported on Unix hosts")
+}
+
+/// Link as `config` says and write the output and any reports, on the
+/// current thread pool.
+pub fn link(config: &Config) -> Result<()> {
+    let inputs = config.resolve_inputs()?;
+    let files = &inputs.files;
+    if files.is_empty() {
+        anyhow::bail!("no input files");
+    }
+    if let Some(path) = config.reproduce() {
+        reproduce::write(&path, config, files)?;
+    }
+
+    let mut trace = Trace::new();
+
+    // Memory-map files
+    let mmaps: Vec<_> = trace.time("load", || {
+        files
+            .par_iter()
+            .map(|input| {
+                let p = &input.path;
+                info!("Loading: {}", p.display());
+                let m = input::map_file(p).with_context(|| format!("open {}", p.display()))?;
+                Ok((input, m))
+            })
+            .collect::<Result<_>>()
+    })?;
+
+    // Pick the backend at run time; the link itself is generic over it.
+    let files: Vec<_> = mmaps
+        .iter()
+        .map(|(input, m)| (input.path.as_path(), &m[..]))
+        .collect();
+    match arch::detect(&files, config.emulation()?)? {
+        object::Architecture::X86_64 => link_for(X86_64, config, &inputs, &mmaps, trace),
+        object::Architecture::I386 => link_for(I686, config, &inputs, &mmaps, trace),
+        object::Architecture::Arm => link_for(Arm, config, &inputs, &mmaps, trace),
+        other => anyhow::bail!("unsupported target {:?}", other),
+    }
+}
+
+/// Link the mapped inputs for the target `arch` and write the output and
+/// any reports.
+fn link_for<A: Architecture>(
+    arch: A,
+    config: &Config,
+    inputs: &Inputs,
+    mmaps: &[(&Input, Mmap)],
+    mut trace: Trace,
+) -> Result<()> {
+    let options = config.link_options()?;
+
+    // Parse the inputs in parallel, then add them in order. Errors are
+    // reported for the first failing input on the command line.
+    let cache = config
+        .object_cache()
+        .map(|dir| ObjectCache::open(&dir))
+        .transpose()?;
+    let parsed: Vec<_> = trace.time("parse", || {
+        mmaps
+            .par_iter()
+            .map(|(input, m)| input::parse_file_with(&input.path, m, A::arch(), cache.as_ref()))
+            .collect()
+    });
+
+    // Link
+    let mut linker = Linker::with_options(arch, options);
+    if config.progress() {
+        linker.set_progress(Box::new(ProgressBar::new(mmaps.len())));
+    }
+    // Objects given in a row are added together, to resolve their symbols
+    // in parallel.
+    let linked = trace.time("symbols", || -> Result<()> {
+        let mut objects = Vec::new();
+        let mut group = None;
+        for ((input, _), parsed) in mmaps.iter().zip(parsed) {
+            if input.group != group {
+                linker.add_objects(std::mem::take(&mut objects))?;
+                linker.end_group()?;
+                if input.group.is_some() {
+                    linker.start_group();
+                }
+                group = input.group;
+            }
+            match parsed {
+                Ok(ParsedFile::Object(obj)) => objects.push((input.path.as_path(), obj)),
+                parsed => {
+                    linker.add_objects(std::mem::take(&mut objects))?;
+                    linker.set_as_needed(input.as_needed);
+                    linker.add_parsed(&input.path, parsed?)?;
+                }
+            }
+        }
+        linker.add_objects(objects)?;
+        linker.end_group()
+    });
+    let linked = linked.and_then(|()| linker.link_with_trace(&mut trace));
+    // Traced inputs are printed even when the link fails, as they help find
+    // out why.
+    linker.write_trace(&mut std::io::stdout().lock())?;
+    linked?;
+    let output_bytes = trace.time("write", || linker.write(&config.output()))?;
+
+    if let Some(path) = config.map_file() {
+        let mut f = report::create(&path)?;
+        linker.write_map(&mut f)?;
+        f.flush()?;
+    }
+    if let Some(path) = config.why_extract() {
+        let mut f = report::create(&path)?;
+        linker.write_why_extract(&mut f)?;
+        f.flush()?;
+    }
+    if let Some(path) = config.size_report() {
+        let mut f = report::create(&path)?;
+        linker.write_size_report(&mut f)?;
+        f.flush()?;
+    }
+    if config.verbose() {
+        linker.write_resolution_log(&mut std::io::stdout().lock())?;
+    }
+    if config.print_map() {
+        linker.write_map(&mut std::io::stdout().lock())?;
+    }
+    if let Some(path) = config.dependency_file() {
+        let mut deps = config.option_files();
+        deps.extend(inputs.opened.iter().cloned());
+        depfile::write(&path, &config.output(), &deps)?;
+    }
+    if let Some(path) = config.time_trace() {
+        let mut f = report::create(&path)?;
+        trace.write_chrome(&mut f)?;
+        f.flush()?;
+    }
+    if config.stats() {
+        let counters = Counters {
+            output_bytes,
+            ..linker.counters()
+        };
+        stats::write_stats(&mut std::io::stderr().lock(), &trace, &counters)?;
+    }
+
+    info!("Wrote: {}", config.output().display());
+    Ok(())
+}
@@ -0,0 +1,347 @@
+//! Dynamic linking metadata.
+//!
+//! Builds the contents of `.dynsym`, `.dynstr`, `.hash`, `.gnu.hash`,
+//! `.rela.dyn`, `.rela.plt` and `.dynamic`, which the runtime loader reads when mapping a
+//! shared object.
+
+use anyhow::{Context, Result};
+use object::elf;
+use object::read::{Object, ObjectSection, ObjectSymbol};
+use object::{Endian, Endianness};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::version;
+
+/// Size of an `Elf64_Sym` entry.
+pub const SYM_SIZE: u64 = 24;
+/// Size of an `Elf64_Rela` entry.
+pub const RELA_SIZE: u64 = 24;
+/// Size of an `Elf64_Dyn` entry.
+pub const DYN_SIZE: u64 = 16;
+
+/// A NUL-terminated string table such as `.dynstr`.
+#[derive(Debug, Clone)]
+pub struct StringTable {
+    data: Vec<u8>,
+    offsets: HashMap<String, u32>,
+}
+
+impl Default for StringTable {
+    fn default() -> Self {
+        // Offset 0 is always the empty string.
+        Self {
+            data: vec![0],
+            offsets: HashMap::new(),
+        }
+    }
+}
+
+impl StringTable {
+    /// Add `s` (deduplicated) and return its offset.
+    pub fn add(&mut self, s: &str) -> u32 {
+        if let Some(&off) = self.offsets.get(s) {
+            return off;
+        }
+        let off = self.data.len() as u32;
+        self.data.extend_from_slice(s.as_bytes());
+        self.data.push(0);
+        self.offsets.insert(s.to_string(), off);
+        off
+    }
+
+    /// Offset of a previously added string.
+    pub fn offset(&self, s: &str) -> Option<u32> {
+        self.offsets.get(s).copied()
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// An entry in `.dynsym`.
+#[derive(Debug, Clone)]
+pub struct DynamicSymbol {
+    pub name: String,
+    pub value: u64,
+    pub size: u64,
+    /// `st_info`: binding and type.
+    pub info: u8,
+    /// `st_other`: the `STV_*` visibility.
+    pub other: u8,
+    /// Output section index, `SHN_UNDEF` or `SHN_ABS`.
+    pub shndx: u16,
+}
+
+/// The architecture-neutral kinds of dynamic relocation uld emits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicRelocKind {
+    /// Add the load base to the addend (`R_X86_64_RELATIVE`).
+    Relative,
+    /// Fill a GOT slot with a symbol's address (`R_X86_64_GLOB_DAT`).
+    GlobDat,
+    /// Store a symbol's address plus addend (`R_X86_64_64`).
+    Absolute,
+    /// Fill a GOT slot with a symbol's offset from the thread pointer
+    /// (`R_X86_64_TPOFF64`).
+    TpOff,
+    /// Fill a two-word GOT slot with a TLS descriptor (`R_X86_64_TLSDESC`).
+    TlsDesc,
+    /// Call the `ifunc` resolver at the addend and store its result
+    /// (`R_X86_64_IRELATIVE`).
+    IRelative,
+    /// Fill the `.got.plt` slot of a `.plt` entry with a function's address
+    /// (`R_X86_64_JUMP_SLOT`).
+    JumpSlot,
+}
+
+/// An entry in `.rela.dyn`.
+#[derive(Debug, Clone, Copy)]
+pub struct DynamicReloc {
+    /// Virtual address to patch.
+    pub offset: u64,
+    /// Relocation type, e.g. `R_X86_64_RELATIVE`.
+    pub r_type: u32,
+    /// Index into `.dynsym` (0 for `R_X86_64_RELATIVE`).
+    pub symbol: u32,
+    pub addend: i64,
+}
+
+/// The SysV ELF hash function used by `.hash`.
+pub fn sysv_hash(name: &str) -> u32 {
+    let mut h: u32 = 0;
+    for &b in name.as_bytes() {
+        h = (h << 4).wrapping_add(b as u32);
+        let g = h & 0xf000_0000;
+        if g != 0 {
+            h ^= g >> 24;
+        }
+        h &= !g;
+    }
+    h
+}
+
+/// Build a SysV `.hash` table for `names`, where `names[i]` is `.dynsym` entry `i + 1`.
+pub fn build_sysv_hash(names: &[String], e: Endianness) -> Vec<u8> {
+    let nchain = names.len() as u32 + 1;
+    let nbucket = nchain.max(1);
+    let mut buckets = vec![0u32; nbucket as usize];
+    let mut chains = vec![0u32; nchain as usize];
+    for (i, name) in names.iter().enumerate() {
+        let idx = i as u32 + 1;
+        let b = (sysv_hash(name) % nbucket) as usize;
+        chains[idx as usize] = buckets[b];
+        buckets[b] = idx;
+    }
+    let mut out = Vec::new();
+    for &v in [nbucket, nchain].iter().chain(&buckets).chain(&chains) {
+        out.extend_from_slice(&e.write_u32_bytes(v));
+    }
+    out
+}
+
+/// The GNU hash function used by `.gnu.hash` (DJB hash).
+pub fn gnu_hash(name: &str) -> u32 {
+    name.bytes()
+        .fold(5381u32, |h, b| h.wrapping_mul(33).wrapping_add(b as u32))
+}
+
+/// Number of `.gnu.hash` buckets for `n` hashed symbols.
+fn gnu_bucket_count(n: usize) -> u32 {
+    (n as u32 / 4).max(1)
+}
+
+/// Shift for the second bloom filter bit, as used by other linkers.
+const GNU_BLOOM_SHIFT: u32 = 26;
+
+/// Reorder `names` into the bucket order `.gnu.hash` requires.
+///
+/// Symbols sharing a bucket must be contiguous in `.dynsym`; the sort is
+/// stable so the output stays deterministic.
+pub fn sort_for_gnu_hash(names: &mut [String]) {
+    let nbuckets = gnu_bucket_count(names.len());
+    names.sort_by_key(|n| gnu_hash(n) % nbuckets);
+}
+
+/// Build a `.gnu.hash` table.
+///
+/// `names` are the hashed (defined) symbols in `.dynsym` order, already
+/// ordered by [`sort_for_gnu_hash`], and start at `.dynsym` index `symoffset`.
+pub fn build_gnu_hash(names: &[String], symoffset: u32, e: Endianness) -> Vec<u8> {
+    let nbuckets = gnu_bucket_count(names.len());
+    let bloom_size = (names.len() as u32 / 8).max(1).next_power_of_two();
+    let hashes: Vec<u32> = names.iter().map(|n| gnu_hash(n)).collect();
+
+    let mut bloom = vec![0u64; bloom_size as usize];
+    for &h in &hashes {
+        let word = &mut bloom[((h / 64) % bloom_size) as usize];
+        *word |= 1 << (h % 64);
+        *word |= 1 << ((h >> GNU_BLOOM_SHIFT) % 64);
+    }
+
+    let mut buckets = vec![0u32; nbuckets as usize];
+    let mut chains = vec![0u32; names.len()];
+    for (i, &h) in hashes.iter().enumerate() {
+        let b = (h % nbuckets) as usize;
+        if buckets[b] == 0 {
+            buckets[b] = symoffset + i as u32;
+        }
+        // The low bit marks the last symbol of a bucket's chain.
+        let last = hashes
+            .get(i + 1)
+            .is_none_or(|&n| n % nbuckets != h % nbuckets);
+        chains[i] = (h & !1) | last as u32;
+    }
+
+    let mut out = Vec::new();
+    for v in [nbuckets, symoffset, bloom_size, GNU_BLOOM_SHIFT] {
+        out.extend_from_slice(&e.write_u32_bytes(v));
+    }
+    for w in bloom {
+        out.extend_from_slice(&e.write_u64_bytes(w));
+    }
+    for &v in buckets.iter().chain(&chains) {
+        out.extend_from_slice(&e.write_u32_bytes(v));
+    }
+    out
+}
+
+/// Encode `.dynsym`, including the leading null symbol, in byte order `e`.
+pub fn encode_symbols(
+    symbols: &[DynamicSymbol],
+    strtab: &mut StringTable,
+    e: Endianness,
+) -> Vec<u8> {
+    let mut out = vec![0u8; SYM_SIZE as usize];
+    for s in symbols {
+        out.extend_from_slice(&e.write_u32_bytes(strtab.add(&s.name)));
+        out.push(s.info);
+        out.push(s.other);
+        out.extend_from_slice(&e.write_u16_bytes(s.shndx));
+        out.extend_from_slice(&e.write_u64_bytes(s.value));
+        out.extend_from_slice(&e.write_u64_bytes(s.size));
+    }
+    out
+}
+
+/// Encode `.rela.dyn` or `.rela.plt` in byte order `e`.
+pub fn encode_relocs(relocs: &[DynamicReloc], e: Endianness) -> Vec<u8> {
+    let mut out = Vec::new();
+    for r in relocs {
+        let info = ((r.symbol as u64) << 32) | r.r_type as u64;
+        out.extend_from_slice(&e.write_u64_bytes(r.offset));
+        out.extend_from_slice(&e.write_u64_bytes(info));
+        out.extend_from_slice(&e.write_i64_bytes(r.addend));
+    }
+    out
+}
+
+/// Encode `.dynamic` from `(tag, value)` pairs, appending `DT_NULL`.
+pub fn encode_dynamic(entries: &[(u32, u64)], e: Endianness) -> Vec<u8> {
+    let mut out = Vec::new();
+    for &(tag, val) in entries.iter().chain(&[(elf::DT_NULL, 0)]) {
+        out.extend_from_slice(&e.write_u64_bytes(tag as u64));
+        out.extend_from_slice(&e.write_u64_bytes(val));
+    }
+    out
+}
+
+/// A shared library given as a link input.
+///
+/// Its code is not copied into the output; references to the symbols it
+/// defines are left for the runtime loader to bind.
+#[derive(Debug, Clone)]
+pub struct SharedLibrary {
+    /// `DT_SONAME`, or the file name if the library has none.
+    pub soname: String,
+    /// Global symbols defined in its `.dynsym`, by plain name for default
+    /// versions and as `name@VERSION` for every versioned definition.
+    pub symbols: HashSet<String>,
+    /// The version of each versioned entry of [`Self::symbols`].
+    pub versions: HashMap<String, String>,
+    /// Only emit `DT_NEEDED` if a reference was resolved against it.
+    pub as_needed: bool,
+}
+
+impl SharedLibrary {
+    /// Read the soname and exported symbols of a parsed `ET_DYN` file.
+    pub fn parse(obj: &object::File, path: &Path, as_needed: bool) -> Result<Self> {
+        let e = obj.endianness();
+        let versym = match obj.section_by_name(".gnu.version") {
+            Some(s) => s.data()?,
+            None => &[],
+        };
+        let verdef = match (
+            obj.section_by_name(".gnu.version_d"),
+            obj.section_by_name(".dynstr"),
+        ) {
+            (Some(d), Some(strtab)) => version::read_verdef(d.data()?, strtab.data()?, e)?,
+            _ => HashMap::new(),
+        };
+        let (mut symbols, mut versions) = (HashSet::new(), HashMap::new());
+        for s in obj.dynamic_symbols() {
+            if s.is_undefined() || s.is_local() {
+                continue;
+            }
+            let name = s.name()?.to_string();
+            let off = s.index().0 * 2;
+            let ndx = versym.get(off..off + 2).map_or(elf::VER_NDX_GLOBAL, |v| {
+                e.read_u16_bytes(v.try_into().unwrap())
+            });
+            let Some(ver) = verdef.get(&(ndx & !elf::VERSYM_HIDDEN)) else {
+                symbols.insert(name);
+                continue;
+            };
+            // Hidden versions are only reachable by an explicit `name@VERSION`.
+            if ndx & elf::VERSYM_HIDDEN == 0 {
+                symbols.insert(name.clone());
+                versions.insert(name.clone(), ver.clone());
+            }
+            let versioned = format!("{}@{}", name, ver);
+            symbols.insert(versioned.clone());
+            versions.insert(versioned, ver.clone());
+        }
+        let soname = read_soname(obj)?.unwrap_or_else(|| {
+            path.file_name()
+                .map(|f| f.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+        Ok(Self {
+            soname,
+            symbols,
+            versions,
+            as_needed,
+        })
+    }
+}
+
+/// The `DT_SONAME` of a shared library, read from its `.dynamic` section.
+fn read_soname(obj: &object::File) -> Result<Option<String>> {
+    let (Some(dynamic), Some(dynstr)) = (
+        obj.section_by_name(".dynamic"),
+        obj.section_by_name(".dynstr"),
+    ) else {
+        return Ok(None);
+    };
+    let (dynamic, dynstr) = (dynamic.data()?, dynstr.data()?);
+    // `Elf32_Dyn` and `Elf64_Dyn` are a tag and a value of the word size.
+    let (e, word) = (obj.endianness(), if obj.is_64() { 8 } else { 4 });
+    let read = |field: &[u8]| match field.try_into() {
+        Ok(bytes) => e.read_u64_bytes(bytes),
+        Err(_) => e.read_u32_bytes(field.try_into().unwrap()) as u64,
+    };
+    for entry in dynamic.chunks_exact(2 * word) {
+        let tag = read(&entry[..word]);
+        let val = read(&entry[word..]) as usize;
+        if tag == elf::DT_NULL as u64 {
+            break;
+        }
+        if tag == elf::DT_SONAME as u64 {
+            let name = dynstr.get(val..).context("DT_SONAME out of range")?;
+            let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            return Ok(Some(String::from_utf8_lossy(&name[..end]).into_owned()));
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,641 @@
+//! `.gdb_index` sections (`--gdb-index`).
+//!
+//! gdb reads this table of compile units, address ranges and global names
+//! at startup instead of scanning all of `.debug_info`, which takes a long
+//! time for large programs. The index is built from the linked debug
+//! sections: the unit headers, `.debug_aranges` (or each unit's
+//! `DW_AT_low_pc` and `DW_AT_high_pc`), and the functions, variables and
+//! types each unit defines at its top level.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Context, Result};
+use object::{Endian, Endianness};
+
+/// Format version 7, which gdb has read since 7.5.
+const VERSION: u32 = 7;
+
+/// Symbol kinds, stored in bits 28-30 of a CU vector entry.
+const KIND_TYPE: u32 = 1;
+const KIND_VARIABLE: u32 = 2;
+const KIND_FUNCTION: u32 = 3;
+/// Set in a CU vector entry for names that are local to their unit.
+const STATIC: u32 = 1 << 31;
+
+// DWARF tags, attributes and forms the index needs.
+const DW_TAG_CLASS_TYPE: u64 = 0x02;
+const DW_TAG_ENUMERATION_TYPE: u64 = 0x04;
+const DW_TAG_STRUCTURE_TYPE: u64 = 0x13;
+const DW_TAG_TYPEDEF: u64 = 0x16;
+const DW_TAG_UNION_TYPE: u64 = 0x17;
+const DW_TAG_BASE_TYPE: u64 = 0x24;
+const DW_TAG_SUBPROGRAM: u64 = 0x2e;
+const DW_TAG_VARIABLE: u64 = 0x34;
+
+const DW_AT_NAME: u64 = 0x03;
+const DW_AT_LOW_PC: u64 = 0x11;
+const DW_AT_HIGH_PC: u64 = 0x12;
+const DW_AT_DECLARATION: u64 = 0x3c;
+const DW_AT_EXTERNAL: u64 = 0x3f;
+const DW_AT_STR_OFFSETS_BASE: u64 = 0x72;
+const DW_AT_ADDR_BASE: u64 = 0x73;
+const DW_AT_GNU_ADDR_BASE: u64 = 0x2133;
+
+const DW_UT_COMPILE: u8 = 0x01;
+const DW_UT_PARTIAL: u8 = 0x03;
+
+/// The linked debug sections, by name.
+pub type Sections<'a> = HashMap<&'a str, &'a [u8]>;
+
+/// Build `.gdb_index` from the linked debug sections in `sections`, whose
+/// contents are in byte order `e`. The index itself is little-endian.
+pub fn build(sections: &Sections, e: Endianness) -> Result<Vec<u8>> {
+    let section = |name: &str| sections.get(name).copied().unwrap_or_default();
+    let units = read_units(&section, e)?;
+    let index_of: HashMap<u64, u32> = units
+        .iter()
+        .enumerate()
+        .map(|(i, u)| (u.offset, i as u32))
+        .collect();
+
+    let mut ranges = read_aranges(section(".debug_aranges"), &index_of, e)?;
+    for (i, unit) in units.iter().enumerate() {
+        if ranges.iter().any(|r| r.2 == i as u32) {
+            continue;
+        }
+        if let Some((low, high)) = unit.pc {
+            if low != 0 && high > low {
+                ranges.push((low, high, i as u32));
+            }
+        }
+    }
+
+    let mut names: BTreeMap<&[u8], Vec<u32>> = BTreeMap::new();
+    for (i, unit) in units.iter().enumerate() {
+        for &(name, attrs) in &unit.names {
+            let entry = i as u32 | attrs;
+            let cus = names.entry(name).or_default();
+            if !cus.contains(&entry) {
+                cus.push(entry);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    let header = 6 * 4;
+    let cu_list = header;
+    let types = cu_list + units.len() * 16;
+    let address_area = types;
+    let symbol_table = address_area + ranges.len() * 20;
+    let slots = (names.len() * 4 / 3 + 1).next_power_of_two();
+    let constant_pool = symbol_table + slots * 8;
+    for v in [
+        VERSION,
+        cu_list as u32,
+        types as u32,
+        address_area as u32,
+        symbol_table as u32,
+        constant_pool as u32,
+    ] {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+    for unit in &units {
+        out.extend_from_slice(&unit.offset.to_le_bytes());
+        out.extend_from_slice(&unit.length.to_le_bytes());
+    }
+    for (low, high, cu) in &ranges {
+        out.extend_from_slice(&low.to_le_bytes());
+        out.extend_from_slice(&high.to_le_bytes());
+        out.extend_from_slice(&cu.to_le_bytes());
+    }
+
+    // The constant pool holds every CU vector, then every name.
+    let mut pool = Vec::new();
+    let mut vectors = Vec::new();
+    for cus in names.values() {
+        vectors.push(pool.len() as u32);
+        pool.extend_from_slice(&(cus.len() as u32).to_le_bytes());
+        for cu in cus {
+            pool.extend_from_slice(&cu.to_le_bytes());
+        }
+    }
+    let mut table = vec![(0u32, 0u32); slots];
+    let mut used = vec![false; slots];
+    let mask = slots as u32 - 1;
+    for (name, vector) in names.keys().zip(vectors) {
+        let offset = pool.len() as u32;
+        pool.extend_from_slice(name);
+        pool.push(0);
+        let h = hash(name);
+        let step = (h.wrapping_mul(17) & mask) | 1;
+        let mut slot = h & mask;
+        while used[slot as usize] {
+            slot = (slot + step) & mask;
+        }
+        used[slot as usize] = true;
+        table[slot as usize] = (offset, vector);
+    }
+    for (name, vector) in table {
+        out.extend_from_slice(&name.to_le_bytes());
+        out.extend_from_slice(&vector.to_le_bytes());
+    }
+    out.extend_from_slice(&pool);
+    Ok(out)
+}
+
+/// gdb's hash of symbol names in version 5 and later indexes.
+fn hash(name: &[u8]) -> u32 {
+    name.iter().fold(0u32, |r, &c| {
+        r.wrapping_mul(67)
+            .wrapping_add(c.to_ascii_lowercase() as u32)
+            .wrapping_sub(113)
+    })
+}
+
+/// A compile unit of `.debug_info`.
+struct Unit<'a> {
+    offset: u64,
+    length: u64,
+    /// `DW_AT_low_pc` and the end address from `DW_AT_high_pc`.
+    pc: Option<(u64, u64)>,
+    /// Names defined at the top level, with their kind and static bits.
+    names: Vec<(&'a [u8], u32)>,
+}
+
+/// An attribute value, as far as the index cares.
+#[derive(Clone, Copy)]
+enum Value<'a> {
+    Address(u64),
+    AddressIndex(u64),
+    Constant(u64),
+    String(&'a [u8]),
+    /// An offset in `.debug_str`.
+    StringOffset(u64),
+    /// An index into the unit's slice of `.debug_str_offsets`.
+    StringIndex(u64),
+    Flag(bool),
+    Other,
+}
+
+/// An abbreviation: a DIE's tag, whether it has children, and its
+/// attributes' names, forms and implicit constants.
+struct Abbrev {
+    tag: u64,
+    children: bool,
+    attrs: Vec<(u64, u64, i64)>,
+}
+
+/// The compile and partial units of `.debug_info`.
+fn read_units<'a>(section: &dyn Fn(&str) -> &'a [u8], e: Endianness) -> Result<Vec<Unit<'a>>> {
+    let info = section(".debug_info");
+    let mut units = Vec::new();
+    let mut r = Reader::new(info, e);
+    while r.pos < info.len() {
+        let offset = r.pos as u64;
+        let (length, offset_size) = r.initial_length()?;
+        let end = r
+            .pos
+            .checked_add(length as usize)
+            .filter(|&end| end <= info.len())
+            .context("truncated .debug_info unit")?;
+        let version = r.u16()?;
+        let (unit_type, abbrev_offset, addr_size) = match version {
+            2..=4 => {
+                let abbrev = r.offset(offset_size)?;
+                (DW_UT_COMPILE, abbrev, r.u8()?)
+            }
+            5 => {
+                let unit_type = r.u8()?;
+                let addr_size = r.u8()?;
+                (unit_type, r.offset(offset_size)?, addr_size)
+            }
+            _ => bail!(".debug_info: unsupported DWARF version {}", version),
+        };
+        if matches!(unit_type, DW_UT_COMPILE | DW_UT_PARTIAL) {
+            let form = Form {
+                version,
+                offset_size,
+                addr_size,
+            };
+            let abbrevs = read_abbrevs(section(".debug_abbrev"), abbrev_offset)?;
+            let mut dies = Reader::new(&info[..end], e);
+            dies.pos = r.pos;
+            let mut unit = read_dies(&mut dies, &abbrevs, form, section)?;
+            unit.offset = offset;
+            unit.length = end as u64 - offset;
+            units.push(unit);
+        }
+        r.pos = end;
+    }
+    Ok(units)
+}
+
+/// Walk the DIEs of one unit, collecting its address range and names.
+fn read_dies<'a>(
+    r: &mut Reader<'a>,
+    abbrevs: &HashMap<u64, Abbrev>,
+    form: Form,
+    section: &dyn Fn(&str) -> &'a [u8],
+) -> Result<Unit<'a>> {
+    let mut unit = Unit {
+        offset: 0,
+        length: 0,
+        pc: None,
+        names: Vec::new(),
+    };
+    let (mut str_base, mut addr_base) = (None, None);
+    let mut pending = Vec::new();
+    let mut depth = 0usize;
+    while r.pos < r.data.len() {
+        let code = r.uleb()?;
+        if code == 0 {
+            depth = depth.saturating_sub(1);
+            continue;
+        }
+        let abbrev = abbrevs
+            .get(&code)
+            .with_context(|| format!(".debug_info: unknown abbreviation {}", code))?;
+        let (mut name, mut low, mut high) = (None, None, None);
+        let (mut external, mut declaration) = (false, false);
+        for &(at, f, implicit) in &abbrev.attrs {
+            let value = form.read(r, f, implicit)?;
+            match (at, value) {
+                (DW_AT_NAME, v) => name = Some(v),
+                (DW_AT_LOW_PC, v) => low = Some(v),
+                (DW_AT_HIGH_PC, v) => high = Some(v),
+                (DW_AT_EXTERNAL, Value::Flag(f)) => external = f,
+                (DW_AT_DECLARATION, Value::Flag(f)) => declaration = f,
+                (DW_AT_STR_OFFSETS_BASE, Value::Constant(v)) => str_base = Some(v),
+                (DW_AT_ADDR_BASE | DW_AT_GNU_ADDR_BASE, Value::Constant(v)) => addr_base = Some(v),
+                _ => {}
+            }
+        }
+        if depth == 0 {
+            if let (Some(low), Some(high)) = (low, high) {
+                let e = r.e;
+                let low = resolve_address(low, addr_base, form, section, e)?;
+                // DWARF 4 and later may give the end as an offset from low_pc.
+                let high = match high {
+                    Value::Constant(size) => low.wrapping_add(size),
+                    v => resolve_address(v, addr_base, form, section, e)?,
+                };
+                unit.pc = Some((low, high));
+            }
+        } else if depth == 1 && !declaration {
+            let kind = match abbrev.tag {
+                DW_TAG_SUBPROGRAM => Some((KIND_FUNCTION, !external)),
+                DW_TAG_VARIABLE => Some((KIND_VARIABLE, !external)),
+                DW_TAG_BASE_TYPE
+                | DW_TAG_TYPEDEF
+                | DW_TAG_STRUCTURE_TYPE
+                | DW_TAG_CLASS_TYPE
+                | DW_TAG_UNION_TYPE
+                | DW_TAG_ENUMERATION_TYPE => Some((KIND_TYPE, true)),
+                _ => None,
+            };
+            if let (Some((kind, is_static)), Some(name)) = (kind, name) {
+                let attrs = (kind << 28) | if is_static { STATIC } else { 0 };
+                pending.push((name, attrs));
+            }
+        }
+        if abbrev.children {
+            depth += 1;
+        } else if depth == 0 {
+            break;
+        }
+    }
+    // String indices are relative to the unit's base, which may come after
+    // the names that use it.
+    for (name, attrs) in pending {
+        let name = match name {
+            Value::String(s) => s,
+            Value::StringOffset(offset) => cstr_at(section(".debug_str"), offset)?,
+            Value::StringIndex(i) => {
+                let base = str_base.unwrap_or(8);
+                let offsets = section(".debug_str_offsets");
+                let mut s = Reader::new(offsets, r.e);
+                s.pos = (base + i * form.offset_size as u64) as usize;
+                let offset = s.offset(form.offset_size)?;
+                cstr_at(section(".debug_str"), offset)?
+            }
+            _ => continue,
+        };
+        unit.names.push((name, attrs));
+    }
+    Ok(unit)
+}
+
+/// An address attribute, looking `DW_FORM_addrx` indices up in `.debug_addr`.
+fn resolve_address<'a>(
+    value: Value,
+    base: Option<u64>,
+    form: Form,
+    section: &dyn Fn(&str) -> &'a [u8],
+    e: Endianness,
+) -> Result<u64> {
+    match value {
+        Value::Address(a) | Value::Constant(a) => Ok(a),
+        Value::AddressIndex(i) => {
+            let mut r = Reader::new(section(".debug_addr"), e);
+            r.pos = (base.unwrap_or(8) + i * form.addr_size as u64) as usize;
+            r.uint(form.addr_size as usize)
+        }
+        _ => bail!(".debug_info: DW_AT_low_pc is not an address"),
+    }
+}
+
+/// The abbreviation table at `offset` in `.debug_abbrev`.
+fn read_abbrevs(data: &[u8], offset: u64) -> Result<HashMap<u64, Abbrev>> {
+    let mut r = Reader::new(data, Endianness::Little);
+    r.pos = offset as usize;
+    let mut abbrevs = HashMap::new();
+    loop {
+        let code = r.uleb()?;
+        if code == 0 {
+            return Ok(abbrevs);
+        }
+        let tag = r.uleb()?;
+        let children = r.u8()? != 0;
+        let mut attrs = Vec::new();
+        loop {
+            let (name, form) = (r.uleb()?, r.uleb()?);
+            if name == 0 && form == 0 {
+                break;
+            }
+            let implicit = if form == DW_FORM_IMPLICIT_CONST {
+                r.sleb()?
+            } else {
+                0
+            };
+            attrs.push((name, form, implicit));
+        }
+        abbrevs.insert(
+            code,
+            Abbrev {
+                tag,
+                children,
+                attrs,
+            },
+        );
+    }
+}
+
+/// The address ranges of `.debug_aranges`, as `(low, high, unit index)`.
+/// Ranges of discarded code, relocated to 0, are left out.
+fn read_aranges(
+    data: &[u8],
+    index_of: &HashMap<u64, u32>,
+    e: Endianness,
+) -> Result<Vec<(u64, u64, u32)>> {
+    let mut ranges = Vec::new();
+    let mut r = Reader::new(data, e);
+    while r.pos < data.len() {
+        let start = r.pos;
+        let (length, offset_size) = r.initial_length()?;
+        let end = r.pos.saturating_add(length as usize).min(data.len());
+        let _version = r.u16()?;
+        let info = r.offset(offset_size)?;
+        let addr_size = r.u8()? as usize;
+        let _segment_size = r.u8()?;
+        let tuple = 2 * addr_size.max(1);
+        r.pos = start + (r.pos - start).div_ceil(tuple) * tuple;
+        while r.pos + tuple <= end {
+            let (low, len) = (r.uint(addr_size)?, r.uint(addr_size)?);
+            if low == 0 && len == 0 {
+                break;
+            }
+            if let (Some(&cu), true) = (index_of.get(&info), low != 0 && len != 0) {
+                ranges.push((low, low + len, cu));
+            }
+        }
+        r.pos = end;
+    }
+    Ok(ranges)
+}
+
+/// The NUL-terminated string at `offset` in `data`.
+fn cstr_at(data: &[u8], offset: u64) -> Result<&[u8]> {
+    let rest = data
+        .get(offset as usize..)
+        .context("string offset outside .debug_str")?;
+    let len = rest
+        .iter()
+        .position(|&b| b == 0)
+        .context("unterminated string in .debug_str")?;
+    Ok(&rest[..len])
+}
+
+const DW_FORM_ADDR: u64 = 0x01;
+const DW_FORM_BLOCK2: u64 = 0x03;
+const DW_FORM_BLOCK4: u64 = 0x04;
+const DW_FORM_DATA2: u64 = 0x05;
+const DW_FORM_DATA4: u64 = 0x06;
+const DW_FORM_DATA8: u64 = 0x07;
+const DW_FORM_STRING: u64 = 0x08;
+const DW_FORM_BLOCK: u64 = 0x09;
+const DW_FORM_BLOCK1: u64 = 0x0a;
+const DW_FORM_DATA1: u64 = 0x0b;
+const DW_FORM_FLAG: u64 = 0x0c;
+const DW_FORM_SDATA: u64 = 0x0d;
+const DW_FORM_STRP: u64 = 0x0e;
+const DW_FORM_UDATA: u64 = 0x0f;
+const DW_FORM_REF_ADDR: u64 = 0x10;
+const DW_FORM_REF1: u64 = 0x11;
+const DW_FORM_REF2: u64 = 0x12;
+const DW_FORM_REF4: u64 = 0x13;
+const DW_FORM_REF8: u64 = 0x14;
+const DW_FORM_REF_UDATA: u64 = 0x15;
+const DW_FORM_INDIRECT: u64 = 0x16;
+const DW_FORM_SEC_OFFSET: u64 = 0x17;
+const DW_FORM_EXPRLOC: u64 = 0x18;
+const DW_FORM_FLAG_PRESENT: u64 = 0x19;
+const DW_FORM_STRX: u64 = 0x1a;
+const DW_FORM_ADDRX: u64 = 0x1b;
+const DW_FORM_REF_SUP4: u64 = 0x1c;
+const DW_FORM_STRP_SUP: u64 = 0x1d;
+const DW_FORM_DATA16: u64 = 0x1e;
+const DW_FORM_LINE_STRP: u64 = 0x1f;
+const DW_FORM_REF_SIG8: u64 = 0x20;
+const DW_FORM_IMPLICIT_CONST: u64 = 0x21;
+const DW_FORM_LOCLISTX: u64 = 0x22;
+const DW_FORM_RNGLISTX: u64 = 0x23;
+const DW_FORM_REF_SUP8: u64 = 0x24;
+const DW_FORM_STRX1: u64 = 0x25;
+const DW_FORM_STRX4: u64 = 0x28;
+const DW_FORM_ADDRX1: u64 = 0x29;
+const DW_FORM_ADDRX4: u64 = 0x2c;
+const DW_FORM_GNU_ADDR_INDEX: u64 = 0x1f01;
+const DW_FORM_GNU_STR_INDEX: u64 = 0x1f02;
+const DW_FORM_GNU_REF_ALT: u64 = 0x1f20;
+const DW_FORM_GNU_STRP_ALT: u64 = 0x1f21;
+
+/// What decoding a unit's attribute values depends on.
+#[derive(Debug, Clone, Copy)]
+struct Form {
+    version: u16,
+    offset_size: usize,
+    addr_size: u8,
+}
+
+impl Form {
+    /// Read a value of `form`; `implicit` is the value of
+    /// `DW_FORM_implicit_const`, which is stored in the abbreviation.
+    fn read<'a>(&self, r: &mut Reader<'a>, form: u64, implicit: i64) -> Result<Value<'a>> {
+        Ok(match form {
+            DW_FORM_ADDR => Value::Address(r.uint(self.addr_size as usize)?),
+            DW_FORM_DATA1 | DW_FORM_REF1 => Value::Constant(r.uint(1)?),
+            DW_FORM_DATA2 | DW_FORM_REF2 => Value::Constant(r.uint(2)?),
+            DW_FORM_DATA4 | DW_FORM_REF4 | DW_FORM_REF_SUP4 => Value::Constant(r.uint(4)?),
+            DW_FORM_DATA8 | DW_FORM_REF8 | DW_FORM_REF_SIG8 | DW_FORM_REF_SUP8 => {
+                Value::Constant(r.uint(8)?)
+            }
+            DW_FORM_DATA16 => {
+                r.bytes(16)?;
+                Value::Other
+            }
+            DW_FORM_UDATA | DW_FORM_REF_UDATA => Value::Constant(r.uleb()?),
+            DW_FORM_SDATA => Value::Constant(r.sleb()? as u64),
+            DW_FORM_IMPLICIT_CONST => Value::Constant(implicit as u64),
+            DW_FORM_FLAG => Value::Flag(r.u8()? != 0),
+            DW_FORM_FLAG_PRESENT => Value::Flag(true),
+            DW_FORM_STRING => Value::String(r.cstr()?),
+            DW_FORM_STRP => Value::StringOffset(r.offset(self.offset_size)?),
+            DW_FORM_LINE_STRP | DW_FORM_STRP_SUP | DW_FORM_GNU_STRP_ALT | DW_FORM_GNU_REF_ALT => {
+                r.offset(self.offset_size)?;
+                Value::Other
+            }
+            DW_FORM_SEC_OFFSET => Value::Constant(r.offset(self.offset_size)?),
+            DW_FORM_REF_ADDR => {
+                let size = if self.version == 2 {
+                    self.addr_size as usize
+                } else {
+                    self.offset_size
+                };
+                Value::Constant(r.uint(size)?)
+            }
+            DW_FORM_STRX | DW_FORM_GNU_STR_INDEX => Value::StringIndex(r.uleb()?),
+            DW_FORM_STRX1..=DW_FORM_STRX4 => {
+                Value::StringIndex(r.uint((form - DW_FORM_STRX1 + 1) as usize)?)
+            }
+            DW_FORM_ADDRX | DW_FORM_GNU_ADDR_INDEX => Value::AddressIndex(r.uleb()?),
+            DW_FORM_ADDRX1..=DW_FORM_ADDRX4 => {
+                Value::AddressIndex(r.uint((form - DW_FORM_ADDRX1 + 1) as usize)?)
+            }
+            DW_FORM_LOCLISTX | DW_FORM_RNGLISTX => Value::Constant(r.uleb()?),
+            DW_FORM_BLOCK1 | DW_FORM_BLOCK2 | DW_FORM_BLOCK4 | DW_FORM_BLOCK | DW_FORM_EXPRLOC => {
+                let len = match form {
+                    DW_FORM_BLOCK1 => r.uint(1)?,
+                    DW_FORM_BLOCK2 => r.uint(2)?,
+                    DW_FORM_BLOCK4 => r.uint(4)?,
+                    _ => r.uleb()?,
+                };
+                r.bytes(len as usize)?;
+                Value::Other
+            }
+            DW_FORM_INDIRECT => {
+                let form = r.uleb()?;
+                return self.read(r, form, implicit);
+            }
+            _ => bail!(".debug_info: unknown attribute form {:#x}", form),
+        })
+    }
+}
+
+/// A cursor over a debug section.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    e: Endianness,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], e: Endianness) -> Self {
+        Reader { data, pos: 0, e }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .context("truncated debug section")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(self.uint(2)? as u16)
+    }
+
+    /// An unsigned integer of `size` bytes.
+    fn uint(&mut self, size: usize) -> Result<u64> {
+        let bytes = self.bytes(size)?;
+        if size > 8 {
+            bail!("unsupported {}-byte value in debug section", size);
+        }
+        let mut buf = [0; 8];
+        if self.e.is_little_endian() {
+            buf[..size].copy_from_slice(bytes);
+        } else {
+            buf[8 - size..].copy_from_slice(bytes);
+        }
+        Ok(self.e.read_u64_bytes(buf))
+    }
+
+    /// A section offset in the 32- or 64-bit DWARF format.
+    fn offset(&mut self, offset_size: usize) -> Result<u64> {
+        self.uint(offset_size)
+    }
+
+    /// A unit length, and the offset size of the format it selects.
+    fn initial_length(&mut self) -> Result<(u64, usize)> {
+        match self.uint(4)? {
+            0xffff_ffff => Ok((self.uint(8)?, 8)),
+            len => Ok((len, 4)),
+        }
+    }
+
+    fn uleb(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            if shift < 64 {
+                value |= ((b & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> Result<i64> {
+        let mut value = 0i64;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            if shift < 64 {
+                value |= ((b & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if b & 0x80 == 0 {
+                if shift < 64 && b & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                return Ok(value);
+            }
+        }
+    }
+
+    /// A NUL-terminated string, without the NUL.
+    fn cstr(&mut self) -> Result<&'a [u8]> {
+        let s = cstr_at(self.data, self.pos as u64)?;
+        self.pos += s.len() + 1;
+        Ok(s)
+    }
+}
@@ -0,0 +1,154 @@
+//! The global offset table.
+//!
+//! Code that can't know where a symbol ends up loads its address, or its
+//! offset from the thread pointer, from a GOT entry that the linker or the
+//! loader fills in. Each symbol gets one entry per kind of use, split over
+//! the two output sections the psABIs describe. Entries for global symbols
+//! are shared by name; those for local symbols belong to the input file
+//! defining them, as other files may define locals of the same name.
+//!
+//!
+//! - `.got` holds addresses, initial-exec TLS offsets and TLS descriptors.
+//! - `.got.plt` starts with three reserved words, the first holding the
+//!   address of `_DYNAMIC`, followed by the slots of `ifunc`s, which the
+//!   loader fills with `IRELATIVE` relocations, and those `.plt` entries
+//!   jump through, filled by `JUMP_SLOT` ones. `_GLOBAL_OFFSET_TABLE_`
+//!   points at its start. The other two reserved words are for lazy PLT
+//!   binding, which uld doesn't do, and stay 0.
+
+use foldhash::HashMap;
+use object::read::{ObjectSymbol, SymbolIndex};
+use object::SymbolFlags;
+
+/// Output section holding address, TLS and descriptor entries.
+pub const SECTION: &str = ".got";
+/// Output section `_GLOBAL_OFFSET_TABLE_` points at.
+pub const PLT_SECTION: &str = ".got.plt";
+/// Words at the start of [`PLT_SECTION`] reserved for the loader.
+pub const RESERVED_WORDS: u64 = 3;
+
+/// A local symbol: the index of the input file defining it, and its index
+/// in that file's symbol table.
+pub type LocalSymbol = (usize, SymbolIndex);
+
+/// What a GOT entry holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntryKind {
+    /// The symbol's address.
+    Address,
+    /// The symbol's offset from the thread pointer, for initial-exec TLS.
+    TlsIe,
+    /// A TLS descriptor: a resolver the loader picks and its argument.
+    TlsDesc,
+    /// The address an `ifunc`'s resolver returns.
+    IRelative,
+    /// The address of a function called through its `.plt` entry.
+    JumpSlot,
+}
+
+impl EntryKind {
+    /// Size of the entry in words.
+    pub fn words(self) -> u64 {
+        match self {
+            EntryKind::TlsDesc => 2,
+            _ => 1,
+        }
+    }
+
+    /// The output section the entry lives in.
+    pub fn section(self) -> &'static str {
+        match self {
+            EntryKind::IRelative | EntryKind::JumpSlot => PLT_SECTION,
+            _ => SECTION,
+        }
+    }
+}
+
+/// An entry of the GOT.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// Name of the symbol the entry is for.
+    pub symbol: String,
+    /// For a local symbol, the input file and index of its definition.
+    pub local: Option<LocalSymbol>,
+    pub kind: EntryKind,
+    /// Offset of the entry in its [`EntryKind::section`].
+    pub offset: u64,
+}
+
+/// The entries of `.got` and `.got.plt`, in the order they were added.
+#[derive(Debug, Clone, Default)]
+pub struct Got {
+    entries: Vec<Entry>,
+    /// Symbol -> its entries, at most one of each kind.
+    index: HashMap<(String, Option<LocalSymbol>), Vec<usize>>,
+    word: u64,
+    got_size: u64,
+    plt_size: u64,
+}
+
+impl Got {
+    /// An empty table for an ELF class with `word`-byte addresses.
+    pub fn new(word: u64) -> Self {
+        Got {
+            word,
+            plt_size: RESERVED_WORDS * word,
+            ..Default::default()
+        }
+    }
+
+    /// Add an entry of `kind` for `symbol`, unless it already has one, and
+    /// return its offset in its section. `local` is set for local symbols.
+    pub fn add(&mut self, symbol: &str, local: Option<LocalSymbol>, kind: EntryKind) -> u64 {
+        if let Some(offset) = self.offset(symbol, local, kind) {
+            return offset;
+        }
+        let size = match kind.section() {
+            PLT_SECTION => &mut self.plt_size,
+            _ => &mut self.got_size,
+        };
+        let offset = *size;
+        *size += kind.words() * self.word;
+        self.index
+            .entry((symbol.to_string(), local))
+            .or_default()
+            .push(self.entries.len());
+        self.entries.push(Entry {
+            symbol: symbol.to_string(),
+            local,
+            kind,
+            offset,
+        });
+        offset
+    }
+
+    /// Offset of the entry of `kind` for `symbol` in its section.
+    pub fn offset(&self, symbol: &str, local: Option<LocalSymbol>, kind: EntryKind) -> Option<u64> {
+        self.index
+            .get(&(symbol.to_string(), local))?
+            .iter()
+            .map(|&i| &self.entries[i])
+            .find(|e| e.kind == kind)
+            .map(|e| e.offset)
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Size of `.got`.
+    pub fn got_size(&self) -> u64 {
+        self.got_size
+    }
+
+    /// Size of `.got.plt`, including the reserved words.
+    pub fn plt_size(&self) -> u64 {
+        self.plt_size
+    }
+}
+
+/// Whether `sym` is an `ifunc`, whose address is what its resolver returns.
+pub fn is_ifunc(sym: &object::Symbol) -> bool {
+    matches!(sym.flags(), SymbolFlags::Elf { st_info, .. }
+        if st_info & 0xf == object::elf::STT_GNU_IFUNC)
+}
@@ -0,0 +1,79 @@
+//! Hooks for programs that drive the linker as a library.
+//!
+//! A [`LinkPlugin`] registered with [`Linker::add_link_plugin`] or
+//! [`LinkerBuilder::link_plugin`] is called at fixed points of the link:
+//! as inputs are added, once symbols are resolved, as input sections are
+//! copied into the output, and before the output is written. Every method
+//! has a default that leaves the link alone, so a plugin only implements
+//! the hooks it needs. Plugins run in the order they were registered.
+//!
+//! [`Linker::add_link_plugin`]: crate::linker::Linker::add_link_plugin
+//! [`LinkerBuilder::link_plugin`]: crate::linker::LinkerBuilder::link_plugin
+
+use anyhow::Result;
+
+use crate::layout::{InputFile, NonAllocSection, Segment};
+use crate::symbol::SyntheticValue;
+
+/// Hooks into a link. Methods take `&self` so that a linker holding
+/// plugins can still move across threads; keep state behind a `Mutex` or
+/// atomics.
+pub trait LinkPlugin: Send + Sync {
+    /// Whether `input` joins the link. Called for each object, extracted
+    /// archive member and shared library; one left out is skipped as if it
+    /// had never been given. An error fails the link.
+    fn accept_input(&self, input: &InputFile) -> Result<bool> {
+        let _ = input;
+        Ok(true)
+    }
+
+    /// A definition for `name`, which is still referenced but defined by no
+    /// input once all inputs are added. The first plugin to answer defines
+    /// it, like a `PROVIDE` in a linker script: it is not exported.
+    fn resolve_undefined(&self, name: &str) -> Option<SyntheticValue> {
+        let _ = name;
+        None
+    }
+
+    /// Called with the contents of `section` as copied into its output
+    /// section, before relocations are applied to them. The contents may be
+    /// rewritten but not resized; bytes that relocations cover are
+    /// overwritten afterwards.
+    fn on_section_loaded(&self, section: &LoadedSection, data: &mut [u8]) -> Result<()> {
+        let _ = (section, data);
+        Ok(())
+    }
+
+    /// Called once the output is laid out and relocated, before it is
+    /// written.
+    fn before_write(&self, output: &mut Output) -> Result<()> {
+        let _ = output;
+        Ok(())
+    }
+}
+
+/// An input section placed in an output section.
+#[derive(Debug)]
+pub struct LoadedSection<'a> {
+    /// The object it comes from.
+    pub input: &'a InputFile,
+    /// Its name in that object (e.g. `.text.main`).
+    pub name: &'a str,
+    /// The output section it was placed in (e.g. `.text`).
+    pub output: &'a str,
+}
+
+/// The linked output, as [`LinkPlugin::before_write`] sees it.
+///
+/// Addresses are assigned: section contents may change, but sizes,
+/// addresses and file offsets of the loaded sections must stay. Each
+/// segment's contents are gathered into its `data` first, so that is the
+/// place to edit them. Sections
+/// that aren't loaded come after them in the file and may be added or
+/// resized freely.
+pub struct Output<'a> {
+    /// Loaded output sections, in address order.
+    pub segments: &'a mut [Segment],
+    /// Output sections that are not loaded (`.symtab`, `.comment`, ...).
+    pub non_alloc: &'a mut Vec<NonAllocSection>,
+}
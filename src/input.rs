@@ -0,0 +1,452 @@
+//! Input file parsing.
+//!
+//! Parsing objects and reading their symbol tables doesn't depend on the
+//! rest of the link, so it runs for every input file in parallel. The linker
+//! then merges the results into its global symbol table in command-line
+//! order, which keeps resolution deterministic.
+//!
+//! Parsed objects borrow the file contents. Callers can keep the contents
+//! alive themselves (the zero-copy path the command line uses), or hand the
+//! linker an [`InputBuffer`] to own.
+
+use anyhow::Result;
+use memmap2::Mmap;
+use object::elf;
+use object::read::archive::ArchiveFile;
+use object::read::{Object, ObjectSection};
+use object::{ObjectKind, ObjectSymbol, SymbolFlags, SymbolKind, SymbolSection};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::cache::ObjectCache;
+use crate::intern::NameMap;
+use crate::lto;
+use crate::symbol::SymbolLocation;
+
+/// The contents of an input file, owned by whoever holds the buffer.
+///
+/// Each variant keeps its bytes at a fixed address however the buffer
+/// itself is moved, which lets the linker hand out references into it.
+pub enum InputBuffer {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+    /// Contents shared with other links, such as a cached library.
+    Shared(Arc<[u8]>),
+    Aligned(AlignedCopy),
+}
+
+impl InputBuffer {
+    pub fn bytes(&self) -> &[u8] {
+        match self {
+            InputBuffer::Mapped(map) => map,
+            InputBuffer::Owned(data) => data,
+            InputBuffer::Shared(data) => data,
+            InputBuffer::Aligned(copy) => copy.bytes(),
+        }
+    }
+}
+
+/// Whether the object parser can read `data` in place: ELF structures must
+/// be naturally aligned.
+pub fn is_aligned(data: &[u8]) -> bool {
+    data.as_ptr().align_offset(8) == 0
+}
+
+/// An 8-byte aligned copy of bytes that weren't, such as an archive member
+/// at an odd offset.
+pub struct AlignedCopy {
+    buffer: Vec<u8>,
+    start: usize,
+    len: usize,
+}
+
+impl AlignedCopy {
+    pub fn new(data: &[u8]) -> Self {
+        let mut buffer = vec![0; data.len() + 7];
+        let start = buffer.as_ptr().align_offset(8);
+        buffer[start..start + data.len()].copy_from_slice(data);
+        Self {
+            buffer,
+            start,
+            len: data.len(),
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.buffer[self.start..self.start + self.len]
+    }
+}
+
+impl From<Mmap> for InputBuffer {
+    fn from(map: Mmap) -> Self {
+        InputBuffer::Mapped(map)
+    }
+}
+
+impl From<Vec<u8>> for InputBuffer {
+    fn from(data: Vec<u8>) -> Self {
+        InputBuffer::Owned(data)
+    }
+}
+
+impl From<Arc<[u8]>> for InputBuffer {
+    fn from(data: Arc<[u8]>) -> Self {
+        InputBuffer::Shared(data)
+    }
+}
+
+/// A global symbol of an input object, read ahead of resolution.
+pub struct InputSymbol<'a> {
+    pub name: &'a str,
+    /// Where the symbol is defined, or `None` for an undefined reference.
+    pub location: Option<SymbolLocation>,
+    pub address: u64,
+    pub size: u64,
+    pub weak: bool,
+    /// An undefined reference that may stay unresolved: weak, hidden or TLS.
+    pub optional: bool,
+    /// `STV_*` visibility from `st_other`.
+    pub visibility: u8,
+    /// `STB_GNU_UNIQUE`: one definition per process, so copies merge.
+    pub unique: bool,
+    /// `STT_*` type from `st_info`.
+    pub st_type: u8,
+}
+
+/// An object file and its global symbols.
+pub struct ParsedObject<'a> {
+    pub obj: object::File<'a>,
+    pub symbols: Vec<InputSymbol<'a>>,
+}
+
+impl<'a> ParsedObject<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        Self::scan(object::File::parse(data)?)
+    }
+
+    /// Read the global symbols of the already parsed `obj`.
+    pub fn scan(obj: object::File<'a>) -> Result<Self> {
+        let symbols = scan_symbols(&obj)?;
+        Ok(Self { obj, symbols })
+    }
+}
+
+/// The contents of an archive member.
+pub enum MemberData<'a> {
+    /// In place in the archive.
+    Borrowed(&'a [u8]),
+    /// Copied because the member isn't aligned within the archive. Only
+    /// members that get extracted are kept beyond the archive's indexing.
+    Copied(AlignedCopy),
+}
+
+impl MemberData<'_> {
+    pub fn bytes(&self) -> &[u8] {
+        match self {
+            MemberData::Borrowed(data) => data,
+            MemberData::Copied(copy) => copy.bytes(),
+        }
+    }
+}
+
+pub struct ArchiveMember<'a> {
+    pub name: &'a [u8],
+    /// The member parsed while indexing, for objects of the archive's
+    /// architecture. Declared before `data` so that it is dropped before the
+    /// copy it may borrow.
+    object: Option<ParsedObject<'a>>,
+    data: MemberData<'a>,
+    /// Offset of the member's contents in the archive file.
+    pub offset: u64,
+}
+
+impl<'a> ArchiveMember<'a> {
+    pub fn bytes(&self) -> &[u8] {
+        self.data.bytes()
+    }
+
+    /// The member's contents for `'a`, with the object parsed from them
+    /// while indexing. A copy is handed to `keep`, which must hold it for
+    /// `'a`; the object is only reused if the bytes returned are the copy's.
+    pub fn extract(
+        &mut self,
+        keep: impl FnOnce(AlignedCopy) -> &'a [u8],
+    ) -> (&'a [u8], Option<ParsedObject<'a>>) {
+        let object = self.object.take();
+        let (data, object) = match std::mem::replace(&mut self.data, MemberData::Borrowed(&[])) {
+            MemberData::Borrowed(data) => (data, object),
+            MemberData::Copied(copy) => {
+                let copied = copy.bytes().as_ptr();
+                let data = keep(copy);
+                (data, object.filter(|_| std::ptr::eq(data.as_ptr(), copied)))
+            }
+        };
+        self.data = MemberData::Borrowed(data);
+        (data, object)
+    }
+}
+
+/// The members of an archive, indexed by the symbols they define.
+pub struct ArchiveIndex<'a> {
+    pub members: Vec<ArchiveMember<'a>>,
+    /// Symbol name -> index into `members`.
+    pub symbols: NameMap<usize>,
+    /// Indices of the members holding LLVM bitcode, whose symbols only an
+    /// LTO backend can read.
+    pub bitcode: Vec<usize>,
+    /// Indices of the members holding GCC LTO IR. Their symbols come from
+    /// the archive's symbol table, which `gcc-ar` writes with the plugin.
+    pub gcc_lto: Vec<usize>,
+}
+
+/// An input file, parsed but not yet added to the link.
+pub enum ParsedFile<'a> {
+    Object(ParsedObject<'a>),
+    Archive(ArchiveIndex<'a>),
+    Shared(object::File<'a>),
+    /// An LLVM bitcode module, compiled at link time.
+    Bitcode(&'a [u8]),
+}
+
+/// Map the file at `path`, asking the kernel to start reading it in.
+///
+/// A mapping reads nothing until its pages are touched, so each first touch
+/// of a cold input would stall parsing or writing the output on I/O, a
+/// round trip per fault on a network file system. The readahead hint lets
+/// the reads of all inputs proceed at once, as soon as they are mapped.
+pub fn map_file(path: &Path) -> io::Result<Mmap> {
+    let file = File::open(path)?;
+    let map = unsafe { Mmap::map(&file)? };
+    // Only a hint, which other hosts go without: pages the kernel doesn't
+    // read ahead are read on demand.
+    #[cfg(unix)]
+    let _ = map.advise(memmap2::Advice::WillNeed);
+    Ok(map)
+}
+
+/// Parse the input file `data` read from `path`.
+///
+/// Archive members built for another architecture than `arch` are skipped.
+pub fn parse_file<'a>(
+    path: &Path,
+    data: &'a [u8],
+    arch: object::Architecture,
+) -> Result<ParsedFile<'a>> {
+    parse_file_with(path, data, arch, None)
+}
+
+/// [`parse_file`], taking what an object holds from `cache` when it has
+/// been parsed before.
+pub fn parse_file_with<'a>(
+    path: &Path,
+    data: &'a [u8],
+    arch: object::Architecture,
+    cache: Option<&ObjectCache>,
+) -> Result<ParsedFile<'a>> {
+    // https://alpha-supernova.dev.filibeto.org/lib/rel/5.1B/DOCS/HTML/SUPPDOCS/OBJSPEC/NV160XXX.HTM
+    if data.starts_with(b"!<arch>\n") {
+        return Ok(ParsedFile::Archive(index_archive(path, data, arch)?));
+    }
+    if lto::is_bitcode(data) {
+        return Ok(ParsedFile::Bitcode(data));
+    }
+    let parsed = match cache {
+        Some(cache) => cache.parse(data)?,
+        None => ParsedObject::parse(data)?,
+    };
+    if parsed.obj.kind() == ObjectKind::Dynamic {
+        return Ok(ParsedFile::Shared(parsed.obj));
+    }
+    Ok(ParsedFile::Object(parsed))
+}
+
+/// Index an archive's members by the global symbols they define.
+fn index_archive<'a>(
+    path: &Path,
+    data: &'a [u8],
+    arch: object::Architecture,
+) -> Result<ArchiveIndex<'a>> {
+    let archive = ArchiveFile::parse(data)?;
+    let mut members = Vec::new();
+    for member in archive.members() {
+        let member = member?;
+        let bytes = member.data(data)?;
+        let data = if is_aligned(bytes) {
+            MemberData::Borrowed(bytes)
+        } else {
+            MemberData::Copied(AlignedCopy::new(bytes))
+        };
+        members.push(ArchiveMember {
+            name: member.name(),
+            object: None,
+            data,
+            offset: member.file_range().0,
+        });
+    }
+
+    let defined: Vec<(Vec<&str>, bool)> = members
+        .par_iter_mut()
+        .map(|member| {
+            let bytes = member.data.bytes();
+            // SAFETY: a copy's bytes don't move when the member does. The
+            // object borrowing them is dropped before the copy, or leaves
+            // the member in `extract` once `keep` holds the copy for `'a`.
+            let bytes: &'a [u8] =
+                unsafe { std::slice::from_raw_parts(bytes.as_ptr(), bytes.len()) };
+            if lto::is_bitcode(bytes) {
+                return Ok((Vec::new(), false));
+            }
+            let Ok(obj) = object::File::parse(bytes) else {
+                tracing::info!(
+                    "Failed to parse archive member {:?} within {:?}",
+                    String::from_utf8_lossy(member.name),
+                    path
+                );
+                return Ok((Vec::new(), false));
+            };
+            // Kind of an edge case but maybe this archive contains different
+            // architectures
+            if obj.architecture() != arch {
+                return Ok((Vec::new(), false));
+            }
+            // Kept for when the member is extracted, so it's parsed once.
+            let gcc_lto = is_gcc_lto(&obj);
+            let parsed = ParsedObject::scan(obj)?;
+            let names = parsed
+                .symbols
+                .iter()
+                .filter(|s| s.location.is_some())
+                .map(|s| s.name)
+                .collect();
+            member.object = Some(parsed);
+            Ok((names, gcc_lto))
+        })
+        .collect::<Result<_>>()?;
+
+    // Later members win, as they would when indexed one at a time.
+    let mut symbols = NameMap::new();
+    let mut gcc_lto = Vec::new();
+    for (i, (names, lto)) in defined.into_iter().enumerate() {
+        for name in names {
+            symbols.insert(name, i);
+        }
+        if lto {
+            gcc_lto.push(i);
+        }
+    }
+    if !gcc_lto.is_empty() {
+        let by_offset: HashMap<u64, usize> = members
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (m.offset, i))
+            .collect();
+        for sym in archive.symbols()?.into_iter().flatten() {
+            let sym = sym?;
+            let offset = archive.member(sym.offset())?.file_range().0;
+            if let Some(&i) = by_offset.get(&offset).filter(|i| gcc_lto.contains(i)) {
+                symbols.get_or_insert(&String::from_utf8_lossy(sym.name()), i);
+            }
+        }
+    }
+    let bitcode = (0..members.len())
+        .filter(|&i| lto::is_bitcode(members[i].bytes()))
+        .collect();
+    Ok(ArchiveIndex {
+        members,
+        symbols,
+        bitcode,
+        gcc_lto,
+    })
+}
+
+/// Whether `obj` carries GCC's LTO IR in `.gnu.lto_*` sections, alone
+/// (slim, the default) or next to machine code (`-ffat-lto-objects`).
+pub fn is_gcc_lto(obj: &object::File) -> bool {
+    obj.sections()
+        .any(|s| s.name().is_ok_and(|n| n.starts_with(".gnu.lto_")))
+}
+
+/// Whether `obj` is a slim GCC LTO object, with no machine code at all.
+pub fn is_slim_lto(obj: &object::File) -> bool {
+    obj.symbols().any(|s| s.name() == Ok("__gnu_lto_slim"))
+}
+
+/// Read the global symbols and undefined references of `obj`.
+fn scan_symbols<'a>(obj: &object::File<'a>) -> Result<Vec<InputSymbol<'a>>> {
+    let mut symbols = Vec::new();
+    for sym in obj.symbols() {
+        let name = sym.name()?;
+        let (visibility, unique, st_type) = match sym.flags() {
+            SymbolFlags::Elf { st_info, st_other } => (
+                st_other & 0x3,
+                st_info >> 4 == elf::STB_GNU_UNIQUE,
+                st_info & 0xf,
+            ),
+            _ => (elf::STV_DEFAULT, false, elf::STT_NOTYPE),
+        };
+        if sym.is_undefined() {
+            symbols.push(InputSymbol {
+                name,
+                location: None,
+                address: 0,
+                size: 0,
+                weak: sym.is_weak(),
+                optional: sym.is_weak() || is_hidden(visibility) || sym.kind() == SymbolKind::Tls,
+                visibility,
+                unique,
+                st_type,
+            });
+            continue;
+        }
+        if sym.is_local() {
+            continue;
+        }
+        let location = match sym.section() {
+            SymbolSection::Section(i) => SymbolLocation::Section(i),
+            SymbolSection::Absolute => SymbolLocation::Absolute,
+            SymbolSection::Common => SymbolLocation::Common {
+                align: sym.address().max(1),
+            },
+            _ => SymbolLocation::Undefined,
+        };
+        symbols.push(InputSymbol {
+            name,
+            location: Some(location),
+            address: sym.address(),
+            size: sym.size(),
+            weak: sym.is_weak(),
+            optional: false,
+            visibility,
+            unique,
+            st_type,
+        });
+    }
+    Ok(symbols)
+}
+
+/// Whether symbols with `STV_*` visibility `v` stay out of the dynamic symbol
+/// table: hidden and internal ones.
+pub fn is_hidden(v: u8) -> bool {
+    v == elf::STV_HIDDEN || v == elf::STV_INTERNAL
+}
+
+/// The more constraining of two `STV_*` visibilities, which is what a symbol
+/// gets when its definition and references disagree: internal, then hidden,
+/// then protected, then default.
+pub fn merge_visibility(a: u8, b: u8) -> u8 {
+    let rank = |v| match v {
+        elf::STV_INTERNAL => 3,
+        elf::STV_HIDDEN => 2,
+        elf::STV_PROTECTED => 1,
+        _ => 0,
+    };
+    if rank(b) > rank(a) {
+        b
+    } else {
+        a
+    }
+}
@@ -0,0 +1,259 @@
+//! Symbol name interning.
+//!
+//! An [`Interner`] keeps each distinct name once, appended to a string
+//! arena, and hands out a [`Name`]: a small index that compares and hashes
+//! as an integer. The resolver keys its tables by `Name`, so a global
+//! symbol costs one hash of its text when it is first seen, not an
+//! allocation and a hash per table it lands in.
+//!
+//! Names are split into [`SHARDS`] shards by the hash of their text, each
+//! with its own arena, and a `Name` records its shard. The symbol tables
+//! are split the same way (see [`crate::symtab`]), so a shard's names can
+//! be interned and resolved on one thread while other threads handle the
+//! other shards.
+//!
+//! Tables keyed by names and sections use [`foldhash`] rather than the
+//! standard library's SipHash, which is built to resist hash flooding a
+//! linker doesn't face.
+
+use std::fmt;
+use std::hash::BuildHasher;
+
+use foldhash::fast::RandomState;
+use hashbrown::HashTable;
+
+/// How many shards names and the symbol tables are split into.
+pub const SHARDS: usize = 1 << SHARD_BITS;
+const SHARD_BITS: u32 = 4;
+
+/// An interned name: its shard and an index into that shard's arena.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Name(u32);
+
+impl Name {
+    fn new(shard: usize, index: u32) -> Self {
+        assert!(index < u32::MAX >> SHARD_BITS, "too many symbol names");
+        Name(index << SHARD_BITS | shard as u32)
+    }
+
+    /// The shard the name, and its entries in the symbol tables, belong to.
+    pub fn shard(self) -> usize {
+        (self.0 as usize) & (SHARDS - 1)
+    }
+
+    fn index(self) -> u32 {
+        self.0 >> SHARD_BITS
+    }
+}
+
+/// Distinct strings stored one after another, found by their hash.
+#[derive(Default)]
+struct Strings {
+    /// Every string, one after another.
+    arena: String,
+    /// End of each string in `arena`; a string starts where the previous
+    /// one ends.
+    ends: Vec<usize>,
+    /// Indices into `ends`, found by the hash of their text.
+    table: HashTable<u32>,
+}
+
+impl Strings {
+    /// The index of `text`, whose hash is `hash`, adding it if it is new.
+    fn intern(&mut self, hasher: &RandomState, hash: u64, text: &str) -> u32 {
+        if let Some(i) = self.find(hash, text) {
+            return i;
+        }
+        let i = self.ends.len() as u32;
+        self.arena.push_str(text);
+        self.ends.push(self.arena.len());
+        let Self { arena, ends, table } = self;
+        table.insert_unique(hash, i, |&i| hasher.hash_one(text_of(arena, ends, i)));
+        i
+    }
+
+    fn find(&self, hash: u64, text: &str) -> Option<u32> {
+        self.table.find(hash, |&i| self.str(i) == text).copied()
+    }
+
+    fn str(&self, i: u32) -> &str {
+        text_of(&self.arena, &self.ends, i)
+    }
+}
+
+/// String `i` of an arena.
+fn text_of<'s>(arena: &'s str, ends: &[usize], i: u32) -> &'s str {
+    let i = i as usize;
+    let start = if i == 0 { 0 } else { ends[i - 1] };
+    &arena[start..ends[i]]
+}
+
+/// The names seen so far, each stored once.
+pub struct Interner {
+    shards: Vec<Strings>,
+    hasher: RandomState,
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Strings::default()).collect(),
+            hasher: RandomState::default(),
+        }
+    }
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The hash names are sharded and found by.
+    pub fn hash(&self, text: &str) -> u64 {
+        self.hasher.hash_one(text)
+    }
+
+    /// The shard of names whose [`Self::hash`] is `hash`.
+    pub fn shard_of(hash: u64) -> usize {
+        // The top bits: hashbrown probes with the low ones.
+        (hash >> (64 - SHARD_BITS)) as usize
+    }
+
+    /// The name for `text`, adding it if it is new.
+    pub fn intern(&mut self, text: &str) -> Name {
+        let hash = self.hash(text);
+        let shard = Self::shard_of(hash);
+        let i = self.shards[shard].intern(&self.hasher, hash, text);
+        Name::new(shard, i)
+    }
+
+    /// The name for `text`, if it has been interned.
+    pub fn get(&self, text: &str) -> Option<Name> {
+        let hash = self.hash(text);
+        let shard = Self::shard_of(hash);
+        self.shards[shard]
+            .find(hash, text)
+            .map(|i| Name::new(shard, i))
+    }
+
+    /// The text of `name`.
+    pub fn str(&self, name: Name) -> &str {
+        self.shards[name.shard()].str(name.index())
+    }
+
+    /// How many names there are.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.ends.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Each shard, to intern names of that shard apart from the others.
+    pub fn shards_mut(&mut self) -> impl Iterator<Item = InternShard<'_>> {
+        let hasher = &self.hasher;
+        self.shards
+            .iter_mut()
+            .enumerate()
+            .map(move |(shard, strings)| InternShard {
+                shard,
+                strings,
+                hasher,
+            })
+    }
+}
+
+/// One shard of an [`Interner`].
+pub struct InternShard<'i> {
+    shard: usize,
+    strings: &'i mut Strings,
+    hasher: &'i RandomState,
+}
+
+impl InternShard<'_> {
+    /// Which shard this is.
+    pub fn shard(&self) -> usize {
+        self.shard
+    }
+
+    /// The name for `text`, whose [`Interner::hash`] is `hash` and falls
+    /// in this shard, adding it if it is new.
+    pub fn intern(&mut self, hash: u64, text: &str) -> Name {
+        debug_assert_eq!(Interner::shard_of(hash), self.shard);
+        Name::new(self.shard, self.strings.intern(self.hasher, hash, text))
+    }
+}
+
+/// A map keyed by names it interns itself, for tables built apart from
+/// the linker's [`Interner`], such as the symbol index of each archive,
+/// which is read in parallel with the other inputs.
+#[derive(Default)]
+pub struct NameMap<V> {
+    names: Strings,
+    hasher: RandomState,
+    /// The value of each name, by index.
+    values: Vec<V>,
+}
+
+impl<V> NameMap<V> {
+    pub fn new() -> Self {
+        Self {
+            names: Strings::default(),
+            hasher: RandomState::default(),
+            values: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&V> {
+        let i = self.names.find(self.hasher.hash_one(name), name)?;
+        Some(&self.values[i as usize])
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Set the value of `name`, replacing any it had.
+    pub fn insert(&mut self, name: &str, value: V) {
+        let i = self.intern(name) as usize;
+        match self.values.get_mut(i) {
+            Some(old) => *old = value,
+            None => self.values.push(value),
+        }
+    }
+
+    /// The value of `name`, set to `value` first if it had none.
+    pub fn get_or_insert(&mut self, name: &str, value: V) -> &mut V {
+        let i = self.intern(name) as usize;
+        if i == self.values.len() {
+            self.values.push(value);
+        }
+        &mut self.values[i]
+    }
+
+    fn intern(&mut self, name: &str) -> u32 {
+        let hash = self.hasher.hash_one(name);
+        self.names.intern(&self.hasher, hash, name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl fmt::Debug for Interner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interner")
+            .field("names", &self.len())
+            .field(
+                "bytes",
+                &self.shards.iter().map(|s| s.arena.len()).sum::<usize>(),
+            )
+            .finish()
+    }
+}
@@ -3,27 +3,68 @@
 //! This module defines the structures for organizing the output executable's memory layout.
 //! It maps sections from input files into aggregated segments (e.g., .text, .data).
 
+use anyhow::Result;
 use object::read::SectionIndex;
-use object::SectionKind;
+use object::{Object, ObjectSection, SectionKind};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Where an input object came from.
+///
+/// Objects pulled out of an archive record both the archive path and the member
+/// name, so identically named sections from different members stay distinguishable.
+#[derive(Debug, Clone)]
+pub struct InputFile {
+    /// Path of the file given on the command line.
+    pub path: PathBuf,
+    /// Archive member name, if the object was extracted from an archive.
+    pub member: Option<String>,
+}
+
+impl fmt::Display for InputFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.member {
+            Some(m) => write!(f, "{}({})", self.path.display(), m),
+            None => write!(f, "{}", self.path.display()),
+        }
+    }
+}
 
 /// Represents a section from an input file.
 ///
 /// A `Section` corresponds to a section from an object file that will be copied
-/// into a specific `Segment`.
+/// into a specific `Segment` when the output is written.
 #[derive(Clone)]
 pub struct Section {
     /// Index of the input file.
     pub file_index: usize,
     /// Index of the section in the input file.
     pub section_index: SectionIndex,
+    /// Name of the section in the input file (e.g. `.text.init`).
+    pub name: String,
     /// The offset where this section starts within the `Segment`.
     pub offset: u64,
+    /// Size of the section in bytes.
+    pub size: u64,
+    /// Bytes of the section stored in the file: its size, or 0 for sections
+    /// such as `.bss` that only take room in memory.
+    pub file_size: u64,
+    /// Whether the section's 8-byte entries were stored in reverse order
+    /// (legacy `.ctors` / `.dtors` converted to init/fini arrays).
+    pub reversed: bool,
+    /// The section's contents once they differ from the input's: reversed,
+    /// rewritten by a link plugin or relocated. Until then they are read
+    /// from the input object when the segment is written.
+    pub data: Option<Vec<u8>>,
 }
 
 /// Represents a segment in the final output executable.
 ///
 /// A `Segment` aggregates multiple input `Section`s of the same type (e.g., all .text sections).
-/// It tracks the total size, the virtual address where it will be loaded, and the raw data bytes.
+/// It tracks the total size, the virtual address where it will be loaded, and the
+/// bytes the linker writes into it. Input sections are not copied until the
+/// segment is written (see [`Segment::write`]), so a large link doesn't hold
+/// every section twice.
 pub struct Segment {
     /// Name of the segment (e.g., ".text", ".data").
     pub name: String,
@@ -33,12 +74,23 @@ pub struct Segment {
     pub size: u64,
     /// The virtual address where this segment starts in memory.
     pub virtual_address: u64,
+    /// The address its contents are stored at (the LMA), which differs from
+    /// `virtual_address` only for sections copied into place at startup.
+    pub load_address: u64,
     /// The file offset where this segment starts in the ELF file.
     pub file_offset: u64,
-    /// The raw data content of the segment.
+    /// Bytes the linker writes itself, starting `data_offset` bytes into the
+    /// segment: all of a table it builds (`.got`, `.dynamic`, notes, ...)
+    /// or the veneers at the end of `.text`. They are laid over the input
+    /// sections.
     pub data: Vec<u8>,
+    /// Where `data` starts in the segment.
+    pub data_offset: u64,
     /// The kind of segment (Text, Data, etc.) used for permissions and mapping.
     pub kind: SectionKind,
+    /// The largest alignment of its input sections; its address and file
+    /// offset are aligned to it.
+    pub align: u64,
 }
 
 impl Segment {
@@ -49,9 +101,187 @@ impl Segment {
             sections: Vec::new(),
             size: 0,
             virtual_address: 0,
+            load_address: 0,
             file_offset: 0,
             data: Vec::new(),
+            data_offset: 0,
             kind,
+            align: table_align(kind),
+        }
+    }
+
+    /// Whether the segment only takes room in memory, as `.bss` and `.tbss`
+    /// do.
+    pub fn is_nobits(&self) -> bool {
+        matches!(
+            self.kind,
+            SectionKind::UninitializedData | SectionKind::UninitializedTls
+        )
+    }
+
+    /// Whether the segment belongs to the TLS block (`.tdata`, `.tbss`).
+    pub fn is_tls(&self) -> bool {
+        matches!(self.kind, SectionKind::Tls | SectionKind::UninitializedTls)
+    }
+
+    /// Bytes of the segment stored in the file: up to the end of its last
+    /// input section with contents, or of `data` if that is later.
+    pub fn file_size(&self) -> u64 {
+        self.sections
+            .iter()
+            .filter(|s| s.file_size > 0)
+            .map(|s| s.offset + s.file_size)
+            .chain([self.data_offset + self.data.len() as u64])
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The segment's file contents as `(offset, bytes)` pieces in offset
+    /// order, with zeros between them: each input section, read from
+    /// `objects` unless it has its own copy, and `data`, which takes the
+    /// place of any sections it covers.
+    pub fn pieces<'s>(&'s self, objects: &'s [object::File<'s>]) -> Result<Vec<(u64, &'s [u8])>> {
+        let data = self.data_offset..self.data_offset + self.data.len() as u64;
+        let mut pieces = Vec::with_capacity(self.sections.len() + 1);
+        for sec in self.sections.iter().filter(|s| s.file_size > 0) {
+            if sec.offset < data.end && data.start < sec.offset + sec.file_size {
+                continue;
+            }
+            let bytes = match &sec.data {
+                Some(data) => data,
+                None => objects[sec.file_index]
+                    .section_by_index(sec.section_index)?
+                    .data()?,
+            };
+            pieces.push((sec.offset, bytes));
+        }
+        if !self.data.is_empty() {
+            pieces.push((self.data_offset, &self.data[..]));
+        }
+        pieces.sort_by_key(|&(offset, _)| offset);
+        Ok(pieces)
+    }
+
+    /// Write the segment's contents into `out`, a zero-filled buffer of at
+    /// least [`Self::file_size`] bytes.
+    pub fn write(&self, objects: &[object::File], out: &mut [u8]) -> Result<()> {
+        for (offset, bytes) in self.pieces(objects)? {
+            let offset = offset as usize;
+            out[offset..offset + bytes.len()].copy_from_slice(bytes);
         }
+        Ok(())
+    }
+
+    /// The segment's file contents, as [`Self::write`] lays them out.
+    pub fn contents(&self, objects: &[object::File]) -> Result<Vec<u8>> {
+        let mut out = vec![0; self.file_size() as usize];
+        self.write(objects, &mut out)?;
+        Ok(out)
+    }
+
+    /// Gather all of the segment's contents into `data`, for code that
+    /// edits them in place.
+    pub fn flatten(&mut self, objects: &[object::File]) -> Result<()> {
+        self.data = self.contents(objects)?;
+        self.data_offset = 0;
+        for sec in &mut self.sections {
+            sec.data = None;
+        }
+        Ok(())
+    }
+}
+
+/// Alignment of the tables the linker builds itself, by section type.
+fn table_align(kind: SectionKind) -> u64 {
+    match kind {
+        SectionKind::Elf(
+            object::elf::SHT_DYNSYM
+            | object::elf::SHT_RELA
+            | object::elf::SHT_DYNAMIC
+            | object::elf::SHT_GNU_HASH
+            | object::elf::SHT_GNU_VERDEF
+            | object::elf::SHT_GNU_VERNEED
+            | object::elf::SHT_PREINIT_ARRAY
+            | object::elf::SHT_INIT_ARRAY
+            | object::elf::SHT_FINI_ARRAY,
+        ) => 8,
+        SectionKind::Elf(object::elf::SHT_HASH) => 4,
+        SectionKind::Elf(object::elf::SHT_GNU_VERSYM) => 2,
+        _ => 1,
+    }
+}
+
+/// A section in the output file that is not loaded at runtime (e.g. `.symtab`).
+///
+/// These are written after all segment data, and reference other output
+/// sections by name for `sh_link` / `sh_info`.
+pub struct NonAllocSection {
+    /// Name of the section (e.g. ".symtab", ".rela.text").
+    pub name: String,
+    /// ELF section type (`SHT_*`).
+    pub sh_type: u32,
+    /// The raw section contents.
+    pub data: Vec<u8>,
+    /// Section whose index becomes `sh_link`.
+    pub link: Option<String>,
+    /// Section whose index becomes `sh_info`; overrides `info` when set.
+    pub info_section: Option<String>,
+    /// Raw `sh_info` value.
+    pub info: u32,
+    /// Size of each entry for table sections.
+    pub entsize: u64,
+    /// `sh_flags`, such as `SHF_MERGE | SHF_STRINGS` for `.comment`.
+    pub flags: u64,
+    /// Required alignment of the section data in the file.
+    pub align: u64,
+}
+
+/// Output sections that are only written while relocating, and so can be
+/// made read-only afterwards under `-z relro`.
+pub const RELRO_SECTIONS: &[&str] = &[
+    ".preinit_array",
+    ".init_array",
+    ".fini_array",
+    ".data.rel.ro",
+    ".got",
+    ".got.plt",
+    ".dynamic",
+];
+
+/// Text sections the compiler names by how often they run, in the order
+/// GNU ld places them ahead of the rest of `.text`.
+pub const TEXT_PREFIXES: &[&str] = &[".text.unlikely", ".text.exit", ".text.startup", ".text.hot"];
+
+/// Index in [`TEXT_PREFIXES`] of the group a section such as
+/// `.text.hot.main` belongs to.
+pub fn text_prefix(name: &str) -> Option<usize> {
+    TEXT_PREFIXES.iter().position(|prefix| {
+        name.strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    })
+}
+
+/// Priority of an `.init_array.NNNNN` / `.fini_array.NNNNN` style section.
+///
+/// Sections with a numeric suffix sort by it, and the unsuffixed array
+/// sections follow all of them. Legacy `.ctors.NNNNN` / `.dtors.NNNNN`
+/// encode the priority as `65535 - NNNNN`. Returns `None` for other sections.
+pub fn init_priority(name: &str) -> Option<u32> {
+    let parse = |rest: &str| match rest {
+        "" => Some(u32::MAX),
+        _ => rest.strip_prefix('.')?.parse::<u32>().ok(),
+    };
+    if let Some(rest) = [".ctors", ".dtors"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+    {
+        return parse(rest).map(|p| match p {
+            u32::MAX => p,
+            p => 65535u32.saturating_sub(p),
+        });
     }
+    [".preinit_array", ".init_array", ".fini_array"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .and_then(parse)
 }
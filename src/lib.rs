@@ -2,17 +2,69 @@
 //!
 //! This library provides the core components for the `uld` linker.
 //! It is organized into several modules:
+//! - `cache`: On-disk cache of parsed input objects (`--object-cache`).
 //! - `config`: CLI configuration.
 //! - `arch`: Architecture-specific backend logic.
+//! - `callgraph`: Call-graph profile section ordering.
+//! - `demangle`: Symbol demangling and suggestions for diagnostics.
+//! - `depfile`: Make dependency files.
+//! - `driver`: The `uld` command in-process, and compiler driver lines.
+//! - `diagnostics`: Structured errors and `--diagnostics-format=json`.
+//! - `dynamic`: Dynamic section contents for shared objects.
+//! - `gdb_index`: `.gdb_index` sections for fast gdb startup.
+//! - `got`: Global offset table entries.
+//! - `hooks`: Link plugins: hooks for programs linking through the library.
+//! - `input`: Parallel parsing of input files.
+//! - `intern`: Symbol name interning and the hasher of the symbol tables.
 //! - `linker`: The main linking orchestration.
 //! - `layout`: Output memory layout management.
+//! - `lto`: Link-time optimization of LLVM bitcode inputs.
+//! - `map`: Link map output.
+//! - `plugin`: The binutils linker plugin interface, for GCC's LTO plugin.
+//! - `progress`: Progress callbacks and the `--progress` bar.
+//! - `property`: GNU property notes (x86 IBT and shadow stack markers).
+//! - `report`: JSON size reports and size diffs.
+//! - `reproduce`: `--reproduce` tarballs of a link's inputs.
+//! - `script`: Linker script stubs naming other inputs.
+//! - `section_rules`: Input section descriptions placing sections by name.
+//! - `stats`: Phase timings and counters for `--stats` and `--time-trace`.
 //! - `symbol`: Symbol table management.
-//! - `writer`: ELF file writing.
+//! - `symtab`: Sharded tables of global symbols, resolved in parallel.
+//! - `threads`: Worker thread pool sizing and the make jobserver.
+//! - `validate`: Sanity checks for malformed input objects and linked outputs.
+//! - `version`: Version scripts and symbol versioning.
+//! - `writer`: Output file writing (ELF and the `--oformat` formats).
 
 pub mod arch;
+pub mod cache;
+pub mod callgraph;
 pub mod config;
+pub mod demangle;
+pub mod depfile;
+pub mod diagnostics;
+pub mod driver;
+pub mod dynamic;
+pub mod gdb_index;
+pub mod got;
+pub mod hooks;
+pub mod input;
+pub mod intern;
 pub mod layout;
 pub mod linker;
+pub mod lto;
+pub mod map;
+pub mod plugin;
+pub mod progress;
+pub mod property;
+pub mod report;
+pub mod reproduce;
+pub mod script;
+pub mod section_rules;
+pub mod stats;
 pub mod symbol;
+pub mod symtab;
+pub mod threads;
 pub mod utils;
+pub mod validate;
+pub mod version;
 pub mod writer;
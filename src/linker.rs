@@ -7,352 +7,4476 @@
 //! 5. Write ELF
 
 use anyhow::{anyhow, Context, Result};
+use foldhash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use memmap2::Mmap;
-use object::read::{Object, ObjectSection, RelocationTarget, SectionIndex};
-use object::{ObjectSymbol, Relocation, RelocationKind, SectionKind, SymbolKind, SymbolVisibility};
-use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
-
-use crate::arch::Architecture;
-use crate::layout::{Section, Segment};
-use crate::symbol::{is_optional_symbol, DefinedSymbol};
-use crate::utils::align_up;
+use object::read::{Object, ObjectSection, RelocationTarget, SectionIndex, SymbolIndex};
+use object::{
+    Endian, ObjectSymbol, Relocation, RelocationFlags, RelocationKind, SectionFlags, SectionKind,
+    SymbolKind, SymbolSection,
+};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::arch::{Architecture, RelocationOverflow, Veneer};
+use crate::callgraph;
+use crate::demangle;
+use crate::diagnostics::{Diagnostic, Kind, Report};
+use crate::dynamic::{
+    self, DynamicReloc, DynamicRelocKind, DynamicSymbol, SharedLibrary, StringTable,
+};
+use crate::gdb_index;
+use crate::got::{self, EntryKind, Got};
+use crate::hooks::{self, LinkPlugin, LoadedSection};
+use crate::input::{
+    self, AlignedCopy, ArchiveIndex, InputBuffer, InputSymbol, ParsedFile, ParsedObject,
+};
+use crate::intern::{Interner, Name};
+use crate::layout::{
+    init_priority, text_prefix, InputFile, NonAllocSection, Section, Segment, RELRO_SECTIONS,
+    TEXT_PREFIXES,
+};
+use crate::lto::{self, LlvmTools};
+use crate::map;
+use crate::plugin::{self, Claimed, Plugin, PluginOptions, Resolution};
+use crate::progress::{LinkProgress, Milestone};
+use crate::property;
+use crate::report;
+use crate::section_rules::SectionRules;
+use crate::stats::{Counters, Trace};
+use crate::symbol::{
+    Boundary, DefinedSymbol, SymbolLocation, SyntheticSymbol, SyntheticSymbols, SyntheticValue,
+};
+use crate::symtab::{self, Batch, Clash, Resolved, Shard, ShardedMap, ShardedSet};
+use crate::utils::{align_up, glob_match, is_c_identifier};
+use crate::validate;
+use crate::version::{self, VersionScript};
 use crate::writer;
 
 const PAGE_SIZE: u64 = 0x1000;
 const BASE_ADDR: u64 = 0x400000;
+/// Marks an object compiled with `-fsplit-stack`.
+const SPLIT_STACK_NOTE: &str = ".note.GNU-split-stack";
+/// Section naming the tools that built the inputs and the output.
+const COMMENT: &str = ".comment";
+/// Section of the `--package-metadata` note.
+const PACKAGE_NOTE: &str = ".note.package";
+/// Note type of the package metadata note, with owner `FDO`.
+const NT_FDO_PACKAGING_METADATA: u32 = 0xcafe_1a7e;
+/// Fewest symbols in a batch of objects for its shards to be resolved on
+/// separate threads.
+const PARALLEL_SYMBOLS: usize = 4096;
+
+/// Extra padding inserted before each input text section.
+///
+/// Each section is aligned to `align` and then shifted by `offset` bytes, which
+/// lets experiments perturb function placement to expose cache-aliasing effects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectionPadding {
+    pub align: u64,
+    pub offset: u64,
+}
+
+/// The kind of ELF file to produce.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputKind {
+    /// A statically linked `ET_EXEC` executable.
+    #[default]
+    Executable,
+    /// A position-independent `ET_DYN` shared object (`-shared`).
+    SharedObject,
+    /// A position-independent `ET_DYN` executable (`-pie`), loaded at any
+    /// address.
+    Pie,
+}
+
+/// The file format written to the output path (`--oformat`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileFormat {
+    /// An ELF file (`elf64-x86-64`).
+    #[default]
+    Elf,
+    /// The loaded sections as a flat image, for boot loaders and firmware
+    /// (`binary`).
+    Binary,
+    /// Intel HEX records for flashing tools (`ihex`).
+    Ihex,
+    /// Motorola S-records for flashing tools (`srec`).
+    Srec,
+}
+
+/// Which symbol hash tables to emit (`--hash-style`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HashStyle {
+    /// The classic SysV `.hash` table.
+    #[default]
+    Sysv,
+    /// The GNU `.gnu.hash` table with a bloom filter.
+    Gnu,
+    /// Both tables, for loaders that only understand one of them.
+    Both,
+}
+
+impl HashStyle {
+    fn sysv(self) -> bool {
+        self != HashStyle::Gnu
+    }
+
+    fn gnu(self) -> bool {
+        self != HashStyle::Sysv
+    }
+}
+
+/// How input sections are sorted within each output section
+/// (`--sort-section`).
+///
+/// This is the equivalent of wrapping every input section pattern without a
+/// `SORT_BY_*` wrapper of its own in `SORT_BY_NAME` or `SORT_BY_ALIGNMENT`.
+/// Init/fini priorities, hot/cold text groups and symbol ordering still take
+/// precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortSection {
+    /// By section name.
+    Name,
+    /// By alignment, largest first, so less padding is needed between them.
+    Alignment,
+}
+
+/// Which undefined symbols are errors (`--unresolved-symbols`).
+///
+/// uld doesn't read the undefined references of shared library inputs, so
+/// only those of object files can be reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnresolvedSymbols {
+    IgnoreAll,
+    ReportAll,
+    IgnoreInObjectFiles,
+    IgnoreInSharedLibs,
+}
+
+impl UnresolvedSymbols {
+    /// Whether undefined references from object files are errors.
+    fn reports_objects(self) -> bool {
+        matches!(
+            self,
+            UnresolvedSymbols::ReportAll | UnresolvedSymbols::IgnoreInSharedLibs
+        )
+    }
+}
+
+/// What to do with orphan sections: allocated input sections that no
+/// section rule places (`--orphan-handling`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OrphanHandling {
+    /// Give each its own output section after a compatible one.
+    #[default]
+    Place,
+    /// Place them, with a warning for each.
+    Warn,
+    /// Fail the link.
+    Error,
+    /// Leave them out of the output.
+    Discard,
+}
+
+/// Keywords given with `-z`.
+///
+/// Where GNU ld has a pair such as `now` / `lazy`, the last one given wins.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZOptions {
+    /// Cover the sections that are only written while relocating with
+    /// `PT_GNU_RELRO`, so the loader can make them read-only (`-z relro`).
+    pub relro: bool,
+    /// Bind every symbol at load time (`-z now`).
+    pub now: bool,
+    /// Mark the stack executable in `PT_GNU_STACK` (`-z execstack`).
+    pub exec_stack: bool,
+    /// Initial stack size recorded as `PT_GNU_STACK`'s `p_memsz`
+    /// (`-z stack-size`); the loader's default when unset.
+    pub stack_size: Option<u64>,
+    /// Reject undefined symbols in shared objects (`-z defs`,
+    /// `--no-undefined`).
+    pub defs: bool,
+    /// Allow a symbol to be defined more than once; the first definition
+    /// wins (`-z muldefs`).
+    pub muldefs: bool,
+    /// Give `.text.hot`, `.text.unlikely`, `.text.startup` and `.text.exit`
+    /// their own output sections instead of grouping them inside `.text`
+    /// (`-z keep-text-section-prefix`).
+    pub keep_text_section_prefix: bool,
+    /// Alignment of the sections in memory and in the file; 4 KiB unless
+    /// given (`-z max-page-size`).
+    pub max_page_size: Option<u64>,
+    /// Mark the output as supporting indirect branch tracking whatever its
+    /// inputs say (`-z ibt`).
+    pub ibt: bool,
+    /// Mark the output as supporting shadow stacks whatever its inputs say
+    /// (`-z shstk`).
+    pub shstk: bool,
+    /// Like `ibt`, but warn about each input built without IBT
+    /// (`-z force-ibt`).
+    pub force_ibt: bool,
+}
+
+impl ZOptions {
+    /// The page size output sections are aligned to.
+    pub fn page_size(&self) -> u64 {
+        self.max_page_size.unwrap_or(PAGE_SIZE)
+    }
+}
+
+/// Options that change how the linker lays out its output.
+#[derive(Debug, Clone, Default)]
+pub struct LinkOptions {
+    /// Padding applied to input text sections (`--pad-sections`).
+    pub pad_sections: Option<SectionPadding>,
+    /// Whether to produce an executable or a shared object.
+    pub output_kind: OutputKind,
+    /// How the linked image is written out.
+    pub file_format: FileFormat,
+    /// `DT_SONAME` for shared objects (`-soname`).
+    pub soname: Option<String>,
+    /// Keep input relocations in the output (`--emit-relocs`).
+    pub emit_relocs: bool,
+    /// Leave the inputs' `.debug_*` sections out (`-S`, `--strip-debug`).
+    pub strip_debug: bool,
+    /// Index the debug info for gdb in a `.gdb_index` section
+    /// (`--gdb-index`).
+    pub gdb_index: bool,
+    /// JSON describing the package the output belongs to, written to a
+    /// `.note.package` note (`--package-metadata`).
+    pub package_metadata: Option<String>,
+    /// Optimization level for LLVM bitcode inputs (`-plugin-opt=O<n>`),
+    /// 2 if `None`.
+    pub lto_opt_level: Option<u8>,
+    /// A linker plugin, such as GCC's LTO plugin, offered the inputs that
+    /// carry compiler IR (`-plugin`, `-plugin-opt`).
+    pub plugin: Option<PluginOptions>,
+    /// Export every defined global from an executable (`--export-dynamic`).
+    pub export_dynamic: bool,
+    /// Glob patterns of globals to export from an executable
+    /// (`--export-dynamic-symbol`, `--dynamic-list`).
+    pub dynamic_exports: Vec<String>,
+    /// File names of archives whose members' symbols are never exported,
+    /// or `ALL` for every archive (`--exclude-libs`).
+    pub exclude_libs: Vec<String>,
+    /// Symbol hash tables to emit alongside `.dynsym`.
+    pub hash_style: HashStyle,
+    /// Export control and symbol versions (`--version-script`).
+    pub version_script: Option<VersionScript>,
+    /// Runtime library search directories (`-rpath`, `-R`).
+    pub rpath: Vec<String>,
+    /// Record `rpath` as `DT_RUNPATH` instead of `DT_RPATH` (`--enable-new-dtags`).
+    pub new_dtags: bool,
+    /// Program interpreter for executables linked against shared libraries
+    /// (`--dynamic-linker`); defaults to the architecture's loader.
+    pub dynamic_linker: Option<String>,
+    /// Leave out `.interp` (`--no-dynamic-linker`), for static-PIE
+    /// executables that relocate themselves.
+    pub no_dynamic_linker: bool,
+    /// Symbols defined on the command line (`--defsym`).
+    pub defsyms: Vec<(String, SyntheticValue)>,
+    /// `-z` keywords.
+    pub z: ZOptions,
+    /// Symbols whose sections are placed first, in this order, within their
+    /// output section (`--symbol-ordering-file`).
+    pub symbol_ordering: Vec<String>,
+    /// Report ordering-file symbols that can't be ordered
+    /// (`--warn-symbol-ordering`).
+    pub warn_symbol_ordering: bool,
+    /// Leave GOT-indirect references to symbols the output defines as the
+    /// inputs wrote them (`--no-relax`).
+    pub no_relax: bool,
+    /// Leave the linker's name and version out of `.comment` (`--no-ident`).
+    pub no_ident: bool,
+    /// Audit the placement of the output sections before writing them, and
+    /// validate the written file (`--check-sections`).
+    pub check_sections: bool,
+    /// Write the output even though relocations refer to discarded
+    /// sections, warning about them instead (`--noinhibit-exec`).
+    pub noinhibit_exec: bool,
+    /// Show C++ and Rust symbol names demangled in diagnostics
+    /// (`--demangle`).
+    pub demangle: bool,
+    /// Record each input file as it is read, including extracted archive
+    /// members, for [`Linker::write_trace`] (`-t` / `--trace`).
+    pub trace: bool,
+    /// Symbols whose definitions and references are recorded as the inputs
+    /// are read, for [`Linker::write_trace`] (`-y` / `--trace-symbol`).
+    pub trace_symbols: std::collections::HashSet<String>,
+    /// Symbols treated as referenced before any input is read, so archive
+    /// members defining them are extracted (`-u` / `--undefined`).
+    pub undefined: Vec<String>,
+    /// Symbols that must end up defined; like `undefined`, but the link
+    /// fails if they don't (`--require-defined`).
+    pub require_defined: Vec<String>,
+    /// Record why each global symbol got its final definition, for
+    /// [`Linker::write_resolution_log`] (`--verbose`).
+    pub verbose: bool,
+    /// Order sections by call-graph profile when no symbol ordering file is
+    /// given (`--call-graph-profile-sort`).
+    pub call_graph_sort: bool,
+    /// Load address of the file header (`--image-base`); by default 0x400000
+    /// for executables and 0 for shared objects.
+    pub image_base: Option<u64>,
+    /// Symbol execution starts at (`-e`); `_start` by default.
+    pub entry: Option<String>,
+    /// Sort input sections within each output section (`--sort-section`).
+    pub sort_section: Option<SortSection>,
+    /// Which undefined symbols are errors (`--unresolved-symbols`). By
+    /// default those of executables are, and those of shared objects only
+    /// with `-z defs`.
+    pub unresolved_symbols: Option<UnresolvedSymbols>,
+    /// What to do with sections no rule places (`--orphan-handling`).
+    pub orphan_handling: OrphanHandling,
+    /// Fixed addresses for output sections (`-Ttext`, `--section-start`).
+    /// The sections after each one follow it.
+    pub section_starts: std::collections::HashMap<String, u64>,
+    /// Load addresses for output sections stored apart from where they run
+    /// (`--section-lma`).
+    pub section_lmas: std::collections::HashMap<String, u64>,
+    /// `(caller, callee, weight)` edges from `--call-graph-ordering-file`,
+    /// used instead of the inputs' `.llvm.call-graph-profile` sections.
+    pub call_graph_edges: Option<Vec<(String, String, u64)>>,
+}
+
+/// Why an archive member was pulled into the link.
+#[derive(Debug, Clone)]
+pub struct Extraction {
+    /// Index of the object whose undefined reference caused the extraction,
+    /// or `None` for a symbol named on the command line.
+    pub reference: Option<usize>,
+    /// Index of the extracted member in the linker's object list.
+    pub extracted: usize,
+    /// The symbol that was needed.
+    pub symbol: String,
+}
 
+/// What a relocation kept by `--emit-relocs` refers to.
+enum EmittedTarget {
+    /// A global symbol, by name.
+    Symbol(String),
+    /// The section symbol of an output segment.
+    Section(usize),
+    /// No symbol, for absolute values.
+    Absolute,
+}
+
+/// A relocation kept by `--emit-relocs`, retargeted to the output file.
+struct EmittedReloc {
+    segment: usize,
+    offset: u64,
+    r_type: u32,
+    target: EmittedTarget,
+    addend: i64,
+}
+
+/// Where a relocation applies: an offset in an input section.
+struct RelocSite {
+    file: usize,
+    section: SectionIndex,
+    offset: u64,
+}
+
+/// An archive of a `--start-group` group, searched again at its end.
+struct GroupArchive<'a> {
+    path: PathBuf,
+    archive: ArchiveIndex<'a>,
+    /// Symbols already looked up in it.
+    tried: HashSet<Name>,
+}
+
+/// Veneers for branches that can't reach their targets, placed at the end
+/// of `.text`. Branches further than their range from there are an error.
+#[derive(Default)]
+struct Veneers {
+    /// Offset of the first veneer in `.text`.
+    offset: u64,
+    /// Space reserved for them, which may be more than they need.
+    size: u64,
+    stubs: Vec<Veneer>,
+}
+
+/// Links input files into an executable or shared object.
+///
+/// Objects borrow their file contents for `'a`. Inputs added with
+/// [`add_file`](Linker::add_file) stay owned by the caller; those added with
+/// [`add_owned`](Linker::add_owned) or [`add_path`](Linker::add_path) are
+/// owned by the linker, so a `Linker<'static, A>` can be built up
+/// incrementally and moved between threads.
 pub struct Linker<'a, A: Architecture> {
     arch: A,
+    options: LinkOptions,
     objects: Vec<object::File<'a>>,
-    symbols: HashMap<String, DefinedSymbol>,
+    /// Provenance of each entry in `objects`.
+    inputs: Vec<InputFile>,
+    /// Names of the global symbols the tables below are keyed by.
+    names: Interner,
+    symbols: ShardedMap<DefinedSymbol>,
     segments: Vec<Segment>,
     section_map: HashMap<(usize, SectionIndex), (usize, u64)>,
-    got: HashMap<String, u64>,
-    weak: HashSet<String>,      // symbols that can be 0
-    undefined: HashSet<String>, // needed for archive linking
+    discarded: Vec<(usize, SectionIndex)>,
+    got: Got,
+    /// Global `ifunc`s the output defines.
+    ifuncs: HashSet<String>,
+    /// Functions called through `.plt`, and the offsets of their entries.
+    /// Each jumps through a [`EntryKind::JumpSlot`] GOT entry.
+    plt: HashMap<String, u64>,
+    weak: ShardedSet,           // only weakly referenced: 0 unless bound at runtime
+    visibility: ShardedMap<u8>, // most constraining STV_* of each global, if not default
+    undefined: ShardedMap<Option<usize>>, // needed for archive linking -> first referencing object
+    extractions: Vec<Extraction>,
+    dynsyms: Vec<String>, // .dynsym order, excluding the null entry
+    dynstr: StringTable,
+    non_alloc: Vec<NonAllocSection>,
+    shared_libs: Vec<SharedLibrary>,
+    imports: ShardedMap<usize>, // symbols bound at runtime -> defining shared library
+    as_needed: bool,
+    synthetic_symbols: SyntheticSymbols,
+    synthetic: HashMap<String, SyntheticSymbol>, // symbols defined from `synthetic_symbols`
+    relocations: usize,                          // input relocations processed
+    resolution_log: Vec<(Name, String)>,         // --verbose: symbol -> decision, in order
+    trace_log: Vec<String>,                      // -t / -y lines, in input order
+    section_rules: SectionRules,
+    veneers: Veneers,
+    /// Compiles LLVM bitcode inputs.
+    lto: Box<dyn lto::Backend>,
+    /// Bitcode modules from the command line waiting to be compiled
+    /// together.
+    bitcode: Vec<(PathBuf, &'a [u8])>,
+    /// The linker plugin, loaded once an input carries compiler IR.
+    plugin: Option<Plugin>,
+    /// Archives of the `--start-group` group being read.
+    group: Option<Vec<GroupArchive<'a>>>,
+    /// Definitions in files the plugin claimed: the claimed file and kind
+    /// of the one that prevails among them.
+    ir_defs: HashMap<String, (usize, plugin::SymbolKind)>,
+    /// Names added to `undefined` for references from claimed files alone.
+    ir_refs: HashSet<Name>,
+    /// Hooks of programs linking through the library.
+    link_plugins: Vec<Box<dyn LinkPlugin>>,
+    /// Told how the link is getting on.
+    progress: Option<Box<dyn LinkProgress>>,
+    /// Contents of the inputs the linker owns. Declared last so everything
+    /// borrowing from them is dropped first.
+    buffers: Vec<InputBuffer>,
 }
 
 impl<'a, A: Architecture> Linker<'a, A> {
     pub fn new(arch: A) -> Self {
+        Self::with_options(arch, LinkOptions::default())
+    }
+
+    pub fn with_options(arch: A, options: LinkOptions) -> Self {
+        let mut synthetic_symbols = SyntheticSymbols::builtin();
+        for (name, value) in &options.defsyms {
+            synthetic_symbols.define(name, value.clone());
+        }
+        // Command-line references come before those of any input.
+        let mut names = Interner::new();
+        let mut undefined = ShardedMap::new();
+        for name in options.undefined.iter().chain(&options.require_defined) {
+            undefined.insert(names.intern(name), None);
+        }
+        let lto = LlvmTools {
+            bin_dir: None,
+            opt_level: options.lto_opt_level.unwrap_or(2),
+            pic: matches!(
+                options.output_kind,
+                OutputKind::SharedObject | OutputKind::Pie
+            ),
+        };
         Self {
             arch,
+            options,
             objects: Vec::new(),
-            symbols: HashMap::new(),
+            inputs: Vec::new(),
+            names,
+            symbols: ShardedMap::new(),
             segments: Vec::new(),
             section_map: HashMap::new(),
-            got: HashMap::new(),
-            weak: HashSet::new(),
-            undefined: HashSet::new(),
+            discarded: Vec::new(),
+            got: Got::default(),
+            ifuncs: HashSet::new(),
+            plt: HashMap::new(),
+            weak: ShardedSet::new(),
+            visibility: ShardedMap::new(),
+            undefined,
+            extractions: Vec::new(),
+            dynsyms: Vec::new(),
+            dynstr: StringTable::default(),
+            non_alloc: Vec::new(),
+            shared_libs: Vec::new(),
+            imports: ShardedMap::new(),
+            as_needed: false,
+            synthetic_symbols,
+            synthetic: HashMap::new(),
+            relocations: 0,
+            resolution_log: Vec::new(),
+            trace_log: Vec::new(),
+            section_rules: SectionRules::default(),
+            veneers: Veneers::default(),
+            lto: Box::new(lto),
+            bitcode: Vec::new(),
+            plugin: None,
+            group: None,
+            ir_defs: HashMap::new(),
+            ir_refs: HashSet::new(),
+            link_plugins: Vec::new(),
+            progress: None,
+            buffers: Vec::new(),
         }
     }
 
-    pub fn add_file(&mut self, path: &PathBuf, mmap: &'a Mmap) -> Result<()> {
-        // https://alpha-supernova.dev.filibeto.org/lib/rel/5.1B/DOCS/HTML/SUPPDOCS/OBJSPEC/NV160XXX.HTM
-        if mmap.starts_with(b"!<arch>\n") {
-            return self.add_archive(path, mmap);
+    /// Compile LLVM bitcode inputs with `backend` instead of LLVM's
+    /// command-line tools.
+    pub fn set_lto_backend(&mut self, backend: Box<dyn lto::Backend>) {
+        self.lto = backend;
+    }
+
+    /// Call `plugin`'s hooks during the link, after those of the plugins
+    /// added before it. Add it before the inputs it should see.
+    pub fn add_link_plugin(&mut self, plugin: Box<dyn LinkPlugin>) {
+        self.link_plugins.push(plugin);
+    }
+
+    /// Report the progress of the link to `progress`.
+    pub fn set_progress(&mut self, progress: Box<dyn LinkProgress>) {
+        self.progress = Some(progress);
+    }
+
+    fn milestone(&self, milestone: Milestone) {
+        if let Some(progress) = &self.progress {
+            progress.milestone(milestone);
         }
-        self.add_object(object::File::parse(&**mmap)?)
     }
 
-    fn add_archive(&mut self, path: &PathBuf, mmap: &'a Mmap) -> Result<()> {
-        let archive = object::read::archive::ArchiveFile::parse(mmap.as_ref())?;
+    /// Define `name` as `value`, replacing any definition from the inputs
+    /// (`--defsym`).
+    pub fn define_symbol(&mut self, name: &str, value: SyntheticValue) {
+        self.synthetic_symbols.define(name, value);
+    }
+
+    /// Whether shared libraries added from now on are only recorded as
+    /// `DT_NEEDED` if they resolve a reference (`--as-needed`).
+    pub fn set_as_needed(&mut self, as_needed: bool) {
+        self.as_needed = as_needed;
+    }
+
+    pub fn add_file(&mut self, path: &Path, mmap: &'a Mmap) -> Result<()> {
+        let parsed = input::parse_file(path, mmap, A::arch())?;
+        self.add_parsed(path, parsed)
+    }
+
+    /// Add an input whose contents the linker takes ownership of.
+    pub fn add_owned(&mut self, path: &Path, buffer: impl Into<InputBuffer>) -> Result<()> {
+        let data = self.keep(buffer.into());
+        let parsed = input::parse_file(path, data, A::arch())?;
+        self.add_parsed(path, parsed)
+    }
+
+    /// Map the input file at `path` and add it, owning the mapping.
+    pub fn add_path(&mut self, path: &Path) -> Result<()> {
+        let map = input::map_file(path).with_context(|| format!("open {}", path.display()))?;
+        self.add_owned(path, map)
+    }
+
+    /// Take ownership of `buffer`, returning its contents for as long as
+    /// the objects parsed from them may live.
+    ///
+    /// Contents the object parser can't read in place are copied to an
+    /// aligned buffer first.
+    fn keep(&mut self, buffer: InputBuffer) -> &'a [u8] {
+        let buffer = if input::is_aligned(buffer.bytes()) {
+            buffer
+        } else {
+            InputBuffer::Aligned(AlignedCopy::new(buffer.bytes()))
+        };
+        let bytes = buffer.bytes();
+        // SAFETY: the bytes don't move when the buffer does, and `buffers`
+        // is dropped after every field that can hold a borrow of them. No
+        // `'a` reference leaves the linker.
+        let bytes = unsafe { std::slice::from_raw_parts(bytes.as_ptr(), bytes.len()) };
+        self.buffers.push(buffer);
+        bytes
+    }
 
-        // Loop over all the object files within the archive
-        // Create an index of symbol name -> archive member data
-        let mut index: HashMap<String, &'a [u8]> = HashMap::new();
-        for member in archive.members() {
-            let member = member?;
-            let mut data = member.data(mmap.as_ref())?;
-            // Align for parsing
-            if data.as_ptr().align_offset(8) != 0 {
-                // Force the data onto the heap to get it aligned
-                // FIXME: Can we avoid this leak?
-                data = Box::leak(data.to_vec().into_boxed_slice());
+    /// Add an input file parsed by [`input::parse_file`].
+    ///
+    /// Files may be parsed in any order (or concurrently), but must be added
+    /// in command-line order for symbol resolution to match it.
+    pub fn add_parsed(&mut self, path: &Path, parsed: ParsedFile<'a>) -> Result<()> {
+        if let Some(progress) = &self.progress {
+            progress.file_loaded(path);
+        }
+        match parsed {
+            ParsedFile::Object(obj) => match self.admit_object(path, obj)? {
+                Some((obj, input)) => self.add_object(obj, input),
+                None => Ok(()),
+            },
+            ParsedFile::Archive(index) => {
+                self.compile_bitcode()?;
+                self.add_archive(path, index)
             }
-            let Ok(obj) = object::File::parse(data) else {
-                tracing::info!(
-                    "Failed to parse archive member {:?} within {:?}",
-                    member,
-                    path
-                );
-                continue;
-            };
-            // Kind of an edge case but maybe this archive contains different
-            // architectures
-            if obj.architecture() != A::arch() {
-                continue;
+            ParsedFile::Shared(obj) => {
+                self.compile_bitcode()?;
+                self.add_shared(path, &obj)
             }
-            for sym in obj.symbols() {
-                let name = sym.name()?;
-                if !sym.is_undefined() && !sym.is_local() {
-                    index.insert(name.to_string(), data);
+            ParsedFile::Bitcode(data) => {
+                let size = data.len() as u64;
+                if !self.claim(path, 0, size, path.display().to_string())? {
+                    self.bitcode.push((path.to_path_buf(), data));
                 }
+                Ok(())
             }
         }
+    }
 
-        // FIXME: If we happen to parse archives before any object files the
-        // needed list will be empty.
-        // Pull in members defining needed symbols (iterate until fixpoint)
-        let mut included = HashSet::new();
-        loop {
-            let needed: Vec<_> = self
-                .undefined
-                .iter()
-                .filter(|s| index.contains_key(*s) && !included.contains(*s))
-                .cloned()
-                .collect();
-            if needed.is_empty() {
-                break;
-            }
-            for sym in needed {
-                if let Some(&data) = index.get(&sym) {
-                    included.insert(sym);
-                    self.add_object(object::File::parse(data)?)?;
-                }
+    /// Whether every link plugin lets `input` join the link.
+    fn accepted(&self, input: &InputFile) -> Result<bool> {
+        for plugin in &self.link_plugins {
+            if !plugin.accept_input(input)? {
+                return Ok(false);
             }
         }
-        Ok(())
+        Ok(true)
     }
 
-    fn add_object(&mut self, obj: object::File<'a>) -> Result<()> {
-        if A::arch() != obj.architecture() {
-            return Err(anyhow!("unsupported: {:?}", obj.architecture()));
-        }
-
-        let idx = self.objects.len();
-
-        for sym in obj.symbols() {
-            let name = sym.name()?;
+    /// Offer the `size` bytes at `offset` in `path`, which carry compiler
+    /// IR, to the linker plugin, loading it first if need be. Returns
+    /// whether the plugin claimed them; without a plugin, it never does.
+    fn claim(&mut self, path: &Path, offset: u64, size: u64, name: String) -> Result<bool> {
+        let Some(options) = &self.options.plugin else {
+            return Ok(false);
+        };
+        let plugin = match self.plugin.take() {
+            Some(plugin) => plugin,
+            None => Plugin::load(options, self.options.output_kind)?,
+        };
+        let plugin = self.plugin.insert(plugin);
+        let Some(file) = plugin.claim(path, offset, size, name)? else {
+            return Ok(false);
+        };
+        let claimed = plugin.claimed()[file].clone();
+        self.add_ir_symbols(file, &claimed)?;
+        Ok(true)
+    }
 
-            if sym.is_undefined() {
-                if sym.is_weak()
-                    || sym.visibility() == SymbolVisibility::Hidden
-                    || (sym.kind() == SymbolKind::Tls)
-                    || is_optional_symbol(name)
+    /// Resolve the symbols of a file the plugin claimed. Its definitions
+    /// satisfy references like those of an object, and its references may
+    /// extract archive members; the objects the plugin compiles from it
+    /// replace both after all symbols are read.
+    fn add_ir_symbols(&mut self, file: usize, claimed: &Claimed) -> Result<()> {
+        for sym in &claimed.symbols {
+            let name = &sym.name;
+            let n = self.names.intern(name);
+            if sym.visibility != object::elf::STV_DEFAULT {
+                let v = self.visibility.entry(n).or_insert(object::elf::STV_DEFAULT);
+                *v = input::merge_visibility(*v, sym.visibility);
+            }
+            if !sym.kind.is_definition() {
+                if self.symbols.contains_key(&n)
+                    || self.imports.contains_key(&n)
+                    || self.ir_defs.contains_key(name)
                 {
-                    self.weak.insert(name.to_string());
-                } else if !self.symbols.contains_key(name) {
-                    self.undefined.insert(name.to_string());
+                    self.note(n, |_| format!("referenced by {}", claimed.name));
+                } else if let Some(lib) = self
+                    .shared_libs
+                    .iter()
+                    .position(|l| l.symbols.contains(name))
+                {
+                    self.imports.insert(n, lib);
+                } else if sym.kind == plugin::SymbolKind::Undef && !self.undefined.contains_key(&n)
+                {
+                    self.note(n, |_| {
+                        format!("referenced by {}, undefined so far", claimed.name)
+                    });
+                    self.undefined.insert(n, None);
+                    self.ir_refs.insert(n);
                 }
                 continue;
             }
+            match self.ir_defs.get(name) {
+                Some(&(other, kind))
+                    if !kind.is_weak() && !sym.kind.is_weak() && !self.options.z.muldefs =>
+                {
+                    let first = self
+                        .plugin
+                        .as_ref()
+                        .map(|p| p.claimed()[other].name.clone())
+                        .unwrap_or_default();
+                    return Err(self.multiple_definition(name, &first, &claimed.name));
+                }
+                Some(&(_, kind)) if !kind.is_weak() || sym.kind.is_weak() => {}
+                _ => {
+                    self.ir_defs.insert(name.clone(), (file, sym.kind));
+                    self.note(n, |_| {
+                        format!("defined in {}, claimed by the linker plugin", claimed.name)
+                    });
+                }
+            }
+            self.undefined.remove(&n);
+            self.ir_refs.remove(&n);
+        }
+        Ok(())
+    }
 
-            if sym.is_local() {
-                continue;
+    /// Whether the definition of `name` in a regular object, if any, wins
+    /// over those in claimed files.
+    fn native_prevails(&self, name: &str) -> bool {
+        self.symbol(name).is_some_and(|native| {
+            !native.is_weak || self.ir_defs.get(name).is_none_or(|d| d.1.is_weak())
+        })
+    }
+
+    /// The error for a second strong definition of `name`.
+    fn multiple_definition(&self, name: &str, first: &str, again: &str) -> anyhow::Error {
+        let shown = self.display_name(name);
+        let message = format!(
+            "multiple definition of `{}`: first defined in {}, again in {}",
+            shown, first, again
+        );
+        let mut d = Diagnostic::new(Kind::MultipleDefinition, message.clone());
+        d.symbol = Some(shown);
+        d.file = Some(again.to_string());
+        Report::new(message, vec![d]).into()
+    }
+
+    /// How the link resolved each symbol of each claimed file, for the
+    /// plugin's `get_symbols`.
+    fn plugin_resolutions(&self) -> Result<Vec<Vec<Resolution>>> {
+        let Some(plugin) = &self.plugin else {
+            return Ok(Vec::new());
+        };
+        // Definitions anything outside the IR may use can't be internalized.
+        let mut external: HashSet<&str> = self
+            .objects
+            .iter()
+            .flat_map(|o| o.symbols())
+            .filter(|s| s.is_undefined())
+            .filter_map(|s| s.name().ok())
+            .collect();
+        external.insert(self.options.entry.as_deref().unwrap_or("_start"));
+        external.extend(
+            self.options
+                .undefined
+                .iter()
+                .chain(&self.options.require_defined)
+                .map(String::as_str),
+        );
+        let export_all = self.is_shared()
+            || self.options.export_dynamic
+            || !self.options.dynamic_exports.is_empty();
+
+        let mut resolutions = Vec::new();
+        for (file, claimed) in plugin.claimed().iter().enumerate() {
+            let mut r = Vec::new();
+            for sym in &claimed.symbols {
+                let name = sym.name.as_str();
+                let resolution = if !sym.kind.is_definition() {
+                    if self.native_prevails(name) {
+                        Resolution::ResolvedExec
+                    } else if self.ir_defs.contains_key(name) {
+                        Resolution::ResolvedIr
+                    } else if self.import(name).is_some() {
+                        Resolution::ResolvedDyn
+                    } else {
+                        Resolution::Undef
+                    }
+                } else if self.native_prevails(name) {
+                    let native = self.symbol(name).context("prevailing definition")?;
+                    if !native.is_weak
+                        && !native.is_common()
+                        && !sym.kind.is_weak()
+                        && !self.options.z.muldefs
+                    {
+                        let first = self.inputs[native.input_file_index].to_string();
+                        return Err(self.multiple_definition(name, &first, &claimed.name));
+                    }
+                    Resolution::PreemptedReg
+                } else if self.ir_defs.get(name).map(|d| d.0) != Some(file) {
+                    Resolution::PreemptedIr
+                } else if export_all || external.contains(name) {
+                    Resolution::PrevailingDef
+                } else {
+                    Resolution::PrevailingDefIronly
+                };
+                r.push(resolution);
             }
+            resolutions.push(r);
+        }
+        Ok(resolutions)
+    }
 
-            // If the symbol is weak, we actually let the next one overwrite it.
-            if self.symbols.contains_key(name) && !self.symbols[name].is_weak {
-                continue;
+    /// Let the linker plugin compile the files it claimed, and add the
+    /// objects and libraries it hands back.
+    ///
+    /// Archives were scanned before this, for the references of the claimed
+    /// files; new references the compiled code makes aren't extracted.
+    fn run_plugin(&mut self) -> Result<()> {
+        let resolutions = self.plugin_resolutions()?;
+        let Some(plugin) = self.plugin.as_mut() else {
+            return Ok(());
+        };
+        let added = plugin.all_symbols_read(resolutions)?;
+        // The compiled objects make whichever of these references survive.
+        for name in std::mem::take(&mut self.ir_refs) {
+            if self.undefined.get(&name) == Some(&None) {
+                self.undefined.remove(&name);
             }
+        }
+        self.ir_defs.clear();
+        for path in &added.files {
+            self.add_path(path)?;
+        }
+        let as_needed = self.as_needed;
+        for lib in &added.libraries {
+            self.as_needed = lib.as_needed;
+            self.add_path(&lib.path)?;
+        }
+        self.as_needed = as_needed;
+        Ok(())
+    }
 
-            self.undefined.remove(name);
-            self.symbols.insert(
-                name.to_string(),
-                DefinedSymbol::new(
-                    idx,
-                    sym.section_index().unwrap_or(SectionIndex(0)),
-                    sym.address(),
-                    sym.is_weak(),
-                    sym.section_index().is_none(),
-                ),
-            );
+    /// Compile the bitcode modules added since the last archive or shared
+    /// library into one native object and add it, so that the references
+    /// they leave undefined can pull in archive members.
+    fn compile_bitcode(&mut self) -> Result<()> {
+        if self.bitcode.is_empty() {
+            return Ok(());
         }
+        let modules = std::mem::take(&mut self.bitcode);
+        let borrowed: Vec<_> = modules.iter().map(|(p, d)| (p.as_path(), *d)).collect();
+        let native = self.lto.compile(&borrowed)?;
+        let data = self.keep(InputBuffer::Owned(native));
+        let input = InputFile {
+            path: PathBuf::from(lto::OBJECT_NAME),
+            member: None,
+        };
+        self.add_object(ParsedObject::parse(data)?, input)
+    }
 
-        self.objects.push(obj);
+    /// Record a shared library input and resolve pending references against it.
+    fn add_shared(&mut self, path: &Path, obj: &object::File) -> Result<()> {
+        let input = InputFile {
+            path: path.to_path_buf(),
+            member: None,
+        };
+        if !self.accepted(&input)? {
+            return Ok(());
+        }
+        let lib = SharedLibrary::parse(obj, path, self.as_needed)
+            .with_context(|| format!("read shared library {}", path.display()))?;
+        if self.options.trace {
+            self.trace_log.push(path.display().to_string());
+        }
+        let mut traced: Vec<_> = self
+            .options
+            .trace_symbols
+            .iter()
+            .filter(|s| lib.symbols.contains(*s))
+            .collect();
+        traced.sort();
+        let lines: Vec<String> = traced
+            .into_iter()
+            .map(|name| {
+                format!(
+                    "{}: definition of {}",
+                    path.display(),
+                    self.display_name(name)
+                )
+            })
+            .collect();
+        self.trace_log.extend(lines);
+        let idx = self.shared_libs.len();
+        let weak = self.weak.iter().filter(|s| {
+            !self.symbols.contains_key(s)
+                && !self.imports.contains_key(s)
+                && !input::is_hidden(self.visibility(self.names.str(**s)))
+        });
+        let resolved: Vec<Name> = self
+            .undefined
+            .keys()
+            .chain(weak)
+            .filter(|&&s| lib.symbols.contains(self.names.str(s)))
+            .copied()
+            .collect();
+        for name in resolved {
+            self.note(name, |_| format!("bound to shared library {}", lib.soname));
+            self.undefined.remove(&name);
+            self.imports.insert(name, idx);
+        }
+        self.shared_libs.push(lib);
         Ok(())
     }
 
-    pub fn link(&mut self) -> Result<()> {
-        self.layout()?;
-        self.resolve_symbols();
-        self.relocate()
-    }
+    fn add_archive(&mut self, path: &Path, mut archive: ArchiveIndex<'a>) -> Result<()> {
+        // Later members win, as they do in the index.
+        for &i in &archive.bitcode {
+            let member = &archive.members[i];
+            let names = self.lto.defined_symbols(member.bytes()).with_context(|| {
+                format!(
+                    "{}({}): reading bitcode symbols",
+                    path.display(),
+                    String::from_utf8_lossy(member.name)
+                )
+            })?;
+            for name in names {
+                let member = archive.symbols.get_or_insert(&name, i);
+                *member = (*member).max(i);
+            }
+        }
 
-    fn layout(&mut self) -> Result<()> {
-        // BSS must be last (no file content)
-        self.segments = vec![
-            Segment::new(".text", SectionKind::Text),
-            Segment::new(".init", SectionKind::Text),
-            Segment::new(".fini", SectionKind::Text),
-            Segment::new(".rodata", SectionKind::ReadOnlyData),
-            Segment::new(".data", SectionKind::Data),
-            Segment::new(".got", SectionKind::Data),
-            Segment::new(".tdata", SectionKind::Tls),
-            Segment::new(".bss", SectionKind::UninitializedData),
-        ];
+        // FIXME: If we happen to parse archives before any object files the
+        // needed list will be empty.
+        let mut tried = HashSet::new();
+        self.extract_members(path, &mut archive, &mut tried)?;
+        if let Some(group) = &mut self.group {
+            group.push(GroupArchive {
+                path: path.to_path_buf(),
+                archive,
+                tried,
+            });
+        }
+        Ok(())
+    }
 
-        for (file_idx, obj) in self.objects.iter().enumerate() {
-            for sec in obj.sections() {
-                if sec.size() == 0 {
+    /// Pull in the members of `archive` defining needed symbols, until none
+    /// is left. Symbols in `tried` have been looked up in it before and
+    /// aren't again. Returns whether any member was extracted.
+    fn extract_members(
+        &mut self,
+        path: &Path,
+        archive: &mut ArchiveIndex<'a>,
+        tried: &mut HashSet<Name>,
+    ) -> Result<bool> {
+        let mut extracted = false;
+        loop {
+            let needed: Vec<_> = self
+                .undefined
+                .iter()
+                .filter(|(s, _)| !tried.contains(*s))
+                .filter_map(|(&s, &r)| Some((s, r, *archive.symbols.get(self.names.str(s))?)))
+                .collect();
+            if needed.is_empty() {
+                return Ok(extracted);
+            }
+            for (sym, reference, i) in needed {
+                // An earlier member from this batch may already define it.
+                if !self.undefined.contains_key(&sym) {
                     continue;
                 }
-                let Some(seg_idx) = self.segment_for(&sec) else {
-                    continue;
+                tried.insert(sym);
+                let member = &mut archive.members[i];
+                // Copies of misaligned members move into the linker for
+                // as long as the object parsed from them.
+                let (data, object) = member.extract(|copy| self.keep(InputBuffer::Aligned(copy)));
+                let input = InputFile {
+                    path: path.to_path_buf(),
+                    member: Some(String::from_utf8_lossy(member.name).into_owned()),
                 };
-
-                let seg = &mut self.segments[seg_idx];
-                let off = align_up(seg.size, sec.align().max(1));
-                seg.size = off + sec.size();
-
-                if sec.kind() != SectionKind::UninitializedData {
-                    seg.data.resize(off as usize, 0);
-                    seg.data.extend_from_slice(sec.data()?);
+                if !self.accepted(&input)? {
+                    continue;
                 }
-
-                seg.sections.push(Section {
-                    file_index: file_idx,
-                    section_index: sec.index(),
-                    offset: off,
+                self.note(sym, |l| match reference {
+                    Some(r) => format!(
+                        "{} extracted to resolve the reference from {}",
+                        input, l.inputs[r]
+                    ),
+                    None => format!("{} extracted for the command line", input),
                 });
-                self.section_map
-                    .insert((file_idx, sec.index()), (seg_idx, off));
+                extracted = true;
+                let bitcode = archive.bitcode.contains(&i);
+                let ir = bitcode || archive.gcc_lto.contains(&i);
+                let (offset, size) = (member.offset, data.len() as u64);
+                if ir && self.claim(path, offset, size, input.to_string())? {
+                    continue;
+                }
+                self.extractions.push(Extraction {
+                    reference,
+                    extracted: self.objects.len(),
+                    symbol: self.names.str(sym).to_string(),
+                });
+                // Bitcode members are compiled on their own: which of them
+                // are needed is only known one at a time.
+                let parsed = if bitcode {
+                    let native = self
+                        .lto
+                        .compile(&[(path, data)])
+                        .with_context(|| format!("{}: LTO", input))?;
+                    ParsedObject::parse(self.keep(InputBuffer::Owned(native)))?
+                } else {
+                    match object {
+                        Some(parsed) => parsed,
+                        None => ParsedObject::parse(data)?,
+                    }
+                };
+                self.add_object(parsed, input)?;
             }
         }
+    }
 
-        self.build_got()?;
+    /// Start a group of archives (`--start-group`), which are searched
+    /// again at [`Self::end_group`].
+    pub fn start_group(&mut self) {
+        self.group = Some(Vec::new());
+    }
 
-        // Assign addresses
-        let (mut va, mut fo) = (BASE_ADDR + PAGE_SIZE, PAGE_SIZE);
-        for seg in &mut self.segments {
-            if seg.size == 0 {
-                continue;
+    /// End the group started by [`Self::start_group`]: search its archives
+    /// again and again until no more members are extracted, so archives in
+    /// it may depend on each other in any order (`--end-group`).
+    pub fn end_group(&mut self) -> Result<()> {
+        let Some(mut archives) = self.group.take() else {
+            return Ok(());
+        };
+        loop {
+            let mut extracted = false;
+            for a in &mut archives {
+                extracted |= self.extract_members(&a.path, &mut a.archive, &mut a.tried)?;
             }
-            va = align_up(va, PAGE_SIZE);
-            fo = align_up(fo, PAGE_SIZE);
-            seg.virtual_address = va;
-            seg.file_offset = fo;
-            va += seg.size;
-            if seg.kind != SectionKind::UninitializedData {
-                fo += seg.size;
+            if !extracted {
+                return Ok(());
             }
         }
-        Ok(())
     }
 
-    /// Which segment should this section go into?
-    fn segment_for(&self, sec: &object::Section) -> Option<usize> {
-        match sec.name().unwrap_or("") {
-            ".init" => Some(1),
-            ".fini" => Some(2),
-            _ => match sec.kind() {
-                SectionKind::Text => Some(0),
-                SectionKind::ReadOnlyData | SectionKind::ReadOnlyString => Some(3),
-                SectionKind::Data | SectionKind::Elf(14) | SectionKind::Elf(15) => Some(4),
-                SectionKind::Tls => Some(6),
-                SectionKind::UninitializedData => Some(7),
-                _ => {
-                    tracing::debug!("Skip: {} ({:?})", sec.name().unwrap_or("?"), sec.kind());
-                    None
-                }
-            },
+    fn add_object(&mut self, parsed: ParsedObject<'a>, input: InputFile) -> Result<()> {
+        self.resolve_objects(vec![(parsed, input)])
+            .map_err(|(_, e)| e)
+    }
+
+    /// Add objects given in a row, resolving their symbols shard by shard
+    /// on the worker threads. The result is the same as adding them one at
+    /// a time with [`Self::add_parsed`].
+    pub fn add_objects(&mut self, objects: Vec<(&Path, ParsedObject<'a>)>) -> Result<()> {
+        self.add_object_batch(objects).map_err(|(_, e)| e)
+    }
+
+    /// [`Self::add_objects`], failing with the path of the object at
+    /// fault.
+    fn add_object_batch(
+        &mut self,
+        objects: Vec<(&Path, ParsedObject<'a>)>,
+    ) -> Result<(), (PathBuf, anyhow::Error)> {
+        let mut batch = Vec::new();
+        for (path, obj) in objects {
+            if let Some(progress) = &self.progress {
+                progress.file_loaded(path);
+            }
+            // The plugin resolves the symbols of what it claims on the spot.
+            if self.options.plugin.is_some() && input::is_gcc_lto(&obj.obj) {
+                self.resolve_objects(std::mem::take(&mut batch))?;
+            }
+            match self.admit_object(path, obj) {
+                Ok(Some(object)) => batch.push(object),
+                Ok(None) => {}
+                Err(e) => return Err((path.to_path_buf(), e)),
+            }
         }
+        self.resolve_objects(batch)
     }
 
-    fn build_got(&mut self) -> Result<()> {
-        let mut off = 0u64;
-        for obj in &self.objects {
-            for sec in obj.sections() {
-                for (_, r) in sec.relocations() {
-                    let needs =
-                        matches!(r.kind(), RelocationKind::Got | RelocationKind::GotRelative)
-                            || matches!(r.target(), RelocationTarget::Symbol(i)
-                            if obj.symbol_by_index(i).is_ok_and(|s| s.kind() == SymbolKind::Tls));
-                    if !needs {
-                        continue;
-                    }
-                    let RelocationTarget::Symbol(i) = r.target() else {
-                        continue;
-                    };
-                    let name = obj.symbol_by_index(i)?.name()?;
-                    if !self.got.contains_key(name) {
-                        self.got.insert(name.to_string(), off);
-                        off += 8;
-                    }
-                }
+    /// Let an object from the command line into the link, unless the
+    /// linker plugin claims it or a link plugin turns it away.
+    fn admit_object(
+        &mut self,
+        path: &Path,
+        obj: ParsedObject<'a>,
+    ) -> Result<Option<(ParsedObject<'a>, InputFile)>> {
+        if input::is_gcc_lto(&obj.obj) {
+            let size = std::fs::metadata(path)
+                .with_context(|| format!("{}: GCC LTO object", path.display()))?
+                .len();
+            if self.claim(path, 0, size, path.display().to_string())? {
+                return Ok(None);
             }
         }
-        if let Some(g) = self.segments.iter_mut().find(|s| s.name == ".got") {
-            g.size = off;
-            g.data.resize(off as usize, 0);
+        let input = InputFile {
+            path: path.to_path_buf(),
+            member: None,
+        };
+        if !self.accepted(&input)? {
+            return Ok(None);
+        }
+        Ok(Some((obj, input)))
+    }
+
+    /// Check that `obj` can join a link for this target.
+    fn check_input(arch: &A, parsed: &ParsedObject, input: &InputFile) -> Result<()> {
+        let obj = &parsed.obj;
+        if A::arch() != obj.architecture() {
+            return Err(anyhow!(
+                "{}: built for {:?}, but linking for {:?}",
+                input,
+                obj.architecture(),
+                A::arch()
+            ));
+        }
+        validate::check_object(obj, &input.to_string(), arch)?;
+        if input::is_slim_lto(obj) {
+            return Err(anyhow!(
+                "{}: GCC LTO object without machine code; link it through the \
+                 compiler's linker plugin (-plugin liblto_plugin.so)",
+                input
+            ));
         }
         Ok(())
     }
 
-    fn resolve_symbols(&mut self) {
-        for sym in self.symbols.values_mut() {
-            sym.resolved_address = if sym.is_absolute {
-                Some(sym.offset)
-            } else if let Some(&(si, o)) = self
-                .section_map
-                .get(&(sym.input_file_index, sym.section_index))
-            {
-                Some(self.segments[si].virtual_address + o + sym.offset)
-            } else {
-                None
-            };
+    /// Resolve the symbols of `objects` and add them, in order. On failure,
+    /// the objects before the one at fault (whose path comes with the
+    /// error) are in the link.
+    fn resolve_objects(
+        &mut self,
+        mut objects: Vec<(ParsedObject<'a>, InputFile)>,
+    ) -> Result<(), (PathBuf, anyhow::Error)> {
+        if objects.is_empty() {
+            return Ok(());
+        }
+        // Only the objects before the first bad one join the link.
+        let arch = &self.arch;
+        let checks: Vec<Result<()>> = objects
+            .par_iter()
+            .map(|(parsed, input)| Self::check_input(arch, parsed, input))
+            .collect();
+        let bad = checks
+            .into_iter()
+            .enumerate()
+            .find_map(|(i, check)| Some((i, check.err()?)));
+        let bad = bad.map(|(i, e)| {
+            let path = objects[i].1.path.clone();
+            objects.truncate(i);
+            (path, e)
+        });
+
+        let first = self.objects.len();
+        // Recorded up front: a symbol may clash with another in this batch.
+        self.inputs
+            .extend(objects.iter().map(|(_, input)| input.clone()));
+        let names = &self.names;
+        let batch = Batch {
+            symbols: objects.iter().map(|(p, _)| &p.symbols[..]).collect(),
+            hashes: objects
+                .par_iter()
+                .map(|(p, _)| p.symbols.iter().map(|s| names.hash(s.name)).collect())
+                .collect(),
+            first,
+        };
+        let cx = symtab::Context {
+            inputs: &self.inputs,
+            shared_libs: &self.shared_libs,
+            synthetic: &self.synthetic_symbols,
+            muldefs: self.options.z.muldefs,
+            verbose: self.options.verbose,
+        };
+        let shards: Vec<Shard> = self
+            .names
+            .shards_mut()
+            .zip(self.symbols.shards_mut())
+            .zip(self.weak.shards_mut())
+            .zip(self.visibility.shards_mut())
+            .zip(self.undefined.shards_mut())
+            .zip(self.imports.shards_mut())
+            .map(
+                |(((((names, symbols), weak), visibility), undefined), imports)| Shard {
+                    names,
+                    symbols,
+                    weak,
+                    visibility,
+                    undefined,
+                    imports,
+                },
+            )
+            .collect();
+        // A thread per shard only pays off for batches of some size.
+        let count: usize = batch.symbols.iter().map(|s| s.len()).sum();
+        let resolved: Vec<Resolved> = if count < PARALLEL_SYMBOLS {
+            shards
+                .into_iter()
+                .map(|mut s| s.resolve(&cx, &batch))
+                .collect()
+        } else {
+            shards
+                .into_par_iter()
+                .map(|mut s| s.resolve(&cx, &batch))
+                .collect()
+        };
+
+        let mut clash: Option<Clash> = None;
+        for r in resolved {
+            self.resolution_log.extend(r.log);
+            if let Some(c) = r.clash.filter(|c| clash.is_none_or(|k| c.at < k.at)) {
+                clash = Some(c);
+            }
+        }
+        // Trace in input order, up to where resolution stopped.
+        let keep = clash.map_or(objects.len(), |c| c.at.0);
+        for (parsed, input) in &objects[..keep] {
+            self.trace_object(input, &parsed.symbols);
         }
+        let error = match clash {
+            Some(Clash { at: (i, j), first }) => {
+                let (parsed, input) = &objects[i];
+                self.trace_object(input, &parsed.symbols[..=j]);
+                let first = self.inputs[first].to_string();
+                let error =
+                    self.multiple_definition(parsed.symbols[j].name, &first, &input.to_string());
+                Some((input.path.clone(), error))
+            }
+            None => bad,
+        };
+        self.objects
+            .extend(objects.into_iter().take(keep).map(|(p, _)| p.obj));
+        error.map_or(Ok(()), Err)
     }
 
-    fn relocate(&mut self) -> Result<()> {
-        // Fill GOT
-        let entries: Vec<_> = self
-            .got
+    /// Record `--trace` and `--trace-symbol` lines for `symbols` of the
+    /// object `input`.
+    fn trace_object(&mut self, input: &InputFile, symbols: &[InputSymbol]) {
+        if self.options.trace {
+            self.trace_log.push(input.to_string());
+        }
+        if self.options.trace_symbols.is_empty() {
+            return;
+        }
+        let lines: Vec<String> = symbols
             .iter()
-            .map(|(name, &offset)| (offset, self.sym_addr(name)))
+            .filter(|sym| self.options.trace_symbols.contains(sym.name))
+            .map(|sym| {
+                let what = match sym.location {
+                    None => "reference to",
+                    Some(SymbolLocation::Common { .. }) => "common",
+                    Some(_) => "definition of",
+                };
+                format!("{}: {} {}", input, what, self.display_name(sym.name))
+            })
             .collect();
-        if let Some(g) = self.segments.iter_mut().find(|s| s.name == ".got") {
-            for (offset, addr) in entries {
-                g.data[offset as usize..][..8].copy_from_slice(&addr.to_le_bytes());
-            }
+        self.trace_log.extend(lines);
+    }
+
+    /// Record a symbol resolution decision for `--verbose`.
+    fn note(&mut self, name: Name, what: impl FnOnce(&Self) -> String) {
+        if self.options.verbose {
+            let what = what(self);
+            self.resolution_log.push((name, what));
         }
+    }
 
-        // Apply relocations
-        let got_va = self.got_addr();
-        for si in 0..self.segments.len() {
-            let patches: Vec<_> = self.segments[si]
-                .sections
-                .clone()
-                .iter()
-                .flat_map(|sec| {
-                    let obj = &self.objects[sec.file_index];
-                    let s = obj.section_by_index(sec.section_index).ok()?;
-                    let base = self.segments[si].virtual_address + sec.offset;
-                    Some(
-                        s.relocations()
-                            .filter_map(|(o, r)| {
-                                let t = self.reloc_target(obj, &r, sec.file_index, got_va).ok()?;
-                                Some((sec.offset + o, r, base + o, t))
-                            })
-                            .collect::<Vec<_>>(),
-                    )
-                })
-                .flatten()
-                .collect();
+    /// Resolve symbols, lay out and relocate the inputs added so far.
+    pub fn link(&mut self) -> Result<()> {
+        self.link_with_trace(&mut Trace::new())
+    }
 
-            for (o, r, p, t) in patches {
-                self.arch
-                    .apply_relocation(o, &r, p, t, r.addend(), &mut self.segments[si].data)?;
+    /// Like [`Self::link`], recording how long each phase took in `trace`.
+    pub fn link_with_trace(&mut self, trace: &mut Trace) -> Result<()> {
+        self.milestone(Milestone::InputsAdded);
+        if self
+            .plugin
+            .as_ref()
+            .is_some_and(|p| !p.claimed().is_empty())
+        {
+            trace.time("lto", || self.run_plugin())?;
+        }
+        if !self.bitcode.is_empty() {
+            trace.time("lto", || self.compile_bitcode())?;
+        }
+        self.resolve_with_plugins();
+        trace.time("layout", || self.layout())?;
+        self.milestone(Milestone::LaidOut);
+        trace.time("resolve", || {
+            self.resolve_symbols();
+            // Veneers can move sections, so they are part of resolving.
+            self.reserve_veneers()
+        })?;
+        self.milestone(Milestone::SymbolsResolved);
+        trace.time("relocate", || self.relocate())?;
+        if !self.link_plugins.is_empty() {
+            // Plugins edit the output's bytes in place.
+            for seg in &mut self.segments {
+                seg.flatten(&self.objects)?;
             }
         }
+        let mut output = hooks::Output {
+            segments: &mut self.segments,
+            non_alloc: &mut self.non_alloc,
+        };
+        for plugin in &self.link_plugins {
+            plugin.before_write(&mut output)?;
+        }
+        self.milestone(Milestone::Relocated);
         Ok(())
     }
 
-    /// Find the address of a relocation target
-    /// Afterwards the arch specific implementation can apply the relocation
-    fn reloc_target(&self, obj: &object::File, r: &Relocation, fi: usize, got: u64) -> Result<u64> {
+    /// Let the link plugins define the symbols no input does.
+    fn resolve_with_plugins(&mut self) {
+        if self.link_plugins.is_empty() {
+            return;
+        }
+        let mut names: Vec<Name> = self.undefined.keys().copied().collect();
+        names.sort_by(|&a, &b| self.names.str(a).cmp(self.names.str(b)));
+        for n in names {
+            let name = self.names.str(n);
+            let value = self
+                .link_plugins
+                .iter()
+                .find_map(|p| p.resolve_undefined(name));
+            if let Some(value) = value {
+                self.synthetic_symbols.provide(name, value);
+                self.note(n, |_| "defined by a link plugin".to_string());
+            }
+        }
+    }
+
+    /// Counts of the inputs and of the work done, for `--stats`.
+    pub fn counters(&self) -> Counters {
+        Counters {
+            objects: self.objects.len(),
+            archive_members: self.extractions.len(),
+            shared_libraries: self.shared_libs.len(),
+            symbols: self.symbols.len(),
+            sections: self.segments.iter().map(|s| s.sections.len()).sum(),
+            relocations: self.relocations,
+            output_bytes: 0,
+        }
+    }
+
+    fn is_shared(&self) -> bool {
+        self.options.output_kind == OutputKind::SharedObject
+    }
+
+    fn is_pie(&self) -> bool {
+        self.options.output_kind == OutputKind::Pie
+    }
+
+    /// Whether the output may be loaded at any address, so that absolute
+    /// addresses in it need `RELATIVE` relocations.
+    fn is_pic(&self) -> bool {
+        self.is_shared() || self.is_pie()
+    }
+
+    /// Whether the output carries `.dynsym` and `.dynamic`: always for shared
+    /// objects and PIEs, whose loader (or startup code, for static-PIE)
+    /// relocates them through `_DYNAMIC`, and for executables that export
+    /// symbols for `dlopen`ed plugins.
+    fn has_dynamic(&self) -> bool {
+        self.is_pic()
+            || self.is_dynamic_executable()
+            || self.options.export_dynamic
+            || !self.options.dynamic_exports.is_empty()
+    }
+
+    /// An executable linked against shared libraries, started by the
+    /// program interpreter named in `.interp`.
+    fn is_dynamic_executable(&self) -> bool {
+        !self.is_shared() && !self.shared_libs.is_empty()
+    }
+
+    /// Whether `.interp` names a program interpreter: for dynamic
+    /// executables and PIEs, unless `--no-dynamic-linker`.
+    fn has_interp(&self) -> bool {
+        (self.is_dynamic_executable() || self.is_pie()) && !self.options.no_dynamic_linker
+    }
+
+    /// Sonames of the shared libraries recorded as `DT_NEEDED`.
+    ///
+    /// Libraries added under `--as-needed` are dropped unless a reference
+    /// was resolved against them.
+    fn needed_libs(&self) -> Vec<String> {
+        let mut needed: Vec<String> = Vec::new();
+        for (i, lib) in self.shared_libs.iter().enumerate() {
+            let used = self.imports.values().any(|&l| l == i);
+            if (used || !lib.as_needed) && !needed.contains(&lib.soname) {
+                needed.push(lib.soname.clone());
+            }
+        }
+        needed
+    }
+
+    /// Whether a defined global belongs in `.dynsym`.
+    fn is_exported(&self, name: &str) -> bool {
+        let wanted = self.is_shared()
+            || self.options.export_dynamic
+            || self
+                .options
+                .dynamic_exports
+                .iter()
+                .any(|p| glob_match(p, name));
+        wanted
+            && !self.synthetic.get(name).is_some_and(|s| s.provide)
+            && !self.version_script().is_some_and(|v| v.is_local(name))
+            && !self.is_from_excluded_lib(name)
+            && !input::is_hidden(self.visibility(name))
+    }
+
+    /// The `STV_*` visibility of global `name`, merged over all inputs.
+    /// Hidden and internal symbols are never exported; protected ones are
+    /// exported, but references from the output bind to its own definition.
+    fn visibility(&self, name: &str) -> u8 {
+        self.names
+            .get(name)
+            .and_then(|n| self.visibility.get(&n))
+            .copied()
+            .unwrap_or(object::elf::STV_DEFAULT)
+    }
+
+    /// The definition of global `name` in the inputs, if any.
+    fn symbol(&self, name: &str) -> Option<&DefinedSymbol> {
+        self.symbols.get(&self.names.get(name)?)
+    }
+
+    /// The shared library global `name` is bound to at runtime, if any.
+    fn import(&self, name: &str) -> Option<usize> {
+        self.imports.get(&self.names.get(name)?).copied()
+    }
+
+    /// Whether `name` is referenced, but defined nowhere so far.
+    fn is_undefined(&self, name: &str) -> bool {
+        self.names
+            .get(name)
+            .is_some_and(|n| self.undefined.contains_key(&n))
+    }
+
+    /// [`Self::symbol`], to update in place.
+    fn symbol_mut(&mut self, name: &str) -> Option<&mut DefinedSymbol> {
+        let name = self.names.get(name)?;
+        self.symbols.get_mut(&name)
+    }
+
+    /// Whether every reference to `name` is weak.
+    fn is_weak_ref(&self, name: &str) -> bool {
+        self.names.get(name).is_some_and(|n| self.weak.contains(&n))
+    }
+
+    /// Whether `name` is defined by a member of an `--exclude-libs` archive.
+    fn is_from_excluded_lib(&self, name: &str) -> bool {
+        let Some(sym) = self.symbol(name) else {
+            return false;
+        };
+        if self.synthetic.contains_key(name) {
+            return false;
+        }
+        let input = &self.inputs[sym.input_file_index];
+        let Some(lib) = input.path.file_name().and_then(|n| n.to_str()) else {
+            return false;
+        };
+        input.member.is_some()
+            && self
+                .options
+                .exclude_libs
+                .iter()
+                .any(|l| l == "ALL" || l == lib)
+    }
+
+    fn version_script(&self) -> Option<&VersionScript> {
+        self.options.version_script.as_ref()
+    }
+
+    /// Whether the output defines versions in `.gnu.version_d`.
+    fn has_versions(&self) -> bool {
+        self.has_dynamic() && self.version_script().is_some_and(|v| v.has_versions())
+    }
+
+    /// Load address of the first byte of the file.
+    fn base_addr(&self) -> u64 {
+        if let Some(base) = self.options.image_base {
+            base
+        } else if self.is_pic() {
+            0
+        } else {
+            BASE_ADDR
+        }
+    }
+
+    /// The combined `.note.gnu.property`: the x86 features every input
+    /// object has, plus those `-z ibt` and `-z shstk` turn on. `None` when
+    /// the output has none.
+    fn gnu_property(&self) -> Result<Option<Vec<u8>>> {
+        if !matches!(
+            A::arch(),
+            object::Architecture::X86_64 | object::Architecture::I386
+        ) {
+            return Ok(None);
+        }
+        let (e, word) = (self.arch.endianness(), self.arch.word_size());
+        let z = &self.options.z;
+        let ibt = object::elf::GNU_PROPERTY_X86_FEATURE_1_IBT;
+        let mut features = if self.objects.is_empty() { 0 } else { u32::MAX };
+        for (file, obj) in self.objects.iter().enumerate() {
+            let found = match obj.section_by_name(property::SECTION) {
+                Some(sec) => property::x86_features(sec.data()?, e, word)
+                    .with_context(|| self.inputs[file].to_string())?,
+                None => 0,
+            };
+            if z.force_ibt && found & ibt == 0 {
+                tracing::warn!(
+                    "{}: -z force-ibt: file does not have GNU_PROPERTY_X86_FEATURE_1_IBT property",
+                    self.inputs[file]
+                );
+            }
+            features &= found;
+        }
+        if z.ibt || z.force_ibt {
+            features |= ibt;
+        }
+        if z.shstk {
+            features |= object::elf::GNU_PROPERTY_X86_FEATURE_1_SHSTK;
+        }
+        Ok((features != 0).then(|| property::encode_x86_features(features, e, word)))
+    }
+
+    fn layout(&mut self) -> Result<()> {
+        // .dynsym, .rela.dyn, .dynamic and --emit-relocs tables are ELF64.
+        if self.arch.word_size() != 8 && (self.has_dynamic() || self.options.emit_relocs) {
+            let libs: Vec<&str> = self.shared_libs.iter().map(|l| &l.soname[..]).collect();
+            return Err(anyhow!(
+                "{:?}: only static executables can be linked for 32-bit targets{}",
+                A::arch(),
+                match &libs[..] {
+                    [] => String::new(),
+                    libs => format!(", not against {}", libs.join(", ")),
+                }
+            ));
+        }
+        // BSS must be last (no file content)
+        self.segments = Vec::new();
+        if self.has_interp() {
+            let interp = self
+                .options
+                .dynamic_linker
+                .as_deref()
+                .unwrap_or(self.arch.default_dynamic_linker());
+            let mut seg = Segment::new(".interp", SectionKind::Elf(object::elf::SHT_PROGBITS));
+            seg.data = [interp.as_bytes(), b"\0"].concat();
+            seg.size = seg.data.len() as u64;
+            self.segments.push(seg);
+        }
+        if let Some(note) = self.gnu_property()? {
+            let mut seg = Segment::new(property::SECTION, SectionKind::Elf(object::elf::SHT_NOTE));
+            seg.size = note.len() as u64;
+            seg.data = note;
+            seg.align = self.arch.word_size();
+            self.segments.push(seg);
+        }
+        if let Some(note) = self.package_note() {
+            let mut seg = Segment::new(PACKAGE_NOTE, SectionKind::Elf(object::elf::SHT_NOTE));
+            seg.size = note.len() as u64;
+            seg.data = note;
+            seg.align = 4;
+            self.segments.push(seg);
+        }
+        for name in self.note_sections() {
+            if self.segment_index(&name).is_none() {
+                self.segments
+                    .push(Segment::new(&name, SectionKind::Elf(object::elf::SHT_NOTE)));
+            }
+        }
+        self.segments.extend([
+            Segment::new(".text", SectionKind::Text),
+            Segment::new(".init", SectionKind::Text),
+            Segment::new(".fini", SectionKind::Text),
+        ]);
+        if self.has_dynamic() {
+            self.segments.push(Segment::new(".plt", SectionKind::Text));
+        }
+        self.segments.extend([
+            Segment::new(".rodata", SectionKind::ReadOnlyData),
+            Segment::new(".eh_frame", SectionKind::ReadOnlyData),
+            Segment::new(".gcc_except_table", SectionKind::ReadOnlyData),
+        ]);
+        if self.has_dynamic() {
+            let style = self.options.hash_style;
+            if style.sysv() {
+                self.segments.push(Segment::new(
+                    ".hash",
+                    SectionKind::Elf(object::elf::SHT_HASH),
+                ));
+            }
+            if style.gnu() {
+                self.segments.push(Segment::new(
+                    ".gnu.hash",
+                    SectionKind::Elf(object::elf::SHT_GNU_HASH),
+                ));
+            }
+            self.segments.extend([
+                Segment::new(".dynsym", SectionKind::Elf(object::elf::SHT_DYNSYM)),
+                Segment::new(".dynstr", SectionKind::Elf(object::elf::SHT_STRTAB)),
+            ]);
+            // Left empty, and so dropped, unless the output defines versions
+            // or needs them from shared libraries.
+            self.segments.extend([
+                Segment::new(
+                    ".gnu.version",
+                    SectionKind::Elf(object::elf::SHT_GNU_VERSYM),
+                ),
+                Segment::new(
+                    ".gnu.version_d",
+                    SectionKind::Elf(object::elf::SHT_GNU_VERDEF),
+                ),
+                Segment::new(
+                    ".gnu.version_r",
+                    SectionKind::Elf(object::elf::SHT_GNU_VERNEED),
+                ),
+            ]);
+            self.segments.extend([
+                Segment::new(".rela.dyn", SectionKind::Elf(object::elf::SHT_RELA)),
+                Segment::new(".rela.plt", SectionKind::Elf(object::elf::SHT_RELA)),
+            ]);
+        }
+        self.segments.extend([
+            Segment::new(
+                ".preinit_array",
+                SectionKind::Elf(object::elf::SHT_PREINIT_ARRAY),
+            ),
+            Segment::new(".init_array", SectionKind::Elf(object::elf::SHT_INIT_ARRAY)),
+            Segment::new(".fini_array", SectionKind::Elf(object::elf::SHT_FINI_ARRAY)),
+            // Written only by relocations, so it can join the RELRO region.
+            Segment::new(".data.rel.ro", SectionKind::Data),
+            Segment::new(".data", SectionKind::Data),
+            Segment::new(got::SECTION, SectionKind::Data),
+            Segment::new(got::PLT_SECTION, SectionKind::Data),
+        ]);
+        if self.has_dynamic() {
+            self.segments.push(Segment::new(
+                ".dynamic",
+                SectionKind::Elf(object::elf::SHT_DYNAMIC),
+            ));
+        }
+        self.segments.extend([
+            Segment::new(".tdata", SectionKind::Tls),
+            Segment::new(".tbss", SectionKind::UninitializedTls),
+            Segment::new(".bss", SectionKind::UninitializedData),
+        ]);
+        if self.options.z.relro {
+            // Keep the sections PT_GNU_RELRO covers together, ahead of .data.
+            if let Some(data) = self.segment_index(".data") {
+                let seg = self.segments.remove(data);
+                let at = self
+                    .segments
+                    .iter()
+                    .rposition(|s| RELRO_SECTIONS.contains(&s.name.as_str()))
+                    .map_or(data, |i| i + 1);
+                self.segments.insert(at, seg);
+            }
+        }
+        if self.options.z.keep_text_section_prefix {
+            self.add_text_prefix_segments();
+        }
+        self.add_encapsulation_segments();
+        self.add_orphan_segments()?;
+        for seg in &self.segments {
+            if is_c_identifier(&seg.name) {
+                self.synthetic_symbols.provide_bounds(
+                    &seg.name,
+                    &format!("__start_{}", seg.name),
+                    &format!("__stop_{}", seg.name),
+                );
+            }
+        }
+
+        // Startup code copying a relocated section needs both of its ranges.
+        let mut lmas: Vec<&String> = self.options.section_lmas.keys().collect();
+        lmas.sort();
+        for seg in lmas {
+            let stem = seg.trim_start_matches('.').replace('.', "_");
+            self.synthetic_symbols.provide_bounds(
+                seg,
+                &format!("__{}_start", stem),
+                &format!("__{}_end", stem),
+            );
+            self.synthetic_symbols.provide(
+                &format!("__{}_load_start", stem),
+                SyntheticValue::SegmentLoadStart(seg.clone()),
+            );
+            self.synthetic_symbols.provide(
+                &format!("__{}_load_end", stem),
+                SyntheticValue::SegmentLoadEnd(seg.clone()),
+            );
+        }
+
+        // Init/fini arrays are ordered by priority and ordering-file sections
+        // by position rather than input order, so decide where every section
+        // goes before placing any.
+        let order = if self.options.symbol_ordering.is_empty() && self.options.call_graph_sort {
+            self.call_graph_order()?
+        } else {
+            self.section_order()
+        };
+        let mut placements = Vec::new();
+        for (file_idx, obj) in self.objects.iter().enumerate() {
+            for sec in obj.sections() {
+                if sec.size() == 0 {
+                    continue;
+                }
+                let Some(seg_idx) = self.segment_for(file_idx, &sec) else {
+                    // Symbol/string/relocation tables and property notes are
+                    // consumed, not discarded.
+                    if sec.kind() != SectionKind::Metadata
+                        && sec.name() != Ok(property::SECTION)
+                        && sec.name() != Ok(COMMENT)
+                        && !self.keeps_debug(&sec)
+                    {
+                        self.discarded.push((file_idx, sec.index()));
+                    }
+                    continue;
+                };
+                let name = sec.name().unwrap_or("");
+                // crtbegin/crtend bracket .ctors/.dtors with -1 and 0 words that
+                // have no meaning in an init/fini array.
+                if legacy_array(name).is_some() && is_ctors_sentinel(&sec) {
+                    self.discarded.push((file_idx, sec.index()));
+                    continue;
+                }
+                let priority = init_priority(name).unwrap_or(0);
+                let file = self.inputs[file_idx].to_string();
+                let sort = self.section_rules.sort_for(&file, name);
+                // Hot/cold text groups stay contiguous, ahead of plain text,
+                // and so do `.text.sorted.*`, whose sorted pattern leads the
+                // `.text` rule.
+                let group = match sec.kind() {
+                    SectionKind::Text => text_prefix(name)
+                        .unwrap_or(TEXT_PREFIXES.len() + usize::from(sort.is_none())),
+                    _ => 0,
+                };
+                let position = order
+                    .get(&(file_idx, sec.index()))
+                    .copied()
+                    .unwrap_or(usize::MAX);
+                let sorted = match sort.or(self.options.sort_section) {
+                    Some(SortSection::Name) => (0, name.to_string()),
+                    Some(SortSection::Alignment) => (u64::MAX - sec.align(), String::new()),
+                    None => (0, String::new()),
+                };
+                placements.push((
+                    (priority, group, position, sorted),
+                    file_idx,
+                    sec.index(),
+                    seg_idx,
+                ));
+            }
+        }
+        // Stable, so everything else keeps input order.
+        placements.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut counts = vec![0; self.segments.len()];
+        for &(.., seg_idx) in &placements {
+            counts[seg_idx] += 1;
+        }
+        for (seg, count) in self.segments.iter_mut().zip(counts) {
+            seg.sections.reserve_exact(count);
+        }
+
+        for (_, file_idx, sec_index, seg_idx) in placements {
+            let sec = self.objects[file_idx].section_by_index(sec_index)?;
+            let seg = &mut self.segments[seg_idx];
+            let align = sec.align().max(1);
+            let off = match self.options.pad_sections {
+                Some(pad) if seg.kind == SectionKind::Text => {
+                    align_up(align_up(seg.size, align.max(pad.align)) + pad.offset, align)
+                }
+                _ => align_up(seg.size, align),
+            };
+            seg.size = off + sec.size();
+            seg.align = seg.align.max(align);
+
+            // .ctors/.dtors run from the end backwards; init/fini arrays run forwards.
+            let reversed = legacy_array(sec.name().unwrap_or("")).is_some();
+            let word = self.arch.word_size() as usize;
+            let mut file_size = 0;
+            let mut data = None;
+            if sec.kind() != SectionKind::UninitializedData {
+                let input = sec.data()?;
+                file_size = input.len() as u64;
+                if reversed {
+                    data = Some(input.chunks(word).rev().flatten().copied().collect());
+                }
+                if !self.link_plugins.is_empty() {
+                    let mut bytes = data.take().unwrap_or_else(|| input.to_vec());
+                    let loaded = LoadedSection {
+                        input: &self.inputs[file_idx],
+                        name: sec.name().unwrap_or("?"),
+                        output: &seg.name,
+                    };
+                    for plugin in &self.link_plugins {
+                        plugin.on_section_loaded(&loaded, &mut bytes)?;
+                    }
+                    // Unchanged sections are read from the input when written.
+                    if reversed || bytes != input {
+                        data = Some(bytes);
+                    }
+                }
+            }
+
+            seg.sections.push(Section {
+                file_index: file_idx,
+                section_index: sec.index(),
+                name: sec.name().unwrap_or("?").to_string(),
+                offset: off,
+                size: sec.size(),
+                file_size,
+                reversed,
+                data,
+            });
+            self.section_map
+                .insert((file_idx, sec.index()), (seg_idx, off));
+        }
+
+        self.allocate_commons();
+        self.define_synthetic_symbols()?;
+        self.check_required()?;
+        self.check_visibility()?;
+        if self.reports_undefined() {
+            self.check_defs()?;
+        }
+        self.check_split_stack()?;
+        self.check_discarded()?;
+        self.build_got()?;
+        if self.has_dynamic() {
+            self.build_dynamic()?;
+        }
+        self.assign_addresses()
+    }
+
+    /// Give the output sections their addresses and file offsets, in order.
+    fn assign_addresses(&mut self) -> Result<()> {
+        let page = self.options.z.page_size();
+        let (mut va, mut fo) = (self.base_addr() + page, page);
+        // Notes follow each other without going to a new page, so that one
+        // PT_NOTE can cover them.
+        let mut after_note = false;
+        for seg in &mut self.segments {
+            if seg.size == 0 {
+                continue;
+            }
+            let note = seg.kind == SectionKind::Elf(object::elf::SHT_NOTE);
+            let start = self.options.section_starts.get(&seg.name);
+            if note && after_note && start.is_none() {
+                va = align_up(va, seg.align);
+                fo = align_up(fo, seg.align);
+            } else {
+                fo = align_up(fo, page);
+                let align = seg.align.max(page);
+                match start {
+                    // Keep the file offset congruent with the address so the
+                    // section can still be mapped.
+                    Some(&addr) => {
+                        if !addr.is_multiple_of(seg.align) {
+                            tracing::warn!(
+                                "address {:#x} of section {} is not a multiple of its alignment ({})",
+                                addr,
+                                seg.name,
+                                seg.align
+                            );
+                        }
+                        va = addr;
+                        fo += addr % page;
+                    }
+                    None => {
+                        va = align_up(va, align);
+                        // Sections aligned beyond a page keep their file offset
+                        // congruent with the address modulo the alignment.
+                        fo += va.wrapping_sub(fo) % align;
+                    }
+                }
+            }
+            after_note = note;
+            seg.virtual_address = va;
+            seg.load_address = self
+                .options
+                .section_lmas
+                .get(&seg.name)
+                .copied()
+                .unwrap_or(va);
+            seg.file_offset = fo;
+            va += seg.size;
+            if !seg.is_nobits() {
+                fo += seg.size;
+            }
+        }
+        if !self.options.section_starts.is_empty() {
+            self.check_overlaps(false)?;
+        }
+        if !self.options.section_lmas.is_empty() {
+            self.check_overlaps(true)?;
+        }
+        self.place_synthetic_symbols();
+        Ok(())
+    }
+
+    /// Reject output sections that were placed on top of each other, by
+    /// virtual address or (for file-backed sections) by load address.
+    fn check_overlaps(&self, load: bool) -> Result<()> {
+        let (address, what): (fn(&Segment) -> u64, _) = if load {
+            (|s| s.load_address, "load range ")
+        } else {
+            (|s| s.virtual_address, "")
+        };
+        let mut placed: Vec<&Segment> = self
+            .segments
+            .iter()
+            .filter(|s| s.size > 0 && !(load && s.is_nobits()))
+            .collect();
+        placed.sort_by_key(|s| address(s));
+        for pair in placed.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if address(a) + a.size > address(b) {
+                return Err(anyhow!(
+                    "section {} {}[{:#x}, {:#x}) overlaps {} [{:#x}, {:#x})",
+                    a.name,
+                    what,
+                    address(a),
+                    address(a) + a.size,
+                    b.name,
+                    address(b),
+                    address(b) + b.size
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The `.note.package` note holding the `--package-metadata` JSON, as
+    /// systemd's coredump tools read it.
+    fn package_note(&self) -> Option<Vec<u8>> {
+        let json = self.options.package_metadata.as_ref()?;
+        let e = self.arch.endianness();
+        let descsz = json.len() as u32 + 1;
+        let mut out = Vec::new();
+        for v in [4, descsz, NT_FDO_PACKAGING_METADATA] {
+            out.extend_from_slice(&e.write_u32_bytes(v));
+        }
+        out.extend_from_slice(b"FDO\0");
+        out.extend_from_slice(json.as_bytes());
+        out.resize(align_up(out.len() as u64 + 1, 4) as usize, 0);
+        Some(out)
+    }
+
+    /// Names of the loaded note sections in the inputs, in the order they
+    /// first appear. Each becomes an output section of its own, up front
+    /// with the property note, for the writer to cover with `PT_NOTE`.
+    fn note_sections(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for (file, obj) in self.objects.iter().enumerate() {
+            for sec in obj.sections() {
+                if !is_note(&sec) || sec.size() == 0 {
+                    continue;
+                }
+                let name = sec.name().unwrap_or("");
+                let ruled = self
+                    .section_rules
+                    .output_for(&self.inputs[file].to_string(), name)
+                    .is_some();
+                if !ruled && !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names
+    }
+
+    /// Which segment should section `sec` of input `file` go into?
+    fn segment_for(&self, file: usize, sec: &object::Section) -> Option<usize> {
+        let name = sec.name().unwrap_or("");
+        // Merged into a note of the linker's own by `gnu_property`.
+        if name == property::SECTION {
+            return None;
+        }
+        if let Some(seg) = self.segment_by_rule(file, sec) {
+            return Some(seg);
+        }
+        if self.orphan_kind(file, sec).is_some() {
+            return self.segment_index(name);
+        }
+        let name = match name {
+            ".init" => ".init",
+            ".fini" => ".fini",
+            _ if legacy_array(name).is_some() => legacy_array(name)?,
+            _ if is_note(sec) => name,
+            _ if is_c_identifier(name) && self.segment_index(name).is_some() => name,
+            _ if self.options.z.keep_text_section_prefix
+                && sec.kind() == SectionKind::Text
+                && text_prefix(name).is_some() =>
+            {
+                TEXT_PREFIXES[text_prefix(name)?]
+            }
+            // Sections the name rules don't place go by their kind.
+            _ => match sec.kind() {
+                SectionKind::Text => ".text",
+                SectionKind::ReadOnlyData | SectionKind::ReadOnlyString => ".rodata",
+                SectionKind::Data => ".data",
+                SectionKind::Elf(object::elf::SHT_PREINIT_ARRAY) => ".preinit_array",
+                SectionKind::Elf(object::elf::SHT_INIT_ARRAY) => ".init_array",
+                SectionKind::Elf(object::elf::SHT_FINI_ARRAY) => ".fini_array",
+                SectionKind::Tls => ".tdata",
+                SectionKind::UninitializedTls => ".tbss",
+                SectionKind::UninitializedData => ".bss",
+                _ => {
+                    tracing::debug!("Skip: {} ({:?})", sec.name().unwrap_or("?"), sec.kind());
+                    return None;
+                }
+            },
+        };
+        self.segment_index(name)
+    }
+
+    /// The segment the [`SectionRules`] place an allocated section in.
+    ///
+    /// The sections handled specially by [`Linker::segment_for`] come first,
+    /// and a rule is only followed to a segment of the same nature: `.bss`
+    /// can't hold contents, and only TLS goes to `.tdata`.
+    fn segment_by_rule(&self, file: usize, sec: &object::Section) -> Option<usize> {
+        let name = sec.name().ok()?;
+        if self.is_special(sec) || !is_alloc(sec) {
+            return None;
+        }
+        let output = self
+            .section_rules
+            .output_for(&self.inputs[file].to_string(), name)?;
+        let seg = self.segment_index(output)?;
+        let kind = self.segments[seg].kind;
+        let nobits = |k| {
+            matches!(
+                k,
+                SectionKind::UninitializedData | SectionKind::UninitializedTls
+            )
+        };
+        let tls = |k| matches!(k, SectionKind::Tls | SectionKind::UninitializedTls);
+        (nobits(kind) == nobits(sec.kind()) && tls(kind) == tls(sec.kind())).then_some(seg)
+    }
+
+    /// Sections [`Linker::segment_for`] places by name ahead of the rules.
+    fn is_special(&self, sec: &object::Section) -> bool {
+        let name = sec.name().unwrap_or("");
+        matches!(name, ".init" | ".fini")
+            || legacy_array(name).is_some()
+            || (is_c_identifier(name) && self.segment_index(name).is_some())
+            || (self.options.z.keep_text_section_prefix
+                && sec.kind() == SectionKind::Text
+                && text_prefix(name).is_some())
+    }
+
+    /// For an orphan section, the kind of output section it needs, going by
+    /// its flags. TLS sections are left to their kind, as are the special
+    /// sections and those a rule describes.
+    fn orphan_kind(&self, file: usize, sec: &object::Section) -> Option<SectionKind> {
+        let name = sec.name().ok()?;
+        if sec.size() == 0
+            || !is_alloc(sec)
+            || name == property::SECTION
+            || is_note(sec)
+            || self.is_special(sec)
+            || matches!(sec.kind(), SectionKind::Tls | SectionKind::UninitializedTls)
+            || self
+                .section_rules
+                .output_for(&self.inputs[file].to_string(), name)
+                .is_some()
+        {
+            return None;
+        }
+        let SectionFlags::Elf { sh_flags } = sec.flags() else {
+            return None;
+        };
+        let flag = |f: u32| sh_flags & u64::from(f) != 0;
+        Some(if flag(object::elf::SHF_EXECINSTR) {
+            SectionKind::Text
+        } else if sec.kind() == SectionKind::UninitializedData {
+            SectionKind::UninitializedData
+        } else if flag(object::elf::SHF_WRITE) {
+            SectionKind::Data
+        } else {
+            SectionKind::ReadOnlyData
+        })
+    }
+
+    /// Add an output section for each orphan section name, after the
+    /// built-in section of the same kind, as `--orphan-handling` says.
+    ///
+    /// An orphan named like an existing output section joins it.
+    fn add_orphan_segments(&mut self) -> Result<()> {
+        let mut orphans = Vec::new();
+        for (file, obj) in self.objects.iter().enumerate() {
+            for sec in obj.sections() {
+                if let Some(kind) = self.orphan_kind(file, &sec) {
+                    let name = sec.name().unwrap_or("").to_string();
+                    orphans.push((file, name, kind));
+                }
+            }
+        }
+        match self.options.orphan_handling {
+            OrphanHandling::Place => {}
+            OrphanHandling::Discard => return Ok(()),
+            OrphanHandling::Warn => {
+                for (file, name, _) in &orphans {
+                    tracing::warn!(
+                        "{}:({}) is being placed in '{}'",
+                        self.inputs[*file],
+                        name,
+                        name
+                    );
+                }
+            }
+            OrphanHandling::Error if orphans.is_empty() => {}
+            OrphanHandling::Error => {
+                let list: Vec<String> = orphans
+                    .iter()
+                    .map(|(file, name, _)| format!("  {}:({})", self.inputs[*file], name))
+                    .collect();
+                return Err(anyhow!(
+                    "orphan sections (--orphan-handling=error):\n{}",
+                    list.join("\n")
+                ));
+            }
+        }
+
+        let mut added: HashSet<String> = HashSet::new();
+        for (_, name, kind) in orphans {
+            if self.segment_index(&name).is_some() {
+                continue;
+            }
+            let anchor = match kind {
+                SectionKind::Text => ".fini",
+                SectionKind::UninitializedData => ".bss",
+                SectionKind::Data => ".data",
+                _ => ".rodata",
+            };
+            let Some(mut at) = self.segment_index(anchor) else {
+                continue;
+            };
+            // After the encapsulation sections and earlier orphans there.
+            while self
+                .segments
+                .get(at + 1)
+                .is_some_and(|s| is_c_identifier(&s.name) || added.contains(&s.name))
+            {
+                at += 1;
+            }
+            self.segments.insert(at + 1, Segment::new(&name, kind));
+            added.insert(name);
+        }
+        Ok(())
+    }
+
+    /// Add an output section ahead of `.text` for each [`TEXT_PREFIXES`]
+    /// group that has input sections, in GNU ld's order.
+    fn add_text_prefix_segments(&mut self) {
+        let Some(text) = self.segment_index(".text") else {
+            return;
+        };
+        let used: Vec<&str> = TEXT_PREFIXES
+            .iter()
+            .copied()
+            .filter(|prefix| {
+                self.objects.iter().flat_map(|o| o.sections()).any(|sec| {
+                    sec.kind() == SectionKind::Text
+                        && sec.size() > 0
+                        && text_prefix(sec.name().unwrap_or("")).map(|i| TEXT_PREFIXES[i])
+                            == Some(*prefix)
+                })
+            })
+            .collect();
+        for (i, prefix) in used.into_iter().enumerate() {
+            self.segments
+                .insert(text + i, Segment::new(prefix, SectionKind::Text));
+        }
+    }
+
+    /// Give every input section named like a C identifier its own output
+    /// section, so `__start_<name>` and `__stop_<name>` bracket all of them.
+    ///
+    /// Each is placed after the built-in section of the same kind. uld never
+    /// garbage-collects sections, so these are always kept.
+    fn add_encapsulation_segments(&mut self) {
+        for obj in &self.objects {
+            for sec in obj.sections() {
+                let name = sec.name().unwrap_or("");
+                let anchor = match sec.kind() {
+                    SectionKind::Text => ".fini",
+                    SectionKind::ReadOnlyData | SectionKind::ReadOnlyString => ".rodata",
+                    SectionKind::Data => ".data",
+                    SectionKind::UninitializedData => ".bss",
+                    _ => continue,
+                };
+                if sec.size() == 0 || !is_c_identifier(name) || self.segment_index(name).is_some() {
+                    continue;
+                }
+                let Some(mut at) = self.segment_index(anchor) else {
+                    continue;
+                };
+                // Keep earlier encapsulation sections of the same kind first.
+                while self
+                    .segments
+                    .get(at + 1)
+                    .is_some_and(|s| is_c_identifier(&s.name))
+                {
+                    at += 1;
+                }
+                let kind = match sec.kind() {
+                    SectionKind::ReadOnlyString => SectionKind::ReadOnlyData,
+                    kind => kind,
+                };
+                self.segments.insert(at + 1, Segment::new(name, kind));
+            }
+        }
+    }
+
+    /// Define the symbols in the synthetic symbol registry.
+    ///
+    /// Built-in symbols are only defined when referenced and not defined by an
+    /// input; user definitions always are, replacing input definitions.
+    /// Bounds of non-empty output sections are relative to their first and
+    /// last input sections; everything else starts out absolute and gets its
+    /// final value in [`Self::place_synthetic_symbols`].
+    ///
+    /// Aliases are defined after the definitions they refer to, in
+    /// registration order otherwise; aliases that refer to each other in a
+    /// cycle are an error.
+    fn define_synthetic_symbols(&mut self) -> Result<()> {
+        let mut pending: Vec<SyntheticSymbol> =
+            self.synthetic_symbols.definitions().cloned().collect();
+        while !pending.is_empty() {
+            let names: HashSet<&String> = pending.iter().map(|d| &d.name).collect();
+            let waits = |d: &SyntheticSymbol| matches!(&d.value, SyntheticValue::Alias(target) if names.contains(target));
+            let (waiting, ready): (Vec<_>, Vec<_>) = pending.iter().cloned().partition(waits);
+            if ready.is_empty() {
+                let mut cycle: Vec<&str> = waiting.iter().map(|d| d.name.as_str()).collect();
+                cycle.sort();
+                return Err(anyhow!(
+                    "symbols are defined in terms of each other: {}",
+                    cycle.join(", ")
+                ));
+            }
+            for def in ready {
+                self.define_synthetic(def)?;
+            }
+            pending = waiting;
+        }
+        Ok(())
+    }
+
+    /// Define one symbol for [`Self::define_synthetic_symbols`].
+    fn define_synthetic(&mut self, def: SyntheticSymbol) -> Result<()> {
+        let name = def.name.as_str();
+        if def.provide {
+            let referenced =
+                self.is_undefined(name) || self.is_weak_ref(name) || self.import(name).is_some();
+            if !referenced || self.symbol(name).is_some_and(|s| !s.is_weak) {
+                return Ok(());
+            }
+        }
+        let bounds = |seg: &String| {
+            let seg = &self.segments[self.segment_index(seg)?];
+            Some((seg.sections.first()?, seg.sections.last()?))
+        };
+        let in_section = |sec: &Section, offset| {
+            DefinedSymbol::new(
+                sec.file_index,
+                SymbolLocation::Section(sec.section_index),
+                offset,
+                0,
+                false,
+            )
+        };
+        let sym = match &def.value {
+            SyntheticValue::SegmentStart(seg) => bounds(seg).map(|(first, _)| in_section(first, 0)),
+            SyntheticValue::SegmentEnd(seg) => {
+                bounds(seg).map(|(_, last)| in_section(last, last.size))
+            }
+            SyntheticValue::Alias(target) => {
+                let target = self.symbol(target).with_context(|| {
+                    format!("symbol {} is defined as undefined symbol {}", name, target)
+                })?;
+                let mut alias = DefinedSymbol::new(
+                    target.input_file_index,
+                    target.location,
+                    target.offset,
+                    0,
+                    false,
+                );
+                alias.st_type = target.st_type;
+                Some(alias)
+            }
+            _ => None,
+        }
+        .unwrap_or(DefinedSymbol::absolute(0));
+        let n = self.names.intern(name);
+        self.undefined.remove(&n);
+        self.imports.remove(&n);
+        self.symbols.insert(n, sym);
+        self.synthetic.insert(def.name.clone(), def);
+        Ok(())
+    }
+
+    /// Set the values of the absolute symbols from
+    /// [`Self::define_synthetic_symbols`] now that addresses are assigned.
+    fn place_synthetic_symbols(&mut self) {
+        let placed = || self.segments.iter().filter(|s| s.size > 0);
+        let end = |s: &Segment| s.virtual_address + s.size;
+        let header = self.base_addr();
+        let text_end = placed()
+            .filter(|s| s.kind == SectionKind::Text)
+            .map(end)
+            .max()
+            .unwrap_or(header);
+        let data_end = placed()
+            .filter(|s| !s.is_nobits())
+            .map(end)
+            .max()
+            .unwrap_or(header);
+        let bss_start = placed()
+            .find(|s| s.kind == SectionKind::UninitializedData)
+            .map_or(data_end, |s| s.virtual_address);
+        let image_end = placed().map(end).max().unwrap_or(header);
+        let segment = |name: &String| {
+            self.segment_index(name)
+                .map(|i| &self.segments[i])
+                .filter(|s| s.size > 0)
+        };
+
+        let mut values = Vec::new();
+        for (name, def) in &self.synthetic {
+            let value = match &def.value {
+                SyntheticValue::Absolute(v) => *v,
+                SyntheticValue::Boundary(Boundary::FileHeader) => header,
+                SyntheticValue::Boundary(Boundary::TextEnd) => text_end,
+                SyntheticValue::Boundary(Boundary::DataEnd) => data_end,
+                SyntheticValue::Boundary(Boundary::BssStart) => bss_start,
+                SyntheticValue::Boundary(Boundary::ImageEnd) => image_end,
+                SyntheticValue::SegmentStart(seg) => segment(seg).map_or(0, |s| s.virtual_address),
+                SyntheticValue::SegmentEnd(seg) => segment(seg).map_or(0, end),
+                SyntheticValue::SegmentLoadStart(seg) => segment(seg).map_or(0, |s| s.load_address),
+                SyntheticValue::SegmentLoadEnd(seg) => {
+                    segment(seg).map_or(0, |s| s.load_address + s.size)
+                }
+                SyntheticValue::Alias(_) | SyntheticValue::Optional => continue,
+            };
+            values.push((name.clone(), value));
+        }
+        // Aliases of absolute symbols copy the target's value once it is
+        // known: an input's at once, another synthetic symbol's once that
+        // is placed. Definition ruled out cycles, so each pass places some.
+        let mut aliases: Vec<(&String, &String)> = self
+            .synthetic
+            .iter()
+            .filter_map(|(name, def)| match &def.value {
+                SyntheticValue::Alias(target) => Some((name, target)),
+                _ => None,
+            })
+            .collect();
+        while !aliases.is_empty() {
+            let before = aliases.len();
+            aliases.retain(|&(name, target)| {
+                let value = if self.synthetic.contains_key(target) {
+                    values.iter().find(|(n, _)| n == target).map(|&(_, v)| v)
+                } else {
+                    self.symbol(target).map(|s| s.offset)
+                };
+                values.extend(value.map(|v| (name.clone(), v)));
+                value.is_none()
+            });
+            if aliases.len() == before {
+                break;
+            }
+        }
+        for (name, value) in values {
+            if let Some(sym) = self.symbol_mut(&name).filter(|s| s.is_absolute()) {
+                sym.offset = value;
+            }
+        }
+    }
+
+    /// Whether references that nothing defines fail the link.
+    fn reports_undefined(&self) -> bool {
+        match self.options.unresolved_symbols {
+            Some(mode) => mode.reports_objects(),
+            None => !self.is_shared() || self.options.z.defs,
+        }
+    }
+
+    /// Fail if a `--require-defined` symbol has no definition in the output.
+    fn check_required(&self) -> Result<()> {
+        let missing: Vec<&str> = self
+            .options
+            .require_defined
+            .iter()
+            .filter(|name| self.symbol(name).is_none())
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "required symbols not defined (--require-defined): {}",
+                missing.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fail if a hidden or internal symbol has no definition in the output:
+    /// it can't be bound at runtime, whether to a shared library or not.
+    fn check_visibility(&self) -> Result<()> {
+        let mut names: Vec<&str> = self
+            .imports
+            .keys()
+            .chain(
+                self.undefined
+                    .iter()
+                    .filter(|(_, r)| r.is_some())
+                    .map(|(n, _)| n),
+            )
+            .map(|&n| self.names.str(n))
+            .filter(|name| input::is_hidden(self.visibility(name)))
+            .collect();
+        names.sort();
+        let Some(name) = names.first() else {
+            return Ok(());
+        };
+        let mut message = format!("hidden symbol {} is not defined", self.display_name(name));
+        if let Some(lib) = self.import(name) {
+            message.push_str(&format!(
+                " (only in shared library {})",
+                self.shared_libs[lib].soname
+            ));
+        }
+        Err(anyhow!(message))
+    }
+
+    /// Fail on references that nothing defines. `-u` symbols no input
+    /// references may stay undefined.
+    fn check_defs(&self) -> Result<()> {
+        // Executables relax the TLS accesses that call it; any other call is
+        // reported when it is relocated.
+        let tls_get_addr = self.arch.tls_get_addr().filter(|_| !self.is_shared());
+        let mut undefined: Vec<_> = self
+            .undefined
+            .iter()
+            .filter_map(|(&name, r)| Some((self.names.str(name), (*r)?)))
+            .filter(|&(name, _)| Some(name) != tls_get_addr)
+            .collect();
+        if undefined.is_empty() {
+            return Ok(());
+        }
+        undefined.sort();
+        let candidates = self.hint_candidates();
+        let diagnostics: Vec<Diagnostic> = undefined
+            .iter()
+            .map(|&(name, idx)| self.undefined_diagnostic(name, idx, &candidates))
+            .collect();
+        let mut text = String::from(if self.options.z.defs {
+            "undefined symbols (-z defs):"
+        } else {
+            "undefined symbols:"
+        });
+        for d in &diagnostics {
+            text.push_str(&format!(
+                "\n  {} (referenced by {})",
+                d.symbol.as_deref().unwrap_or_default(),
+                d.file.as_deref().unwrap_or_default()
+            ));
+            if let Some(hint) = &d.suggestion {
+                text.push_str(&format!("\n    {}", hint));
+            }
+        }
+        Err(Report::new(text, diagnostics).into())
+    }
+
+    /// Fail on relocations in the output that refer to a discarded section,
+    /// which has no address to give them. The `.ctors` sentinels are
+    /// discarded on purpose, so references to them are left alone.
+    fn check_discarded(&self) -> Result<()> {
+        let discarded: HashSet<(usize, SectionIndex)> = self
+            .discarded
+            .iter()
+            .copied()
+            .filter(|&(fi, si)| {
+                self.objects[fi].section_by_index(si).is_ok_and(|s| {
+                    legacy_array(s.name().unwrap_or("")).is_none() || !is_ctors_sentinel(&s)
+                })
+            })
+            .collect();
+        if discarded.is_empty() {
+            return Ok(());
+        }
+        let section_name = |fi: usize, si| {
+            self.objects[fi]
+                .section_by_index(si)
+                .and_then(|s| s.name().map(str::to_string))
+                .unwrap_or_default()
+        };
+        let placed = self
+            .segments
+            .iter()
+            .flat_map(|seg| &seg.sections)
+            .map(|sec| (sec.file_index, sec.section_index));
+        let mut refs = Vec::new();
+        for (fi, si) in placed {
+            let obj = &self.objects[fi];
+            for (offset, r) in obj.section_by_index(si)?.relocations() {
+                // The section the target lives in, and the symbol's name.
+                let (target, name) = match r.target() {
+                    RelocationTarget::Section(i) => ((fi, i), None),
+                    RelocationTarget::Symbol(i) => {
+                        let s = obj.symbol_by_index(i)?;
+                        let name = (s.kind() != SymbolKind::Section)
+                            .then(|| s.name().unwrap_or("").to_string());
+                        if s.is_local() || s.kind() == SymbolKind::Section {
+                            let Some(i) = s.section_index() else {
+                                continue;
+                            };
+                            ((fi, i), name)
+                        } else {
+                            match self.symbol(s.name()?) {
+                                Some(DefinedSymbol {
+                                    input_file_index,
+                                    location: SymbolLocation::Section(i),
+                                    ..
+                                }) => ((*input_file_index, *i), name),
+                                _ => continue,
+                            }
+                        }
+                    }
+                    _ => continue,
+                };
+                if !discarded.contains(&target) {
+                    continue;
+                }
+                let what = match name {
+                    Some(name) => format!("`{}' in ", self.display_name(&name)),
+                    None => String::new(),
+                };
+                refs.push(format!(
+                    "{}:({}+0x{:x}): {}discarded section `{}' of {}",
+                    self.inputs[fi],
+                    section_name(fi, si),
+                    offset,
+                    what,
+                    section_name(target.0, target.1),
+                    self.inputs[target.0]
+                ));
+            }
+        }
+        if refs.is_empty() {
+            return Ok(());
+        }
+        if self.options.noinhibit_exec {
+            for r in &refs {
+                tracing::warn!("relocation refers to discarded section: {}", r);
+            }
+            return Ok(());
+        }
+        Err(anyhow!(
+            "relocations refer to discarded sections:\n  {}",
+            refs.join("\n  ")
+        ))
+    }
+
+    /// Fail on calls from split-stack code to functions built without
+    /// `-fsplit-stack`.
+    ///
+    /// Split-stack functions run on small stack segments, growing them
+    /// through `__morestack` (linked from libgcc like any other routine). A
+    /// callee that doesn't check its stack can overrun the segment; other
+    /// linkers rewrite the caller's prologue to ask for a larger one, which
+    /// uld does not, so the mix is rejected here rather than at run time.
+    fn check_split_stack(&self) -> Result<()> {
+        let is_split = |obj: &object::File| obj.section_by_name(SPLIT_STACK_NOTE).is_some();
+        if !self.objects.iter().any(is_split) {
+            return Ok(());
+        }
+        let mut calls = BTreeSet::new();
+        for (fi, obj) in self.objects.iter().enumerate() {
+            if !is_split(obj) {
+                continue;
+            }
+            for sec in obj.sections().filter(|s| s.kind() == SectionKind::Text) {
+                for (_, r) in sec.relocations() {
+                    if !matches!(
+                        self.arch.reloc_kind(&r),
+                        RelocationKind::Relative | RelocationKind::PltRelative
+                    ) {
+                        continue;
+                    }
+                    let RelocationTarget::Symbol(i) = r.target() else {
+                        continue;
+                    };
+                    let s = obj.symbol_by_index(i)?;
+                    let name = s.name()?;
+                    if s.is_local() || name.starts_with("__morestack") {
+                        continue;
+                    }
+                    let Some(def) = self.symbol(name) else {
+                        continue;
+                    };
+                    let Some(callee) = self.objects.get(def.input_file_index) else {
+                        continue;
+                    };
+                    let is_function = match def.location {
+                        SymbolLocation::Section(si) => callee
+                            .section_by_index(si)
+                            .is_ok_and(|s| s.kind() == SectionKind::Text),
+                        _ => false,
+                    };
+                    if is_function && !is_split(callee) {
+                        calls.insert((fi, name.to_string(), def.input_file_index));
+                    }
+                }
+            }
+        }
+        if calls.is_empty() {
+            return Ok(());
+        }
+        let mut message = String::from(
+            "split-stack code calls functions built without -fsplit-stack, \
+             which uld cannot adjust for:",
+        );
+        for (caller, name, callee) in calls {
+            message.push_str(&format!(
+                "\n  {} (defined in {}, called from {})",
+                self.display_name(&name),
+                self.inputs[callee],
+                self.inputs[caller]
+            ));
+        }
+        Err(anyhow!(message))
+    }
+
+    /// An undefined reference to `name` from input `fi`, located at its
+    /// first relocation there, with a hint from `candidates`.
+    fn undefined_diagnostic(
+        &self,
+        name: &str,
+        fi: usize,
+        candidates: &demangle::Candidates,
+    ) -> Diagnostic {
+        let shown = self.display_name(name);
+        let mut d = Diagnostic::new(
+            Kind::UndefinedSymbol,
+            format!("undefined symbol: {}", shown),
+        );
+        d.symbol = Some(shown);
+        d.file = Some(self.inputs[fi].to_string());
+        if let Some((section, offset)) = self.first_reference(fi, name) {
+            d.section = Some(section);
+            d.offset = Some(offset);
+        }
+        d.suggestion = self.undefined_hint(name, candidates);
+        d
+    }
+
+    /// The section and offset of the first relocation in input `fi` against
+    /// the global symbol `name`.
+    fn first_reference(&self, fi: usize, name: &str) -> Option<(String, u64)> {
+        let obj = &self.objects[fi];
+        obj.sections().find_map(|sec| {
+            let offset = sec.relocations().find_map(|(offset, r)| match r.target() {
+                RelocationTarget::Symbol(i) => {
+                    let sym = obj.symbol_by_index(i).ok()?;
+                    (sym.name().ok()? == name).then_some(offset)
+                }
+                _ => None,
+            })?;
+            Some((sec.name().ok()?.to_string(), offset))
+        })
+    }
+
+    /// `name` as diagnostics show it: demangled unless `--no-demangle`.
+    fn display_name(&self, name: &str) -> String {
+        if self.options.demangle {
+            demangle::demangle(name).into_owned()
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// The defined symbols to draw "did you mean" hints from.
+    fn hint_candidates(&self) -> demangle::Candidates<'_> {
+        demangle::Candidates::new(self.symbols.keys().map(|&n| self.names.str(n)))
+    }
+
+    /// A "did you mean" hint for an undefined reference to `name`, naming
+    /// the defined symbol that was probably meant and where it comes from.
+    fn undefined_hint(&self, name: &str, candidates: &demangle::Candidates) -> Option<String> {
+        let (hint, candidate) = candidates.suggest(name, |n| self.display_name(n))?;
+        let input = &self.inputs[self.symbol(candidate)?.input_file_index];
+        Some(format!("{} (defined in {})", hint, input))
+    }
+
+    fn segment_index(&self, name: &str) -> Option<usize> {
+        self.segments.iter().position(|s| s.name == name)
+    }
+
+    fn build_got(&mut self) -> Result<()> {
+        let mut ifuncs = HashSet::new();
+        for (fi, obj) in self.objects.iter().enumerate() {
+            for s in obj.symbols() {
+                if !got::is_ifunc(&s) || s.is_local() || s.is_undefined() {
+                    continue;
+                }
+                let name = s.name()?;
+                if self.symbol(name).is_some_and(|d| d.input_file_index == fi) {
+                    ifuncs.insert(name.to_string());
+                }
+            }
+        }
+        self.ifuncs = ifuncs;
+
+        let mut got = Got::new(self.arch.word_size());
+        let mut plt = HashMap::new();
+        // GOT-relative references need the reserved words of .got.plt, where
+        // `_GLOBAL_OFFSET_TABLE_` points, even if nothing else goes there.
+        let mut needs_base = self.has_dynamic();
+        for (fi, obj) in self.objects.iter().enumerate() {
+            for sec in obj.sections() {
+                let code = sec.data()?;
+                let tls_calls = self.relaxed_tls_calls(&sec);
+                for (offset, r) in sec.relocations() {
+                    if tls_calls.contains(&offset) {
+                        continue;
+                    }
+                    needs_base |= matches!(
+                        self.arch.reloc_kind(&r),
+                        RelocationKind::Got
+                            | RelocationKind::GotBaseOffset
+                            | RelocationKind::GotBaseRelative
+                    );
+                    let RelocationTarget::Symbol(i) = r.target() else {
+                        continue;
+                    };
+                    let s = obj.symbol_by_index(i)?;
+                    needs_base |= s.name() == Ok("_GLOBAL_OFFSET_TABLE_");
+                    if let Some(name) = self.plt_call(obj, &r) {
+                        got.add(&name, None, EntryKind::JumpSlot);
+                        let offset = plt.len() as u64 * self.arch.plt_entry_size();
+                        plt.entry(name).or_insert(offset);
+                        continue;
+                    }
+                    let Some(kind) = self.got_entry_kind(&r, &s) else {
+                        continue;
+                    };
+                    if !self.relaxes_got(obj, &r, code, offset) {
+                        got.add(s.name()?, s.is_local().then_some((fi, i)), kind);
+                    }
+                }
+            }
+        }
+        if !self.has_dynamic() {
+            for e in got.entries() {
+                match e.kind {
+                    EntryKind::TlsDesc => return Err(anyhow!(
+                        "TLS descriptor for `{}' needs a dynamic output: uld doesn't relax TLS descriptors in static executables",
+                        e.symbol
+                    )),
+                    EntryKind::IRelative => return Err(anyhow!(
+                        "GOT entry for ifunc `{}' needs a dynamic output: uld doesn't apply IRELATIVE relocations in static executables",
+                        e.symbol
+                    )),
+                    _ => {}
+                }
+            }
+        }
+        let plt_size = if needs_base || got.plt_size() > got::RESERVED_WORDS * self.arch.word_size()
+        {
+            got.plt_size()
+        } else {
+            0
+        };
+        for (name, size) in [(got::SECTION, got.got_size()), (got::PLT_SECTION, plt_size)] {
+            if let Some(g) = self.segments.iter_mut().find(|s| s.name == name) {
+                g.size = size;
+                g.data.resize(size as usize, 0);
+                g.align = self.arch.word_size();
+            }
+        }
+        if let Some(p) = self.segments.iter_mut().find(|s| s.name == ".plt") {
+            p.size = plt.len() as u64 * self.arch.plt_entry_size();
+            p.data.resize(p.size as usize, 0);
+            p.align = self.arch.plt_entry_size();
+        }
+        self.got = got;
+        self.plt = plt;
+        Ok(())
+    }
+
+    /// The kind of GOT entry through which `r` refers to `s`, if any.
+    /// `ifunc`s get a slot the loader fills with their resolver's result.
+    fn got_entry_kind(&self, r: &Relocation, s: &object::Symbol) -> Option<EntryKind> {
+        match self.arch.got_entry_kind(r)? {
+            EntryKind::Address if self.is_ifunc(s) => Some(EntryKind::IRelative),
+            kind => Some(kind),
+        }
+    }
+
+    /// Whether `s` refers to an `ifunc`, defined in its own file or another.
+    fn is_ifunc(&self, s: &object::Symbol) -> bool {
+        got::is_ifunc(s) || (!s.is_local() && s.name().is_ok_and(|name| self.ifuncs.contains(name)))
+    }
+
+    /// Whether the GOT entry is for a symbol the output defines.
+    fn got_defined(&self, entry: &got::Entry) -> bool {
+        entry.local.is_some() || self.symbol(&entry.symbol).is_some()
+    }
+
+    /// Whether the GOT entry holds the output's own definition for good,
+    /// rather than whatever definition the loader binds the symbol to.
+    fn got_binds_locally(&self, entry: &got::Entry) -> bool {
+        self.got_defined(entry)
+            && (entry.local.is_some()
+                || !matches!(entry.kind, EntryKind::Address | EntryKind::JumpSlot)
+                || !self.is_preemptible(&entry.symbol))
+    }
+
+    /// Whether references to global `name`, which a shared object being
+    /// linked defines, may bind to an earlier definition at runtime, as
+    /// exported default-visibility symbols do. Protected symbols don't.
+    ///
+    /// Only references through the GOT, the PLT and word-sized pointers are
+    /// preemptible: other PC-relative accesses always bind to the definition
+    /// in the output.
+    fn is_preemptible(&self, name: &str) -> bool {
+        self.is_shared()
+            && self.symbol(name).is_some_and(|s| !s.is_absolute())
+            && self.visibility(name) == object::elf::STV_DEFAULT
+            && self.is_exported(name)
+    }
+
+    /// Whether the GOT-indirect reference `r` at `offset` in `code` is
+    /// rewritten to use the address of its symbol, which the output defines,
+    /// directly. Such references need no GOT entry.
+    fn relaxes_got(&self, obj: &object::File, r: &Relocation, code: &[u8], offset: u64) -> bool {
+        let RelocationTarget::Symbol(i) = r.target() else {
+            return false;
+        };
+        let Ok(s) = obj.symbol_by_index(i) else {
+            return false;
+        };
+        let defined = if s.kind() == SymbolKind::Tls || self.is_ifunc(&s) {
+            false
+        } else if s.is_local() {
+            s.section_index().is_some()
+        } else {
+            s.name().ok().is_some_and(|name| {
+                self.symbol(name).is_some_and(|sym| !sym.is_absolute())
+                    && !self.is_preemptible(name)
+            })
+        };
+        defined && !self.options.no_relax && self.arch.can_relax_got(r, code, offset as usize)
+    }
+
+    /// Offsets in `sec` of the `__tls_get_addr` calls that general- and
+    /// local-dynamic TLS accesses are relaxed away from, whose relocations
+    /// go with them. Only executables, which know where their TLS block is
+    /// from the thread pointer, relax them.
+    fn relaxed_tls_calls(&self, sec: &object::Section) -> HashSet<u64> {
+        let Ok(code) = sec.data() else {
+            return HashSet::new();
+        };
+        if self.is_shared() {
+            return HashSet::new();
+        }
+        sec.relocations()
+            .filter_map(|(o, r)| Some(o + self.arch.tls_call_offset(&r, code, o as usize)?))
+            .collect()
+    }
+
+    /// The start and end of the TLS block. x86 puts it right below the
+    /// thread pointer, so offsets from the thread pointer count back from
+    /// its end.
+    fn tls_block(&self) -> (u64, u64) {
+        let tls: Vec<&Segment> = self
+            .segments
+            .iter()
+            .filter(|s| s.size > 0 && s.is_tls())
+            .collect();
+        let (Some(first), Some(last)) = (tls.first(), tls.last()) else {
+            return (0, 0);
+        };
+        let align = tls.iter().map(|s| s.align).max().unwrap_or(1).max(1);
+        let start = first.virtual_address;
+        let size = last.virtual_address + last.size - start;
+        (start, start + align_up(size, align))
+    }
+
+    /// Relocations that must be replayed by the runtime loader: every 64-bit
+    /// absolute address in a shared object, and those against runtime-bound
+    /// symbols (`undef`) in an executable.
+    fn needs_dynamic_reloc(&self, r: &Relocation, undef: bool) -> bool {
+        self.arch.reloc_kind(r) == RelocationKind::Absolute
+            && r.size() == 64
+            && (self.is_pic() || undef)
+    }
+
+    /// Whether the GOT entry is filled by the runtime loader.
+    fn got_needs_reloc(&self, entry: &got::Entry) -> bool {
+        let imported = entry.local.is_none() && self.import(&entry.symbol).is_some();
+        match entry.kind {
+            EntryKind::Address => self.is_pic() || imported,
+            // An executable's TLS block is at a fixed offset from the
+            // thread pointer wherever it is loaded.
+            EntryKind::TlsIe => self.is_shared() || imported,
+            EntryKind::TlsDesc | EntryKind::IRelative | EntryKind::JumpSlot => true,
+        }
+    }
+
+    /// Name of the undefined global symbol a relocation refers to directly
+    /// (not through the GOT), if any. Such references are bound at runtime.
+    fn undefined_ref(&self, obj: &object::File, r: &Relocation) -> Option<String> {
+        if matches!(
+            self.arch.reloc_kind(r),
+            RelocationKind::Got | RelocationKind::GotRelative
+        ) {
+            return None;
+        }
+        let RelocationTarget::Symbol(i) = r.target() else {
+            return None;
+        };
+        let s = obj.symbol_by_index(i).ok()?;
+        if !s.is_undefined() || s.is_local() || s.kind() == SymbolKind::Tls {
+            return None;
+        }
+        let name = s.name().ok()?;
+        self.symbol(name).is_none().then(|| name.to_string())
+    }
+
+    /// Like [`Self::undefined_ref`], but only for symbols the runtime loader
+    /// will bind: any undefined symbol in a shared object, symbols from
+    /// shared library inputs in an executable, and preemptible definitions
+    /// that word-sized pointers refer to.
+    fn runtime_ref(&self, obj: &object::File, r: &Relocation) -> Option<String> {
+        if let Some(name) = self.undefined_ref(obj, r) {
+            return (self.is_shared() || self.import(&name).is_some()).then_some(name);
+        }
+        let RelocationTarget::Symbol(i) = r.target() else {
+            return None;
+        };
+        let s = obj.symbol_by_index(i).ok()?;
+        if s.is_local() || s.kind() == SymbolKind::Tls || !self.needs_dynamic_reloc(r, true) {
+            return None;
+        }
+        let name = s.name().ok()?;
+        self.is_preemptible(name).then(|| name.to_string())
+    }
+
+    /// The function the call `r` goes through its `.plt` entry to: one the
+    /// runtime loader binds, either imported from a shared library or left
+    /// undefined in a shared object, or a preemptible definition.
+    fn plt_call(&self, obj: &object::File, r: &Relocation) -> Option<String> {
+        if self.arch.plt_entry_size() == 0 || self.arch.reloc_kind(r) != RelocationKind::PltRelative
+        {
+            return None;
+        }
+        if let Some(name) = self.undefined_ref(obj, r) {
+            return (self.is_shared() || self.import(&name).is_some()).then_some(name);
+        }
+        let RelocationTarget::Symbol(i) = r.target() else {
+            return None;
+        };
+        let s = obj.symbol_by_index(i).ok()?;
+        if s.is_local() || s.kind() == SymbolKind::Tls || self.is_ifunc(&s) {
+            return None;
+        }
+        let name = s.name().ok()?;
+        self.is_preemptible(name).then(|| name.to_string())
+    }
+
+    /// Size the dynamic sections and fill in the parts that do not depend on
+    /// final addresses (`.dynstr` and `.hash`).
+    fn build_dynamic(&mut self) -> Result<()> {
+        // Exported globals come first, followed by the undefined symbols that
+        // dynamic relocations refer to. Executables only need dynamic
+        // relocations for symbols imported from shared libraries.
+        let mut exported: Vec<String> = self
+            .symbols
+            .keys()
+            .map(|&n| self.names.str(n))
+            .filter(|n| self.is_exported(n))
+            .map(str::to_string)
+            .collect();
+        exported.sort();
+        let mut imported = Vec::new();
+        let mut n_relocs = self
+            .got
+            .entries()
+            .iter()
+            .filter(|e| self.got_needs_reloc(e) && e.kind != EntryKind::JumpSlot)
+            .count();
+        for seg in &self.segments {
+            for sec in &seg.sections {
+                let obj = &self.objects[sec.file_index];
+                let s = obj.section_by_index(sec.section_index)?;
+                let tls_calls = self.relaxed_tls_calls(&s);
+                for (o, r) in s.relocations() {
+                    if tls_calls.contains(&o) {
+                        continue;
+                    }
+                    let undef = self.runtime_ref(obj, &r);
+                    if self.needs_dynamic_reloc(&r, undef.is_some()) {
+                        n_relocs += 1;
+                    }
+                    if let Some(name) = undef.filter(|n| self.symbol(n).is_none()) {
+                        imported.push(name);
+                    }
+                }
+            }
+        }
+        imported.extend(
+            self.got
+                .entries()
+                .iter()
+                .filter(|e| self.got_needs_reloc(e) && !self.got_defined(e))
+                .map(|e| e.symbol.clone()),
+        );
+        imported.sort();
+        imported.dedup();
+        let style = self.options.hash_style;
+        // .gnu.hash only covers defined symbols, which must come last and be
+        // grouped by bucket.
+        let symoffset = imported.len() as u32 + 1;
+        self.dynsyms = if style.gnu() {
+            dynamic::sort_for_gnu_hash(&mut exported);
+            imported.into_iter().chain(exported).collect()
+        } else {
+            exported.into_iter().chain(imported).collect()
+        };
+
+        for lib in self.needed_libs() {
+            self.dynstr.add(&lib);
+        }
+        if let Some(soname) = &self.options.soname {
+            self.dynstr.add(soname);
+        }
+        if let Some(rpath) = self.rpath() {
+            self.dynstr.add(&rpath);
+        }
+        // Versioned references like `memcpy@GLIBC_2.14` are named without
+        // their version; `.gnu.version` says which one they need.
+        let names: Vec<String> = self
+            .dynsyms
+            .iter()
+            .map(|n| version::split_version(n).0.to_string())
+            .collect();
+        for name in &names {
+            self.dynstr.add(name);
+        }
+        let endian = self.arch.endianness();
+        let mut indices = vec![object::elf::VER_NDX_GLOBAL; self.dynsyms.len()];
+        let mut verdef = Vec::new();
+        if self.has_versions() {
+            let script = self
+                .options
+                .version_script
+                .as_ref()
+                .context("version script")?;
+            for (i, n) in self.dynsyms.iter().enumerate() {
+                if self.symbol(n).is_some() {
+                    indices[i] = script.version_index(n);
+                }
+            }
+            let base = self.options.soname.as_deref().unwrap_or_default();
+            verdef = script.encode_verdef(base, &mut self.dynstr, endian);
+        }
+        // The versions needed from shared libraries are numbered after the
+        // ones the output defines.
+        let mut next_index = version::verdef_count(&verdef, endian).max(1) as u16 + 1;
+        let mut needed: Vec<version::Needed> = Vec::new();
+        for (i, n) in self.dynsyms.iter().enumerate() {
+            let Some(lib) = self.import(n).filter(|_| self.symbol(n).is_none()) else {
+                continue;
+            };
+            let lib = &self.shared_libs[lib];
+            let Some(ver) = lib.versions.get(n) else {
+                continue;
+            };
+            let need = match needed.iter().position(|d| d.soname == lib.soname) {
+                Some(d) => &mut needed[d],
+                None => {
+                    needed.push(version::Needed {
+                        soname: lib.soname.clone(),
+                        versions: Vec::new(),
+                    });
+                    needed.last_mut().unwrap()
+                }
+            };
+            indices[i] = match need.versions.iter().find(|(v, _)| v == ver) {
+                Some(&(_, ndx)) => ndx,
+                None => {
+                    need.versions.push((ver.clone(), next_index));
+                    next_index += 1;
+                    next_index - 1
+                }
+            };
+        }
+        let verneed = version::encode_verneed(&needed, &mut self.dynstr, endian);
+        let versym = if verdef.is_empty() && verneed.is_empty() {
+            Vec::new()
+        } else {
+            version::encode_versym(&indices, endian)
+        };
+        // The version tables decide which DT_VER* entries `.dynamic` has.
+        for (name, data) in [
+            (".gnu.version", versym),
+            (".gnu.version_d", verdef),
+            (".gnu.version_r", verneed),
+        ] {
+            if let Some(idx) = self.segment_index(name) {
+                let seg = &mut self.segments[idx];
+                seg.size = data.len() as u64;
+                seg.data = data;
+            }
+        }
+
+        let dynamic_entries = self.dynamic_entries().len() as u64 + 1;
+        let hash = dynamic::build_sysv_hash(&names, endian);
+        let gnu_hash = if style.gnu() {
+            dynamic::build_gnu_hash(&names[symoffset as usize - 1..], symoffset, endian)
+        } else {
+            Vec::new()
+        };
+        let dynstr = self.dynstr.data().to_vec();
+        let nsyms = self.dynsyms.len() as u64 + 1;
+        for (name, data, size) in [
+            (".hash", Some(hash), 0),
+            (".gnu.hash", Some(gnu_hash), 0),
+            (".dynstr", Some(dynstr), 0),
+            (".dynsym", None, nsyms * dynamic::SYM_SIZE),
+            (".rela.dyn", None, n_relocs as u64 * dynamic::RELA_SIZE),
+            (
+                ".rela.plt",
+                None,
+                self.plt.len() as u64 * dynamic::RELA_SIZE,
+            ),
+            (".dynamic", None, dynamic_entries * dynamic::DYN_SIZE),
+        ] {
+            // Optional tables (hash styles) may have no segment.
+            let Some(idx) = self.segment_index(name) else {
+                continue;
+            };
+            let seg = &mut self.segments[idx];
+            seg.data = data.unwrap_or_else(|| vec![0; size as usize]);
+            seg.size = seg.data.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// The `-rpath` directories joined into a single search path.
+    fn rpath(&self) -> Option<String> {
+        (!self.options.rpath.is_empty()).then(|| self.options.rpath.join(":"))
+    }
+
+    /// `.dynamic` entries; values are only meaningful after address assignment.
+    fn dynamic_entries(&self) -> Vec<(u32, u64)> {
+        use object::elf;
+        let addr = |name: &str| {
+            self.segment_index(name)
+                .map(|i| self.segments[i].virtual_address)
+                .unwrap_or(0)
+        };
+        let size = |name: &str| {
+            self.segment_index(name)
+                .map(|i| self.segments[i].size)
+                .unwrap_or(0)
+        };
+        let mut entries = Vec::new();
+        for lib in self.needed_libs() {
+            entries.push((elf::DT_NEEDED, self.dynstr.offset(&lib).unwrap_or(0) as u64));
+        }
+        if let Some(soname) = &self.options.soname {
+            let off = self.dynstr.offset(soname).unwrap_or(0);
+            entries.push((elf::DT_SONAME, off as u64));
+        }
+        if let Some(rpath) = self.rpath() {
+            let tag = if self.options.new_dtags {
+                elf::DT_RUNPATH
+            } else {
+                elf::DT_RPATH
+            };
+            entries.push((tag, self.dynstr.offset(&rpath).unwrap_or(0) as u64));
+        }
+        for (tag, name) in [(elf::DT_INIT, "_init"), (elf::DT_FINI, "_fini")] {
+            if self.symbol(name).is_some() {
+                entries.push((tag, self.sym_addr(name)));
+            }
+        }
+        for (tag, size_tag, name) in [
+            (
+                elf::DT_PREINIT_ARRAY,
+                elf::DT_PREINIT_ARRAYSZ,
+                ".preinit_array",
+            ),
+            (elf::DT_INIT_ARRAY, elf::DT_INIT_ARRAYSZ, ".init_array"),
+            (elf::DT_FINI_ARRAY, elf::DT_FINI_ARRAYSZ, ".fini_array"),
+        ] {
+            if size(name) > 0 {
+                entries.extend([(tag, addr(name)), (size_tag, size(name))]);
+            }
+        }
+        let style = self.options.hash_style;
+        if style.sysv() {
+            entries.push((elf::DT_HASH, addr(".hash")));
+        }
+        if style.gnu() {
+            entries.push((elf::DT_GNU_HASH, addr(".gnu.hash")));
+        }
+        entries.extend([
+            (elf::DT_STRTAB, addr(".dynstr")),
+            (elf::DT_SYMTAB, addr(".dynsym")),
+            (elf::DT_STRSZ, size(".dynstr")),
+            (elf::DT_SYMENT, dynamic::SYM_SIZE),
+            (elf::DT_RELA, addr(".rela.dyn")),
+            (elf::DT_RELASZ, size(".rela.dyn")),
+            (elf::DT_RELAENT, dynamic::RELA_SIZE),
+        ]);
+        if !self.plt.is_empty() {
+            entries.extend([
+                (elf::DT_PLTGOT, addr(got::PLT_SECTION)),
+                (elf::DT_PLTRELSZ, size(".rela.plt")),
+                (elf::DT_PLTREL, elf::DT_RELA as u64),
+                (elf::DT_JMPREL, addr(".rela.plt")),
+            ]);
+        }
+        let endian = self.arch.endianness();
+        let data = |name: &str| {
+            self.segment_index(name)
+                .map_or(&[][..], |i| &self.segments[i].data)
+        };
+        if size(".gnu.version") > 0 {
+            entries.push((elf::DT_VERSYM, addr(".gnu.version")));
+        }
+        if size(".gnu.version_d") > 0 {
+            let count = version::verdef_count(data(".gnu.version_d"), endian);
+            entries.extend([
+                (elf::DT_VERDEF, addr(".gnu.version_d")),
+                (elf::DT_VERDEFNUM, count as u64),
+            ]);
+        }
+        if size(".gnu.version_r") > 0 {
+            let count = version::verneed_count(data(".gnu.version_r"), endian);
+            entries.extend([
+                (elf::DT_VERNEED, addr(".gnu.version_r")),
+                (elf::DT_VERNEEDNUM, count as u64),
+            ]);
+        }
+        if !self.is_shared() {
+            // Where the loader leaves its link map for debuggers.
+            entries.push((elf::DT_DEBUG, 0));
+        }
+        // `.plt` entries have no lazy binding stubs to start from, so the
+        // loader binds them up front.
+        let now = self.options.z.now || !self.plt.is_empty();
+        if now {
+            entries.push((elf::DT_FLAGS, elf::DF_BIND_NOW as u64));
+        }
+        let mut flags_1 = 0;
+        if now {
+            flags_1 |= elf::DF_1_NOW;
+        }
+        if self.is_pie() {
+            flags_1 |= elf::DF_1_PIE;
+        }
+        if flags_1 != 0 {
+            entries.push((elf::DT_FLAGS_1, flags_1 as u64));
+        }
+        entries
+    }
+
+    /// Index of `name` in `.dynsym`.
+    fn dynsym_index(&self, name: &str) -> Option<u32> {
+        self.dynsyms
+            .iter()
+            .position(|n| n == name)
+            .map(|i| i as u32 + 1)
+    }
+
+    /// Fill `.dynsym`, `.rela.dyn`, `.rela.plt` and `.dynamic` once addresses
+    /// are final.
+    fn finish_dynamic(
+        &mut self,
+        relocs: &[DynamicReloc],
+        plt_relocs: &[DynamicReloc],
+    ) -> Result<()> {
+        let symbols: Vec<_> = self
+            .dynsyms
+            .iter()
+            .map(|name| {
+                let Some(sym) = self.symbol(name) else {
+                    // The loader leaves weak references it can't bind at 0.
+                    let bind = if self.is_weak_ref(name) {
+                        object::elf::STB_WEAK
+                    } else {
+                        object::elf::STB_GLOBAL
+                    };
+                    return DynamicSymbol {
+                        name: version::split_version(name).0.to_string(),
+                        value: 0,
+                        size: 0,
+                        info: (bind << 4) | object::elf::STT_NOTYPE,
+                        other: object::elf::STV_DEFAULT,
+                        shndx: object::elf::SHN_UNDEF,
+                    };
+                };
+                let shndx = self.symbol_shndx(sym);
+                DynamicSymbol {
+                    name: name.clone(),
+                    value: sym.resolved_address.unwrap_or(0),
+                    size: sym.size,
+                    info: sym.info(),
+                    other: self.visibility(name),
+                    shndx,
+                }
+            })
+            .collect();
+
+        let mut strtab = self.dynstr.clone();
+        let endian = self.arch.endianness();
+        let dynsym = dynamic::encode_symbols(&symbols, &mut strtab, endian);
+        let rela = dynamic::encode_relocs(relocs, endian);
+        let rela_plt = dynamic::encode_relocs(plt_relocs, endian);
+        let dyn_data = dynamic::encode_dynamic(&self.dynamic_entries(), endian);
+        for (name, data) in [
+            (".dynsym", dynsym),
+            (".rela.dyn", rela),
+            (".rela.plt", rela_plt),
+            (".dynamic", dyn_data),
+        ] {
+            let idx = self.segment_index(name).context(name)?;
+            let seg = &mut self.segments[idx];
+            if data.len() as u64 != seg.size {
+                return Err(anyhow!("{} size changed after layout", name));
+            }
+            seg.data = data;
+        }
+        Ok(())
+    }
+
+    fn resolve_symbols(&mut self) {
+        let addresses: Vec<_> = self
+            .symbols
+            .iter()
+            .map(|(&name, sym)| {
+                let addr = match sym.location {
+                    SymbolLocation::Absolute => Some(sym.offset),
+                    _ => self
+                        .symbol_segment(sym)
+                        .map(|(si, o)| self.segments[si].virtual_address + o + sym.offset),
+                };
+                (name, addr)
+            })
+            .collect();
+        for (name, addr) in addresses {
+            self.symbols.get_mut(&name).unwrap().resolved_address = addr;
+        }
+    }
+
+    /// The output segment a section-relative or common symbol lives in, and
+    /// the offset of its section within it.
+    fn symbol_segment(&self, sym: &DefinedSymbol) -> Option<(usize, u64)> {
+        match sym.location {
+            SymbolLocation::Section(sec) => {
+                self.section_map.get(&(sym.input_file_index, sec)).copied()
+            }
+            SymbolLocation::Common { .. } => Some((self.segment_index(".bss")?, 0)),
+            SymbolLocation::Absolute | SymbolLocation::Undefined => None,
+        }
+    }
+
+    /// `st_shndx` of a defined symbol in the output.
+    fn symbol_shndx(&self, sym: &DefinedSymbol) -> u16 {
+        match sym.location {
+            SymbolLocation::Absolute => object::elf::SHN_ABS,
+            _ => self
+                .symbol_segment(sym)
+                .map_or(object::elf::SHN_UNDEF, |(si, _)| si as u16 + 1),
+        }
+    }
+
+    /// The position in `--symbol-ordering-file` of the first listed symbol
+    /// each input section defines.
+    ///
+    /// Globals order the section of their resolved definition; a listed name
+    /// also orders every local symbol of that name, so `static` functions can
+    /// be placed too.
+    fn section_order(&self) -> HashMap<(usize, SectionIndex), usize> {
+        let mut order = HashMap::new();
+        if self.options.symbol_ordering.is_empty() {
+            return order;
+        }
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        for (i, name) in self.options.symbol_ordering.iter().enumerate() {
+            if positions.contains_key(name.as_str()) {
+                if self.options.warn_symbol_ordering {
+                    tracing::warn!(
+                        "symbol ordering file: symbol '{}' specified multiple times",
+                        name
+                    );
+                }
+                continue;
+            }
+            positions.insert(name, i);
+        }
+
+        let mut found = HashSet::new();
+        for (pos, name) in self.options.symbol_ordering.iter().enumerate() {
+            if positions[name.as_str()] != pos {
+                continue;
+            }
+            let Some(sym) = self.symbol(name) else {
+                continue;
+            };
+            found.insert(name.as_str());
+            let kind = match sym.location {
+                SymbolLocation::Section(sec) => {
+                    order.entry((sym.input_file_index, sec)).or_insert(pos);
+                    continue;
+                }
+                SymbolLocation::Absolute => "absolute",
+                SymbolLocation::Common { .. } => "common",
+                SymbolLocation::Undefined => "undefined",
+            };
+            if self.options.warn_symbol_ordering {
+                tracing::warn!(
+                    "symbol ordering file: unable to order {} symbol: {}",
+                    kind,
+                    name
+                );
+            }
+        }
+        for (file_idx, obj) in self.objects.iter().enumerate() {
+            for sym in obj.symbols().filter(|s| s.is_local()) {
+                let (Ok(name), SymbolSection::Section(sec)) = (sym.name(), sym.section()) else {
+                    continue;
+                };
+                let Some((name, &pos)) = positions.get_key_value(name) else {
+                    continue;
+                };
+                found.insert(*name);
+                let entry = order.entry((file_idx, sec)).or_insert(pos);
+                *entry = (*entry).min(pos);
+            }
+        }
+
+        if self.options.warn_symbol_ordering {
+            for name in &self.options.symbol_ordering {
+                if !found.contains(name.as_str()) {
+                    tracing::warn!("symbol ordering file: no such symbol: {}", name);
+                }
+            }
+        }
+        order
+    }
+
+    /// Layout positions for sections that take part in profiled calls,
+    /// clustered with [`callgraph::hfsort`].
+    ///
+    /// Edges come from `--call-graph-ordering-file` if given, otherwise from
+    /// each input's `.llvm.call-graph-profile`: one weight per call, with the
+    /// caller and callee named by a pair of `R_X86_64_NONE` relocations.
+    /// Calls between different output sections can't be shortened and are
+    /// ignored.
+    fn call_graph_order(&self) -> Result<HashMap<(usize, SectionIndex), usize>> {
+        let mut calls = Vec::new();
+        match &self.options.call_graph_edges {
+            Some(edges) => {
+                for (from, to, weight) in edges {
+                    let Some(caller) = self.global_section(from) else {
+                        tracing::warn!("call graph file: no such symbol: {}", from);
+                        continue;
+                    };
+                    let Some(callee) = self.global_section(to) else {
+                        tracing::warn!("call graph file: no such symbol: {}", to);
+                        continue;
+                    };
+                    calls.push((caller, callee, *weight));
+                }
+            }
+            None => {
+                for (file_idx, obj) in self.objects.iter().enumerate() {
+                    let Some(sec) = obj.section_by_name(".llvm.call-graph-profile") else {
+                        continue;
+                    };
+                    let data = sec.data()?;
+                    let targets: Vec<SymbolIndex> = sec
+                        .relocations()
+                        .filter_map(|(_, r)| match r.target() {
+                            RelocationTarget::Symbol(i) => Some(i),
+                            _ => None,
+                        })
+                        .collect();
+                    for (k, [caller, callee]) in targets.as_chunks::<2>().0.iter().enumerate() {
+                        let Some(weight) = data.get(k * 8..k * 8 + 8) else {
+                            return Err(anyhow!(
+                                "{}: truncated .llvm.call-graph-profile",
+                                self.inputs[file_idx]
+                            ));
+                        };
+                        let weight = obj.endianness().read_u64_bytes(weight.try_into().unwrap());
+                        if let (Some(caller), Some(callee)) = (
+                            self.symbol_section(file_idx, *caller),
+                            self.symbol_section(file_idx, *callee),
+                        ) {
+                            calls.push((caller, callee, weight));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut nodes = Vec::new();
+        let mut ids = HashMap::new();
+        let mut sizes = Vec::new();
+        let mut edges = Vec::new();
+        for (caller, callee, weight) in calls {
+            let mut endpoints = [0; 2];
+            let mut segs = [None; 2];
+            for (i, key) in [caller, callee].into_iter().enumerate() {
+                let sec = self.objects[key.0].section_by_index(key.1)?;
+                segs[i] = self.segment_for(key.0, &sec);
+                endpoints[i] = *ids.entry(key).or_insert_with(|| {
+                    nodes.push(key);
+                    sizes.push(sec.size());
+                    nodes.len() - 1
+                });
+            }
+            if segs[0].is_some() && segs[0] == segs[1] {
+                edges.push((endpoints[0], endpoints[1], weight));
+            }
+        }
+        Ok(callgraph::hfsort(&sizes, &edges)
+            .into_iter()
+            .enumerate()
+            .map(|(pos, n)| (nodes[n], pos))
+            .collect())
+    }
+
+    /// The input section holding a global definition, if it has one.
+    fn global_section(&self, name: &str) -> Option<(usize, SectionIndex)> {
+        let sym = self.symbol(name)?;
+        match sym.location {
+            SymbolLocation::Section(sec) => Some((sym.input_file_index, sec)),
+            _ => None,
+        }
+    }
+
+    /// The input section a symbol of `file_idx` resolves to, following
+    /// globals to their definition.
+    fn symbol_section(&self, file_idx: usize, index: SymbolIndex) -> Option<(usize, SectionIndex)> {
+        let sym = self.objects[file_idx].symbol_by_index(index).ok()?;
+        if !sym.is_local() {
+            return self.global_section(sym.name().ok()?);
+        }
+        match sym.section() {
+            SymbolSection::Section(sec) => Some((file_idx, sec)),
+            _ => None,
+        }
+    }
+
+    /// Allocate tentative (`SHN_COMMON`) definitions at the end of `.bss`,
+    /// in input order.
+    fn allocate_commons(&mut self) {
+        let Some(bss) = self.segment_index(".bss") else {
+            return;
+        };
+        let mut commons: Vec<_> = self
+            .symbols
+            .iter()
+            .filter(|(_, s)| s.is_common())
+            .map(|(&name, s)| (s.input_file_index, name))
+            .collect();
+        commons.sort_by_key(|&(file, name)| (file, self.names.str(name)));
+        for (_, name) in commons {
+            let sym = self.symbols.get_mut(&name).unwrap();
+            let SymbolLocation::Common { align } = sym.location else {
+                continue;
+            };
+            let seg = &mut self.segments[bss];
+            sym.offset = align_up(seg.size, align.next_power_of_two());
+            seg.size = sym.offset + sym.size;
+            seg.align = seg.align.max(align.next_power_of_two());
+        }
+    }
+
+    /// The addend of `r`, whose field starts at `field`: its own for
+    /// `SHT_RELA` sections, the one in the field for `SHT_REL`.
+    fn addend(&self, r: &Relocation, field: &[u8]) -> Result<i64> {
+        if r.has_implicit_addend() {
+            self.arch.implicit_addend(r, field)
+        } else {
+            Ok(r.addend())
+        }
+    }
+
+    /// The veneers needed by branches at the current addresses, in order of
+    /// first use.
+    fn needed_veneers(&self) -> Result<Vec<Veneer>> {
+        let mut seen = HashSet::new();
+        let mut veneers = Vec::new();
+        for seg in &self.segments {
+            for sec in &seg.sections {
+                let obj = &self.objects[sec.file_index];
+                let s = obj.section_by_index(sec.section_index)?;
+                let code = s.data()?;
+                for (o, r) in s.relocations() {
+                    if !self.arch.is_branch(&r) {
+                        continue;
+                    }
+                    let Ok(t) = self.reloc_target(obj, &r, sec.file_index, false) else {
+                        continue;
+                    };
+                    let a = self.addend(&r, code.get(o as usize..).unwrap_or_default())?;
+                    let p = seg.virtual_address + sec.offset + o;
+                    if let Some(v) = self.arch.veneer(&r, p, t, a) {
+                        if seen.insert(v) {
+                            veneers.push(v);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(veneers)
+    }
+
+    /// Reserve room at the end of `.text` for the veneers branches need.
+    ///
+    /// Growing `.text` moves what follows it, which can put more targets out
+    /// of range, so addresses are reassigned until the room suffices.
+    fn reserve_veneers(&mut self) -> Result<()> {
+        let Some(text) = self.segment_index(".text") else {
+            return Ok(());
+        };
+        loop {
+            let stubs = self.needed_veneers()?;
+            let size: u64 = stubs.iter().map(|v| self.arch.veneer_size(v.kind)).sum();
+            if size <= self.veneers.size {
+                self.veneers.stubs = stubs;
+                return Ok(());
+            }
+            let seg = &mut self.segments[text];
+            if self.veneers.size == 0 {
+                self.veneers.offset = align_up(seg.size, 4);
+                seg.data_offset = self.veneers.offset;
+            }
+            self.veneers.size = size;
+            seg.size = self.veneers.offset + size;
+            seg.data.resize(size as usize, 0);
+            seg.align = seg.align.max(4);
+            self.assign_addresses()?;
+            self.resolve_symbols();
+        }
+    }
+
+    /// The address of the `.plt` entry calls to `name` go through.
+    fn plt_entry(&self, name: &str) -> Result<u64> {
+        let offset = self
+            .plt
+            .get(name)
+            .with_context(|| format!("Missing PLT entry for: {}", name))?;
+        Ok(self.segment_addr(".plt") + offset)
+    }
+
+    /// Write the `.plt` entries, each jumping through its `.got.plt` slot.
+    fn write_plt(&mut self) -> Result<()> {
+        let Some(p) = self.segment_index(".plt") else {
+            return Ok(());
+        };
+        let slots = self.segment_addr(got::PLT_SECTION);
+        let base = self.segments[p].virtual_address;
+        for (name, &offset) in &self.plt {
+            let slot = self
+                .got
+                .offset(name, None, EntryKind::JumpSlot)
+                .with_context(|| format!("Missing GOT entry for: {}", name))?;
+            let data = &mut self.segments[p].data[offset as usize..];
+            self.arch.write_plt_entry(base + offset, slots + slot, data);
+        }
+        Ok(())
+    }
+
+    /// Write the veneers into `.text`, returning the address branches to each
+    /// jump to.
+    fn write_veneers(&mut self) -> HashMap<Veneer, u64> {
+        let mut entries = HashMap::new();
+        let Some(text) = self.segment_index(".text") else {
+            return entries;
+        };
+        let seg = &mut self.segments[text];
+        let mut off = self.veneers.offset;
+        for v in &self.veneers.stubs {
+            let p = seg.virtual_address + off;
+            let at = (off - seg.data_offset) as usize;
+            let entry = self.arch.write_veneer(v, p, &mut seg.data[at..]);
+            entries.insert(*v, entry);
+            off += self.arch.veneer_size(v.kind);
+        }
+        entries
+    }
+
+    fn relocate(&mut self) -> Result<()> {
+        let mut dyn_relocs = Vec::new();
+        let mut plt_relocs = Vec::new();
+        let mut emitted = Vec::new();
+
+        // Fill the GOT.
+        let (tls_start, tls_end) = self.tls_block();
+        let mut slots = Vec::new();
+        for e in self.got.entries() {
+            let addr = match e.local {
+                Some((fi, i)) => self.resolve_sym(fi, &self.objects[fi].symbol_by_index(i)?)?,
+                None => self.sym_addr(&e.symbol),
+            };
+            let at = self.segment_addr(e.kind.section()) + e.offset;
+            let (value, kind, addend) = match e.kind {
+                EntryKind::Address if self.got_binds_locally(e) => {
+                    (addr, DynamicRelocKind::Relative, addr as i64)
+                }
+                EntryKind::Address => (addr, DynamicRelocKind::GlobDat, 0),
+                EntryKind::TlsIe => (
+                    addr.wrapping_sub(tls_end),
+                    DynamicRelocKind::TpOff,
+                    addr.wrapping_sub(tls_start) as i64,
+                ),
+                EntryKind::TlsDesc => (
+                    0,
+                    DynamicRelocKind::TlsDesc,
+                    addr.wrapping_sub(tls_start) as i64,
+                ),
+                EntryKind::IRelative => (addr, DynamicRelocKind::IRelative, addr as i64),
+                EntryKind::JumpSlot => (addr, DynamicRelocKind::JumpSlot, 0),
+            };
+            if self.got_needs_reloc(e) {
+                // Entries for definitions in the output are relative to the
+                // load base or its TLS block; the rest are bound.
+                let (symbol, addend) = if self.got_binds_locally(e) {
+                    (0, addend)
+                } else {
+                    let index = self.dynsym_index(&e.symbol).context(e.symbol.clone())?;
+                    (index, 0)
+                };
+                let reloc = DynamicReloc {
+                    offset: at,
+                    r_type: self.arch.dynamic_reloc_type(kind),
+                    symbol,
+                    addend,
+                };
+                match e.kind {
+                    EntryKind::JumpSlot => plt_relocs.push(reloc),
+                    _ => dyn_relocs.push(reloc),
+                }
+            }
+            slots.push((e.kind.section(), e.offset, value));
+        }
+        // The loader finds its own dynamic section through .got.plt.
+        slots.push((got::PLT_SECTION, 0, self.segment_addr(".dynamic")));
+        let word = self.arch.word_size() as usize;
+        let endian = self.arch.endianness();
+        for (section, offset, value) in slots {
+            let Some(g) = self.segment_index(section) else {
+                continue;
+            };
+            let bytes = match word {
+                8 => endian.write_u64_bytes(value).to_vec(),
+                _ => endian.write_u32_bytes(value as u32).to_vec(),
+            };
+            if let Some(slot) = self.segments[g]
+                .data
+                .get_mut(offset as usize..offset as usize + word)
+            {
+                slot.copy_from_slice(&bytes);
+            }
+        }
+
+        self.write_plt()?;
+        let veneers = self.write_veneers();
+        let got_base = self.got_base();
+
+        // Apply relocations
+        for si in 0..self.segments.len() {
+            let patches = self.segments[si]
+                .sections
+                .iter()
+                .enumerate()
+                .flat_map(|(j, sec)| {
+                    let obj = &self.objects[sec.file_index];
+                    let s = obj.section_by_index(sec.section_index).ok()?;
+                    let code = s.data().ok()?;
+                    let base = self.segments[si].virtual_address + sec.offset;
+                    let tls_calls = self.relaxed_tls_calls(&s);
+                    Some(
+                        s.relocations()
+                            .filter(move |(o, _)| !tls_calls.contains(o))
+                            .map(|(o, r)| {
+                                let relaxed = self.relaxes_got(obj, &r, code, o);
+                                // Entries of a converted .ctors section were reversed.
+                                let o = if sec.reversed {
+                                    sec.size - self.arch.word_size() - o
+                                } else {
+                                    o
+                                };
+                                let plt = self.plt_call(obj, &r);
+                                let undef = match plt {
+                                    Some(_) => None,
+                                    None => self.runtime_ref(obj, &r),
+                                };
+                                let t = match plt {
+                                    Some(name) => self.plt_entry(&name)?,
+                                    None => {
+                                        match self.reloc_target(obj, &r, sec.file_index, relaxed) {
+                                            Ok(t) => t,
+                                            Err(_) if undef.is_some() => 0,
+                                            Err(e) => return Err(e),
+                                        }
+                                    }
+                                };
+                                let global = match r.target() {
+                                    RelocationTarget::Symbol(i) => obj
+                                        .symbol_by_index(i)
+                                        .ok()
+                                        .filter(|s| {
+                                            !s.is_local() && s.kind() != SymbolKind::Section
+                                        })
+                                        .and_then(|s| s.name().ok())
+                                        .map(str::to_string),
+                                    _ => None,
+                                };
+                                let site = RelocSite {
+                                    file: sec.file_index,
+                                    section: sec.section_index,
+                                    offset: o,
+                                };
+                                Ok(((j, o), r, base + o, t, undef, global, site, relaxed))
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .flatten()
+                .collect::<Result<Vec<_>>>()?;
+
+            self.relocations += patches.len();
+            if let Some(progress) = self.progress.as_ref().filter(|_| !patches.is_empty()) {
+                progress.relocated(&self.segments[si].name, patches.len(), self.relocations);
+            }
+            for ((j, o), r, p, t, undef, global, site, relaxed) in patches {
+                self.copy_section(si, j)?;
+                let field = self.segments[si].sections[j]
+                    .data
+                    .as_deref()
+                    .and_then(|data| data.get(o as usize..))
+                    .unwrap_or_default();
+                let addend = self
+                    .addend(&r, field)
+                    .map_err(|e| self.overflow_error(e, &site, &r, si, t))?;
+                if self.options.emit_relocs {
+                    let r_type = match r.flags() {
+                        RelocationFlags::Elf { r_type } => r_type,
+                        _ => 0,
+                    };
+                    let (target, addend) = match &global {
+                        Some(name) => (EmittedTarget::Symbol(name.clone()), addend),
+                        // Locals are rewritten against their output section.
+                        None => {
+                            let (target, off) = self.emitted_local_target(&site, &r)?;
+                            (target, off + addend)
+                        }
+                    };
+                    emitted.push(EmittedReloc {
+                        segment: si,
+                        offset: p,
+                        r_type,
+                        target,
+                        addend,
+                    });
+                }
+                if self.needs_dynamic_reloc(&r, undef.is_some()) {
+                    dyn_relocs.push(match &undef {
+                        Some(name) => DynamicReloc {
+                            offset: p,
+                            r_type: self.arch.dynamic_reloc_type(DynamicRelocKind::Absolute),
+                            symbol: self.dynsym_index(name).context(name.clone())?,
+                            addend,
+                        },
+                        None => DynamicReloc {
+                            offset: p,
+                            r_type: self.arch.dynamic_reloc_type(DynamicRelocKind::Relative),
+                            symbol: 0,
+                            addend: t as i64 + addend,
+                        },
+                    });
+                    if undef.is_some() {
+                        continue;
+                    }
+                } else if let Some(name) = undef {
+                    // Calls go through the PLT where the backend has one.
+                    let (missing, hint) = if self.arch.plt_entry_size() > 0 {
+                        ("copy relocation or canonical PLT entry", "-fPIC")
+                    } else {
+                        ("PLT entry or copy relocation", "-fno-plt")
+                    };
+                    return Err(anyhow!(
+                        "relocation {:?} against runtime-bound symbol `{}` needs a {}, which uld \
+                         does not generate (recompile with {})",
+                        self.arch.reloc_kind(&r),
+                        name,
+                        missing,
+                        hint
+                    ));
+                }
+                // Executables find TLS variables at fixed offsets from the
+                // thread pointer, so accesses meant for shared objects are
+                // relaxed to use them too.
+                let code = self.segments[si].sections[j].data.as_deref();
+                let tls_call = self
+                    .arch
+                    .tls_call_offset(&r, code.unwrap_or_default(), o as usize);
+                let tp_relative = self.arch.is_tp_relative(&r);
+                if tls_call.is_some() || tp_relative {
+                    let err = if self.is_shared() {
+                        Some(anyhow!(
+                            "{} needs a TLS model for shared objects, which uld doesn't implement (recompile with -ftls-model=initial-exec)",
+                            self.arch.reloc_name(&r)
+                        ))
+                    } else {
+                        global.as_ref().filter(|n| self.symbol(n).is_none()).map(|name| {
+                            anyhow!(
+                                "TLS variable `{}' isn't defined in the output; uld only relaxes accesses to its own",
+                                self.display_name(name)
+                            )
+                        })
+                    };
+                    if let Some(err) = err {
+                        return Err(self.overflow_error(err, &site, &r, si, t));
+                    }
+                }
+                let tpoff = t.wrapping_sub(tls_end);
+                let t = if tp_relative { tpoff } else { t };
+                let data = self.segments[si].sections[j].data.get_or_insert_default();
+                if relaxed {
+                    self.arch.relax_got(&r, data, o as usize);
+                }
+                if tls_call.is_some() {
+                    self.arch
+                        .relax_tls(&r, data, o as usize, tpoff as i64)
+                        .map_err(|e| self.overflow_error(e, &site, &r, si, t))?;
+                    continue;
+                }
+                // Branches that can't reach their target go through a veneer.
+                let (t, addend) = match self.arch.veneer(&r, p, t, addend) {
+                    Some(v) => {
+                        let entry = *veneers.get(&v).context("branch has no veneer")?;
+                        let addend = addend + t as i64 - v.target as i64;
+                        // All veneers sit at the end of .text, which in a large
+                        // enough .text is out of range too.
+                        if self.arch.veneer(&r, p, entry, addend).is_some() {
+                            let obj = &self.objects[site.file];
+                            let section = obj.section_by_index(site.section)?;
+                            return Err(anyhow!(
+                                "{}:({}+0x{:x}): branch can't reach its veneer at 0x{:x}: uld \
+                                 places veneers at the end of .text, beyond the branch's range \
+                                 from here",
+                                self.inputs[site.file],
+                                section.name()?,
+                                site.offset,
+                                entry
+                            ));
+                        }
+                        (entry, addend)
+                    }
+                    None => (t, addend),
+                };
+                let data = self.segments[si].sections[j]
+                    .data
+                    .get_or_insert_default()
+                    .get_mut(o as usize..)
+                    .with_context(|| format!("relocation offset 0x{:x} out of bounds", o))?;
+                self.arch
+                    .apply_relocation(&r, p, t, addend, got_base, data)
+                    .map_err(|e| self.overflow_error(e, &site, &r, si, t))?;
+            }
+        }
+
+        if self.has_dynamic() {
+            self.finish_dynamic(&dyn_relocs, &plt_relocs)?;
+        }
+        self.build_comment()?;
+        self.build_debug_sections()?;
+        if self.options.emit_relocs {
+            self.build_emitted_relocs(emitted);
+        }
+        Ok(())
+    }
+
+    /// Give section `j` of segment `si` its own copy of its contents, for
+    /// relocations to be applied to.
+    fn copy_section(&mut self, si: usize, j: usize) -> Result<()> {
+        let sec = &mut self.segments[si].sections[j];
+        if sec.data.is_none() {
+            let input = self.objects[sec.file_index].section_by_index(sec.section_index)?;
+            sec.data = Some(input.data()?.to_vec());
+        }
+        Ok(())
+    }
+
+    /// Whether `sec` is a debug section copied to the output by
+    /// [`Self::build_debug_sections`].
+    fn keeps_debug(&self, sec: &object::Section) -> bool {
+        !self.options.strip_debug
+            && !is_alloc(sec)
+            && sec.name().is_ok_and(|n| n.starts_with(".debug_"))
+    }
+
+    /// Copy the inputs' `.debug_*` sections into one output section per
+    /// name, relocated against final addresses. References between debug
+    /// sections, such as `.debug_info` into `.debug_abbrev`, become offsets
+    /// into the merged sections.
+    /// Merge the NUL-terminated strings of the inputs' `.comment` sections,
+    /// which name the tools that built them, into one `.comment` without
+    /// repeats, and add the linker's own.
+    fn build_comment(&mut self) -> Result<()> {
+        let mut strings: Vec<&[u8]> = Vec::new();
+        for obj in &self.objects {
+            for sec in obj.sections().filter(|s| s.name() == Ok(COMMENT)) {
+                for s in sec.data()?.split(|&b| b == 0) {
+                    if !s.is_empty() && !strings.contains(&s) {
+                        strings.push(s);
+                    }
+                }
+            }
+        }
+        let ident = format!("uld {}", env!("CARGO_PKG_VERSION"));
+        if !self.options.no_ident && !strings.contains(&ident.as_bytes()) {
+            strings.push(ident.as_bytes());
+        }
+        if strings.is_empty() {
+            return Ok(());
+        }
+        let data = strings.iter().flat_map(|s| s.iter().chain(&[0])).copied();
+        self.non_alloc.push(NonAllocSection {
+            name: COMMENT.to_string(),
+            sh_type: object::elf::SHT_PROGBITS,
+            data: data.collect(),
+            link: None,
+            info_section: None,
+            info: 0,
+            entsize: 1,
+            flags: u64::from(object::elf::SHF_MERGE | object::elf::SHF_STRINGS),
+            align: 1,
+        });
+        Ok(())
+    }
+
+    fn build_debug_sections(&mut self) -> Result<()> {
+        // Size each output section first, so its bytes are allocated once
+        // rather than grown input by input.
+        let mut sizes: Vec<(String, u64, u64)> = Vec::new();
+        let mut placed: HashMap<(usize, SectionIndex), (usize, u64)> = HashMap::new();
+        let mut pieces = Vec::new();
+        for (fi, obj) in self.objects.iter().enumerate() {
+            for sec in obj.sections() {
+                if sec.size() == 0 || !self.keeps_debug(&sec) {
+                    continue;
+                }
+                let name = sec.name()?;
+                let out = match sizes.iter().position(|(n, ..)| n == name) {
+                    Some(out) => out,
+                    None => {
+                        sizes.push((name.to_string(), 0, 1));
+                        sizes.len() - 1
+                    }
+                };
+                let (_, size, align) = &mut sizes[out];
+                *align = (*align).max(sec.align());
+                let offset = align_up(*size, sec.align().max(1));
+                *size = offset + sec.compressed_data()?.uncompressed_size;
+                placed.insert((fi, sec.index()), (out, offset));
+                pieces.push((fi, sec.index(), out, offset));
+            }
+        }
+        let mut outputs: Vec<(String, Vec<u8>, u64)> = sizes
+            .into_iter()
+            .map(|(name, size, align)| (name, vec![0; size as usize], align))
+            .collect();
+        for (fi, si, out, offset) in pieces {
+            let bytes = self.objects[fi].section_by_index(si)?.uncompressed_data()?;
+            let offset = offset as usize;
+            outputs[out].1[offset..offset + bytes.len()].copy_from_slice(&bytes);
+        }
+        for (&(fi, si), &(out, base)) in &placed {
+            let obj = &self.objects[fi];
+            let sec = obj.section_by_index(si)?;
+            let name = sec.name()?;
+            for (o, r) in sec.relocations() {
+                let in_debug = match r.target() {
+                    RelocationTarget::Section(i) => placed.get(&(fi, i)).map(|p| p.1),
+                    RelocationTarget::Symbol(i) => {
+                        let s = obj.symbol_by_index(i)?;
+                        s.section_index()
+                            .filter(|_| s.is_local())
+                            .and_then(|i| placed.get(&(fi, i)))
+                            .map(|p| p.1 + s.address())
+                    }
+                    _ => None,
+                };
+                // References to code that was not linked in resolve to 0.
+                let t = match in_debug {
+                    Some(t) => t,
+                    None => self.reloc_target(obj, &r, fi, false).unwrap_or(0),
+                };
+                let site = || format!("{}:({}+0x{:x})", self.inputs[fi], name, o);
+                let data = outputs[out]
+                    .1
+                    .get_mut((base + o) as usize..)
+                    .with_context(|| format!("{}: relocation out of bounds", site()))?;
+                let addend = self.addend(&r, data)?;
+                self.arch
+                    .apply_relocation(&r, base + o, t, addend, 0, data)
+                    .with_context(site)?;
+            }
+        }
+        if self.options.gdb_index && outputs.iter().any(|(n, ..)| n == ".debug_info") {
+            let sections: gdb_index::Sections = outputs
+                .iter()
+                .map(|(n, d, _)| (n.as_str(), &d[..]))
+                .collect();
+            let index = gdb_index::build(&sections, self.arch.endianness())
+                .context("building .gdb_index")?;
+            outputs.push((".gdb_index".to_string(), index, 4));
+        }
+        for (name, data, align) in outputs {
+            self.non_alloc.push(NonAllocSection {
+                name,
+                sh_type: object::elf::SHT_PROGBITS,
+                data,
+                link: None,
+                info_section: None,
+                info: 0,
+                entsize: 0,
+                flags: 0,
+                align,
+            });
+        }
+        Ok(())
+    }
+
+    /// Turn a [`RelocationOverflow`] from the backend into a diagnostic
+    /// naming the reference and its target, with ways to fix it. Other
+    /// errors are given the place of the relocation.
+    fn overflow_error(
+        &self,
+        err: anyhow::Error,
+        site: &RelocSite,
+        r: &Relocation,
+        si: usize,
+        t: u64,
+    ) -> anyhow::Error {
+        let obj = &self.objects[site.file];
+        let section_name = |i| {
+            obj.section_by_index(i)
+                .and_then(|s| s.name().map(str::to_string))
+                .unwrap_or_default()
+        };
+        let section = section_name(site.section);
+        let Some(overflow) = err.downcast_ref::<RelocationOverflow>() else {
+            return err.context(format!(
+                "{}:({}+0x{:x})",
+                self.inputs[site.file], section, site.offset
+            ));
+        };
+
+        // The target symbol and the input defining it; references to locals
+        // and sections are named after their section.
+        let (target, defined_in) = match r.target() {
+            RelocationTarget::Symbol(i) => match obj.symbol_by_index(i) {
+                Ok(s) if s.kind() != SymbolKind::Section && !s.name().unwrap_or("").is_empty() => {
+                    let name = s.name().unwrap_or("");
+                    let file = match self.symbol(name) {
+                        Some(sym) if !s.is_local() => sym.input_file_index,
+                        _ => site.file,
+                    };
+                    (self.display_name(name), Some(file))
+                }
+                Ok(s) => (
+                    s.section_index().map(section_name).unwrap_or_default(),
+                    Some(site.file),
+                ),
+                Err(_) => (String::new(), None),
+            },
+            RelocationTarget::Section(i) => (section_name(i), Some(site.file)),
+            _ => (String::new(), None),
+        };
+
+        let mut message = format!(
+            "{}:({}+0x{:x}): relocation {} against `{}`",
+            self.inputs[site.file],
+            section,
+            site.offset,
+            self.arch.reloc_name(r),
+            target
+        );
+        if let Some(file) = defined_in.filter(|&f| f != site.file) {
+            message.push_str(&format!(" (defined in {})", self.inputs[file]));
+        }
+        message.push_str(&format!(
+            " out of range: 0x{:x} is not in [{}, {}]",
+            overflow.value, overflow.min, overflow.max
+        ));
+
+        let here = &self.segments[si].name;
+        let there = self.segment_containing(t).map(|i| &self.segments[i].name);
+        let pinned = |name: &String| self.options.section_starts.contains_key(name);
+        let absolute = self.arch.reloc_kind(r) == RelocationKind::Absolute;
+        let mut fixes = Vec::new();
+        if absolute && self.is_pic() {
+            fixes.push("recompile with -fPIC".to_string());
+        } else if let Some(hint) = self.arch.overflow_hint(r) {
+            fixes.push(hint.to_string());
+        }
+        // An absolute field holds addresses up to its maximum; a relative one
+        // reaches as far as the larger of its bounds.
+        let limit = span(overflow.max as u64 + 1);
+        let reach = span((overflow.max as u64 + 1).max(overflow.min.unsigned_abs()));
+        match there {
+            Some(there) if absolute => fixes.push(format!(
+                "place `{}` below {}{}",
+                there,
+                limit,
+                if pinned(there) {
+                    " (its address is set with --section-start)"
+                } else {
+                    ", e.g. with a lower --image-base"
+                }
+            )),
+            Some(there) if there != here => fixes.push(format!(
+                "move `{}` within {} of `{}`{}",
+                there,
+                reach,
+                here,
+                match (pinned(there), pinned(here)) {
+                    (true, true) => format!(
+                        " (the addresses of `{}` and `{}` are set with --section-start)",
+                        there, here
+                    ),
+                    (true, false) | (false, true) => format!(
+                        " (the address of `{}` is set with --section-start)",
+                        if pinned(there) { there } else { here }
+                    ),
+                    (false, false) => String::new(),
+                }
+            )),
+            _ => {}
+        }
+        let suggestion = fixes.join(", or ");
+
+        let mut d = Diagnostic::new(Kind::RelocationOverflow, message.clone());
+        d.symbol = Some(target).filter(|t| !t.is_empty());
+        d.file = Some(self.inputs[site.file].to_string());
+        d.section = Some(section);
+        d.offset = Some(site.offset);
+        if suggestion.is_empty() {
+            return Report::new(message, vec![d]).into();
+        }
+        d.suggestion = Some(suggestion.clone());
+        Report::new(format!("{}\n{}", message, suggestion), vec![d]).into()
+    }
+
+    /// What a relocation at `site` against a local symbol or section refers
+    /// to in the output, with the offset from it, for `--emit-relocs`.
+    fn emitted_local_target(
+        &self,
+        site: &RelocSite,
+        r: &Relocation,
+    ) -> Result<(EmittedTarget, i64)> {
+        let obj = &self.objects[site.file];
+        let (section, offset) = match r.target() {
+            RelocationTarget::Section(i) => (i, 0),
+            RelocationTarget::Symbol(i) => {
+                let sym = obj.symbol_by_index(i)?;
+                match sym.section() {
+                    SymbolSection::Section(sec) => (sec, sym.address() as i64),
+                    SymbolSection::Absolute => {
+                        return Ok((EmittedTarget::Absolute, sym.address() as i64))
+                    }
+                    _ => {
+                        return Err(anyhow!(
+                            "{}: cannot keep a relocation against `{}', which isn't defined in a section",
+                            self.inputs[site.file],
+                            sym.name().unwrap_or("")
+                        ))
+                    }
+                }
+            }
+            _ => return Ok((EmittedTarget::Absolute, 0)),
+        };
+        let Some(&(seg, base)) = self.section_map.get(&(site.file, section)) else {
+            let name = obj
+                .section_by_index(section)
+                .and_then(|s| s.name())
+                .unwrap_or("");
+            return Err(anyhow!(
+                "{}: cannot keep a relocation against section {}, which isn't in the output",
+                self.inputs[site.file],
+                name
+            ));
+        };
+        Ok((EmittedTarget::Section(seg), base as i64 + offset))
+    }
+
+    /// Index of the segment whose address range contains `addr`.
+    fn segment_containing(&self, addr: u64) -> Option<usize> {
+        self.segments.iter().position(|s| {
+            s.size > 0 && s.virtual_address <= addr && addr <= s.virtual_address + s.size
+        })
+    }
+
+    /// Build `.rela.*`, `.symtab` and `.strtab` for `--emit-relocs`.
+    ///
+    /// The symbol table holds a section symbol per output segment followed by
+    /// every global symbol, so relocations can be resolved by post-link tools.
+    fn build_emitted_relocs(&mut self, relocs: Vec<EmittedReloc>) {
+        use object::elf;
+
+        let mut globals: Vec<String> = self
+            .symbols
+            .keys()
+            .map(|&n| self.names.str(n).to_string())
+            .collect();
+        globals.sort();
+        let mut undefined: Vec<String> = relocs
+            .iter()
+            .filter_map(|r| match &r.target {
+                EmittedTarget::Symbol(n) if self.symbol(n).is_none() => Some(n.clone()),
+                _ => None,
+            })
+            .collect();
+        undefined.sort();
+        undefined.dedup();
+        globals.extend(undefined);
+
+        let n_locals = self.segments.len() as u32 + 1;
+        let global_index: HashMap<&str, u32> = globals
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.as_str(), n_locals + i as u32))
+            .collect();
+
+        let mut symbols: Vec<DynamicSymbol> = self
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(i, seg)| DynamicSymbol {
+                name: String::new(),
+                value: seg.virtual_address,
+                size: 0,
+                info: (elf::STB_LOCAL << 4) | elf::STT_SECTION,
+                other: elf::STV_DEFAULT,
+                shndx: i as u16 + 1,
+            })
+            .collect();
+        symbols.extend(globals.iter().map(|name| match self.symbol(name) {
+            Some(sym) => DynamicSymbol {
+                name: name.clone(),
+                value: sym.resolved_address.unwrap_or(0),
+                size: sym.size,
+                info: sym.info(),
+                other: self.visibility(name),
+                shndx: self.symbol_shndx(sym),
+            },
+            None => DynamicSymbol {
+                name: name.clone(),
+                value: 0,
+                size: 0,
+                info: (elf::STB_GLOBAL << 4) | elf::STT_NOTYPE,
+                other: self.visibility(name),
+                shndx: elf::SHN_UNDEF,
+            },
+        }));
+
+        for (si, seg) in self.segments.iter().enumerate() {
+            let entries: Vec<DynamicReloc> = relocs
+                .iter()
+                .filter(|r| r.segment == si)
+                .map(|r| DynamicReloc {
+                    offset: r.offset,
+                    r_type: r.r_type,
+                    symbol: match &r.target {
+                        EmittedTarget::Symbol(n) => global_index[n.as_str()],
+                        EmittedTarget::Section(s) => *s as u32 + 1,
+                        EmittedTarget::Absolute => 0,
+                    },
+                    addend: r.addend,
+                })
+                .collect();
+            if entries.is_empty() {
+                continue;
+            }
+            self.non_alloc.push(NonAllocSection {
+                name: format!(".rela{}", seg.name),
+                sh_type: elf::SHT_RELA,
+                data: dynamic::encode_relocs(&entries, self.arch.endianness()),
+                link: Some(".symtab".to_string()),
+                info_section: Some(seg.name.clone()),
+                info: 0,
+                entsize: dynamic::RELA_SIZE,
+                flags: 0,
+                align: 8,
+            });
+        }
+
+        let mut strtab = StringTable::default();
+        let symtab = dynamic::encode_symbols(&symbols, &mut strtab, self.arch.endianness());
+        self.non_alloc.push(NonAllocSection {
+            name: ".symtab".to_string(),
+            sh_type: elf::SHT_SYMTAB,
+            data: symtab,
+            link: Some(".strtab".to_string()),
+            info_section: None,
+            info: n_locals,
+            entsize: dynamic::SYM_SIZE,
+            flags: 0,
+            align: 8,
+        });
+        self.non_alloc.push(NonAllocSection {
+            name: ".strtab".to_string(),
+            sh_type: elf::SHT_STRTAB,
+            data: strtab.data().to_vec(),
+            link: None,
+            info_section: None,
+            info: 0,
+            entsize: 0,
+            flags: 0,
+            align: 1,
+        });
+    }
+
+    /// Find the address of a relocation target
+    /// Afterwards the arch specific implementation can apply the relocation
+    ///
+    /// GOT-indirect references that are `relaxed` target the symbol itself.
+    fn reloc_target(
+        &self,
+        obj: &object::File,
+        r: &Relocation,
+        fi: usize,
+        relaxed: bool,
+    ) -> Result<u64> {
         Ok(match r.target() {
             RelocationTarget::Symbol(i) => {
                 let s = obj.symbol_by_index(i)?;
-                let use_got = matches!(r.kind(), RelocationKind::Got | RelocationKind::GotRelative)
-                    || s.kind() == SymbolKind::Tls;
-                if use_got {
+                if self.arch.is_size_reloc(r) {
+                    return Ok(self.sym_size(&s));
+                }
+                let kind = if relaxed {
+                    None
+                } else {
+                    self.got_entry_kind(r, &s)
+                };
+                if let Some(kind) = kind {
                     let name = s.name()?;
-                    got + self
+                    let offset = self
                         .got
-                        .get(name)
-                        .context(format!("Missing GOT entry for: {}", name))?
+                        .offset(name, s.is_local().then_some((fi, i)), kind)
+                        .context(format!("Missing GOT entry for: {}", name))?;
+                    self.segment_addr(kind.section()) + offset
+                } else if self.is_ifunc(&s) {
+                    // Its address is what the resolver returns, which only
+                    // a GOT slot filled by the loader holds.
+                    return Err(anyhow!(
+                        "direct reference to ifunc `{}': uld only supports calling ifuncs through the GOT (compile with -fno-plt)",
+                        self.display_name(s.name()?)
+                    ));
                 } else {
                     self.resolve_sym(fi, &s)?
                 }
@@ -372,23 +4496,40 @@ impl<'a, A: Architecture> Linker<'a, A> {
             return Ok(base + s.address());
         }
         let name = s.name()?;
-        let addr = self.sym_addr(name);
-        if addr == 0
-            && !self.weak.contains(name)
-            && !is_optional_symbol(name)
-            && !self.symbols.contains_key(name)
-        {
-            return Err(anyhow!("undefined: {}", name));
+        if self.symbol(name).is_some() {
+            return Ok(self.sym_addr(name));
+        }
+        // Weak references nothing defines in the output are 0, unless bound
+        // at runtime through a dynamic relocation instead.
+        if self.is_weak_ref(name) || self.synthetic_symbols.is_optional(name) {
+            return Ok(0);
+        }
+        // As are the references --unresolved-symbols says to ignore.
+        if !self.reports_undefined() {
+            return Ok(0);
         }
-        Ok(addr)
+        let d = self.undefined_diagnostic(name, fi, &self.hint_candidates());
+        let mut text = format!("undefined: {}", d.symbol.as_deref().unwrap_or_default());
+        if let Some(hint) = &d.suggestion {
+            text.push_str(&format!("\n{}", hint));
+        }
+        Err(Report::new(text, vec![d]).into())
     }
 
-    fn sym_addr(&self, name: &str) -> u64 {
-        if name == "_GLOBAL_OFFSET_TABLE_" {
-            return self.got_addr();
+    /// `st_size` of the definition `s` refers to, for size relocations.
+    /// Symbols bound at runtime have no known size and give 0.
+    fn sym_size(&self, s: &object::Symbol) -> u64 {
+        if s.is_local() {
+            return s.size();
         }
-        self.symbols
-            .get(name)
+        s.name()
+            .ok()
+            .and_then(|name| self.symbol(name))
+            .map_or(0, |sym| sym.size)
+    }
+
+    fn sym_addr(&self, name: &str) -> u64 {
+        self.symbol(name)
             .and_then(|s| s.resolved_address)
             .unwrap_or(0)
     }
@@ -400,15 +4541,509 @@ impl<'a, A: Architecture> Linker<'a, A> {
             .unwrap_or(0)
     }
 
-    fn got_addr(&self) -> u64 {
-        self.segments
+    fn segment_addr(&self, name: &str) -> u64 {
+        self.segment_index(name)
+            .map_or(0, |i| self.segments[i].virtual_address)
+    }
+
+    /// The address GOT-relative relocations count from, which
+    /// `_GLOBAL_OFFSET_TABLE_` names.
+    fn got_base(&self) -> u64 {
+        self.segment_addr(got::PLT_SECTION)
+    }
+
+    /// Write a link map describing the final layout.
+    pub fn write_map(&self, out: &mut dyn Write) -> Result<()> {
+        map::write_map(
+            out,
+            &self.segments,
+            &self.objects,
+            &self.inputs,
+            &self.discarded,
+            &self.named_symbols(),
+        )
+    }
+
+    /// The global definitions with their names, for the reports.
+    fn named_symbols(&self) -> Vec<(&str, &DefinedSymbol)> {
+        self.symbols
             .iter()
-            .find(|s| s.name == ".got")
-            .map(|s| s.virtual_address)
-            .unwrap_or(0)
+            .map(|(&n, sym)| (self.names.str(n), sym))
+            .collect()
+    }
+
+    /// Write the `--why-extract` report listing each extracted archive member.
+    pub fn write_why_extract(&self, out: &mut dyn Write) -> Result<()> {
+        map::write_why_extract(out, &self.inputs, &self.extractions)
+    }
+
+    /// Write the `-t` / `--trace` and `-y` / `--trace-symbol` lines: each
+    /// input as it was read, and the definitions and references of the
+    /// traced symbols in it.
+    pub fn write_trace(&self, out: &mut dyn Write) -> Result<()> {
+        for line in &self.trace_log {
+            writeln!(out, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    /// Write the `--verbose` symbol resolution log: where each global symbol
+    /// ended up coming from, followed by every decision taken on the way.
+    pub fn write_resolution_log(&self, out: &mut dyn Write) -> Result<()> {
+        let mut decisions: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (name, what) in &self.resolution_log {
+            decisions
+                .entry(self.names.str(*name))
+                .or_default()
+                .push(what);
+        }
+        writeln!(out, "Symbol resolution:")?;
+        for (name, whats) in decisions {
+            let outcome = if let Some(sym) = self.symbol(name) {
+                format!("defined in {}", self.inputs[sym.input_file_index])
+            } else if let Some(lib) = self.import(name) {
+                format!("imported from {}", self.shared_libs[lib].soname)
+            } else if self.is_weak_ref(name) {
+                "undefined (weak)".to_string()
+            } else {
+                "undefined".to_string()
+            };
+            writeln!(out, "{}: {}", self.display_name(name), outcome)?;
+            for what in whats {
+                writeln!(out, "  {}", what)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a JSON report of per-segment, per-object and per-symbol sizes.
+    pub fn write_size_report(&self, out: &mut dyn Write) -> Result<()> {
+        report::write_size_report(out, &self.segments, &self.inputs, &self.named_symbols())
+    }
+
+    /// Address of the entry symbol, or 0 if it isn't defined.
+    fn entry_point(&self) -> u64 {
+        let name = self.options.entry.as_deref().unwrap_or("_start");
+        if self.options.entry.is_some() && self.symbol(name).is_none() {
+            tracing::warn!(
+                "cannot find entry symbol {}; not setting start address",
+                name
+            );
+        }
+        self.sym_addr(name)
+    }
+
+    /// The linked output, for the writers.
+    fn image(&self) -> writer::Image<'_> {
+        writer::Image {
+            segments: &self.segments,
+            objects: &self.objects,
+            non_alloc: &self.non_alloc,
+            entry_point: self.entry_point(),
+            e_type: if self.is_pic() {
+                object::elf::ET_DYN
+            } else {
+                object::elf::ET_EXEC
+            },
+            base_addr: self.base_addr(),
+            os_abi: if self.symbols.values().any(|s| s.is_unique) {
+                object::elf::ELFOSABI_GNU
+            } else {
+                object::elf::ELFOSABI_SYSV
+            },
+            e_machine: self.arch.e_machine(),
+            e_flags: self.arch.e_flags(),
+            is_64: self.arch.word_size() == 8,
+            endian: self.arch.endianness(),
+            z: &self.options.z,
+        }
+    }
+
+    /// The writer for `--oformat`; `name` is the output file name, which
+    /// S-records carry in their header.
+    fn output_format(&self, name: &str) -> Box<dyn writer::OutputFormat> {
+        match self.options.file_format {
+            FileFormat::Elf => Box::new(writer::ElfWriter),
+            FileFormat::Binary => Box::new(writer::BinaryWriter),
+            FileFormat::Ihex => Box::new(writer::IhexWriter),
+            FileFormat::Srec => Box::new(writer::SrecWriter {
+                header: name.to_string(),
+            }),
+        }
+    }
+
+    /// Write the output file, returning its size.
+    pub fn write(&self, out: &Path) -> Result<u64> {
+        let name = out
+            .file_name()
+            .map_or(String::new(), |n| n.to_string_lossy().into_owned());
+        let size = writer::write_file(
+            out,
+            &*self.output_format(&name),
+            &self.checked_image()?,
+            self.progress.as_deref(),
+        )?;
+        if self.validates_output() {
+            let bytes =
+                std::fs::read(out).with_context(|| format!("reading back {}", out.display()))?;
+            self.validate_output(&bytes)?;
+        }
+        self.milestone(Milestone::Written);
+        Ok(size)
+    }
+
+    /// The output file contents, for callers that don't want a file on disk
+    /// (tests, `memfd_create`, embedding).
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let bytes = writer::encode(&*self.output_format(""), &self.checked_image()?)?;
+        if self.validates_output() {
+            self.validate_output(&bytes)?;
+        }
+        if let Some(progress) = &self.progress {
+            let len = bytes.len() as u64;
+            progress.written(len, len);
+        }
+        self.milestone(Milestone::Written);
+        Ok(bytes)
+    }
+
+    /// Whether the written output is checked with
+    /// [`validate::check_executable`]: under `--check-sections`, for ELF.
+    fn validates_output(&self) -> bool {
+        self.options.check_sections && self.options.file_format == FileFormat::Elf
+    }
+
+    fn validate_output(&self, bytes: &[u8]) -> Result<()> {
+        let violations = validate::check_executable(bytes);
+        if violations.is_empty() {
+            return Ok(());
+        }
+        let mut text = "output validation failed (--check-sections):".to_string();
+        for v in violations {
+            text.push_str(&format!("\n  {}", v));
+        }
+        Err(anyhow!(text))
+    }
+
+    /// The image to write, audited first under `--check-sections`.
+    fn checked_image(&self) -> Result<writer::Image<'_>> {
+        let image = self.image();
+        if self.options.check_sections {
+            writer::check_layout(&image, self.options.file_format == FileFormat::Elf)?;
+        }
+        Ok(image)
+    }
+
+    /// Write the output file contents to `out`, returning their size.
+    ///
+    /// ELF output goes to `out` piece by piece, straight from the inputs;
+    /// under `--check-sections` it is built in memory first to be checked.
+    pub fn write_to(&self, out: &mut dyn Write) -> Result<u64> {
+        if self.validates_output() {
+            let bytes = self.to_bytes()?;
+            out.write_all(&bytes)?;
+            return Ok(bytes.len() as u64);
+        }
+        let size = writer::write_stream(
+            out,
+            &*self.output_format(""),
+            &self.checked_image()?,
+            self.progress.as_deref(),
+        )?;
+        self.milestone(Milestone::Written);
+        Ok(size)
+    }
+
+    /// Link the inputs added so far and return the output file contents.
+    pub fn link_to_bytes(&mut self) -> Result<Vec<u8>> {
+        self.link()?;
+        self.to_bytes()
+    }
+}
+
+/// Why a link started from a [`LinkerBuilder`] failed.
+#[derive(Debug)]
+pub enum LinkError {
+    /// An input file could not be read.
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// An input could not be parsed or added to the link, for example
+    /// because of a duplicate definition.
+    Input {
+        name: PathBuf,
+        source: anyhow::Error,
+    },
+    /// An option has a value the linker can't use.
+    InvalidOption(String),
+    /// Layout, symbol resolution or relocation failed.
+    Link(anyhow::Error),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::Io { path, source } => write!(f, "read {}: {}", path.display(), source),
+            LinkError::Input { name, source } => write!(f, "{}: {:#}", name.display(), source),
+            LinkError::InvalidOption(message) => f.write_str(message),
+            LinkError::Link(source) => write!(f, "{:#}", source),
+        }
+    }
+}
+
+impl std::error::Error for LinkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LinkError::Io { source, .. } => Some(source),
+            LinkError::Input { source, .. } | LinkError::Link(source) => Some(source.as_ref()),
+            LinkError::InvalidOption(_) => None,
+        }
+    }
+}
+
+/// The contents of an input given to a [`LinkerBuilder`].
+enum InputData<'a> {
+    Borrowed(&'a [u8]),
+    Buffer(InputBuffer),
+}
+
+/// Sets up a link without the command line: inputs are byte buffers or
+/// paths, and options are typed setters.
+///
+/// Inputs are linked in the order they are added, as on the command line.
+/// Buffers given to the builder move into the [`Linker`] it returns.
+///
+/// ```
+/// use object::write::{Object, StandardSection, Symbol, SymbolSection};
+/// use object::{Architecture, BinaryFormat, Endianness, Object as _, ObjectSection};
+/// use object::{SymbolFlags, SymbolKind, SymbolScope};
+/// use uld::arch::x86_64::X86_64;
+/// use uld::linker::{LinkError, LinkerBuilder};
+///
+/// // An object whose `main` exits with status 42.
+/// let mut obj = Object::new(BinaryFormat::Elf, Architecture::X86_64, Endianness::Little);
+/// let text = obj.section_id(StandardSection::Text);
+/// let code = [0xbf, 42, 0, 0, 0, 0xb8, 60, 0, 0, 0, 0x0f, 0x05];
+/// let offset = obj.append_section_data(text, &code, 16);
+/// obj.add_symbol(Symbol {
+///     name: b"main".to_vec(),
+///     value: offset,
+///     size: code.len() as u64,
+///     kind: SymbolKind::Text,
+///     scope: SymbolScope::Linkage,
+///     weak: false,
+///     section: SymbolSection::Section(text),
+///     flags: SymbolFlags::None,
+/// });
+/// let main_o = obj.write()?;
+///
+/// let exe = LinkerBuilder::new(X86_64)
+///     .input_bytes("main.o", &main_o)
+///     .entry("main")
+///     .image_base(0x800000)
+///     .link()?
+///     .to_bytes()?;
+/// let exe = object::File::parse(&*exe)?;
+/// let text = exe.section_by_name(".text").unwrap();
+/// assert_eq!(exe.entry(), text.address() + offset);
+/// assert!(exe.entry() > 0x800000 && exe.entry() < 0x900000);
+///
+/// let misaligned = LinkerBuilder::new(X86_64)
+///     .input_bytes("main.o", &main_o)
+///     .image_base(0x800010)
+///     .link();
+/// assert!(matches!(misaligned, Err(LinkError::InvalidOption(_))));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct LinkerBuilder<'a, A: Architecture> {
+    arch: A,
+    options: LinkOptions,
+    inputs: Vec<(PathBuf, InputData<'a>)>,
+    link_plugins: Vec<Box<dyn LinkPlugin>>,
+    progress: Option<Box<dyn LinkProgress>>,
+}
+
+impl<'a, A: Architecture> LinkerBuilder<'a, A> {
+    pub fn new(arch: A) -> Self {
+        Self {
+            arch,
+            options: LinkOptions::default(),
+            inputs: Vec::new(),
+            link_plugins: Vec::new(),
+            progress: None,
+        }
+    }
+
+    /// Replace every option at once; later setters still apply on top.
+    pub fn options(mut self, options: LinkOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Start execution at `symbol` instead of `_start`.
+    pub fn entry(mut self, symbol: impl Into<String>) -> Self {
+        self.options.entry = Some(symbol.into());
+        self
+    }
+
+    /// Load the file header at `base`, which must be page aligned.
+    pub fn image_base(mut self, base: u64) -> Self {
+        self.options.image_base = Some(base);
+        self
+    }
+
+    pub fn output_kind(mut self, kind: OutputKind) -> Self {
+        self.options.output_kind = kind;
+        self
+    }
+
+    /// Call `plugin`'s hooks during the link; see [`hooks`].
+    pub fn link_plugin(mut self, plugin: impl LinkPlugin + 'static) -> Self {
+        self.link_plugins.push(Box::new(plugin));
+        self
+    }
+
+    /// Report the progress of the link to `progress`; see [`progress`].
+    ///
+    /// [`progress`]: crate::progress
+    pub fn progress(mut self, progress: impl LinkProgress + 'static) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Add an object, archive or shared library held in memory. `name` is
+    /// only used in diagnostics and the link map.
+    pub fn input_bytes(mut self, name: impl Into<PathBuf>, data: &'a [u8]) -> Self {
+        let data = if input::is_aligned(data) {
+            InputData::Borrowed(data)
+        } else {
+            InputData::Buffer(InputBuffer::Aligned(AlignedCopy::new(data)))
+        };
+        self.inputs.push((name.into(), data));
+        self
+    }
+
+    /// Add an input held in memory, handing its buffer to the builder.
+    pub fn input_vec(self, name: impl Into<PathBuf>, data: Vec<u8>) -> Self {
+        self.input_buffer(name, data)
+    }
+
+    /// Add an input in any buffer the linker can own, such as contents
+    /// shared between links with an `Arc<[u8]>`.
+    pub fn input_buffer(mut self, name: impl Into<PathBuf>, data: impl Into<InputBuffer>) -> Self {
+        self.inputs
+            .push((name.into(), InputData::Buffer(data.into())));
+        self
+    }
+
+    /// Add an input file, which is mapped into memory right away.
+    pub fn input_path(self, path: impl Into<PathBuf>) -> Result<Self, LinkError> {
+        let path = path.into();
+        let io = |source| LinkError::Io {
+            path: path.clone(),
+            source,
+        };
+        let map = input::map_file(&path).map_err(io)?;
+        Ok(self.input_buffer(path, map))
+    }
+
+    /// Parse the inputs and link them, returning the linked [`Linker`] to
+    /// write out.
+    pub fn link(self) -> Result<Linker<'a, A>, LinkError> {
+        if let Some(base) = self.options.image_base {
+            let page = self.options.z.page_size();
+            if base % page != 0 {
+                return Err(LinkError::InvalidOption(format!(
+                    "image base {:#x}: not a multiple of the page size {:#x}",
+                    base, page
+                )));
+            }
+        }
+
+        let mut linker = Linker::with_options(self.arch, self.options);
+        for plugin in self.link_plugins {
+            linker.add_link_plugin(plugin);
+        }
+        if let Some(progress) = self.progress {
+            linker.set_progress(progress);
+        }
+        let inputs: Vec<(PathBuf, &'a [u8])> = self
+            .inputs
+            .into_iter()
+            .map(|(name, data)| match data {
+                InputData::Borrowed(bytes) => (name, bytes),
+                InputData::Buffer(buffer) => (name, linker.keep(buffer)),
+            })
+            .collect();
+        let parsed: Vec<_> = inputs
+            .par_iter()
+            .map(|&(ref name, bytes)| input::parse_file(name, bytes, A::arch()))
+            .collect();
+        let input_error = |name: &PathBuf, source| LinkError::Input {
+            name: name.clone(),
+            source,
+        };
+        let mut objects = Vec::new();
+        for ((name, _), parsed) in inputs.iter().zip(parsed) {
+            match parsed {
+                Ok(ParsedFile::Object(obj)) => objects.push((name.as_path(), obj)),
+                parsed => {
+                    linker
+                        .add_object_batch(std::mem::take(&mut objects))
+                        .map_err(|(name, source)| input_error(&name, source))?;
+                    parsed
+                        .and_then(|p| linker.add_parsed(name, p))
+                        .map_err(|source| input_error(name, source))?;
+                }
+            }
+        }
+        linker
+            .add_object_batch(objects)
+            .map_err(|(name, source)| input_error(&name, source))?;
+        linker.link().map_err(LinkError::Link)?;
+        Ok(linker)
     }
+}
 
-    pub fn write(&self, out: &PathBuf) -> Result<()> {
-        writer::write_elf(out, &self.segments, self.sym_addr("_start"))
+/// A size for relocation overflow advice: in GiB, MiB or KiB when it is a
+/// whole number of them, otherwise in bytes.
+fn span(bytes: u64) -> String {
+    for (unit, shift) in [("GiB", 30), ("MiB", 20), ("KiB", 10)] {
+        if bytes >= 1 << shift && bytes.is_multiple_of(1 << shift) {
+            return format!("{} {}", bytes >> shift, unit);
+        }
     }
+    format!("{} bytes", bytes)
+}
+
+/// The array a legacy `.ctors` / `.dtors` section is converted into.
+fn legacy_array(name: &str) -> Option<&'static str> {
+    let (rest, array) = name
+        .strip_prefix(".ctors")
+        .map(|r| (r, ".init_array"))
+        .or_else(|| name.strip_prefix(".dtors").map(|r| (r, ".fini_array")))?;
+    (rest.is_empty() || rest.starts_with('.')).then_some(array)
+}
+
+/// Whether a `.ctors` / `.dtors` section only holds the -1 / 0 list markers.
+fn is_ctors_sentinel(sec: &object::Section) -> bool {
+    sec.relocations().next().is_none()
+        && sec.data().is_ok_and(|d| {
+            d.chunks(8)
+                .all(|w| w.iter().all(|&b| b == 0) || w.iter().all(|&b| b == 0xff))
+        })
+}
+
+/// Whether an input section is a note loaded at run time, such as
+/// `.note.ABI-tag` (`SHT_NOTE`, `SHF_ALLOC`). The GNU property note isn't:
+/// the linker merges those into one of its own.
+fn is_note(sec: &object::Section) -> bool {
+    sec.kind() == SectionKind::Note && is_alloc(sec) && sec.name() != Ok(property::SECTION)
+}
+
+/// Whether an input section occupies memory at run time (`SHF_ALLOC`).
+fn is_alloc(sec: &object::Section) -> bool {
+    matches!(sec.flags(), SectionFlags::Elf { sh_flags }
+        if sh_flags & u64::from(object::elf::SHF_ALLOC) != 0)
 }
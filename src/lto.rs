@@ -0,0 +1,154 @@
+//! Link-time optimization of LLVM bitcode inputs.
+//!
+//! Compilers building with `-flto` write LLVM bitcode instead of machine
+//! code into their "objects". The linker hands the bitcode modules to an
+//! LTO [`Backend`], which optimizes them as one program and compiles them
+//! to a native relocatable object that joins the link like any other.
+//!
+//! Bitcode given on the command line is compiled in batches: modules that
+//! follow each other are optimized together, up to the next archive or
+//! shared library, whose members are picked by the references the batch
+//! leaves undefined. Bitcode archive members are indexed by the symbols the
+//! backend says they define and compiled one at a time when extracted.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{bail, Context, Result};
+
+/// Magic at the start of a raw bitcode file: `BC` `0xC0DE`.
+const RAW_MAGIC: &[u8] = b"BC\xc0\xde";
+/// Magic of the wrapper header Darwin toolchains put around bitcode.
+const WRAPPER_MAGIC: &[u8] = &[0xde, 0xc0, 0x17, 0x0b];
+
+/// Name the native object compiled from bitcode goes by in diagnostics
+/// and the link map, as with other linkers.
+pub const OBJECT_NAME: &str = "ld-temp.o";
+
+/// Whether `data` is an LLVM bitcode module.
+pub fn is_bitcode(data: &[u8]) -> bool {
+    data.starts_with(RAW_MAGIC) || data.starts_with(WRAPPER_MAGIC)
+}
+
+/// Optimizes and compiles bitcode modules to native code.
+pub trait Backend: Send + Sync {
+    /// Optimize `modules`, each named by the file it came from, as one
+    /// program and compile them to a single relocatable ELF object. Every
+    /// global the modules define stays visible to the rest of the link.
+    fn compile(&self, modules: &[(&Path, &[u8])]) -> Result<Vec<u8>>;
+
+    /// The global symbols `module` defines.
+    fn defined_symbols(&self, module: &[u8]) -> Result<Vec<String>>;
+}
+
+/// A [`Backend`] running LLVM's command-line tools: `llvm-nm` to read
+/// symbol tables and `llvm-lto` to optimize and compile.
+#[derive(Debug, Clone)]
+pub struct LlvmTools {
+    /// Directory holding the tools, or `None` to find them on `PATH`.
+    pub bin_dir: Option<PathBuf>,
+    /// Optimization level, 0 to 3 (`-plugin-opt=O<n>`).
+    pub opt_level: u8,
+    /// Compile position-independent code, for shared objects and PIEs.
+    pub pic: bool,
+}
+
+impl LlvmTools {
+    fn tool(&self, name: &str) -> Command {
+        match &self.bin_dir {
+            Some(dir) => Command::new(dir.join(name)),
+            None => Command::new(name),
+        }
+    }
+
+    /// Run `cmd`, failing with its standard error if it does.
+    fn run(mut cmd: Command, name: &str) -> Result<Vec<u8>> {
+        let output = cmd.output().with_context(|| {
+            format!(
+                "running {} for LLVM bitcode inputs; is LLVM installed?",
+                name
+            )
+        })?;
+        if !output.status.success() {
+            bail!(
+                "{} failed ({}):\n{}",
+                name,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            );
+        }
+        Ok(output.stdout)
+    }
+}
+
+impl Backend for LlvmTools {
+    fn compile(&self, modules: &[(&Path, &[u8])]) -> Result<Vec<u8>> {
+        let dir = ScratchDir::new()?;
+        let mut exported = Vec::new();
+        let mut cmd = self.tool("llvm-lto");
+        for (i, &(path, data)) in modules.iter().enumerate() {
+            exported.extend(
+                self.defined_symbols(data)
+                    .with_context(|| format!("reading symbols of {}", path.display()))?,
+            );
+            let file = dir.path.join(format!("{}.bc", i));
+            std::fs::write(&file, data)?;
+            cmd.arg(file);
+        }
+        let out = dir.path.join(OBJECT_NAME);
+        cmd.arg(format!("-O{}", self.opt_level.min(3)))
+            .arg(format!(
+                "-relocation-model={}",
+                if self.pic { "pic" } else { "static" }
+            ))
+            .arg("-o")
+            .arg(&out);
+        // Without an export list llvm-lto internalizes, and then discards,
+        // every definition.
+        for name in exported {
+            cmd.arg(format!("-exported-symbol={}", name));
+        }
+        Self::run(cmd, "llvm-lto")?;
+        Ok(std::fs::read(&out)?)
+    }
+
+    fn defined_symbols(&self, module: &[u8]) -> Result<Vec<String>> {
+        let dir = ScratchDir::new()?;
+        let file = dir.path.join("module.bc");
+        std::fs::write(&file, module)?;
+        let mut cmd = self.tool("llvm-nm");
+        cmd.args(["--defined-only", "--extern-only", "--format=just-symbols"])
+            .arg(&file);
+        let stdout = Self::run(cmd, "llvm-nm")?;
+        Ok(String::from_utf8_lossy(&stdout)
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+/// A temporary directory, removed with its contents when dropped.
+struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    fn new() -> Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "uld-lto-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).with_context(|| format!("creating {}", path.display()))?;
+        Ok(ScratchDir { path })
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,101 @@
+//! Link map output.
+//!
+//! Produces a human-readable report of the final layout: every output segment,
+//! the input sections placed into it, the sections that were discarded, and the
+//! final address of every global symbol. Enabled with `-Map=FILE` or `--print-map`.
+//!
+//! Also writes the `--why-extract` report explaining archive member extraction.
+
+use anyhow::Result;
+use object::read::{Object, ObjectSection, SectionIndex};
+use std::io::Write;
+
+use crate::layout::{InputFile, Segment};
+use crate::linker::Extraction;
+use crate::symbol::DefinedSymbol;
+
+/// Write a link map to `out`.
+pub fn write_map(
+    out: &mut dyn Write,
+    segments: &[Segment],
+    objects: &[object::File],
+    inputs: &[InputFile],
+    discarded: &[(usize, SectionIndex)],
+    symbols: &[(&str, &DefinedSymbol)],
+) -> Result<()> {
+    // Sections are identified by name, owning input, and input index so that
+    // identically named sections from different archive members stay distinct.
+    let section_name = |fi: usize, si: SectionIndex| -> (String, u64) {
+        objects[fi]
+            .section_by_index(si)
+            .map(|s| (s.name().unwrap_or("?").to_string(), s.size()))
+            .unwrap_or_else(|_| ("?".to_string(), 0))
+    };
+
+    writeln!(out, "Memory map")?;
+    writeln!(out)?;
+    writeln!(out, "{:<18} {:>18} {:>10}", "Segment", "Address", "Size")?;
+    for seg in segments.iter().filter(|s| s.size > 0) {
+        writeln!(
+            out,
+            "{:<18} {:>#18x} {:>#10x}",
+            seg.name, seg.virtual_address, seg.size
+        )?;
+        for sec in &seg.sections {
+            writeln!(
+                out,
+                "  {:<16} {:>#18x} {:>#10x} {} [#{}]",
+                sec.name,
+                seg.virtual_address + sec.offset,
+                sec.size,
+                inputs[sec.file_index],
+                sec.file_index
+            )?;
+        }
+    }
+
+    writeln!(out)?;
+    writeln!(out, "Discarded input sections")?;
+    writeln!(out)?;
+    for &(fi, si) in discarded {
+        let (name, size) = section_name(fi, si);
+        writeln!(
+            out,
+            "  {:<16} {:>#10x} {} [#{}]",
+            name, size, inputs[fi], fi
+        )?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "Symbols")?;
+    writeln!(out)?;
+    let mut syms: Vec<_> = symbols
+        .iter()
+        .filter_map(|(name, s)| s.resolved_address.map(|a| (a, name)))
+        .collect();
+    syms.sort();
+    for (addr, name) in syms {
+        writeln!(out, "  {:>#18x} {}", addr, name)?;
+    }
+    Ok(())
+}
+
+/// Write a `--why-extract` report: one tab-separated line per extracted member.
+pub fn write_why_extract(
+    out: &mut dyn Write,
+    inputs: &[InputFile],
+    extractions: &[Extraction],
+) -> Result<()> {
+    writeln!(out, "reference\textracted\tsymbol")?;
+    for e in extractions {
+        writeln!(
+            out,
+            "{}\t{}\t{}",
+            e.reference
+                .map_or("<command line>".to_string(), |r| inputs[r].to_string()),
+            inputs[e.extracted],
+            e.symbol
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,791 @@
+//! The binutils linker plugin interface (`-plugin`).
+//!
+//! `gcc -flto` writes GIMPLE into `.gnu.lto_*` sections instead of machine
+//! code and links with `-plugin liblto_plugin.so`, GCC's plugin for the
+//! interface GNU ld and gold implement. The plugin is a shared library whose
+//! `onload` receives a transfer vector of linker callbacks, and it works in
+//! three steps:
+//!
+//! 1. The linker offers it each input carrying compiler IR through its
+//!    claim-file hook. The plugin claims the file and describes its symbols
+//!    with `add_symbols`; the linker resolves them like those of any object.
+//! 2. Once every input is read, the linker records how each of those symbols
+//!    was resolved, for `get_symbols`, and calls the all-symbols-read hook.
+//!    There the plugin runs the compiler's LTO back end and hands the native
+//!    objects it produced back through `add_input_file`.
+//! 3. After the link, the cleanup hook removes the plugin's temporary files.
+//!
+//! The callbacks carry no context pointer, so the state they work on is
+//! global: one link at a time per process can use a plugin.
+
+use std::ffi::{c_char, c_int, c_uint, c_void, CStr, CString, VaList};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::{anyhow, bail, Context, Result};
+use object::elf;
+
+use crate::config::{self, Input};
+use crate::linker::OutputKind;
+use crate::utils::find_library;
+
+// Transfer vector tags (`enum ld_plugin_tag`).
+const LDPT_NULL: c_int = 0;
+const LDPT_API_VERSION: c_int = 1;
+const LDPT_LINKER_OUTPUT: c_int = 3;
+const LDPT_OPTION: c_int = 4;
+const LDPT_REGISTER_CLAIM_FILE_HOOK: c_int = 5;
+const LDPT_REGISTER_ALL_SYMBOLS_READ_HOOK: c_int = 6;
+const LDPT_REGISTER_CLEANUP_HOOK: c_int = 7;
+const LDPT_ADD_SYMBOLS: c_int = 8;
+const LDPT_GET_SYMBOLS: c_int = 9;
+const LDPT_ADD_INPUT_FILE: c_int = 10;
+const LDPT_MESSAGE: c_int = 11;
+const LDPT_GET_INPUT_FILE: c_int = 12;
+const LDPT_RELEASE_INPUT_FILE: c_int = 13;
+const LDPT_ADD_INPUT_LIBRARY: c_int = 14;
+const LDPT_SET_EXTRA_LIBRARY_PATH: c_int = 16;
+const LDPT_GET_VIEW: c_int = 18;
+const LDPT_GET_SYMBOLS_V2: c_int = 25;
+
+// Status codes (`enum ld_plugin_status`).
+const LDPS_OK: c_int = 0;
+const LDPS_BAD_HANDLE: c_int = 2;
+const LDPS_ERR: c_int = 3;
+
+// Output kinds (`enum ld_plugin_output_file_type`).
+const LDPO_EXEC: c_int = 1;
+const LDPO_DYN: c_int = 2;
+const LDPO_PIE: c_int = 3;
+
+// Message levels (`enum ld_plugin_level`).
+const LDPL_INFO: c_int = 0;
+const LDPL_WARNING: c_int = 1;
+
+// Symbol visibilities (`enum ld_plugin_symbol_visibility`).
+const LDPV_PROTECTED: c_char = 1;
+const LDPV_INTERNAL: c_char = 2;
+const LDPV_HIDDEN: c_char = 3;
+
+/// The plugin to load and what to tell it.
+#[derive(Debug, Clone, Default)]
+pub struct PluginOptions {
+    /// Path of the plugin's shared library (`-plugin`).
+    pub path: PathBuf,
+    /// Options passed through to it, in order (`-plugin-opt`).
+    pub options: Vec<String>,
+    /// Directories searched for libraries the plugin adds to the link.
+    pub library_dirs: Vec<PathBuf>,
+    /// Whether those libraries may be shared (`-Bdynamic` in effect at the
+    /// end of the command line).
+    pub dynamic_libraries: bool,
+}
+
+/// How a claimed file uses a symbol (`enum ld_plugin_symbol_kind`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Def,
+    WeakDef,
+    Undef,
+    WeakUndef,
+    Common,
+}
+
+impl SymbolKind {
+    pub fn is_definition(self) -> bool {
+        !matches!(self, SymbolKind::Undef | SymbolKind::WeakUndef)
+    }
+
+    /// Whether another definition replaces this one without a clash.
+    pub fn is_weak(self) -> bool {
+        matches!(
+            self,
+            SymbolKind::WeakDef | SymbolKind::WeakUndef | SymbolKind::Common
+        )
+    }
+}
+
+/// A symbol of a claimed file, as the plugin described it.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// `STV_*` visibility.
+    pub visibility: u8,
+    pub size: u64,
+}
+
+/// How the link resolved a symbol of a claimed file
+/// (`enum ld_plugin_symbol_resolution`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// A reference nothing defines.
+    Undef = 1,
+    /// The definition the link uses, also referenced from outside the IR.
+    PrevailingDef = 2,
+    /// The definition the link uses, referenced only from IR, so the plugin
+    /// may internalize it.
+    PrevailingDefIronly = 3,
+    /// A definition replaced by one in a regular object.
+    PreemptedReg = 4,
+    /// A definition replaced by one in another claimed file.
+    PreemptedIr = 5,
+    /// A reference to a definition in a claimed file.
+    ResolvedIr = 6,
+    /// A reference to a definition in a regular object.
+    ResolvedExec = 7,
+    /// A reference bound to a shared library.
+    ResolvedDyn = 8,
+}
+
+/// An input file the plugin claimed.
+#[derive(Debug, Clone)]
+pub struct Claimed {
+    /// The file, or `archive(member)`, for diagnostics.
+    pub name: String,
+    pub symbols: Vec<Symbol>,
+    handle: usize,
+}
+
+/// What the plugin added to the link after all symbols were read.
+#[derive(Debug, Default)]
+pub struct Added {
+    /// Native objects compiled from the claimed files.
+    pub files: Vec<PathBuf>,
+    /// Libraries it asked for, found in the search directories, with any
+    /// linker script stub among them expanded.
+    pub libraries: Vec<Input>,
+}
+
+/// A loaded plugin.
+pub struct Plugin {
+    claimed: Vec<Claimed>,
+    /// Set once all symbols were read, after which nothing more is claimed.
+    done: bool,
+    dynamic_libraries: bool,
+    /// The option strings given to `onload`, which the plugin may keep.
+    _options: Vec<CString>,
+}
+
+/// An input offered to the plugin, by handle.
+struct Offered {
+    name: CString,
+    file: File,
+    offset: u64,
+    size: u64,
+    symbols: Vec<Symbol>,
+    resolutions: Vec<Resolution>,
+    /// Contents read for `get_view`, kept until the link ends.
+    views: Vec<Vec<u8>>,
+}
+
+/// Everything the callbacks share with the linker.
+#[derive(Default)]
+struct State {
+    claim_file: Option<ClaimFileHook>,
+    all_symbols_read: Option<Hook>,
+    cleanup: Option<Hook>,
+    offered: Vec<Offered>,
+    added_files: Vec<PathBuf>,
+    added_libraries: Vec<String>,
+    library_dirs: Vec<PathBuf>,
+    errors: Vec<String>,
+}
+
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+fn state() -> MutexGuard<'static, Option<State>> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Run `f` on the shared state, if a plugin is loaded.
+fn with_state<T>(f: impl FnOnce(&mut State) -> T) -> Option<T> {
+    state().as_mut().map(f)
+}
+
+#[repr(C)]
+union TvValue {
+    val: c_int,
+    ptr: *const c_void,
+}
+
+/// An entry of the transfer vector (`struct ld_plugin_tv`).
+#[repr(C)]
+struct Tv {
+    tag: c_int,
+    value: TvValue,
+}
+
+/// `struct ld_plugin_input_file`.
+#[repr(C)]
+struct InputFile {
+    name: *const c_char,
+    fd: c_int,
+    offset: i64,
+    filesize: i64,
+    handle: *mut c_void,
+}
+
+/// `struct ld_plugin_symbol`, in its little-endian layout.
+#[repr(C)]
+struct PluginSymbol {
+    name: *const c_char,
+    version: *const c_char,
+    def: c_char,
+    symbol_type: c_char,
+    section_kind: c_char,
+    unused: c_char,
+    visibility: c_int,
+    size: u64,
+    comdat_key: *const c_char,
+    resolution: c_int,
+}
+
+type Onload = unsafe extern "C" fn(*const Tv) -> c_int;
+type ClaimFileHook = unsafe extern "C" fn(*const InputFile, *mut c_int) -> c_int;
+type Hook = unsafe extern "C" fn() -> c_int;
+
+impl Plugin {
+    /// Load the plugin and run its `onload`.
+    pub fn load(options: &PluginOptions, output_kind: OutputKind) -> Result<Self> {
+        let path = options.path.display();
+        {
+            let mut state = state();
+            if state.is_some() {
+                bail!("{}: another link in this process is using a plugin", path);
+            }
+            *state = Some(State {
+                library_dirs: options.library_dirs.clone(),
+                ..Default::default()
+            });
+        }
+        let plugin = Plugin {
+            claimed: Vec::new(),
+            done: false,
+            dynamic_libraries: options.dynamic_libraries,
+            _options: options
+                .options
+                .iter()
+                .map(|o| CString::new(o.as_str()))
+                .collect::<Result<_, _>>()
+                .context("-plugin-opt contains a NUL byte")?,
+        };
+
+        let file = host::c_path(&options.path)?;
+        // SAFETY: loading a plugin runs its initializers, which the user
+        // asked for with -plugin.
+        let handle =
+            unsafe { host::open_library(&file) }.map_err(|e| anyhow!("-plugin {}: {}", path, e))?;
+        // SAFETY: `handle` is a library open_library returned.
+        let onload = unsafe { host::symbol(handle, c"onload") }
+            .map_err(|e| anyhow!("-plugin {}: no onload function: {}", path, e))?;
+        // SAFETY: plugins export `onload` with this signature.
+        let onload: Onload = unsafe { std::mem::transmute(onload) };
+
+        let output = match output_kind {
+            OutputKind::Executable => LDPO_EXEC,
+            OutputKind::SharedObject => LDPO_DYN,
+            OutputKind::Pie => LDPO_PIE,
+        };
+        let mut tv = vec![
+            Tv::val(LDPT_API_VERSION, 1),
+            Tv::val(LDPT_LINKER_OUTPUT, output),
+        ];
+        for option in &plugin._options {
+            tv.push(Tv::ptr(LDPT_OPTION, option.as_ptr().cast()));
+        }
+        tv.extend([
+            Tv::ptr(
+                LDPT_REGISTER_CLAIM_FILE_HOOK,
+                register_claim_file as *const c_void,
+            ),
+            Tv::ptr(
+                LDPT_REGISTER_ALL_SYMBOLS_READ_HOOK,
+                register_all_symbols_read as *const c_void,
+            ),
+            Tv::ptr(
+                LDPT_REGISTER_CLEANUP_HOOK,
+                register_cleanup as *const c_void,
+            ),
+            Tv::ptr(LDPT_ADD_SYMBOLS, add_symbols as *const c_void),
+            Tv::ptr(LDPT_GET_SYMBOLS, get_symbols as *const c_void),
+            Tv::ptr(LDPT_GET_SYMBOLS_V2, get_symbols as *const c_void),
+            Tv::ptr(LDPT_ADD_INPUT_FILE, add_input_file as *const c_void),
+            Tv::ptr(LDPT_ADD_INPUT_LIBRARY, add_input_library as *const c_void),
+            Tv::ptr(
+                LDPT_SET_EXTRA_LIBRARY_PATH,
+                set_extra_library_path as *const c_void,
+            ),
+            Tv::ptr(LDPT_MESSAGE, message as *const c_void),
+            Tv::ptr(LDPT_GET_INPUT_FILE, get_input_file as *const c_void),
+            Tv::ptr(LDPT_RELEASE_INPUT_FILE, release_input_file as *const c_void),
+            Tv::ptr(LDPT_GET_VIEW, get_view as *const c_void),
+            Tv::val(LDPT_NULL, 0),
+        ]);
+        // SAFETY: the vector and the strings it points to outlive the call,
+        // and the options outlive the plugin.
+        let status = unsafe { onload(tv.as_ptr()) };
+        check(status, &format!("-plugin {}", path))?;
+        if with_state(|s| s.claim_file.is_none()).unwrap_or(true) {
+            bail!("-plugin {}: registered no claim-file hook", path);
+        }
+        Ok(plugin)
+    }
+
+    /// Offer the `size` bytes at `offset` in the file at `path` to the
+    /// plugin, and return the index of the claimed file if it takes them.
+    /// `name` is shown in diagnostics.
+    pub fn claim(
+        &mut self,
+        path: &Path,
+        offset: u64,
+        size: u64,
+        name: String,
+    ) -> Result<Option<usize>> {
+        if self.done {
+            return Ok(None);
+        }
+        let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+        let fd = host::raw_fd(&file);
+        let c_name = host::c_path(path)?;
+        let name_ptr = c_name.as_ptr();
+        let (handle, hook) = with_state(|s| {
+            s.offered.push(Offered {
+                name: c_name,
+                file,
+                offset,
+                size,
+                symbols: Vec::new(),
+                resolutions: Vec::new(),
+                views: Vec::new(),
+            });
+            (s.offered.len(), s.claim_file)
+        })
+        .ok_or_else(|| anyhow!("linker plugin state is gone"))?;
+        let Some(hook) = hook else {
+            return Ok(None);
+        };
+        let input = InputFile {
+            name: name_ptr,
+            fd,
+            offset: offset as i64,
+            filesize: size as i64,
+            handle: handle as *mut c_void,
+        };
+        let mut claimed: c_int = 0;
+        // SAFETY: the name and file stay open in the shared state for as
+        // long as the plugin may use them.
+        let status = unsafe { hook(&input, &mut claimed) };
+        check(status, &name)?;
+        if claimed == 0 {
+            return Ok(None);
+        }
+        let symbols =
+            with_state(|s| std::mem::take(&mut s.offered[handle - 1].symbols)).unwrap_or_default();
+        self.claimed.push(Claimed {
+            name,
+            symbols,
+            handle,
+        });
+        Ok(Some(self.claimed.len() - 1))
+    }
+
+    /// The files claimed so far, in the order they were claimed.
+    pub fn claimed(&self) -> &[Claimed] {
+        &self.claimed
+    }
+
+    /// Tell the plugin how the link resolved the symbols of each claimed
+    /// file, in the order of [`Self::claimed`], and let it compile them.
+    pub fn all_symbols_read(&mut self, resolutions: Vec<Vec<Resolution>>) -> Result<Added> {
+        self.done = true;
+        let hook = with_state(|s| {
+            for (claimed, r) in self.claimed.iter().zip(resolutions) {
+                s.offered[claimed.handle - 1].resolutions = r;
+            }
+            s.all_symbols_read
+        })
+        .flatten();
+        let Some(hook) = hook else {
+            return Ok(Added::default());
+        };
+        // SAFETY: the plugin registered the hook for this call.
+        let status = unsafe { hook() };
+        check(status, "linker plugin")?;
+        let (files, libraries, dirs) = with_state(|s| {
+            (
+                std::mem::take(&mut s.added_files),
+                std::mem::take(&mut s.added_libraries),
+                s.library_dirs.clone(),
+            )
+        })
+        .unwrap_or_default();
+        let mut found = Vec::new();
+        for name in libraries {
+            let path = find_library(&name, &dirs, self.dynamic_libraries)
+                .ok_or_else(|| anyhow!("linker plugin: library -l{} not found", name))?;
+            found.extend(config::expand_library(path, &dirs, self.dynamic_libraries)?);
+        }
+        Ok(Added {
+            files,
+            libraries: found,
+        })
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        if let Some(cleanup) = with_state(|s| s.cleanup).flatten() {
+            // SAFETY: the plugin registered the hook for this call.
+            let status = unsafe { cleanup() };
+            if let Err(e) = check(status, "linker plugin cleanup") {
+                tracing::warn!("{:#}", e);
+            }
+        }
+        *state() = None;
+    }
+}
+
+impl Tv {
+    fn val(tag: c_int, val: c_int) -> Self {
+        Tv {
+            tag,
+            value: TvValue { val },
+        }
+    }
+
+    fn ptr(tag: c_int, ptr: *const c_void) -> Self {
+        Tv {
+            tag,
+            value: TvValue { ptr },
+        }
+    }
+}
+
+/// Fail if `status` isn't `LDPS_OK` or the plugin reported errors, with
+/// its messages.
+fn check(status: c_int, what: &str) -> Result<()> {
+    let errors = with_state(|s| std::mem::take(&mut s.errors)).unwrap_or_default();
+    if status == LDPS_OK && errors.is_empty() {
+        return Ok(());
+    }
+    let mut message = format!("{}: plugin failed (status {})", what, status);
+    for e in errors {
+        message.push_str("\n  ");
+        message.push_str(&e);
+    }
+    bail!(message)
+}
+
+/// What the interface needs from the host: loading shared libraries,
+/// file descriptors and paths as C strings. Plugins are shared libraries
+/// built for Unix hosts; elsewhere the rest of the linker still builds and
+/// `-plugin` reports that it is unsupported.
+#[cfg(unix)]
+mod host {
+    use std::ffi::{c_int, c_void, CStr, CString, OsStr};
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::FileExt;
+    use std::path::{Path, PathBuf};
+
+    pub fn c_path(path: &Path) -> anyhow::Result<CString> {
+        Ok(CString::new(path.as_os_str().as_bytes())?)
+    }
+
+    pub fn path_from_c(path: &CStr) -> PathBuf {
+        PathBuf::from(OsStr::from_bytes(path.to_bytes()))
+    }
+
+    pub fn raw_fd(file: &File) -> c_int {
+        file.as_raw_fd()
+    }
+
+    pub fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        file.read_exact_at(buf, offset)
+    }
+
+    /// # Safety
+    ///
+    /// Runs the library's initializers.
+    pub unsafe fn open_library(path: &CStr) -> Result<*mut c_void, String> {
+        let handle = libc::dlopen(path.as_ptr(), libc::RTLD_NOW);
+        if handle.is_null() {
+            return Err(dl_error());
+        }
+        Ok(handle)
+    }
+
+    /// # Safety
+    ///
+    /// `handle` must come from [`open_library`].
+    pub unsafe fn symbol(handle: *mut c_void, name: &CStr) -> Result<*mut c_void, String> {
+        let sym = libc::dlsym(handle, name.as_ptr());
+        if sym.is_null() {
+            return Err(dl_error());
+        }
+        Ok(sym)
+    }
+
+    fn dl_error() -> String {
+        // SAFETY: dlerror returns NULL or a NUL-terminated string.
+        let e = unsafe { libc::dlerror() };
+        if e.is_null() {
+            return "unknown error".to_string();
+        }
+        // SAFETY: checked non-NULL above.
+        unsafe { CStr::from_ptr(e) }.to_string_lossy().into_owned()
+    }
+}
+
+#[cfg(not(unix))]
+mod host {
+    use std::ffi::{c_int, c_void, CStr, CString};
+    use std::fs::File;
+    use std::io::{self, Read, Seek, SeekFrom};
+    use std::path::{Path, PathBuf};
+
+    use anyhow::Context;
+
+    pub fn c_path(path: &Path) -> anyhow::Result<CString> {
+        let path = path.to_str().context("path is not valid UTF-8")?;
+        Ok(CString::new(path)?)
+    }
+
+    pub fn path_from_c(path: &CStr) -> PathBuf {
+        PathBuf::from(path.to_string_lossy().into_owned())
+    }
+
+    pub fn raw_fd(_file: &File) -> c_int {
+        -1
+    }
+
+    pub fn read_at(mut file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(buf)
+    }
+
+    /// # Safety
+    ///
+    /// Nothing is loaded.
+    pub unsafe fn open_library(_path: &CStr) -> Result<*mut c_void, String> {
+        Err("linker plugins are only supported on Unix hosts".to_string())
+    }
+
+    /// # Safety
+    ///
+    /// Never called: no library can be opened.
+    pub unsafe fn symbol(_handle: *mut c_void, _name: &CStr) -> Result<*mut c_void, String> {
+        Err("linker plugins are only supported on Unix hosts".to_string())
+    }
+}
+
+/// A string from the plugin, or `""` for NULL.
+///
+/// # Safety
+/// `s` is NULL or a NUL-terminated string.
+unsafe fn c_str(s: *const c_char) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(s).to_string_lossy().into_owned()
+}
+
+unsafe extern "C" fn register_claim_file(hook: ClaimFileHook) -> c_int {
+    with_state(|s| s.claim_file = Some(hook));
+    LDPS_OK
+}
+
+unsafe extern "C" fn register_all_symbols_read(hook: Hook) -> c_int {
+    with_state(|s| s.all_symbols_read = Some(hook));
+    LDPS_OK
+}
+
+unsafe extern "C" fn register_cleanup(hook: Hook) -> c_int {
+    with_state(|s| s.cleanup = Some(hook));
+    LDPS_OK
+}
+
+unsafe extern "C" fn add_symbols(
+    handle: *mut c_void,
+    nsyms: c_int,
+    syms: *const PluginSymbol,
+) -> c_int {
+    let syms = match nsyms {
+        n if n > 0 && !syms.is_null() => std::slice::from_raw_parts(syms, n as usize),
+        _ => &[],
+    };
+    let symbols: Vec<Symbol> = syms
+        .iter()
+        .map(|s| Symbol {
+            name: c_str(s.name),
+            kind: match s.def {
+                0 => SymbolKind::Def,
+                1 => SymbolKind::WeakDef,
+                3 => SymbolKind::WeakUndef,
+                4 => SymbolKind::Common,
+                _ => SymbolKind::Undef,
+            },
+            visibility: match s.visibility as c_char {
+                LDPV_PROTECTED => elf::STV_PROTECTED,
+                LDPV_INTERNAL => elf::STV_INTERNAL,
+                LDPV_HIDDEN => elf::STV_HIDDEN,
+                _ => elf::STV_DEFAULT,
+            },
+            size: s.size,
+        })
+        .collect();
+    with_state(
+        |s| match s.offered.get_mut((handle as usize).wrapping_sub(1)) {
+            Some(o) => {
+                o.symbols.extend(symbols);
+                LDPS_OK
+            }
+            None => LDPS_BAD_HANDLE,
+        },
+    )
+    .unwrap_or(LDPS_ERR)
+}
+
+unsafe extern "C" fn get_symbols(
+    handle: *const c_void,
+    nsyms: c_int,
+    syms: *mut PluginSymbol,
+) -> c_int {
+    let syms = match nsyms {
+        n if n > 0 && !syms.is_null() => std::slice::from_raw_parts_mut(syms, n as usize),
+        _ => &mut [],
+    };
+    with_state(|s| match s.offered.get((handle as usize).wrapping_sub(1)) {
+        Some(o) => {
+            for (sym, r) in syms.iter_mut().zip(&o.resolutions) {
+                sym.resolution = *r as c_int;
+            }
+            LDPS_OK
+        }
+        None => LDPS_BAD_HANDLE,
+    })
+    .unwrap_or(LDPS_ERR)
+}
+
+unsafe extern "C" fn add_input_file(path: *const c_char) -> c_int {
+    if path.is_null() {
+        return LDPS_ERR;
+    }
+    let path = host::path_from_c(CStr::from_ptr(path));
+    with_state(|s| s.added_files.push(path));
+    LDPS_OK
+}
+
+unsafe extern "C" fn add_input_library(name: *const c_char) -> c_int {
+    let name = c_str(name);
+    with_state(|s| s.added_libraries.push(name));
+    LDPS_OK
+}
+
+unsafe extern "C" fn set_extra_library_path(path: *const c_char) -> c_int {
+    let path = PathBuf::from(c_str(path));
+    with_state(|s| s.library_dirs.push(path));
+    LDPS_OK
+}
+
+/// `message(level, format, ...)`.
+unsafe extern "C" fn message(level: c_int, format: *const c_char, mut args: ...) -> c_int {
+    let text = format_message(&c_str(format), &mut args);
+    match level {
+        LDPL_INFO => tracing::info!("linker plugin: {}", text),
+        LDPL_WARNING => tracing::warn!("linker plugin: {}", text),
+        _ => {
+            with_state(|s| s.errors.push(text));
+        }
+    }
+    LDPS_OK
+}
+
+/// Expand the `%s`, `%d`, `%i`, `%u`, `%x`, `%c` and `%%` conversions of
+/// a `printf` format with `args`.
+///
+/// # Safety
+/// `args` holds an argument of the type each conversion names, and the
+/// arguments of `%s` conversions are NULL or NUL-terminated strings.
+unsafe fn format_message(format: &str, args: &mut VaList) -> String {
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let mut long = false;
+        let conversion = loop {
+            match chars.next() {
+                Some('l' | 'z' | 'j' | 't') => long = true,
+                Some(c) if c.is_ascii_digit() || "-+ #.h".contains(c) => {}
+                other => break other,
+            }
+        };
+        match conversion {
+            Some('%') => out.push('%'),
+            Some('s') => out.push_str(&c_str(args.next_arg::<*const c_char>())),
+            Some('d' | 'i') if long => out.push_str(&args.next_arg::<i64>().to_string()),
+            Some('d' | 'i') => out.push_str(&args.next_arg::<c_int>().to_string()),
+            Some('u') if long => out.push_str(&args.next_arg::<u64>().to_string()),
+            Some('u') => out.push_str(&args.next_arg::<c_uint>().to_string()),
+            Some('x') if long => out.push_str(&format!("{:x}", args.next_arg::<u64>())),
+            Some('x') => out.push_str(&format!("{:x}", args.next_arg::<c_uint>())),
+            Some('c') => out.push(args.next_arg::<c_int>() as u8 as char),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+    out
+}
+
+unsafe extern "C" fn get_input_file(handle: *const c_void, file: *mut InputFile) -> c_int {
+    if file.is_null() {
+        return LDPS_ERR;
+    }
+    with_state(|s| match s.offered.get((handle as usize).wrapping_sub(1)) {
+        Some(o) => {
+            *file = InputFile {
+                name: o.name.as_ptr(),
+                fd: host::raw_fd(&o.file),
+                offset: o.offset as i64,
+                filesize: o.size as i64,
+                handle: handle as *mut c_void,
+            };
+            LDPS_OK
+        }
+        None => LDPS_BAD_HANDLE,
+    })
+    .unwrap_or(LDPS_ERR)
+}
+
+unsafe extern "C" fn release_input_file(_handle: *const c_void) -> c_int {
+    LDPS_OK
+}
+
+unsafe extern "C" fn get_view(handle: *const c_void, view: *mut *const c_void) -> c_int {
+    if view.is_null() {
+        return LDPS_ERR;
+    }
+    with_state(|s| {
+        let Some(o) = s.offered.get_mut((handle as usize).wrapping_sub(1)) else {
+            return LDPS_BAD_HANDLE;
+        };
+        let mut data = vec![0; o.size as usize];
+        if host::read_at(&o.file, &mut data, o.offset).is_err() {
+            return LDPS_ERR;
+        }
+        *view = data.as_ptr().cast();
+        o.views.push(data);
+        LDPS_OK
+    })
+    .unwrap_or(LDPS_ERR)
+}
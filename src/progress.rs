@@ -0,0 +1,223 @@
+//! Progress reporting.
+//!
+//! A [`LinkProgress`] set with [`Linker::set_progress`] or
+//! [`LinkerBuilder::progress`] is told about each input as it joins the
+//! link, each phase as it finishes, the relocations applied to each output
+//! section and the output as it reaches the disk. Links of thousands of
+//! inputs over a network file system can take minutes without printing
+//! anything; `--progress` draws a [`ProgressBar`] on stderr from these
+//! calls.
+//!
+//! [`Linker::set_progress`]: crate::linker::Linker::set_progress
+//! [`LinkerBuilder::progress`]: crate::linker::LinkerBuilder::progress
+
+use std::fmt;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Observes a link. Every method has a default that ignores the event.
+/// Methods take `&self` so that a linker holding an observer can still move
+/// across threads.
+pub trait LinkProgress: Send + Sync {
+    /// The input file at `path` (an object, archive or shared library) was
+    /// added to the link.
+    fn file_loaded(&self, path: &Path) {
+        let _ = path;
+    }
+
+    /// The link reached `milestone`.
+    fn milestone(&self, milestone: Milestone) {
+        let _ = milestone;
+    }
+
+    /// `count` relocations were applied to the output section `section`,
+    /// `total` so far across the output.
+    fn relocated(&self, section: &str, count: usize, total: usize) {
+        let _ = (section, count, total);
+    }
+
+    /// `written` of the `total` bytes of the output are written.
+    fn written(&self, written: u64, total: u64) {
+        let _ = (written, total);
+    }
+}
+
+/// A point the link passes, in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Milestone {
+    /// Every input is added and archive members are extracted.
+    InputsAdded,
+    /// Output sections are placed and have addresses.
+    LaidOut,
+    /// Every symbol has its final value.
+    SymbolsResolved,
+    /// Relocations are applied; the output is ready to write.
+    Relocated,
+    /// The output is written.
+    Written,
+}
+
+impl fmt::Display for Milestone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Milestone::InputsAdded => "inputs added",
+            Milestone::LaidOut => "laid out",
+            Milestone::SymbolsResolved => "symbols resolved",
+            Milestone::Relocated => "relocated",
+            Milestone::Written => "written",
+        })
+    }
+}
+
+/// Width of the bar, in characters.
+const BAR_WIDTH: usize = 30;
+/// Least time between two redraws of the bar.
+const REDRAW: Duration = Duration::from_millis(50);
+
+/// The `--progress` display: a bar redrawn in place when stderr is a
+/// terminal, and otherwise a line per [`Milestone`].
+pub struct ProgressBar {
+    /// Inputs expected on the command line, for the loading bar.
+    inputs: usize,
+    state: Mutex<BarState>,
+}
+
+struct BarState {
+    out: Box<dyn Write + Send>,
+    terminal: bool,
+    loaded: usize,
+    relocations: usize,
+    written: u64,
+    /// When the bar was last drawn, while it is on screen.
+    drawn: Option<Instant>,
+}
+
+impl ProgressBar {
+    /// A bar on stderr for a link of `inputs` input files.
+    pub fn new(inputs: usize) -> Self {
+        let terminal = std::io::stderr().is_terminal();
+        Self::with_output(inputs, Box::new(std::io::stderr()), terminal)
+    }
+
+    /// A bar written to `out`, redrawn in place if `terminal`.
+    pub fn with_output(inputs: usize, out: Box<dyn Write + Send>, terminal: bool) -> Self {
+        Self {
+            inputs,
+            state: Mutex::new(BarState {
+                out,
+                terminal,
+                loaded: 0,
+                relocations: 0,
+                written: 0,
+                drawn: None,
+            }),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, BarState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl BarState {
+    /// Redraw the bar as `done` of `total` with `text` after it. Redraws
+    /// closer together than [`REDRAW`] are dropped, except the last one.
+    fn draw(&mut self, done: u64, total: u64, text: &str) {
+        if !self.terminal {
+            return;
+        }
+        let now = Instant::now();
+        if done < total && self.drawn.is_some_and(|t| now - t < REDRAW) {
+            return;
+        }
+        let filled = if total == 0 {
+            0
+        } else {
+            (done.min(total) as usize * BAR_WIDTH) / total as usize
+        };
+        let _ = write!(
+            self.out,
+            "\r\x1b[K[{}{}] {}",
+            "=".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled),
+            text
+        );
+        let _ = self.out.flush();
+        self.drawn = Some(now);
+    }
+
+    /// Take the bar off the screen.
+    fn clear(&mut self) {
+        if self.drawn.take().is_some() {
+            let _ = write!(self.out, "\r\x1b[K");
+            let _ = self.out.flush();
+        }
+    }
+}
+
+impl LinkProgress for ProgressBar {
+    fn file_loaded(&self, path: &Path) {
+        let mut state = self.state();
+        state.loaded += 1;
+        let (loaded, inputs) = (state.loaded, self.inputs.max(state.loaded));
+        let text = format!("loading {}/{} {}", loaded, inputs, path.display());
+        state.draw(loaded as u64, inputs as u64, &text);
+    }
+
+    fn milestone(&self, milestone: Milestone) {
+        let mut state = self.state();
+        if state.terminal {
+            let next = match milestone {
+                Milestone::InputsAdded => "laying out",
+                Milestone::LaidOut => "resolving symbols",
+                Milestone::SymbolsResolved => "relocating",
+                Milestone::Relocated => "writing",
+                Milestone::Written => return state.clear(),
+            };
+            // Phases aren't counted, so the bar just empties between them.
+            state.drawn = None;
+            state.draw(0, 1, next);
+            return;
+        }
+        let detail = match milestone {
+            Milestone::InputsAdded => format!(" (inputs: {})", state.loaded),
+            Milestone::Relocated => format!(" (relocations: {})", state.relocations),
+            Milestone::Written => format!(" (bytes: {})", state.written),
+            _ => String::new(),
+        };
+        let _ = writeln!(state.out, "progress: {}{}", milestone, detail);
+    }
+
+    fn relocated(&self, section: &str, _count: usize, total: usize) {
+        let mut state = self.state();
+        state.relocations = total;
+        let text = format!("relocating {} ({} relocations)", section, total);
+        state.draw(0, 1, &text);
+    }
+
+    fn written(&self, written: u64, total: u64) {
+        let mut state = self.state();
+        state.written = written;
+        let text = format!("writing {} of {}", size(written), size(total));
+        state.draw(written, total, &text);
+    }
+}
+
+/// `bytes` in KiB, or in MiB from one MiB up.
+fn size(bytes: u64) -> String {
+    if bytes < 1 << 20 {
+        format!("{} KiB", bytes.div_ceil(1 << 10))
+    } else {
+        format!("{:.1} MiB", bytes as f64 / (1 << 20) as f64)
+    }
+}
+
+impl Drop for ProgressBar {
+    /// Leave the line clean for whatever is printed next, such as the
+    /// error that ended the link.
+    fn drop(&mut self) {
+        self.state().clear();
+    }
+}
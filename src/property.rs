@@ -0,0 +1,74 @@
+//! GNU property notes.
+//!
+//! Objects built with `-fcf-protection` carry a `.note.gnu.property` section
+//! saying which x86 control-flow protections their code supports: IBT
+//! (indirect branch tracking, every indirect branch target starts with
+//! `endbr`) and SHSTK (shadow stacks). The output only has a feature if every
+//! input does, so the linker ANDs the inputs' bits and writes one combined
+//! note, which the loader finds through `PT_GNU_PROPERTY`.
+
+use anyhow::{bail, Result};
+use object::elf;
+use object::{Endian, Endianness};
+
+use crate::utils::align_up;
+
+/// Name of the note section, in inputs and in the output.
+pub const SECTION: &str = ".note.gnu.property";
+
+/// The `GNU_PROPERTY_X86_FEATURE_1_AND` bits of a `.note.gnu.property`
+/// section, or 0 if it has none.
+///
+/// `word` is the address size of the ELF class, which each property's data
+/// is padded to.
+pub fn x86_features(data: &[u8], e: Endianness, word: u64) -> Result<u32> {
+    let u32_at = |off: usize| -> Result<u32> {
+        match data.get(off..off + 4) {
+            Some(b) => Ok(e.read_u32_bytes(b.try_into().unwrap())),
+            None => bail!("truncated {} section", SECTION),
+        }
+    };
+    let mut off = 0;
+    while off < data.len() {
+        let namesz = u32_at(off)? as u64;
+        let descsz = u32_at(off + 4)? as u64;
+        let n_type = u32_at(off + 8)?;
+        let name = off as u64 + 12;
+        let desc = name + align_up(namesz, 4);
+        let end = desc + descsz;
+        if end > data.len() as u64 {
+            bail!("truncated {} section", SECTION);
+        }
+        if n_type == elf::NT_GNU_PROPERTY_TYPE_0
+            && data[name as usize..desc as usize].starts_with(b"GNU\0")
+        {
+            let mut p = desc;
+            while p + 8 <= end {
+                let pr_type = u32_at(p as usize)?;
+                let pr_datasz = u32_at(p as usize + 4)? as u64;
+                if pr_type == elf::GNU_PROPERTY_X86_FEATURE_1_AND && pr_datasz == 4 {
+                    return u32_at(p as usize + 8);
+                }
+                p += 8 + align_up(pr_datasz, word);
+            }
+        }
+        off = align_up(end, word) as usize;
+    }
+    Ok(0)
+}
+
+/// Encode a `.note.gnu.property` section holding `features` as its
+/// `GNU_PROPERTY_X86_FEATURE_1_AND` property.
+pub fn encode_x86_features(features: u32, e: Endianness, word: u64) -> Vec<u8> {
+    let descsz = align_up(12, word) as u32;
+    let mut out = Vec::new();
+    for v in [4, descsz, elf::NT_GNU_PROPERTY_TYPE_0] {
+        out.extend_from_slice(&e.write_u32_bytes(v));
+    }
+    out.extend_from_slice(b"GNU\0");
+    for v in [elf::GNU_PROPERTY_X86_FEATURE_1_AND, 4, features] {
+        out.extend_from_slice(&e.write_u32_bytes(v));
+    }
+    out.resize(16 + descsz as usize, 0);
+    out
+}
@@ -0,0 +1,173 @@
+//! Size reports.
+//!
+//! Writes a JSON summary of how many bytes each input object and each global
+//! symbol contributes to the output, and compares two such reports so CI can
+//! catch binary growth (`uld size-diff old.json new.json`). Each placed
+//! section is listed with its input and input index, which `uld inspect
+//! report.json` prints back the way the link map does.
+//!
+//! Any report path ending in `.zst` is transparently zstd-compressed on write
+//! and decompressed on read.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::layout::{InputFile, Segment};
+use crate::symbol::DefinedSymbol;
+
+/// Open `path` for writing, compressing with zstd if it ends in `.zst`.
+pub fn create(path: &Path) -> Result<Box<dyn Write>> {
+    let f = File::create(path).with_context(|| format!("create {}", path.display()))?;
+    if is_zstd(path) {
+        Ok(Box::new(zstd::Encoder::new(f, 0)?.auto_finish()))
+    } else {
+        Ok(Box::new(BufWriter::new(f)))
+    }
+}
+
+/// Open `path` for reading, decompressing with zstd if it ends in `.zst`.
+pub fn open(path: &Path) -> Result<Box<dyn Read>> {
+    let f = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    if is_zstd(path) {
+        Ok(Box::new(zstd::Decoder::new(f)?))
+    } else {
+        Ok(Box::new(BufReader::new(f)))
+    }
+}
+
+fn is_zstd(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "zst")
+}
+
+/// Write a JSON size report for the final layout.
+pub fn write_size_report(
+    out: &mut dyn Write,
+    segments: &[Segment],
+    inputs: &[InputFile],
+    symbols: &[(&str, &DefinedSymbol)],
+) -> Result<()> {
+    let mut objects: BTreeMap<String, u64> = BTreeMap::new();
+    for sec in segments.iter().flat_map(|s| &s.sections) {
+        *objects
+            .entry(inputs[sec.file_index].to_string())
+            .or_default() += sec.size;
+    }
+    let symbols: BTreeMap<&str, u64> = symbols
+        .iter()
+        .filter(|(_, s)| s.resolved_address.is_some())
+        .map(|&(name, s)| (name, s.size))
+        .collect();
+    let sections: Vec<Value> = segments
+        .iter()
+        .flat_map(|seg| {
+            seg.sections.iter().map(move |sec| {
+                let input = &inputs[sec.file_index];
+                json!({
+                    "segment": seg.name,
+                    "name": sec.name,
+                    "address": seg.virtual_address + sec.offset,
+                    "size": sec.size,
+                    "path": input.path,
+                    "member": input.member,
+                    "input": sec.file_index,
+                })
+            })
+        })
+        .collect();
+    let segments: BTreeMap<&str, u64> = segments
+        .iter()
+        .filter(|s| s.size > 0)
+        .map(|s| (s.name.as_str(), s.size))
+        .collect();
+
+    let report = json!({
+        "segments": segments,
+        "objects": objects,
+        "symbols": symbols,
+        "sections": sections,
+    });
+    serde_json::to_writer_pretty(&mut *out, &report)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Compare two size reports and print every entry whose size changed.
+pub fn size_diff(old: &Path, new: &Path, out: &mut dyn Write) -> Result<()> {
+    let read = |p: &Path| -> Result<Value> {
+        serde_json::from_reader(open(p)?).with_context(|| format!("parse {}", p.display()))
+    };
+    let (old, new) = (read(old)?, read(new)?);
+
+    let mut total = 0i64;
+    for category in ["segments", "objects", "symbols"] {
+        let table = |v: &Value| {
+            v.get(category)
+                .and_then(Value::as_object)
+                .cloned()
+                .unwrap_or_default()
+        };
+        let (old, new) = (table(&old), table(&new));
+
+        let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+        let mut deltas: Vec<(i64, &str, u64, u64)> = keys
+            .into_iter()
+            .map(|k| {
+                let size = |m: &Map<String, Value>| m.get(k).and_then(Value::as_u64).unwrap_or(0);
+                let (o, n) = (size(&old), size(&new));
+                (n as i64 - o as i64, k.as_str(), o, n)
+            })
+            .filter(|&(d, ..)| d != 0)
+            .collect();
+        deltas.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+
+        writeln!(out, "{}:", category)?;
+        for (delta, name, o, n) in deltas {
+            writeln!(out, "  {:>+10} {:>10} -> {:<10} {}", delta, o, n, name)?;
+            if category == "segments" {
+                total += delta;
+            }
+        }
+    }
+    writeln!(out, "total: {:+}", total)?;
+    Ok(())
+}
+
+/// Print the placed sections of a size report, one per line with the input
+/// that contributed it, as `-Map` does.
+pub fn inspect(report: &Path, out: &mut dyn Write) -> Result<()> {
+    let report: Value = serde_json::from_reader(open(report)?)
+        .with_context(|| format!("parse {}", report.display()))?;
+    let sections = report
+        .get("sections")
+        .and_then(Value::as_array)
+        .context("size report has no sections")?;
+
+    let mut segment = "";
+    for sec in sections {
+        let text = |k: &str| sec.get(k).and_then(Value::as_str);
+        let number = |k: &str| sec.get(k).and_then(Value::as_u64).unwrap_or(0);
+        let seg = text("segment").unwrap_or("");
+        if seg != segment {
+            writeln!(out, "{}", seg)?;
+            segment = seg;
+        }
+        let input = InputFile {
+            path: text("path").unwrap_or("").into(),
+            member: text("member").map(String::from),
+        };
+        writeln!(
+            out,
+            "  {:<16} {:>#18x} {:>#10x} {} [#{}]",
+            text("name").unwrap_or(""),
+            number("address"),
+            number("size"),
+            input,
+            number("input")
+        )?;
+    }
+    Ok(())
+}
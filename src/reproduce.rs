@@ -0,0 +1,139 @@
+//! Reproduce tarballs (`--reproduce=FILE.tar`).
+//!
+//! A link that goes wrong is easiest to debug with everything it read. The
+//! tarball holds, under a directory named after it:
+//!
+//! - `response.txt`: the command line, response files expanded, with `-l`
+//!   resolved to the libraries found and every path made relative;
+//! - `version.txt`: the `uld` version that wrote it;
+//! - every input and option file, at its absolute path below the directory.
+//!
+//! Running `uld @response.txt` in that directory repeats the link. The
+//! archive is a plain ustar file, with PAX headers for paths that don't fit.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::config::{Config, Input};
+
+/// Size of a tar header and of the blocks file contents are padded to.
+const BLOCK: usize = 512;
+
+/// Write the tarball for linking `files` with `config` to `path`.
+pub fn write(path: &Path, config: &Config, files: &[Input]) -> Result<()> {
+    let root = path
+        .file_stem()
+        .map_or_else(|| "repro".into(), |s| s.to_string_lossy().into_owned());
+    let args = config.reproduce_args(files, |p| archived(p).display().to_string());
+    let mut response = String::new();
+    for arg in &args {
+        response.push_str(&quote(arg));
+        response.push('\n');
+    }
+
+    let f = File::create(path).with_context(|| format!("create {}", path.display()))?;
+    let mut tar = Tar(BufWriter::new(f));
+    tar.add(&format!("{}/response.txt", root), response.as_bytes())?;
+    let version = format!("uld {}\n", env!("CARGO_PKG_VERSION"));
+    tar.add(&format!("{}/version.txt", root), version.as_bytes())?;
+    let mut seen = HashSet::new();
+    let read = files
+        .iter()
+        .map(|f| f.path.clone())
+        .chain(config.option_files());
+    for file in read {
+        let name = archived(&file);
+        if !seen.insert(name.clone()) {
+            continue;
+        }
+        let data = fs::read(&file).with_context(|| format!("read {}", file.display()))?;
+        tar.add(&format!("{}/{}", root, name.display()), &data)?;
+    }
+    tar.finish()
+        .with_context(|| format!("write {}", path.display()))
+}
+
+/// Where `path` goes in the archive: its absolute path, made relative.
+fn archived(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(c) => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `arg` quoted for a response file, if it needs to be.
+fn quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && !arg
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\'))
+    {
+        return arg.to_string();
+    }
+    let mut out = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    out
+}
+
+/// A tar archive being written.
+struct Tar<W: Write>(W);
+
+impl<W: Write> Tar<W> {
+    /// Add a regular file named `name` holding `data`.
+    fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        if name.len() > 100 {
+            // "LEN path=NAME\n", where LEN counts its own digits.
+            let rest = " path=".len() + name.len() + 1;
+            let mut len = rest + 1;
+            while (len.to_string().len() + rest) != len {
+                len = len.to_string().len() + rest;
+            }
+            let record = format!("{} path={}\n", len, name);
+            self.entry(b"././@PaxHeader", b'x', record.as_bytes())?;
+        }
+        // Readers without PAX support get the end of the path.
+        let name = name.as_bytes();
+        self.entry(&name[name.len().saturating_sub(100)..], b'0', data)
+    }
+
+    /// Write a header of `kind` for `name`, then `data`.
+    fn entry(&mut self, name: &[u8], kind: u8, data: &[u8]) -> Result<()> {
+        let mut header = [0u8; BLOCK];
+        header[..name.len()].copy_from_slice(name);
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[108..116].copy_from_slice(b"0000000\0");
+        header[116..124].copy_from_slice(b"0000000\0");
+        header[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+        header[136..148].copy_from_slice(b"00000000000\0");
+        header[148..156].fill(b' ');
+        header[156] = kind;
+        header[257..265].copy_from_slice(b"ustar\x0000");
+        let sum: u32 = header.iter().map(|&b| b as u32).sum();
+        header[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+        self.0.write_all(&header)?;
+        self.0.write_all(data)?;
+        let pad = (BLOCK - data.len() % BLOCK) % BLOCK;
+        self.0.write_all(&[0; BLOCK][..pad])?;
+        Ok(())
+    }
+
+    /// End the archive with its two zero blocks.
+    fn finish(mut self) -> Result<()> {
+        self.0.write_all(&[0; 2 * BLOCK])?;
+        self.0.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+//! Linker script stubs.
+//!
+//! Some "libraries" are short text linker scripts that name the real inputs,
+//! e.g. glibc's `libc.so`:
+//!
+//! ```text
+//! OUTPUT_FORMAT(elf64-x86-64)
+//! GROUP ( /lib/x86_64-linux-gnu/libc.so.6 /usr/lib/x86_64-linux-gnu/libc_nonshared.a
+//!         AS_NEEDED ( /lib64/ld-linux-x86-64.so.2 ) )
+//! ```
+//!
+//! Only the commands such stubs use are understood: `GROUP`, `INPUT`,
+//! `AS_NEEDED`, `SEARCH_DIR`, and the informational `OUTPUT_FORMAT` and
+//! `OUTPUT_ARCH`, which are ignored.
+
+use anyhow::{bail, Context, Result};
+
+/// A file named by a linker script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptInput {
+    /// A path, or `-lname` to search the library path.
+    pub name: String,
+    /// Listed inside `AS_NEEDED ( ... )`.
+    pub as_needed: bool,
+}
+
+/// What a linker script stub asks for.
+#[derive(Debug, Clone, Default)]
+pub struct InputScript {
+    /// Inputs from `GROUP` and `INPUT`, in order.
+    pub inputs: Vec<ScriptInput>,
+    /// Directories from `SEARCH_DIR`.
+    pub search_dirs: Vec<String>,
+}
+
+/// Whether `data` looks like a linker script rather than an object or archive.
+pub fn is_script(data: &[u8]) -> bool {
+    !data.starts_with(b"\x7fELF")
+        && !data.starts_with(b"!<arch>\n")
+        && std::str::from_utf8(data).is_ok()
+}
+
+/// Split a script into words and the punctuation `( ) ,`, dropping comments.
+fn tokenize(text: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut cur = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if prev == '*' => break,
+                        Some(c) => prev = c,
+                        None => bail!("unterminated comment"),
+                    }
+                }
+            }
+            '(' | ')' | ',' => {
+                if !cur.is_empty() {
+                    tokens.push(std::mem::take(&mut cur));
+                }
+                if c != ',' {
+                    tokens.push(c.to_string());
+                }
+            }
+            c if c.is_whitespace() => {
+                if !cur.is_empty() {
+                    tokens.push(std::mem::take(&mut cur));
+                }
+            }
+            c => cur.push(c),
+        }
+    }
+    if !cur.is_empty() {
+        tokens.push(cur);
+    }
+    Ok(tokens)
+}
+
+impl InputScript {
+    /// Parse the text of a linker script stub.
+    pub fn parse(text: &str) -> Result<Self> {
+        let tokens = tokenize(text)?;
+        let mut tokens = tokens.iter().map(String::as_str);
+        let mut script = Self::default();
+
+        while let Some(command) = tokens.next() {
+            if !matches!(
+                command,
+                "GROUP" | "INPUT" | "SEARCH_DIR" | "OUTPUT_FORMAT" | "OUTPUT_ARCH"
+            ) {
+                bail!("unsupported linker script command {}", command);
+            }
+            if tokens.next() != Some("(") {
+                bail!("expected `(` after {}", command);
+            }
+            match command {
+                "GROUP" | "INPUT" => script.parse_inputs(&mut tokens, false)?,
+                "SEARCH_DIR" => {
+                    let dir = tokens.next().context("SEARCH_DIR expects a directory")?;
+                    script.search_dirs.push(dir.to_string());
+                    if tokens.next() != Some(")") {
+                        bail!("expected `)` after SEARCH_DIR");
+                    }
+                }
+                "OUTPUT_FORMAT" | "OUTPUT_ARCH" => {
+                    tokens
+                        .by_ref()
+                        .find(|&t| t == ")")
+                        .with_context(|| format!("unterminated {}", command))?;
+                }
+                _ => unreachable!(),
+            }
+        }
+        Ok(script)
+    }
+
+    /// Parse the file list of `GROUP ( ... )` or `AS_NEEDED ( ... )` up to the
+    /// closing parenthesis.
+    fn parse_inputs<'t>(
+        &mut self,
+        tokens: &mut impl Iterator<Item = &'t str>,
+        as_needed: bool,
+    ) -> Result<()> {
+        loop {
+            match tokens.next().context("unterminated file list")? {
+                ")" => return Ok(()),
+                "AS_NEEDED" => {
+                    if tokens.next() != Some("(") {
+                        bail!("expected `(` after AS_NEEDED");
+                    }
+                    self.parse_inputs(tokens, true)?;
+                }
+                name => self.inputs.push(ScriptInput {
+                    name: name.to_string(),
+                    as_needed,
+                }),
+            }
+        }
+    }
+}
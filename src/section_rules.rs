@@ -0,0 +1,196 @@
+//! Input section descriptions.
+//!
+//! Which output section an input section goes to is decided by its name,
+//! using the input section descriptions of linker scripts:
+//!
+//! ```text
+//! *(.text .stub .text.* .gnu.linkonce.t.*)
+//! *crtbegin.o(.ctors)
+//! ```
+//!
+//! Each description is a file name pattern followed by section name patterns
+//! in parentheses, with `*` and `?` wildcards. A pattern wrapped in
+//! `SORT_BY_NAME(...)` (or `SORT(...)`) or `SORT_BY_ALIGNMENT(...)` sorts the
+//! sections it matches within their output section. The default rules follow GNU
+//! ld's default script, so `-ffunction-sections` / `-fdata-sections` output
+//! such as `.text.foo`, `.rodata.cst16` or `.data.rel.ro.local` lands where it
+//! would with other linkers. Allocated sections no rule describes are
+//! orphans, handled as `--orphan-handling` says.
+
+use anyhow::{bail, Result};
+
+use crate::linker::SortSection;
+use crate::utils::glob_match;
+
+/// A section name pattern of an input section description.
+#[derive(Debug, Clone)]
+pub struct SectionPattern {
+    pub pattern: String,
+    /// How the sections it matches are sorted, from a `SORT_BY_*` wrapper.
+    pub sort: Option<SortSection>,
+}
+
+/// One `FILE(SECTION ...)` input section description.
+#[derive(Debug, Clone)]
+pub struct InputSectionSpec {
+    /// Pattern for the input file; `*` matches every file.
+    pub file: String,
+    /// Patterns for the section names.
+    pub sections: Vec<SectionPattern>,
+}
+
+impl InputSectionSpec {
+    /// Whether section `section` of `file` is described.
+    pub fn matches(&self, file: &str, section: &str) -> bool {
+        self.pattern_for(file, section).is_some()
+    }
+
+    /// The first pattern describing section `section` of `file`.
+    pub fn pattern_for(&self, file: &str, section: &str) -> Option<&SectionPattern> {
+        let base = file.rsplit('/').next().unwrap_or(file);
+        if !glob_match(&self.file, file) && !glob_match(&self.file, base) {
+            return None;
+        }
+        self.sections
+            .iter()
+            .find(|p| glob_match(&p.pattern, section))
+    }
+}
+
+/// Parse a list of input section descriptions, e.g.
+/// `*(.data .data.*) *(.data1)` or `*(SORT_BY_NAME(.text.sorted.*))`.
+pub fn parse_specs(text: &str) -> Result<Vec<InputSectionSpec>> {
+    let mut specs = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let Some((file, after)) = rest.split_once('(') else {
+            bail!("expected FILE(SECTION ...) in {:?}", text);
+        };
+        let mut sections = Vec::new();
+        rest = after.trim_start();
+        loop {
+            if let Some(after) = rest.strip_prefix(')') {
+                rest = after.trim_start();
+                break;
+            }
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                .unwrap_or(rest.len());
+            let (word, after) = rest.split_at(end);
+            if word.is_empty() {
+                bail!("missing ) in {:?}", text);
+            }
+            let Some(inner) = after.strip_prefix('(') else {
+                sections.push(SectionPattern {
+                    pattern: word.to_string(),
+                    sort: None,
+                });
+                rest = after.trim_start();
+                continue;
+            };
+            let sort = match word {
+                "SORT" | "SORT_BY_NAME" => SortSection::Name,
+                "SORT_BY_ALIGNMENT" => SortSection::Alignment,
+                _ => bail!("unknown section pattern wrapper {} in {:?}", word, text),
+            };
+            let Some((patterns, after)) = inner.split_once(')') else {
+                bail!("missing ) after {} in {:?}", word, text);
+            };
+            if patterns.contains('(') || patterns.split_whitespace().next().is_none() {
+                bail!("{} takes section name patterns in {:?}", word, text);
+            }
+            sections.extend(patterns.split_whitespace().map(|p| SectionPattern {
+                pattern: p.to_string(),
+                sort: Some(sort),
+            }));
+            rest = after.trim_start();
+        }
+        let file = file.trim();
+        if file.is_empty() || sections.is_empty() {
+            bail!("empty input section description in {:?}", text);
+        }
+        specs.push(InputSectionSpec {
+            file: file.to_string(),
+            sections,
+        });
+    }
+    Ok(specs)
+}
+
+/// The output section for input sections matching any of `inputs`.
+#[derive(Debug, Clone)]
+pub struct SectionRule {
+    pub output: String,
+    pub inputs: Vec<InputSectionSpec>,
+}
+
+/// The default placement of input sections, from GNU ld's default script.
+///
+/// Unwind tables and exception tables get output sections of their own, so
+/// each stays contiguous for the unwinder.
+const DEFAULT_RULES: &[(&str, &str)] = &[
+    (
+        ".text",
+        "*(SORT_BY_NAME(.text.sorted.*)) *(.text .stub .text.* .gnu.linkonce.t.*)",
+    ),
+    (
+        ".rodata",
+        "*(.rodata .rodata.* .gnu.linkonce.r.*) *(.rodata1)",
+    ),
+    (".eh_frame", "*(.eh_frame)"),
+    (
+        ".gcc_except_table",
+        "*(.gcc_except_table .gcc_except_table.*)",
+    ),
+    (".preinit_array", "*(.preinit_array)"),
+    (".init_array", "*(.init_array .init_array.*)"),
+    (".fini_array", "*(.fini_array .fini_array.*)"),
+    (
+        ".data.rel.ro",
+        "*(.data.rel.ro.local* .data.rel.ro .data.rel.ro.*)",
+    ),
+    (".data", "*(.data .data.* .gnu.linkonce.d.*) *(.data1)"),
+    (".tdata", "*(.tdata .tdata.* .gnu.linkonce.td.*)"),
+    (".tbss", "*(.tbss .tbss.* .gnu.linkonce.tb.*) *(.tcommon)"),
+    (".bss", "*(.dynbss) *(.bss .bss.* .gnu.linkonce.b.*)"),
+];
+
+/// Rules mapping input sections to output sections, tried in order.
+#[derive(Debug, Clone)]
+pub struct SectionRules {
+    rules: Vec<SectionRule>,
+}
+
+impl Default for SectionRules {
+    fn default() -> Self {
+        let rules = DEFAULT_RULES
+            .iter()
+            .map(|(output, inputs)| SectionRule {
+                output: output.to_string(),
+                inputs: parse_specs(inputs).expect("default section rules parse"),
+            })
+            .collect();
+        Self { rules }
+    }
+}
+
+impl SectionRules {
+    /// The output section of the first rule describing section `section` of
+    /// `file`.
+    pub fn output_for(&self, file: &str, section: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|r| r.inputs.iter().any(|s| s.matches(file, section)))
+            .map(|r| r.output.as_str())
+    }
+
+    /// How the `SORT_BY_*` wrapper of the pattern placing section `section`
+    /// of `file` sorts it, if it has one.
+    pub fn sort_for(&self, file: &str, section: &str) -> Option<SortSection> {
+        self.rules
+            .iter()
+            .flat_map(|r| &r.inputs)
+            .find_map(|s| s.pattern_for(file, section))?
+            .sort
+    }
+}
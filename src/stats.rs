@@ -0,0 +1,131 @@
+//! Link statistics.
+//!
+//! [`Trace`] times the phases of a link. The timings, along with counts of
+//! what was linked, are printed by `--stats`, and `--time-trace` writes them
+//! as a Chrome trace that can be loaded in `about:tracing` or Perfetto.
+
+use anyhow::Result;
+use serde_json::json;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// A timed phase of the link.
+struct Span {
+    name: &'static str,
+    /// Time from the start of the trace.
+    start: Duration,
+    duration: Duration,
+}
+
+/// Timings of the link phases, in the order they finished.
+pub struct Trace {
+    start: Instant,
+    spans: Vec<Span>,
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            spans: Vec::new(),
+        }
+    }
+
+    /// Run `f` as the phase `name`.
+    pub fn time<T>(&mut self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.spans.push(Span {
+            name,
+            start: start - self.start,
+            duration: start.elapsed(),
+        });
+        result
+    }
+
+    /// Time since the trace started.
+    pub fn total(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Write the phases in the Chrome trace event format, under a `Total`
+    /// event covering the whole link.
+    pub fn write_chrome(&self, out: &mut dyn Write) -> Result<()> {
+        let event = |name: &str, start: Duration, duration: Duration| {
+            json!({
+                "name": name,
+                "ph": "X",
+                "ts": start.as_micros() as u64,
+                "dur": duration.as_micros() as u64,
+                "pid": 1,
+                "tid": 0,
+            })
+        };
+        let mut events = vec![event("Total", Duration::ZERO, self.total())];
+        events.extend(
+            self.spans
+                .iter()
+                .map(|s| event(s.name, s.start, s.duration)),
+        );
+        let trace = json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ms",
+        });
+        serde_json::to_writer(&mut *out, &trace)?;
+        writeln!(out)?;
+        Ok(())
+    }
+}
+
+/// Counts of what went into and came out of a link.
+#[derive(Debug, Default)]
+pub struct Counters {
+    /// Object files, including extracted archive members.
+    pub objects: usize,
+    pub archive_members: usize,
+    pub shared_libraries: usize,
+    /// Global symbols defined by the objects.
+    pub symbols: usize,
+    /// Input sections placed in the output.
+    pub sections: usize,
+    pub relocations: usize,
+    pub output_bytes: u64,
+}
+
+/// Write the `--stats` summary: the phase timings, then the counters.
+pub fn write_stats(out: &mut dyn Write, trace: &Trace, counters: &Counters) -> Result<()> {
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    writeln!(out, "{:<20} {:>12}", "Phase", "Time (ms)")?;
+    for span in &trace.spans {
+        writeln!(out, "{:<20} {:>12.3}", span.name, ms(span.duration))?;
+    }
+    writeln!(out, "{:<20} {:>12.3}", "total", ms(trace.total()))?;
+    writeln!(out)?;
+    let Counters {
+        objects,
+        archive_members,
+        shared_libraries,
+        symbols,
+        sections,
+        relocations,
+        output_bytes,
+    } = counters;
+    for (name, value) in [
+        ("objects", *objects as u64),
+        ("archive members", *archive_members as u64),
+        ("shared libraries", *shared_libraries as u64),
+        ("symbols", *symbols as u64),
+        ("sections", *sections as u64),
+        ("relocations", *relocations as u64),
+        ("output bytes", *output_bytes),
+    ] {
+        writeln!(out, "{:<20} {:>12}", name, value)?;
+    }
+    Ok(())
+}
@@ -2,8 +2,29 @@
 //!
 //! Tracks symbols from input object files and resolves them to final addresses.
 
+use object::elf;
 use object::read::SectionIndex;
 
+use crate::utils::glob_match;
+
+/// Where a symbol is defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolLocation {
+    /// In a section of the input file; the offset is relative to it.
+    Section(SectionIndex),
+    /// `SHN_ABS`: the offset is the address itself.
+    Absolute,
+    /// `SHN_COMMON`: a tentative definition the linker allocates in `.bss`.
+    /// The offset is relative to `.bss` once allocated.
+    Common {
+        /// Required alignment (the input's `st_value`).
+        align: u64,
+    },
+    /// Not defined anywhere the linker can place, such as a reserved section
+    /// index it does not understand.
+    Undefined,
+}
+
 /// A symbol defined in an input object file.
 ///
 /// Initially stores indices for deferred address resolution.
@@ -12,14 +33,18 @@ use object::read::SectionIndex;
 pub struct DefinedSymbol {
     /// Index of the input file in the linker's file list.
     pub input_file_index: usize,
-    /// Section index within that input file.
-    pub section_index: SectionIndex,
-    /// Offset within the section (or absolute address if `is_absolute`).
+    /// Where the symbol is defined.
+    pub location: SymbolLocation,
+    /// Offset within the location (the address for absolute symbols).
     pub offset: u64,
+    /// Size of the symbol in bytes (`st_size`).
+    pub size: u64,
     /// Whether this is a weak symbol (can be overridden).
     pub is_weak: bool,
-    /// Whether this is an absolute symbol (not section-relative).
-    pub is_absolute: bool,
+    /// Whether this is a `STB_GNU_UNIQUE` symbol, whose copies merge.
+    pub is_unique: bool,
+    /// `STT_*` type of the definition (function, object, TLS...).
+    pub st_type: u8,
     /// Final virtual address (populated after layout).
     pub resolved_address: Option<u64>,
 }
@@ -27,32 +52,200 @@ pub struct DefinedSymbol {
 impl DefinedSymbol {
     pub fn new(
         input_file_index: usize,
-        section_index: SectionIndex,
+        location: SymbolLocation,
         offset: u64,
+        size: u64,
         is_weak: bool,
-        is_absolute: bool,
     ) -> Self {
         Self {
             input_file_index,
-            section_index,
+            location,
             offset,
+            size,
             is_weak,
-            is_absolute,
+            is_unique: false,
+            st_type: elf::STT_NOTYPE,
             resolved_address: None,
         }
     }
 
+    /// An absolute symbol whose value is filled in later.
+    pub fn absolute(value: u64) -> Self {
+        Self::new(0, SymbolLocation::Absolute, value, 0, false)
+    }
+
+    /// Whether the symbol is `SHN_ABS`.
+    pub fn is_absolute(&self) -> bool {
+        self.location == SymbolLocation::Absolute
+    }
+
+    /// Whether the symbol is a tentative (`SHN_COMMON`) definition.
+    pub fn is_common(&self) -> bool {
+        matches!(self.location, SymbolLocation::Common { .. })
+    }
+
+    /// `STB_*` binding in the output symbol tables.
+    pub fn binding(&self) -> u8 {
+        if self.is_unique {
+            elf::STB_GNU_UNIQUE
+        } else if self.is_weak {
+            elf::STB_WEAK
+        } else {
+            elf::STB_GLOBAL
+        }
+    }
+
+    /// `st_info` in the output symbol tables.
+    pub fn info(&self) -> u8 {
+        (self.binding() << 4) | self.st_type
+    }
+
     /// Get the resolved address, panics if not yet resolved.
     pub fn address(&self) -> u64 {
         self.resolved_address.expect("symbol not yet resolved")
     }
 }
 
-/// Known symbols that can remain undefined (resolve to 0).
-pub fn is_optional_symbol(name: &str) -> bool {
-    matches!(
-        name,
-        "_DYNAMIC" | "__dso_handle" | "_dl_find_object" | "__TMC_END__"
-    ) || name.starts_with("__TMC_")
-        || name.starts_with("__gcc_")
+/// A layout boundary that a linker-defined symbol points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    /// The ELF file header at the start of the loaded image.
+    FileHeader,
+    /// End of the executable code.
+    TextEnd,
+    /// End of the initialized data.
+    DataEnd,
+    /// Start of `.bss`.
+    BssStart,
+    /// End of the loaded image, `.bss` included.
+    ImageEnd,
+}
+
+/// The value of a symbol the linker defines itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntheticValue {
+    /// Never defined; references resolve to 0 as if weak.
+    Optional,
+    /// A fixed address.
+    Absolute(u64),
+    /// A layout boundary.
+    Boundary(Boundary),
+    /// Start of the named output section (0 if it is empty).
+    SegmentStart(String),
+    /// End of the named output section (0 if it is empty).
+    SegmentEnd(String),
+    /// Load address of the named output section (0 if it is empty).
+    SegmentLoadStart(String),
+    /// End of the named output section's load range (0 if it is empty).
+    SegmentLoadEnd(String),
+    /// The address of another symbol.
+    Alias(String),
+}
+
+/// A symbol the linker defines itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntheticSymbol {
+    /// Symbol name; a `*` / `?` glob for [`SyntheticValue::Optional`].
+    pub name: String,
+    pub value: SyntheticValue,
+    /// Like GNU ld's `PROVIDE_HIDDEN`: only defined when referenced and not
+    /// defined by an input, and never exported. User definitions are not
+    /// provided; they replace input definitions and export normally.
+    pub provide: bool,
+}
+
+/// Registry of linker-defined symbols: the built-in ones, plus any the user
+/// adds with `--defsym` or [`crate::linker::Linker::define_symbol`].
+#[derive(Debug, Clone, Default)]
+pub struct SyntheticSymbols {
+    symbols: Vec<SyntheticSymbol>,
+}
+
+impl SyntheticSymbols {
+    /// The symbols every link provides.
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        // Runtime hooks that are fine to leave undefined.
+        for name in ["__dso_handle", "_dl_find_object", "__TMC_*", "__gcc_*"] {
+            registry.provide(name, SyntheticValue::Optional);
+        }
+        registry.provide("_DYNAMIC", SyntheticValue::SegmentStart(".dynamic".into()));
+        registry.provide(
+            "_GLOBAL_OFFSET_TABLE_",
+            SyntheticValue::SegmentStart(crate::got::PLT_SECTION.into()),
+        );
+        // The `IRELATIVE` relocations static startup code applies itself.
+        // uld keeps them in .rela.dyn, which static-PIE startup code applies
+        // with the rest, so the range is empty.
+        for name in ["__rela_iplt_start", "__rela_iplt_end"] {
+            registry.provide(name, SyntheticValue::SegmentEnd(".rela.dyn".into()));
+        }
+        for (name, boundary) in [
+            ("__ehdr_start", Boundary::FileHeader),
+            ("etext", Boundary::TextEnd),
+            ("_etext", Boundary::TextEnd),
+            ("__etext", Boundary::TextEnd),
+            ("edata", Boundary::DataEnd),
+            ("_edata", Boundary::DataEnd),
+            ("__bss_start", Boundary::BssStart),
+            ("end", Boundary::ImageEnd),
+            ("_end", Boundary::ImageEnd),
+        ] {
+            registry.provide(name, SyntheticValue::Boundary(boundary));
+        }
+        for array in ["preinit_array", "init_array", "fini_array"] {
+            registry.provide_bounds(
+                &format!(".{}", array),
+                &format!("__{}_start", array),
+                &format!("__{}_end", array),
+            );
+        }
+        registry
+    }
+
+    /// Add a built-in definition, unless one for `name` already exists.
+    pub fn provide(&mut self, name: &str, value: SyntheticValue) {
+        if self.get(name).is_none() {
+            self.symbols.push(SyntheticSymbol {
+                name: name.to_string(),
+                value,
+                provide: true,
+            });
+        }
+    }
+
+    /// Provide `start` and `end` at the bounds of output section `segment`.
+    pub fn provide_bounds(&mut self, segment: &str, start: &str, end: &str) {
+        self.provide(start, SyntheticValue::SegmentStart(segment.to_string()));
+        self.provide(end, SyntheticValue::SegmentEnd(segment.to_string()));
+    }
+
+    /// Add a user definition, replacing any earlier one for `name`.
+    pub fn define(&mut self, name: &str, value: SyntheticValue) {
+        self.symbols.retain(|s| s.name != name);
+        self.symbols.push(SyntheticSymbol {
+            name: name.to_string(),
+            value,
+            provide: false,
+        });
+    }
+
+    /// The definition of `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&SyntheticSymbol> {
+        self.symbols.iter().find(|s| s.name == name)
+    }
+
+    /// Whether `name` may stay undefined, resolving to 0.
+    pub fn is_optional(&self, name: &str) -> bool {
+        self.symbols
+            .iter()
+            .any(|s| s.value == SyntheticValue::Optional && glob_match(&s.name, name))
+    }
+
+    /// Every definition that gives a symbol a value, in registration order.
+    pub fn definitions(&self) -> impl Iterator<Item = &SyntheticSymbol> {
+        self.symbols
+            .iter()
+            .filter(|s| s.value != SyntheticValue::Optional)
+    }
 }
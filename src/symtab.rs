@@ -0,0 +1,388 @@
+//! Sharded tables of global symbols.
+//!
+//! The resolver's per-name tables (definitions, references, visibility and
+//! imports) are each split into [`SHARDS`] maps by the shard of the
+//! [`Name`]. Resolving a name only reads and updates the entries of that
+//! name, so each shard of a batch of objects can be resolved on its own
+//! thread: every name is handled by exactly one thread, in input order,
+//! and the outcome is the same however the threads interleave.
+//!
+//! [`Shard::resolve`] applies the precedence rules to the symbols of a
+//! [`Batch`] of objects that fall in one shard.
+
+use foldhash::{HashMap, HashSet};
+use std::ops::Index;
+
+use crate::dynamic::SharedLibrary;
+use crate::input::{self, InputSymbol};
+use crate::intern::{InternShard, Interner, Name, SHARDS};
+use crate::layout::InputFile;
+use crate::symbol::{DefinedSymbol, SymbolLocation, SyntheticSymbols};
+
+/// A map from [`Name`]s split into shards.
+#[derive(Debug, Clone)]
+pub struct ShardedMap<V> {
+    shards: Vec<HashMap<Name, V>>,
+}
+
+impl<V> Default for ShardedMap<V> {
+    fn default() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| HashMap::default()).collect(),
+        }
+    }
+}
+
+impl<V> ShardedMap<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &Name) -> Option<&V> {
+        self.shards[name.shard()].get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &Name) -> Option<&mut V> {
+        self.shards[name.shard()].get_mut(name)
+    }
+
+    pub fn contains_key(&self, name: &Name) -> bool {
+        self.shards[name.shard()].contains_key(name)
+    }
+
+    pub fn insert(&mut self, name: Name, value: V) -> Option<V> {
+        self.shards[name.shard()].insert(name, value)
+    }
+
+    pub fn remove(&mut self, name: &Name) -> Option<V> {
+        self.shards[name.shard()].remove(name)
+    }
+
+    pub fn entry(&mut self, name: Name) -> std::collections::hash_map::Entry<'_, Name, V> {
+        self.shards[name.shard()].entry(name)
+    }
+
+    /// The entries of every shard, shard by shard.
+    pub fn iter(&self) -> impl Iterator<Item = (&Name, &V)> {
+        self.shards.iter().flatten()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Name> {
+        self.iter().map(|(name, _)| name)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|s| s.is_empty())
+    }
+
+    /// The shards, indexed by [`Name::shard`].
+    pub fn shards_mut(&mut self) -> &mut [HashMap<Name, V>] {
+        &mut self.shards
+    }
+}
+
+impl<V> Index<&Name> for ShardedMap<V> {
+    type Output = V;
+
+    fn index(&self, name: &Name) -> &V {
+        &self.shards[name.shard()][name]
+    }
+}
+
+/// A set of [`Name`]s split into shards.
+#[derive(Debug, Clone)]
+pub struct ShardedSet {
+    shards: Vec<HashSet<Name>>,
+}
+
+impl Default for ShardedSet {
+    fn default() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| HashSet::default()).collect(),
+        }
+    }
+}
+
+impl ShardedSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, name: &Name) -> bool {
+        self.shards[name.shard()].contains(name)
+    }
+
+    pub fn insert(&mut self, name: Name) -> bool {
+        self.shards[name.shard()].insert(name)
+    }
+
+    pub fn remove(&mut self, name: &Name) -> bool {
+        self.shards[name.shard()].remove(name)
+    }
+
+    /// The names of every shard, shard by shard.
+    pub fn iter(&self) -> impl Iterator<Item = &Name> {
+        self.shards.iter().flatten()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|s| s.is_empty())
+    }
+
+    /// The shards, indexed by [`Name::shard`].
+    pub fn shards_mut(&mut self) -> &mut [HashSet<Name>] {
+        &mut self.shards
+    }
+}
+
+/// Objects whose symbols are resolved together, in input order.
+pub struct Batch<'b, 'a> {
+    /// The global symbols of each object.
+    pub symbols: Vec<&'b [InputSymbol<'a>]>,
+    /// The [`Interner::hash`] of each symbol's name, parallel to `symbols`.
+    pub hashes: Vec<Vec<u64>>,
+    /// Index of the first object in the linker's file list; the others
+    /// follow it.
+    pub first: usize,
+}
+
+/// What resolving a shard reads but doesn't change.
+pub struct Context<'c> {
+    pub inputs: &'c [InputFile],
+    pub shared_libs: &'c [SharedLibrary],
+    pub synthetic: &'c SyntheticSymbols,
+    /// `-z muldefs`: the first of several strong definitions wins.
+    pub muldefs: bool,
+    /// Record each decision, for `--verbose`.
+    pub verbose: bool,
+}
+
+/// Two strong definitions of a name, which end the link.
+#[derive(Debug, Clone, Copy)]
+pub struct Clash {
+    /// Where the second one is: an object of the [`Batch`] and one of its
+    /// symbols.
+    pub at: (usize, usize),
+    /// Index of the file holding the first one.
+    pub first: usize,
+}
+
+/// The outcome of resolving one shard of a [`Batch`].
+#[derive(Debug, Default)]
+pub struct Resolved {
+    /// Decisions for `--verbose`, in input order for each name.
+    pub log: Vec<(Name, String)>,
+    /// The first clash in the shard; resolution of the shard stops there.
+    pub clash: Option<Clash>,
+}
+
+/// One shard of each of the linker's per-name tables.
+pub struct Shard<'s> {
+    pub names: InternShard<'s>,
+    pub symbols: &'s mut HashMap<Name, DefinedSymbol>,
+    /// Only weakly referenced: 0 unless bound at runtime.
+    pub weak: &'s mut HashSet<Name>,
+    /// The most constraining `STV_*` of each global, if not default.
+    pub visibility: &'s mut HashMap<Name, u8>,
+    /// Referenced but not defined -> the first referencing object.
+    pub undefined: &'s mut HashMap<Name, Option<usize>>,
+    /// Bound at runtime -> the defining shared library.
+    pub imports: &'s mut HashMap<Name, usize>,
+}
+
+impl Shard<'_> {
+    /// Resolve the symbols of `batch` whose names fall in this shard.
+    pub fn resolve(&mut self, cx: &Context, batch: &Batch) -> Resolved {
+        let mut log = Vec::new();
+        let shard = self.names.shard();
+        for (i, (symbols, hashes)) in batch.symbols.iter().zip(&batch.hashes).enumerate() {
+            for (j, (sym, &hash)) in symbols.iter().zip(hashes).enumerate() {
+                if Interner::shard_of(hash) != shard {
+                    continue;
+                }
+                let n = self.names.intern(hash, sym.name);
+                let mut note = |what: &dyn Fn() -> String| {
+                    if cx.verbose {
+                        log.push((n, what()));
+                    }
+                };
+                if let Err(first) = self.add(cx, batch.first + i, sym, n, &mut note) {
+                    let clash = Some(Clash { at: (i, j), first });
+                    return Resolved { log, clash };
+                }
+            }
+        }
+        Resolved { log, clash: None }
+    }
+
+    /// Add the symbol `sym`, named `n`, of file `idx`. Fails with the file
+    /// of the first definition if `sym` is a second strong one.
+    fn add(
+        &mut self,
+        cx: &Context,
+        idx: usize,
+        sym: &InputSymbol,
+        n: Name,
+        note: &mut dyn FnMut(&dyn Fn() -> String),
+    ) -> Result<(), usize> {
+        let input = &cx.inputs[idx];
+        if sym.visibility != object::elf::STV_DEFAULT {
+            let v = self.visibility.entry(n).or_insert(object::elf::STV_DEFAULT);
+            *v = input::merge_visibility(*v, sym.visibility);
+        }
+        let Some(location) = sym.location else {
+            let optional = sym.optional || cx.synthetic.is_optional(sym.name);
+            // A symbol is weak only as long as every reference to it is.
+            let strong = self.undefined.contains_key(&n)
+                || (self.imports.contains_key(&n) && !self.weak.contains(&n));
+            if !optional {
+                self.weak.remove(&n);
+            } else if !strong {
+                self.weak.insert(n);
+            }
+            // Weak references may still be bound to a shared library at
+            // runtime; hidden ones may not.
+            let bindable = !optional || (sym.weak && !input::is_hidden(sym.visibility));
+            if self.symbols.contains_key(&n) || self.imports.contains_key(&n) {
+                note(&|| format!("referenced by {}", input));
+            } else if let Some(lib) = cx
+                .shared_libs
+                .iter()
+                .position(|l| bindable && l.symbols.contains(sym.name))
+            {
+                note(&|| {
+                    format!(
+                        "referenced by {}, bound to shared library {}",
+                        input, cx.shared_libs[lib].soname
+                    )
+                });
+                self.imports.insert(n, lib);
+            } else if optional {
+                note(&|| format!("optional reference from {}", input));
+            } else {
+                note(&|| format!("referenced by {}, undefined so far", input));
+                self.undefined.entry(n).or_default().get_or_insert(idx);
+            }
+            return Ok(());
+        };
+
+        let mut new = DefinedSymbol::new(idx, location, sym.address, sym.size, sym.weak);
+        new.is_unique = sym.unique;
+        new.st_type = sym.st_type;
+        if let SymbolLocation::Common { align } = location {
+            new.offset = 0;
+            match self.symbols.get(&n).map(|old| (old.location, old)) {
+                // Tentative definitions merge into the largest, most aligned one.
+                Some((SymbolLocation::Common { align: old_align }, old)) => {
+                    new.location = SymbolLocation::Common {
+                        align: align.max(old_align),
+                    };
+                    new.size = new.size.max(old.size);
+                    let old_file = &cx.inputs[old.input_file_index];
+                    note(&|| {
+                        format!(
+                            "common in {} merged with common in {} (size {}, alignment {})",
+                            input,
+                            old_file,
+                            new.size,
+                            align.max(old_align)
+                        )
+                    });
+                }
+                // Any other non-weak definition wins over a common one.
+                Some((_, old)) if !old.is_weak => {
+                    let old_file = &cx.inputs[old.input_file_index];
+                    note(&|| format!("common in {} ignored: defined in {}", input, old_file));
+                    return Ok(());
+                }
+                Some((_, old)) => {
+                    let old_file = &cx.inputs[old.input_file_index];
+                    note(&|| {
+                        format!(
+                            "common in {} replaces weak definition in {}",
+                            input, old_file
+                        )
+                    });
+                }
+                None => note(&|| format!("common in {}", input)),
+            }
+        } else if let Some(old) = self
+            .symbols
+            .get(&n)
+            .filter(|old| !old.is_weak && !old.is_common())
+        {
+            let old_file = &cx.inputs[old.input_file_index];
+            if new.is_unique && old.is_unique {
+                // Like COMDAT copies: the first one is kept.
+                note(&|| {
+                    format!(
+                        "unique definition in {} merged with unique definition in {}",
+                        input, old_file
+                    )
+                });
+                return Ok(());
+            }
+            if !new.is_weak && !cx.muldefs {
+                return Err(old.input_file_index);
+            }
+            note(&|| {
+                if new.is_weak {
+                    format!(
+                        "weak definition in {} ignored: strong definition in {} wins",
+                        input, old_file
+                    )
+                } else {
+                    format!(
+                        "definition in {} ignored (-z muldefs): first definition in {} wins",
+                        input, old_file
+                    )
+                }
+            });
+            // If the symbol is weak, we actually let the next one overwrite it.
+            return Ok(());
+        } else {
+            let old = self.symbols.get(&n);
+            let lib = self.imports.get(&n).copied();
+            note(&|| {
+                let what = if new.is_weak {
+                    "weak definition"
+                } else {
+                    "definition"
+                };
+                match (old, lib) {
+                    (Some(old), _) if old.is_common() => format!(
+                        "{} in {} replaces common in {}",
+                        what, input, cx.inputs[old.input_file_index]
+                    ),
+                    (Some(old), _) => format!(
+                        "{} in {} overrides weak definition in {}",
+                        what, input, cx.inputs[old.input_file_index]
+                    ),
+                    (None, Some(lib)) => format!(
+                        "{} in {} takes precedence over shared library {}",
+                        what, input, cx.shared_libs[lib].soname
+                    ),
+                    (None, None) => format!("{} in {}", what, input),
+                }
+            });
+        }
+
+        // Regular definitions take precedence over shared libraries.
+        self.undefined.remove(&n);
+        self.imports.remove(&n);
+        self.symbols.insert(n, new);
+        Ok(())
+    }
+}
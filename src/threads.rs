@@ -0,0 +1,56 @@
+//! Worker thread pool sizing.
+//!
+//! Every parallel phase runs on a thread pool of the link's own. Its size
+//! comes from `--threads` when given. Otherwise, under a GNU make jobserver
+//! (advertised in `MAKEFLAGS`), the linker takes as many free job slots as
+//! it can use, so a link inside `make -jN` doesn't oversubscribe the
+//! machine. Failing both, it uses every available core.
+
+use anyhow::Result;
+use jobserver::{Acquired, Client};
+use std::thread;
+use tracing::info;
+
+/// Job slots taken from the jobserver, returned to it when dropped.
+pub struct Threads {
+    _tokens: Vec<Acquired>,
+}
+
+/// A thread pool for one link; `requested` is the `--threads` value.
+/// Links get a pool each rather than sizing rayon's global one, which can
+/// only be done once in a process that may link again.
+pub fn pool(requested: Option<usize>) -> Result<(rayon::ThreadPool, Threads)> {
+    let (count, threads) = take(requested);
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(count).build()?;
+    info!("Using {} threads", count);
+    Ok((pool, threads))
+}
+
+/// How many threads to use, and the job slots taken for them.
+fn take(requested: Option<usize>) -> (usize, Threads) {
+    let mut tokens = Vec::new();
+    let count = match requested {
+        Some(n) => n,
+        None => {
+            let cores = thread::available_parallelism().map_or(1, |n| n.get());
+            // SAFETY: the descriptors named in MAKEFLAGS are only ever used
+            // through this client.
+            match unsafe { Client::from_env() } {
+                Some(client) => {
+                    // Every process gets one implicit slot; the rest must be
+                    // taken from the pool without waiting for them.
+                    while tokens.len() + 1 < cores {
+                        match client.try_acquire() {
+                            Ok(Some(token)) => tokens.push(token),
+                            _ => break,
+                        }
+                    }
+                    info!("jobserver: {} job slots", tokens.len() + 1);
+                    tokens.len() + 1
+                }
+                None => cores,
+            }
+        }
+    };
+    (count, Threads { _tokens: tokens })
+}
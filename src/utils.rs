@@ -8,8 +8,58 @@ pub fn align_up(addr: u64, align: u64) -> u64 {
     (addr + align - 1) & !(align - 1)
 }
 
-/// Find `lib{name}.a` in search paths.
-pub fn find_library(name: &str, paths: &[PathBuf]) -> Option<PathBuf> {
-    let filename = format!("lib{}.a", name);
-    paths.iter().map(|p| p.join(&filename)).find(|p| p.exists())
+/// Find the library for `-l{name}` in search paths.
+///
+/// Each directory is searched for `lib{name}.so` (when `dynamic` is allowed)
+/// and then `lib{name}.a` before moving on to the next. `-l:file` names a
+/// file exactly, which is how versioned libraries such as `libfoo.so.1` are
+/// requested.
+pub fn find_library(name: &str, paths: &[PathBuf], dynamic: bool) -> Option<PathBuf> {
+    let candidates = match name.strip_prefix(':') {
+        Some(exact) => vec![exact.to_string()],
+        None if dynamic => vec![format!("lib{}.so", name), format!("lib{}.a", name)],
+        None => vec![format!("lib{}.a", name)],
+    };
+    paths
+        .iter()
+        .flat_map(|p| candidates.iter().map(move |c| p.join(c)))
+        .find(|p| p.is_file())
+}
+
+/// Match `name` against a shell-style glob supporting `*` and `?`.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (p, n): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    // Position after the last `*` seen in the pattern, and where in `name` it matched.
+    let (mut pi, mut ni, mut star) = (0, 0, None);
+    while ni < n.len() {
+        match p.get(pi) {
+            Some('*') => {
+                star = Some((pi + 1, ni));
+                pi += 1;
+            }
+            Some(&c) if c == '?' || c == n[ni] => {
+                pi += 1;
+                ni += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    pi = sp;
+                    ni = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Whether `name` is a valid C identifier, which is what makes a section an
+/// encapsulation section with `__start_` / `__stop_` symbols.
+pub fn is_c_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
 }
@@ -0,0 +1,743 @@
+//! Sanity checks for input objects and linked outputs.
+//!
+//! The `object` crate parses headers lazily, so a truncated or hostile
+//! object can still parse while holding section indices, offsets and sizes
+//! that the layout and relocation passes trust. [`check_object`] looks at
+//! all of them once, when the object joins the link, and reports what is
+//! wrong as diagnostics for that file instead of a panic deep inside
+//! `relocate`.
+//!
+//! [`check_executable`] goes the other way: it reads a linked executable or
+//! shared library the way `readelf` and the loader do and lists what is
+//! structurally wrong with it. `--check-sections` runs it on every output,
+//! and it works on files from any linker, so tests can run it on theirs.
+
+use std::fmt;
+
+use object::elf;
+use object::read::elf::{FileHeader, ProgramHeader, SectionHeader};
+use object::read::{Object, ObjectSection, ObjectSymbol, RelocationTarget};
+use object::{Endianness, ObjectKind, SectionKind, SymbolSection};
+
+use crate::arch::Architecture;
+use crate::diagnostics::{Diagnostic, Kind, Report};
+
+/// Largest section alignment accepted: alignments are added to addresses,
+/// so anything near `u64::MAX` would overflow them.
+const MAX_ALIGN: u64 = 1 << 32;
+/// Largest section size accepted, the span of a 48-bit address space;
+/// sections are summed into output sizes that must not overflow.
+const MAX_SIZE: u64 = 1 << 48;
+
+/// Check that `obj`, read from `file`, is a relocatable object, and that
+/// its sections, symbols and relocations are consistent. Relocated fields
+/// are as wide as `arch` reads and writes them.
+pub fn check_object(
+    obj: &object::File,
+    file: &str,
+    arch: &impl Architecture,
+) -> Result<(), Report> {
+    let mut problems: Vec<(Option<String>, Option<u64>, String)> = Vec::new();
+    if obj.kind() != ObjectKind::Relocatable {
+        problems.push((
+            None,
+            None,
+            format!("not a relocatable object ({:?})", obj.kind()),
+        ));
+    }
+
+    let mut indices = Vec::new();
+    for sec in obj.sections() {
+        let name = sec.name().unwrap_or("?").to_string();
+        let align = sec.align();
+        if align > 1 && (!align.is_power_of_two() || align > MAX_ALIGN) {
+            problems.push((
+                Some(name.clone()),
+                None,
+                format!("alignment {:#x} is not a power of two up to 4 GiB", align),
+            ));
+        }
+        if sec.size() > MAX_SIZE {
+            problems.push((
+                Some(name.clone()),
+                None,
+                format!("size {:#x} is larger than an address space", sec.size()),
+            ));
+        } else if sec.kind() != SectionKind::UninitializedData
+            && sec.kind() != SectionKind::UninitializedTls
+            && sec.data().map(|d| d.len() as u64) != Ok(sec.size())
+        {
+            problems.push((
+                Some(name),
+                None,
+                format!("contents ({} bytes) lie outside the file", sec.size()),
+            ));
+        }
+        indices.push(sec.index());
+    }
+
+    for sym in obj.symbols() {
+        // A common symbol's value is its alignment.
+        if sym.section() == SymbolSection::Common {
+            if sym.address() > MAX_ALIGN || sym.size() > MAX_SIZE {
+                problems.push((
+                    None,
+                    None,
+                    format!(
+                        "common symbol `{}' has alignment {:#x} and size {:#x}, beyond what fits in memory",
+                        sym.name().unwrap_or("?"),
+                        sym.address(),
+                        sym.size()
+                    ),
+                ));
+            }
+            continue;
+        }
+        let SymbolSection::Section(i) = sym.section() else {
+            continue;
+        };
+        if !indices.contains(&i) {
+            problems.push((
+                None,
+                None,
+                format!(
+                    "symbol `{}' is in section index {}, which does not exist",
+                    sym.name().unwrap_or("?"),
+                    i.0
+                ),
+            ));
+        }
+    }
+
+    for sec in obj.sections() {
+        let (name, size) = (sec.name().unwrap_or("?"), sec.size());
+        for (offset, r) in sec.relocations() {
+            let bytes = (arch.reloc_size(&r) as u64 / 8).max(1);
+            let problem = if offset.checked_add(bytes).is_none_or(|end| end > size) {
+                Some("relocation lies past the end of its section".to_string())
+            } else {
+                match r.target() {
+                    RelocationTarget::Symbol(i) if obj.symbol_by_index(i).is_err() => {
+                        Some(format!(
+                            "relocation refers to symbol index {}, which does not exist",
+                            i.0
+                        ))
+                    }
+                    RelocationTarget::Section(i) if !indices.contains(&i) => Some(format!(
+                        "relocation refers to section index {}, which does not exist",
+                        i.0
+                    )),
+                    _ => None,
+                }
+            };
+            if let Some(problem) = problem {
+                problems.push((Some(name.to_string()), Some(offset), problem));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let mut text = format!("{}: malformed input:", file);
+    let mut diagnostics = Vec::new();
+    for (section, offset, message) in problems {
+        let place = match (&section, offset) {
+            (Some(s), Some(o)) => format!("({}+0x{:x}): ", s, o),
+            (Some(s), None) => format!("({}): ", s),
+            _ => String::new(),
+        };
+        text.push_str(&format!("\n  {}{}", place, message));
+        let mut d = Diagnostic::new(Kind::MalformedInput, message);
+        d.file = Some(file.to_string());
+        d.section = section;
+        d.offset = offset;
+        diagnostics.push(d);
+    }
+    Err(Report::new(text, diagnostics))
+}
+
+/// A structural problem [`check_executable`] found in a linked file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// What it is about: `ELF header`, `program header 2 (PT_LOAD)` or
+    /// `section .text`.
+    pub place: String,
+    /// What is wrong with it.
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.place, self.message)
+    }
+}
+
+/// Sections the loader only writes while relocating, which every linker
+/// puts under `PT_GNU_RELRO` when it emits one. `.got.plt` is left out: it
+/// stays writable under lazy binding.
+const RELRO_REQUIRED: &[&str] = &[
+    ".preinit_array",
+    ".init_array",
+    ".fini_array",
+    ".data.rel.ro",
+    ".got",
+    ".dynamic",
+];
+
+/// Sections written after startup, which `PT_GNU_RELRO` must not cover.
+const RELRO_FORBIDDEN: &[&str] = &[".data", ".bss"];
+
+/// Check the structure of `bytes`, an ELF executable or shared library:
+/// the header fields, that program and section headers lie in the file and
+/// agree with each other, that the entry point is in an executable
+/// segment and that `PT_GNU_RELRO` covers what it should. Returns every
+/// problem found; an empty list means the file is well formed.
+pub fn check_executable(bytes: &[u8]) -> Vec<Violation> {
+    if bytes.get(..4) != Some(&elf::ELFMAG[..]) {
+        return vec![violation("ELF header", "not an ELF file".to_string())];
+    }
+    // The class is the fifth byte of `e_ident`.
+    match bytes.get(4) {
+        Some(&elf::ELFCLASS64) => check_elf::<elf::FileHeader64<Endianness>>(bytes),
+        Some(&elf::ELFCLASS32) => check_elf::<elf::FileHeader32<Endianness>>(bytes),
+        class => vec![violation(
+            "ELF header",
+            format!("unknown class {:?}", class),
+        )],
+    }
+}
+
+fn violation(place: &str, message: String) -> Violation {
+    Violation {
+        place: place.to_string(),
+        message,
+    }
+}
+
+/// A program header, widened to 64 bits.
+struct Phdr {
+    index: usize,
+    kind: u32,
+    flags: u32,
+    offset: u64,
+    vaddr: u64,
+    filesz: u64,
+    memsz: u64,
+    align: u64,
+}
+
+impl Phdr {
+    fn place(&self) -> String {
+        format!(
+            "program header {} ({})",
+            self.index,
+            segment_type(self.kind)
+        )
+    }
+
+    /// Whether the file bytes `[offset, offset + size)` are mapped by this
+    /// segment at `addr`.
+    fn maps(&self, offset: u64, addr: u64, size: u64) -> bool {
+        offset >= self.offset
+            && offset.saturating_add(size) <= self.file_end()
+            && addr.wrapping_sub(self.vaddr) == offset - self.offset
+    }
+
+    /// Whether `[addr, addr + size)` lies in this segment's memory.
+    fn holds(&self, addr: u64, size: u64) -> bool {
+        addr >= self.vaddr && addr.saturating_add(size) <= self.end()
+    }
+
+    fn end(&self) -> u64 {
+        self.vaddr.saturating_add(self.memsz)
+    }
+
+    fn file_end(&self) -> u64 {
+        self.offset.saturating_add(self.filesz)
+    }
+}
+
+/// A section header, widened to 64 bits.
+struct Shdr {
+    name: String,
+    kind: u32,
+    flags: u64,
+    addr: u64,
+    offset: u64,
+    size: u64,
+    align: u64,
+    link: u32,
+}
+
+impl Shdr {
+    fn place(&self) -> String {
+        format!("section {}", self.name)
+    }
+
+    fn end(&self) -> u64 {
+        self.addr.saturating_add(self.size)
+    }
+
+    fn has_file_data(&self) -> bool {
+        self.kind != elf::SHT_NOBITS && self.kind != elf::SHT_NULL
+    }
+}
+
+fn segment_type(kind: u32) -> String {
+    let name = match kind {
+        elf::PT_NULL => "PT_NULL",
+        elf::PT_LOAD => "PT_LOAD",
+        elf::PT_DYNAMIC => "PT_DYNAMIC",
+        elf::PT_INTERP => "PT_INTERP",
+        elf::PT_NOTE => "PT_NOTE",
+        elf::PT_PHDR => "PT_PHDR",
+        elf::PT_TLS => "PT_TLS",
+        elf::PT_GNU_EH_FRAME => "PT_GNU_EH_FRAME",
+        elf::PT_GNU_STACK => "PT_GNU_STACK",
+        elf::PT_GNU_RELRO => "PT_GNU_RELRO",
+        elf::PT_GNU_PROPERTY => "PT_GNU_PROPERTY",
+        _ => return format!("{:#x}", kind),
+    };
+    name.to_string()
+}
+
+fn check_elf<Elf: FileHeader<Endian = Endianness>>(data: &[u8]) -> Vec<Violation> {
+    let mut problems = Vec::new();
+    let header = match Elf::parse(data) {
+        Ok(header) => header,
+        Err(e) => return vec![violation("ELF header", e.to_string())],
+    };
+    let Ok(e) = header.endian() else {
+        return vec![violation("ELF header", "unknown byte order".to_string())];
+    };
+
+    // Header fields.
+    let kind = header.e_type(e);
+    if kind != elf::ET_EXEC && kind != elf::ET_DYN {
+        problems.push(violation(
+            "ELF header",
+            format!("type {} is neither ET_EXEC nor ET_DYN", kind),
+        ));
+    }
+    if header.e_version(e) != u32::from(elf::EV_CURRENT) {
+        problems.push(violation(
+            "ELF header",
+            format!("version {} is not EV_CURRENT", header.e_version(e)),
+        ));
+    }
+    if usize::from(header.e_ehsize(e)) != size_of::<Elf>() {
+        problems.push(violation(
+            "ELF header",
+            format!(
+                "e_ehsize is {}, not the header size {}",
+                header.e_ehsize(e),
+                size_of::<Elf>()
+            ),
+        ));
+    }
+    let phentsize = size_of::<Elf::ProgramHeader>();
+    if header.e_phnum(e) > 0 && usize::from(header.e_phentsize(e)) != phentsize {
+        problems.push(violation(
+            "ELF header",
+            format!(
+                "e_phentsize is {}, not the program header size {}",
+                header.e_phentsize(e),
+                phentsize
+            ),
+        ));
+    }
+    let shentsize = size_of::<Elf::SectionHeader>();
+    if header.e_shnum(e) > 0 && usize::from(header.e_shentsize(e)) != shentsize {
+        problems.push(violation(
+            "ELF header",
+            format!(
+                "e_shentsize is {}, not the section header size {}",
+                header.e_shentsize(e),
+                shentsize
+            ),
+        ));
+    }
+    if !problems.is_empty() {
+        // Headers of the wrong size can't be read further.
+        return problems;
+    }
+
+    let phdrs: Vec<Phdr> = match header.program_headers(e, data) {
+        Ok(phdrs) => phdrs
+            .iter()
+            .enumerate()
+            .map(|(index, p)| Phdr {
+                index,
+                kind: p.p_type(e),
+                flags: p.p_flags(e),
+                offset: p.p_offset(e).into(),
+                vaddr: p.p_vaddr(e).into(),
+                filesz: p.p_filesz(e).into(),
+                memsz: p.p_memsz(e).into(),
+                align: p.p_align(e).into(),
+            })
+            .collect(),
+        Err(err) => {
+            problems.push(violation("program headers", err.to_string()));
+            Vec::new()
+        }
+    };
+    let shdrs: Vec<Shdr> = match header.section_headers(e, data) {
+        Ok(shdrs) => {
+            let strings = header.section_strings(e, data, shdrs);
+            if let Err(err) = &strings {
+                problems.push(violation("section name table", err.to_string()));
+            }
+            shdrs
+                .iter()
+                .enumerate()
+                .map(|(index, s)| {
+                    let name = strings
+                        .ok()
+                        .and_then(|strings| s.name(e, strings).ok())
+                        .map_or_else(
+                            || format!("[{}]", index),
+                            |n| String::from_utf8_lossy(n).into_owned(),
+                        );
+                    Shdr {
+                        name,
+                        kind: s.sh_type(e),
+                        flags: s.sh_flags(e).into(),
+                        addr: s.sh_addr(e).into(),
+                        offset: s.sh_offset(e).into(),
+                        size: s.sh_size(e).into(),
+                        align: s.sh_addralign(e).into(),
+                        link: s.sh_link(e),
+                    }
+                })
+                .collect()
+        }
+        Err(err) => {
+            problems.push(violation("section headers", err.to_string()));
+            Vec::new()
+        }
+    };
+
+    let file_size = data.len() as u64;
+    let phoff: u64 = header.e_phoff(e).into();
+    let entry: u64 = header.e_entry(e).into();
+    let phsize = (phdrs.len() * phentsize) as u64;
+    check_segments(&phdrs, (phoff, phsize), file_size, &mut problems);
+    check_sections(&shdrs, &phdrs, file_size, &mut problems);
+
+    let loads: Vec<&Phdr> = phdrs.iter().filter(|p| p.kind == elf::PT_LOAD).collect();
+    if loads.is_empty() {
+        problems.push(violation(
+            "program headers",
+            "no PT_LOAD segment".to_string(),
+        ));
+    }
+    // An entry point of zero means there is none, as in most shared
+    // libraries.
+    if entry != 0 {
+        match loads.iter().find(|p| p.holds(entry, 1)) {
+            Some(p) if p.flags & elf::PF_X == 0 => problems.push(violation(
+                "ELF header",
+                format!(
+                    "entry point {:#x} is in non-executable {}",
+                    entry,
+                    p.place()
+                ),
+            )),
+            Some(_) => {}
+            None => problems.push(violation(
+                "ELF header",
+                format!("entry point {:#x} is outside every PT_LOAD", entry),
+            )),
+        }
+    }
+    check_relro(&phdrs, &shdrs, &mut problems);
+    problems
+}
+
+/// Program headers on their own and against the `PT_LOAD`s.
+fn check_segments(
+    phdrs: &[Phdr],
+    (phoff, phsize): (u64, u64),
+    file_size: u64,
+    problems: &mut Vec<Violation>,
+) {
+    let loads: Vec<&Phdr> = phdrs.iter().filter(|p| p.kind == elf::PT_LOAD).collect();
+    let first_load = loads.first().map_or(usize::MAX, |p| p.index);
+    for p in phdrs {
+        let mut problem = |message: String| problems.push(violation(&p.place(), message));
+        if p.kind == elf::PT_NULL || p.kind == elf::PT_GNU_STACK {
+            continue;
+        }
+        if p.filesz > p.memsz {
+            problem(format!(
+                "file size {:#x} is larger than memory size {:#x}",
+                p.filesz, p.memsz
+            ));
+        }
+        // PT_GNU_RELRO only describes memory.
+        if p.kind != elf::PT_GNU_RELRO
+            && p.offset
+                .checked_add(p.filesz)
+                .is_none_or(|end| end > file_size)
+        {
+            problem(format!(
+                "file range {:#x}..{:#x} lies outside the file ({:#x} bytes)",
+                p.offset,
+                p.offset.wrapping_add(p.filesz),
+                file_size
+            ));
+            continue;
+        }
+        if p.align > 1 && !p.align.is_power_of_two() {
+            problem(format!("alignment {:#x} is not a power of two", p.align));
+        } else if p.kind == elf::PT_LOAD && p.align > 1 && p.offset % p.align != p.vaddr % p.align {
+            problem(format!(
+                "offset {:#x} and address {:#x} differ modulo the alignment {:#x}",
+                p.offset, p.vaddr, p.align
+            ));
+        }
+        if (p.kind == elf::PT_PHDR || p.kind == elf::PT_INTERP) && p.index > first_load {
+            problem("follows a PT_LOAD".to_string());
+        }
+        if p.kind != elf::PT_LOAD
+            && p.kind != elf::PT_GNU_RELRO
+            && p.filesz > 0
+            && !loads.iter().any(|l| l.maps(p.offset, p.vaddr, p.filesz))
+        {
+            problem("is not mapped by any PT_LOAD".to_string());
+        }
+    }
+
+    for kind in [
+        elf::PT_PHDR,
+        elf::PT_INTERP,
+        elf::PT_DYNAMIC,
+        elf::PT_TLS,
+        elf::PT_GNU_RELRO,
+    ] {
+        let count = phdrs.iter().filter(|p| p.kind == kind).count();
+        if count > 1 {
+            problems.push(violation(
+                "program headers",
+                format!(
+                    "{} {} segments; at most one is allowed",
+                    count,
+                    segment_type(kind)
+                ),
+            ));
+        }
+    }
+    if let Some(p) = phdrs.iter().find(|p| p.kind == elf::PT_PHDR) {
+        if p.offset != phoff || p.filesz != phsize {
+            problems.push(violation(
+                &p.place(),
+                format!(
+                    "covers {:#x}..{:#x}, not the program headers at {:#x}..{:#x}",
+                    p.offset,
+                    p.file_end(),
+                    phoff,
+                    phoff.saturating_add(phsize)
+                ),
+            ));
+        }
+    }
+
+    for pair in loads.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if b.vaddr < a.vaddr {
+            problems.push(violation(
+                &b.place(),
+                format!("address {:#x} is below the previous PT_LOAD's", b.vaddr),
+            ));
+        } else if b.vaddr < a.end() {
+            problems.push(violation(
+                &b.place(),
+                format!("overlaps {} in memory", a.place()),
+            ));
+        }
+    }
+}
+
+/// Section headers on their own and against the program headers.
+fn check_sections(shdrs: &[Shdr], phdrs: &[Phdr], file_size: u64, problems: &mut Vec<Violation>) {
+    let Some((null, shdrs)) = shdrs.split_first() else {
+        return;
+    };
+    if null.kind != elf::SHT_NULL || null.flags != 0 || null.addr != 0 || null.offset != 0 {
+        problems.push(violation(
+            "section headers",
+            "the first section header is not SHT_NULL".to_string(),
+        ));
+    }
+    let loads: Vec<&Phdr> = phdrs.iter().filter(|p| p.kind == elf::PT_LOAD).collect();
+    let tls = phdrs.iter().find(|p| p.kind == elf::PT_TLS);
+    let count = shdrs.len() + 1;
+    for s in shdrs {
+        let mut problem = |message: String| problems.push(violation(&s.place(), message));
+        if s.has_file_data()
+            && s.offset
+                .checked_add(s.size)
+                .is_none_or(|end| end > file_size)
+        {
+            problem(format!(
+                "contents at {:#x}..{:#x} lie outside the file ({:#x} bytes)",
+                s.offset,
+                s.offset.wrapping_add(s.size),
+                file_size
+            ));
+            continue;
+        }
+        if s.link as usize >= count {
+            problem(format!("links to section {}, which does not exist", s.link));
+        }
+        let alloc = s.flags & u64::from(elf::SHF_ALLOC) != 0;
+        if s.align > 1 && !s.align.is_power_of_two() {
+            problem(format!("alignment {:#x} is not a power of two", s.align));
+        } else if alloc && s.align > 1 && s.addr % s.align != 0 {
+            problem(format!(
+                "address {:#x} is not aligned to {:#x}",
+                s.addr, s.align
+            ));
+        }
+        if !alloc || s.size == 0 {
+            continue;
+        }
+        let is_tls = s.flags & u64::from(elf::SHF_TLS) != 0;
+        if is_tls && !tls.is_some_and(|t| t.holds(s.addr, s.size)) {
+            problem("is thread-local but outside PT_TLS".to_string());
+        }
+        // .tbss takes no room in the address space it appears at.
+        if is_tls && !s.has_file_data() {
+            continue;
+        }
+        match loads.iter().find(|p| p.holds(s.addr, s.size)) {
+            None => problem(format!(
+                "{:#x}..{:#x} lies outside every PT_LOAD",
+                s.addr,
+                s.end()
+            )),
+            Some(p) if s.has_file_data() && !p.maps(s.offset, s.addr, s.size) => problem(format!(
+                "file offset {:#x} is not where {} maps address {:#x}",
+                s.offset,
+                p.place(),
+                s.addr
+            )),
+            Some(_) => {}
+        }
+    }
+
+    let mut in_file: Vec<&Shdr> = shdrs
+        .iter()
+        .filter(|s| s.has_file_data() && s.size > 0)
+        .collect();
+    in_file.sort_by_key(|s| s.offset);
+    for pair in in_file.windows(2) {
+        if pair[1].offset < pair[0].offset.saturating_add(pair[0].size) {
+            problems.push(violation(
+                &pair[1].place(),
+                format!("overlaps {} in the file", pair[0].place()),
+            ));
+        }
+    }
+
+    // The loader finds these through the program headers, tools through
+    // the section headers; both must point at the same bytes.
+    for (section, segment) in [
+        (elf::SHT_DYNAMIC, elf::PT_DYNAMIC),
+        (elf::SHT_PROGBITS, elf::PT_INTERP),
+    ] {
+        let s = shdrs.iter().find(|s| {
+            s.kind == section
+                && (segment != elf::PT_INTERP || s.name == ".interp")
+                && s.flags & u64::from(elf::SHF_ALLOC) != 0
+        });
+        let p = phdrs.iter().find(|p| p.kind == segment);
+        match (s, p) {
+            (Some(s), Some(p)) if s.addr != p.vaddr || s.size != p.memsz => {
+                problems.push(violation(
+                    &p.place(),
+                    format!(
+                        "covers {:#x}..{:#x}, but {} is at {:#x}..{:#x}",
+                        p.vaddr,
+                        p.end(),
+                        s.place(),
+                        s.addr,
+                        s.end()
+                    ),
+                ))
+            }
+            (Some(s), None) => problems.push(violation(
+                &s.place(),
+                format!("has no {} segment", segment_type(segment)),
+            )),
+            _ => {}
+        }
+    }
+}
+
+/// `PT_GNU_RELRO` against the segments and sections it protects.
+fn check_relro(phdrs: &[Phdr], shdrs: &[Shdr], problems: &mut Vec<Violation>) {
+    let Some(relro) = phdrs.iter().find(|p| p.kind == elf::PT_GNU_RELRO) else {
+        return;
+    };
+    let (start, end) = (relro.vaddr, relro.end());
+    let loads: Vec<&Phdr> = phdrs.iter().filter(|p| p.kind == elf::PT_LOAD).collect();
+    match loads.iter().find(|p| p.holds(start, 1)) {
+        Some(p) if p.flags & elf::PF_W == 0 => problems.push(violation(
+            &relro.place(),
+            format!("starts in read-only {}", p.place()),
+        )),
+        Some(_) => {}
+        None => problems.push(violation(
+            &relro.place(),
+            format!("starts at {:#x}, outside every PT_LOAD", start),
+        )),
+    }
+    // The loader protects whole pages, so the end may run on to the end of
+    // the last page a PT_LOAD maps.
+    let page = loads.iter().map(|p| p.align).max().unwrap_or(1).max(1);
+    let mut covered = start;
+    while covered < end {
+        let next = loads
+            .iter()
+            .filter(|p| p.vaddr <= covered)
+            .map(|p| p.end().checked_next_multiple_of(page).unwrap_or(u64::MAX))
+            .filter(|&e| e > covered)
+            .max();
+        let Some(next) = next else {
+            problems.push(violation(
+                &relro.place(),
+                format!("{:#x}..{:#x} is not mapped by any PT_LOAD", covered, end),
+            ));
+            break;
+        };
+        covered = next;
+    }
+    for s in shdrs {
+        if s.flags & u64::from(elf::SHF_ALLOC) == 0 || s.size == 0 {
+            continue;
+        }
+        let inside = s.addr >= start && s.end() <= end;
+        let overlaps = s.addr < end && s.end() > start;
+        if RELRO_REQUIRED.contains(&s.name.as_str())
+            && s.flags & u64::from(elf::SHF_WRITE) != 0
+            && !inside
+        {
+            problems.push(violation(
+                &s.place(),
+                format!("is outside PT_GNU_RELRO ({:#x}..{:#x})", start, end),
+            ));
+        }
+        if RELRO_FORBIDDEN.contains(&s.name.as_str()) && overlaps {
+            problems.push(violation(
+                &s.place(),
+                format!(
+                    "is written at run time but inside PT_GNU_RELRO ({:#x}..{:#x})",
+                    start, end
+                ),
+            ));
+        }
+    }
+}
@@ -0,0 +1,462 @@
+//! Version scripts and symbol versioning.
+//!
+//! Parses GNU ld version scripts (`--version-script`), decides which globals
+//! stay exported and which version node each belongs to, and encodes the
+//! `.gnu.version`, `.gnu.version_d` and `.gnu.version_r` sections.
+//!
+//! ```text
+//! VERS_1 { global: foo; bar*; "odd*name"; local: *; };
+//! VERS_2 { global: extern "C++" { ns::baz*; }; } VERS_1;
+//! ```
+//!
+//! Quoted patterns match literally, without globbing; patterns in an
+//! `extern "C++"` block match demangled names.
+
+use anyhow::{bail, Context, Result};
+use object::elf;
+use object::{Endian, Endianness};
+
+use std::collections::HashMap;
+
+use crate::demangle::demangle;
+use crate::dynamic::{sysv_hash, StringTable};
+use crate::utils::glob_match;
+
+/// Size of an `Elf64_Verdef` entry.
+const VERDEF_SIZE: u32 = 20;
+/// Size of an `Elf64_Verdaux` entry.
+const VERDAUX_SIZE: u32 = 8;
+/// Size of an `Elf64_Verneed` entry.
+const VERNEED_SIZE: u32 = 16;
+/// Size of an `Elf64_Vernaux` entry.
+const VERNAUX_SIZE: u32 = 16;
+
+/// A symbol pattern in a version script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    pub text: String,
+    /// Quoted: matched literally, even if it contains `*` or `?`.
+    pub quoted: bool,
+    /// From an `extern "C++"` block: matched against demangled names.
+    pub cplusplus: bool,
+}
+
+impl Pattern {
+    /// Whether the pattern names a single symbol; those take precedence
+    /// over globs.
+    fn is_exact(&self) -> bool {
+        self.quoted || !self.text.contains(['*', '?'])
+    }
+
+    /// Match against a symbol, given its mangled and demangled names.
+    fn matches(&self, name: &str, demangled: &str) -> bool {
+        let name = if self.cplusplus { demangled } else { name };
+        if self.quoted {
+            self.text == name
+        } else {
+            glob_match(&self.text, name)
+        }
+    }
+}
+
+/// One `NAME { ... } PARENT;` block of a version script.
+#[derive(Debug, Clone, Default)]
+pub struct VersionNode {
+    /// Version name; `None` for an anonymous `{ ... };` script.
+    pub name: Option<String>,
+    /// Patterns of symbols exported under this version.
+    pub globals: Vec<Pattern>,
+    /// Patterns of symbols made local.
+    pub locals: Vec<Pattern>,
+    /// The version this one inherits from.
+    pub parent: Option<String>,
+}
+
+/// A parsed version script.
+#[derive(Debug, Clone, Default)]
+pub struct VersionScript {
+    pub nodes: Vec<VersionNode>,
+}
+
+/// What a version script says about a symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// Hidden from the dynamic symbol table.
+    Local,
+    /// Exported with this `.gnu.version` index.
+    Version(u16),
+}
+
+/// A word or punctuation mark of a version script.
+#[derive(Debug)]
+struct Token {
+    text: String,
+    /// A `"..."` string, which is never a keyword or punctuation.
+    quoted: bool,
+}
+
+impl Token {
+    /// The token as a keyword or punctuation mark, if it isn't quoted.
+    fn bare(&self) -> Option<&str> {
+        (!self.quoted).then_some(self.text.as_str())
+    }
+}
+
+/// Split a version script into words, `"..."` strings and the punctuation
+/// `{ } ; :`, dropping `/* */` and `#` comments.
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut cur = String::new();
+    let flush = |cur: &mut String, tokens: &mut Vec<Token>| {
+        if !cur.is_empty() {
+            tokens.push(Token {
+                text: std::mem::take(cur),
+                quoted: false,
+            });
+        }
+    };
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if prev == '*' => break,
+                        Some(c) => prev = c,
+                        None => bail!("unterminated comment"),
+                    }
+                }
+            }
+            '#' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                flush(&mut cur, &mut tokens);
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => text.push(c),
+                        None => bail!("unterminated string"),
+                    }
+                }
+                tokens.push(Token { text, quoted: true });
+            }
+            // `::` separates C++ scopes.
+            ':' if chars.peek() == Some(&':') => {
+                chars.next();
+                cur.push_str("::");
+            }
+            '{' | '}' | ';' | ':' => {
+                flush(&mut cur, &mut tokens);
+                tokens.push(Token {
+                    text: c.to_string(),
+                    quoted: false,
+                });
+            }
+            c if c.is_whitespace() => flush(&mut cur, &mut tokens),
+            c => cur.push(c),
+        }
+    }
+    flush(&mut cur, &mut tokens);
+    Ok(tokens)
+}
+
+impl VersionScript {
+    /// Parse the text of a version script.
+    pub fn parse(text: &str) -> Result<Self> {
+        let tokens = tokenize(text)?;
+        let mut tokens = tokens.iter().peekable();
+        let mut nodes: Vec<VersionNode> = Vec::new();
+
+        while let Some(tok) = tokens.next() {
+            let mut node = VersionNode::default();
+            if tok.bare() != Some("{") {
+                let name = &tok.text;
+                if nodes.iter().any(|n| n.name.as_ref() == Some(name)) {
+                    bail!("duplicate version node {}", name);
+                }
+                node.name = Some(name.clone());
+                if tokens.next().and_then(Token::bare) != Some("{") {
+                    bail!("expected `{{` after version {}", name);
+                }
+            }
+
+            let mut local = false;
+            // Inside an `extern "C"` or `extern "C++"` block: whether it is C++.
+            let mut block = None;
+            loop {
+                let tok = tokens.next().context("unexpected end of version script")?;
+                let next = tokens.peek().and_then(|t| t.bare());
+                match tok.bare() {
+                    Some("}") if block.is_some() => {
+                        block = None;
+                        if next == Some(";") {
+                            tokens.next();
+                        }
+                    }
+                    Some("}") => break,
+                    Some(scope @ ("global" | "local")) if next == Some(":") => {
+                        tokens.next();
+                        local = scope == "local";
+                    }
+                    Some("extern") if block.is_none() => {
+                        let lang = tokens.next().context("expected language after extern")?;
+                        block = match lang.text.as_str() {
+                            "C++" => Some(true),
+                            "C" => Some(false),
+                            other => bail!("unsupported extern language \"{}\"", other),
+                        };
+                        if tokens.next().and_then(Token::bare) != Some("{") {
+                            bail!("expected `{{` after extern \"{}\"", lang.text);
+                        }
+                    }
+                    Some(";") => {}
+                    _ => {
+                        // The last pattern of a block may omit its `;`.
+                        match next {
+                            Some(";") => {
+                                tokens.next();
+                            }
+                            Some("}") => {}
+                            _ => bail!("expected `;` after {}", tok.text),
+                        }
+                        let list = if local {
+                            &mut node.locals
+                        } else {
+                            &mut node.globals
+                        };
+                        list.push(Pattern {
+                            text: tok.text.clone(),
+                            quoted: tok.quoted,
+                            cplusplus: block == Some(true),
+                        });
+                    }
+                }
+            }
+
+            match tokens.next().and_then(Token::bare) {
+                Some(";") => {}
+                Some(parent) => {
+                    if !nodes.iter().any(|n| n.name.as_deref() == Some(parent)) {
+                        bail!("version {} depends on unknown version {}", tok.text, parent);
+                    }
+                    node.parent = Some(parent.to_string());
+                    if tokens.next().and_then(Token::bare) != Some(";") {
+                        bail!("expected `;` after version {}", tok.text);
+                    }
+                }
+                None => bail!("expected `;` after version node"),
+            }
+            nodes.push(node);
+        }
+
+        if nodes.len() > 1 && nodes.iter().any(|n| n.name.is_none()) {
+            bail!("an anonymous version node must be the only one in the script");
+        }
+        Ok(Self { nodes })
+    }
+
+    /// Whether the script defines named versions (and so `.gnu.version_d`).
+    pub fn has_versions(&self) -> bool {
+        self.nodes.iter().any(|n| n.name.is_some())
+    }
+
+    /// `.gnu.version` index of node `i`: named versions start at 2, after the
+    /// base version.
+    fn node_index(&self, i: usize) -> u16 {
+        if self.nodes[i].name.is_some() {
+            i as u16 + 2
+        } else {
+            elf::VER_NDX_GLOBAL
+        }
+    }
+
+    /// What the script says about `name`, if any pattern matches.
+    ///
+    /// Exact names take precedence over globs, and within each a `global:`
+    /// match wins over a `local:` one.
+    pub fn binding(&self, name: &str) -> Option<Binding> {
+        let demangled = demangle(name);
+        for exact in [true, false] {
+            let matches = |p: &Pattern| p.is_exact() == exact && p.matches(name, &demangled);
+            if let Some(i) = self
+                .nodes
+                .iter()
+                .position(|n| n.globals.iter().any(matches))
+            {
+                return Some(Binding::Version(self.node_index(i)));
+            }
+            if self.nodes.iter().any(|n| n.locals.iter().any(matches)) {
+                return Some(Binding::Local);
+            }
+        }
+        None
+    }
+
+    /// Whether `name` is hidden from the dynamic symbol table.
+    pub fn is_local(&self, name: &str) -> bool {
+        self.binding(name) == Some(Binding::Local)
+    }
+
+    /// `.gnu.version` entry for an exported symbol.
+    pub fn version_index(&self, name: &str) -> u16 {
+        match self.binding(name) {
+            Some(Binding::Version(v)) => v,
+            _ => elf::VER_NDX_GLOBAL,
+        }
+    }
+
+    /// Encode `.gnu.version_d`: the base version `base` followed by each
+    /// named node, adding the names to `strtab`.
+    pub fn encode_verdef(&self, base: &str, strtab: &mut StringTable, e: Endianness) -> Vec<u8> {
+        let mut defs: Vec<(u16, u16, &str, Option<&str>)> =
+            vec![(elf::VER_FLG_BASE, elf::VER_NDX_GLOBAL, base, None)];
+        for (i, n) in self.nodes.iter().enumerate() {
+            if let Some(name) = &n.name {
+                defs.push((0, self.node_index(i), name, n.parent.as_deref()));
+            }
+        }
+
+        let mut out = Vec::new();
+        for (i, &(flags, ndx, name, parent)) in defs.iter().enumerate() {
+            let cnt = 1 + parent.is_some() as u16;
+            let size = VERDEF_SIZE + VERDAUX_SIZE * cnt as u32;
+            let next = if i + 1 < defs.len() { size } else { 0 };
+            out.extend_from_slice(&e.write_u16_bytes(1)); // vd_version
+            out.extend_from_slice(&e.write_u16_bytes(flags));
+            out.extend_from_slice(&e.write_u16_bytes(ndx));
+            out.extend_from_slice(&e.write_u16_bytes(cnt));
+            out.extend_from_slice(&e.write_u32_bytes(sysv_hash(name)));
+            out.extend_from_slice(&e.write_u32_bytes(VERDEF_SIZE)); // vd_aux
+            out.extend_from_slice(&e.write_u32_bytes(next));
+            for (j, aux) in std::iter::once(name).chain(parent).enumerate() {
+                let next = if j + 1 < cnt as usize {
+                    VERDAUX_SIZE
+                } else {
+                    0
+                };
+                out.extend_from_slice(&e.write_u32_bytes(strtab.add(aux)));
+                out.extend_from_slice(&e.write_u32_bytes(next));
+            }
+        }
+        out
+    }
+}
+
+/// Number of entries in a chain of `.gnu.version_d` or `.gnu.version_r`
+/// entries, whose offset to the next entry is at `next_field`.
+fn chain_len(data: &[u8], next_field: usize, e: Endianness) -> u32 {
+    let mut count = 0;
+    let mut off = 0usize;
+    while let Some(next) = data.get(off + next_field..off + next_field + 4) {
+        count += 1;
+        let next = e.read_u32_bytes(next.try_into().unwrap());
+        if next == 0 {
+            break;
+        }
+        off += next as usize;
+    }
+    count
+}
+
+/// Number of entries in an encoded `.gnu.version_d` (its `sh_info`).
+pub fn verdef_count(data: &[u8], e: Endianness) -> u32 {
+    chain_len(data, 16, e)
+}
+
+/// Number of entries in an encoded `.gnu.version_r` (its `sh_info`).
+pub fn verneed_count(data: &[u8], e: Endianness) -> u32 {
+    chain_len(data, 12, e)
+}
+
+/// Split a reference like `memcpy@GLIBC_2.14` into the symbol name and the
+/// version it asks for.
+pub fn split_version(name: &str) -> (&str, Option<&str>) {
+    match name.split_once('@') {
+        Some((base, version)) => (base, Some(version.trim_start_matches('@'))),
+        None => (name, None),
+    }
+}
+
+/// Read a shared library's `.gnu.version_d`: the name of each version it
+/// defines, by `.gnu.version` index. The base version (the library's own
+/// name) is left out, as references to it are unversioned.
+pub fn read_verdef(data: &[u8], strtab: &[u8], e: Endianness) -> Result<HashMap<u16, String>> {
+    let field = |off: usize, len: usize| {
+        data.get(off..off + len)
+            .context(".gnu.version_d entry out of range")
+    };
+    let mut names = HashMap::new();
+    let mut off = 0usize;
+    while off < data.len() {
+        let flags = e.read_u16_bytes(field(off + 2, 2)?.try_into()?);
+        let ndx = e.read_u16_bytes(field(off + 4, 2)?.try_into()?);
+        let aux = e.read_u32_bytes(field(off + 12, 4)?.try_into()?) as usize;
+        let next = e.read_u32_bytes(field(off + 16, 4)?.try_into()?) as usize;
+        if flags & elf::VER_FLG_BASE == 0 {
+            let name = e.read_u32_bytes(field(off + aux, 4)?.try_into()?) as usize;
+            let name = strtab.get(name..).context("version name out of range")?;
+            let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            names.insert(ndx, String::from_utf8_lossy(&name[..end]).into_owned());
+        }
+        if next == 0 {
+            break;
+        }
+        off += next;
+    }
+    Ok(names)
+}
+
+/// The versions the output needs from one shared library.
+#[derive(Debug, Clone)]
+pub struct Needed {
+    pub soname: String,
+    /// Each version's name and the `.gnu.version` index assigned to it.
+    pub versions: Vec<(String, u16)>,
+}
+
+/// Encode `.gnu.version_r`, adding sonames and version names to `strtab`.
+pub fn encode_verneed(needs: &[Needed], strtab: &mut StringTable, e: Endianness) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (i, need) in needs.iter().enumerate() {
+        let cnt = need.versions.len() as u32;
+        let next = if i + 1 < needs.len() {
+            VERNEED_SIZE + VERNAUX_SIZE * cnt
+        } else {
+            0
+        };
+        out.extend_from_slice(&e.write_u16_bytes(1)); // vn_version
+        out.extend_from_slice(&e.write_u16_bytes(cnt as u16));
+        out.extend_from_slice(&e.write_u32_bytes(strtab.add(&need.soname)));
+        out.extend_from_slice(&e.write_u32_bytes(VERNEED_SIZE)); // vn_aux
+        out.extend_from_slice(&e.write_u32_bytes(next));
+        for (j, (name, ndx)) in need.versions.iter().enumerate() {
+            let next = if j + 1 < need.versions.len() {
+                VERNAUX_SIZE
+            } else {
+                0
+            };
+            out.extend_from_slice(&e.write_u32_bytes(sysv_hash(name)));
+            out.extend_from_slice(&e.write_u16_bytes(0)); // vna_flags
+            out.extend_from_slice(&e.write_u16_bytes(*ndx));
+            out.extend_from_slice(&e.write_u32_bytes(strtab.add(name)));
+            out.extend_from_slice(&e.write_u32_bytes(next));
+        }
+    }
+    out
+}
+
+/// Encode `.gnu.version` from one index per `.dynsym` entry after the null one.
+pub fn encode_versym(indices: &[u16], e: Endianness) -> Vec<u8> {
+    std::iter::once(elf::VER_NDX_LOCAL)
+        .chain(indices.iter().copied())
+        .flat_map(|i| e.write_u16_bytes(i))
+        .collect()
+}
@@ -1,204 +1,1328 @@
-//! ELF file writer.
+//! Output file writers.
 //!
-//! This module handles writing the final ELF executable file.
+//! Each [`OutputFormat`] encodes the linked [`Image`]: the ELF executable or
+//! shared object by default, or a raw binary, Intel HEX or S-record image for
+//! `--oformat`. ELF output is written as a list of [`Piece`]s, so section
+//! bytes go from the mapped inputs to the destination with vectored writes,
+//! not gathered into a buffer the size of the file unless sections overlap.
+//! Relocations are applied to copies of the sections they patch, which are
+//! written out in their place. The other formats report the file size up
+//! front and are written into a memory mapping of the destination.
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use memmap2::MmapMut;
+use object::elf::{FileHeader32, FileHeader64, ProgramHeader32, ProgramHeader64};
+use object::elf::{SectionHeader32, SectionHeader64};
 use object::endian::{U16, U32, U64};
 use object::pod::bytes_of;
 use object::{Endianness, SectionKind};
-use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, IoSlice, Write};
+use std::path::Path;
 
-use crate::layout::Segment;
+use crate::layout::{NonAllocSection, Segment, RELRO_SECTIONS};
+use crate::linker::ZOptions;
+use crate::progress::LinkProgress;
+use crate::property;
+use crate::utils::align_up;
+use crate::version;
 
-const PAGE_SIZE: u64 = 0x1000;
-const BASE_ADDR: u64 = 0x400000;
+fn u16(e: Endianness, v: u16) -> U16<Endianness> {
+    U16::new(e, v)
+}
+fn u32(e: Endianness, v: u32) -> U32<Endianness> {
+    U32::new(e, v)
+}
+fn u64(e: Endianness, v: u64) -> U64<Endianness> {
+    U64::new(e, v)
+}
 
-fn u16(v: u16) -> U16<Endianness> {
-    U16::new(Endianness::Little, v)
+/// Name of the section that `name`'s `sh_link` refers to, if any.
+fn section_link(name: &str) -> Option<&'static str> {
+    match name {
+        ".dynsym" | ".dynamic" | ".gnu.version_d" | ".gnu.version_r" => Some(".dynstr"),
+        ".hash" | ".gnu.hash" | ".gnu.version" | ".rela.dyn" | ".rela.plt" => Some(".dynsym"),
+        _ => None,
+    }
 }
-fn u32(v: u32) -> U32<Endianness> {
-    U32::new(Endianness::Little, v)
+
+/// `sh_entsize` for sections holding fixed-size entries; `word` is the size
+/// of an address.
+fn entry_size(kind: SectionKind, word: u64) -> u64 {
+    match kind {
+        SectionKind::Elf(object::elf::SHT_DYNSYM) => 24,
+        SectionKind::Elf(object::elf::SHT_RELA) => 24,
+        SectionKind::Elf(object::elf::SHT_DYNAMIC) => 16,
+        SectionKind::Elf(object::elf::SHT_HASH) => 4,
+        SectionKind::Elf(object::elf::SHT_GNU_VERSYM) => 2,
+        SectionKind::Elf(
+            object::elf::SHT_PREINIT_ARRAY
+            | object::elf::SHT_INIT_ARRAY
+            | object::elf::SHT_FINI_ARRAY,
+        ) => word,
+        _ => 0,
+    }
 }
-fn u64(v: u64) -> U64<Endianness> {
-    U64::new(Endianness::Little, v)
+
+/// The linked image handed to an [`OutputFormat`].
+#[derive(Clone, Copy)]
+pub struct Image<'a> {
+    /// Allocated output sections, in address order.
+    pub segments: &'a [Segment],
+    /// The input objects, which the loaded sections' contents are read from.
+    pub objects: &'a [object::File<'a>],
+    /// Output sections that are not loaded (`.symtab`, `.rela.*`, ...).
+    pub non_alloc: &'a [NonAllocSection],
+    pub entry_point: u64,
+    /// `ET_EXEC` or `ET_DYN`.
+    pub e_type: u16,
+    /// Address the first byte of the file is mapped at (0 for
+    /// position-independent `ET_DYN` output).
+    pub base_addr: u64,
+    /// `EI_OSABI`: `ELFOSABI_GNU` when GNU extensions such as
+    /// `STB_GNU_UNIQUE` are used.
+    pub os_abi: u8,
+    pub e_machine: u16,
+    pub e_flags: u32,
+    /// `ELFCLASS64` output, or `ELFCLASS32` for 32-bit targets.
+    pub is_64: bool,
+    /// Byte order of the headers and tables, from the target.
+    pub endian: Endianness,
+    pub z: &'a ZOptions,
 }
 
-/// Write an ELF executable to disk.
-pub fn write_elf(output_path: &PathBuf, segments: &[Segment], entry_point: u64) -> Result<()> {
-    let mut buffer = Vec::new();
-    let num_sections = segments.len() as u32 + 2;
+/// A run of the output file's bytes and its file offset: borrowed from the
+/// image or its inputs, or owned for headers built as the file is written.
+pub type Piece<'i> = (u64, Cow<'i, [u8]>);
 
-    // ELF file header
-    let file_header = object::elf::FileHeader64::<Endianness> {
-        e_ident: object::elf::Ident {
-            magic: object::elf::ELFMAG,
-            class: object::elf::ELFCLASS64,
-            data: object::elf::ELFDATA2LSB,
-            version: object::elf::EV_CURRENT,
-            os_abi: object::elf::ELFOSABI_SYSV,
-            abi_version: 0,
-            padding: [0; 7],
-        },
-        e_type: u16(object::elf::ET_EXEC),
-        e_machine: u16(object::elf::EM_X86_64),
-        e_version: u32(object::elf::EV_CURRENT as u32),
-        e_entry: u64(entry_point),
-        e_phoff: u64(64),
-        e_shoff: u64(0), // Will be patched later
-        e_flags: u32(0),
-        e_ehsize: u16(64),
-        e_phentsize: u16(56),
-        e_phnum: u16(1),
-        e_shentsize: u16(64),
-        e_shnum: u16(num_sections as u16),
-        e_shstrndx: u16(num_sections as u16 - 1),
-    };
-    buffer.extend_from_slice(bytes_of(&file_header));
+/// A file format the linked image can be written in (`--oformat`).
+pub trait OutputFormat {
+    /// Size of the output file in bytes.
+    fn size(&self, image: &Image) -> Result<u64>;
+
+    /// Encode the image into `out`, a zero-filled buffer of [`size`] bytes.
+    ///
+    /// [`size`]: OutputFormat::size
+    fn write(&self, image: &Image, out: &mut [u8]) -> Result<()>;
+
+    /// The file's contents as pieces in file offset order, not
+    /// overlapping, with zeros between them, to be written without
+    /// assembling the file in memory. `None` if the format can only be
+    /// [`write`](OutputFormat::write)n into a buffer.
+    fn pieces<'i>(&self, image: &Image<'i>) -> Result<Option<Vec<Piece<'i>>>> {
+        let _ = image;
+        Ok(None)
+    }
 
-    // Calculate file and memory sizes for the LOAD segment
-    let last_segment = segments
+    /// Whether the output file is made executable.
+    fn executable(&self) -> bool {
+        false
+    }
+}
+
+/// Bytes of the output written or flushed to disk between progress reports.
+const FLUSH_CHUNK: u64 = 8 << 20;
+
+/// Zeros for the gaps between pieces.
+static ZEROS: [u8; 4096] = [0; 4096];
+
+/// Write `image` to `output_path` in `format`, returning the file size.
+///
+/// Formats that list their [`OutputFormat::pieces`] are written from them.
+/// Otherwise the file is sized first and then mapped, so the contents are
+/// written in place rather than assembled in memory, and flushed a chunk
+/// at a time, telling `progress` how far the disk has got.
+pub fn write_file(
+    output_path: &Path,
+    format: &dyn OutputFormat,
+    image: &Image,
+    progress: Option<&dyn LinkProgress>,
+) -> Result<u64> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(output_path)?;
+    if let Some(pieces) = format.pieces(image)? {
+        let size = pieces_end(&pieces);
+        write_pieces(&mut file, &pieces, size, progress)?;
+        finish_output(output_path, format.executable())?;
+        return Ok(size);
+    }
+    let size = format.size(image)?;
+    if size > 0 {
+        // An empty mapping can't be created, and there is nothing to write
+        // anyway.
+        file.set_len(size)?;
+        // SAFETY: the file was just created or truncated and sized here, and
+        // the mapping is dropped before this returns. Like any linker, uld
+        // assumes nothing else truncates its output while it is written.
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        format.write(image, &mut map)?;
+        let mut flushed = 0;
+        while flushed < size {
+            let len = FLUSH_CHUNK.min(size - flushed);
+            map.flush_range(flushed as usize, len as usize)?;
+            flushed += len;
+            if let Some(progress) = progress {
+                progress.written(flushed, size);
+            }
+        }
+    }
+    finish_output(output_path, format.executable())?;
+    Ok(size)
+}
+
+/// Finish the written output file at `path`, marking it executable if
+/// `executable`. Hosts without Unix permission bits leave it as it is.
+pub fn finish_output(path: &Path, executable: bool) -> Result<()> {
+    #[cfg(unix)]
+    if executable {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = std::fs::metadata(path)?.permissions();
+        perms.set_mode(0o755);
+        std::fs::set_permissions(path, perms)?;
+    }
+    #[cfg(not(unix))]
+    let _ = (path, executable);
+    Ok(())
+}
+
+/// Write `image` to `out` in `format`, returning the size written: from
+/// its [`OutputFormat::pieces`] if it has them, or else encoded in memory
+/// first.
+pub fn write_stream(
+    out: &mut dyn Write,
+    format: &dyn OutputFormat,
+    image: &Image,
+    progress: Option<&dyn LinkProgress>,
+) -> Result<u64> {
+    match format.pieces(image)? {
+        Some(pieces) => {
+            let size = pieces_end(&pieces);
+            write_pieces(out, &pieces, size, progress)?;
+            Ok(size)
+        }
+        None => {
+            let bytes = encode(format, image)?;
+            out.write_all(&bytes)?;
+            let size = bytes.len() as u64;
+            if let Some(progress) = progress {
+                progress.written(size, size);
+            }
+            Ok(size)
+        }
+    }
+}
+
+/// End of the last of the file's `pieces`: the file size.
+fn pieces_end(pieces: &[Piece]) -> u64 {
+    pieces
         .iter()
-        .rfind(|s| s.kind != SectionKind::UninitializedData && s.size > 0);
+        .map(|(offset, bytes)| offset + bytes.len() as u64)
+        .max()
+        .unwrap_or(0)
+}
 
-    let file_size = if let Some(seg) = last_segment {
-        seg.file_offset + seg.size
-    } else {
-        PAGE_SIZE
-    };
+/// Write `pieces` into `out` in order, later ones over earlier ones.
+fn fill(pieces: &[Piece], out: &mut [u8]) {
+    for (offset, bytes) in pieces {
+        let offset = *offset as usize;
+        out[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+}
 
-    let mem_size = segments
+/// Write the `pieces` of a file of `size` bytes to `out`, zeros filling the
+/// gaps, handing the slices to `write_vectored` as many at a time as it
+/// takes.
+fn write_pieces(
+    out: &mut dyn Write,
+    pieces: &[Piece],
+    size: u64,
+    progress: Option<&dyn LinkProgress>,
+) -> io::Result<()> {
+    let mut slices = Vec::with_capacity(pieces.len() * 2);
+    let mut at = 0;
+    for (offset, bytes) in pieces
         .iter()
-        .map(|s| {
-            if s.virtual_address > 0 {
-                s.virtual_address + s.size
-            } else {
-                BASE_ADDR
+        .map(|(o, b)| (*o, &b[..]))
+        .chain([(size, &[][..])])
+    {
+        while at < offset {
+            let len = (offset - at).min(ZEROS.len() as u64);
+            slices.push(IoSlice::new(&ZEROS[..len as usize]));
+            at += len;
+        }
+        if !bytes.is_empty() {
+            slices.push(IoSlice::new(bytes));
+            at += bytes.len() as u64;
+        }
+    }
+    let mut rest = &mut slices[..];
+    let (mut written, mut reported) = (0, 0);
+    while !rest.is_empty() {
+        let n = out.write_vectored(rest)?;
+        if n == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        IoSlice::advance_slices(&mut rest, n);
+        written += n as u64;
+        if let Some(progress) = progress {
+            if written - reported >= FLUSH_CHUNK || written == size {
+                progress.written(written, size);
+                reported = written;
             }
-        })
-        .max()
-        .unwrap_or(BASE_ADDR)
-        - BASE_ADDR;
-
-    // Single LOAD program header
-    let prog_header = object::elf::ProgramHeader64::<Endianness> {
-        p_type: u32(object::elf::PT_LOAD),
-        p_flags: u32(object::elf::PF_R | object::elf::PF_W | object::elf::PF_X),
-        p_offset: u64(0),
-        p_vaddr: u64(BASE_ADDR),
-        p_paddr: u64(BASE_ADDR),
-        p_filesz: u64(file_size),
-        p_memsz: u64(mem_size),
-        p_align: u64(PAGE_SIZE),
-    };
-    buffer.extend_from_slice(bytes_of(&prog_header));
+        }
+    }
+    Ok(())
+}
 
-    // Pad to first page boundary
-    if (buffer.len() as u64) < PAGE_SIZE {
-        buffer.resize(PAGE_SIZE as usize, 0);
+/// Encode `image` in `format` into memory.
+pub fn encode(format: &dyn OutputFormat, image: &Image) -> Result<Vec<u8>> {
+    if let Some(pieces) = format.pieces(image)? {
+        let mut bytes = vec![0; pieces_end(&pieces) as usize];
+        fill(&pieces, &mut bytes);
+        return Ok(bytes);
     }
+    let mut bytes = vec![0; format.size(image)? as usize];
+    format.write(image, &mut bytes)?;
+    Ok(bytes)
+}
 
-    // Write segment data
-    for segment in segments {
-        if segment.kind == SectionKind::UninitializedData {
+/// A `PT_LOAD` covering sections whose addresses and file offsets advance
+/// together.
+struct Load {
+    offset: u64,
+    vaddr: u64,
+    paddr: u64,
+    filesz: u64,
+    memsz: u64,
+    /// The page size, or more for sections aligned beyond a page.
+    align: u64,
+}
+
+/// The `PT_NOTE` ranges: each starts at a note section and spans the ones
+/// directly after it with the same alignment, which readers walk as one
+/// list of entries. Returns the first section of each and its size.
+fn note_runs(segments: &[Segment]) -> Vec<(&Segment, u64)> {
+    let mut runs: Vec<(&Segment, u64)> = Vec::new();
+    for seg in segments.iter().filter(|s| s.size > 0) {
+        if seg.kind != SectionKind::Elf(object::elf::SHT_NOTE) {
             continue;
         }
-        let current = buffer.len() as u64;
-        if segment.file_offset > current {
-            buffer.resize(segment.file_offset as usize, 0);
+        match runs.last_mut() {
+            Some((first, size))
+                if first.align == seg.align
+                    && first.virtual_address + *size == seg.virtual_address =>
+            {
+                *size += seg.size;
+            }
+            _ => runs.push((seg, seg.size)),
         }
-        buffer.extend_from_slice(&segment.data);
     }
+    runs
+}
 
-    // Build section header string table
-    let mut shstrtab = Vec::new();
-    shstrtab.push(0);
-    let mut section_name_offsets = Vec::new();
-    section_name_offsets.push(0);
-
-    for segment in segments {
-        let off = shstrtab.len();
-        section_name_offsets.push(off);
-        shstrtab.extend_from_slice(segment.name.as_bytes());
-        shstrtab.push(0);
+/// Group the sections into `PT_LOAD`s.
+///
+/// Normally everything is one run, mapped along with the file header from
+/// offset 0. A section pinned to an address (`-Ttext`, `--section-start`)
+/// or loaded elsewhere (`--section-lma`) starts a new run, as does
+/// file-backed data after `.bss`.
+fn loads(segments: &[Segment], base_addr: u64, page_size: u64) -> Vec<Load> {
+    let mut loads: Vec<Load> = Vec::new();
+    let mut nobits = false;
+    for s in segments.iter().filter(|s| s.size > 0) {
+        let bss = s.is_nobits();
+        let joins = loads.last().is_some_and(|l| {
+            s.virtual_address >= l.vaddr + l.memsz
+                && s.load_address.wrapping_sub(s.virtual_address) == l.paddr.wrapping_sub(l.vaddr)
+                && (bss || (!nobits && s.virtual_address - l.vaddr == s.file_offset - l.offset))
+        });
+        if !joins {
+            // The first run also maps the headers when there is room below it.
+            let offset = if loads.is_empty() && s.virtual_address >= s.file_offset {
+                0
+            } else {
+                s.file_offset
+            };
+            loads.push(Load {
+                offset,
+                vaddr: s.virtual_address - (s.file_offset - offset),
+                paddr: s.load_address - (s.file_offset - offset),
+                filesz: 0,
+                memsz: 0,
+                align: page_size,
+            });
+            nobits = false;
+        }
+        let l = loads.last_mut().unwrap();
+        l.memsz = s.virtual_address + s.size - l.vaddr;
+        // Only an aligned start can honor the alignment.
+        if (l.vaddr - l.offset).is_multiple_of(s.align) {
+            l.align = l.align.max(s.align);
+        }
+        if bss {
+            nobits = true;
+        } else {
+            l.filesz = s.file_offset + s.size - l.offset;
+        }
     }
+    if loads.is_empty() {
+        loads.push(Load {
+            offset: 0,
+            vaddr: base_addr,
+            paddr: base_addr,
+            filesz: page_size,
+            memsz: 0,
+            align: page_size,
+        });
+    }
+    loads
+}
 
-    let shstrtab_offset = shstrtab.len();
-    section_name_offsets.push(shstrtab_offset);
-    shstrtab.extend_from_slice(b".shstrtab\0");
-
-    let shoff = buffer.len();
-
-    // Null section header
-    let null_sec = object::elf::SectionHeader64::<Endianness> {
-        sh_name: u32(0),
-        sh_type: u32(object::elf::SHT_NULL),
-        sh_flags: u64(0),
-        sh_addr: u64(0),
-        sh_offset: u64(0),
-        sh_size: u64(0),
-        sh_link: u32(0),
-        sh_info: u32(0),
-        sh_addralign: u64(0),
-        sh_entsize: u64(0),
+/// Audit where the loaded sections ended up before writing them
+/// (`--check-sections`): no two overlap in memory or in the file, and each
+/// holds as many bytes as it is long. For ELF output (`elf`), each address
+/// must also be congruent with its file offset modulo the page size, and
+/// fall in a `PT_LOAD` that maps it from that offset, past the headers.
+///
+/// Failures are listed with a table of every loaded section.
+pub fn check_layout(image: &Image, elf: bool) -> Result<()> {
+    let page = image.z.page_size();
+    let placed: Vec<&Segment> = image.segments.iter().filter(|s| s.size > 0).collect();
+    let file_backed = |s: &Segment| {
+        !matches!(
+            s.kind,
+            SectionKind::UninitializedData | SectionKind::UninitializedTls
+        )
     };
-    buffer.extend_from_slice(bytes_of(&null_sec));
-
-    // Section headers for each segment
-    for (i, segment) in segments.iter().enumerate() {
-        let sec_header = object::elf::SectionHeader64::<Endianness> {
-            sh_name: u32(section_name_offsets[i + 1] as u32),
-            sh_type: u32(if segment.kind == SectionKind::UninitializedData {
-                object::elf::SHT_NOBITS
+    let mut problems = Vec::new();
+
+    let mut by_address = placed.clone();
+    by_address.sort_by_key(|s| s.virtual_address);
+    for pair in by_address.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if a.virtual_address + a.size > b.virtual_address {
+            problems.push(format!("{} overlaps {} in memory", a.name, b.name));
+        }
+    }
+    let mut by_offset: Vec<&Segment> = placed.iter().copied().filter(|s| file_backed(s)).collect();
+    by_offset.sort_by_key(|s| s.file_offset);
+    for pair in by_offset.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if a.file_offset + a.size > b.file_offset {
+            problems.push(format!("{} overlaps {} in the file", a.name, b.name));
+        }
+    }
+    for s in by_offset.iter().filter(|s| s.file_size() != s.size) {
+        problems.push(format!(
+            "{} holds {:#x} bytes but is {:#x} long",
+            s.name,
+            s.file_size(),
+            s.size
+        ));
+    }
+    if elf {
+        for s in &by_offset {
+            if s.virtual_address.wrapping_sub(s.file_offset) % page != 0 {
+                problems.push(format!(
+                    "{}: address {:#x} and file offset {:#x} differ modulo the page size {:#x}",
+                    s.name, s.virtual_address, s.file_offset, page
+                ));
+            }
+        }
+    }
+
+    // The program headers only make sense for a consistent layout.
+    let mut covered = vec![None; placed.len()];
+    if elf && problems.is_empty() {
+        let loads = loads(image.segments, image.base_addr, page);
+        for (i, s) in placed.iter().enumerate() {
+            let end = s.virtual_address + s.size;
+            covered[i] = loads.iter().position(|l| {
+                l.vaddr <= s.virtual_address
+                    && end <= l.vaddr + l.memsz
+                    && (!file_backed(s)
+                        || (s.file_offset.checked_sub(l.offset)
+                            == Some(s.virtual_address - l.vaddr)
+                            && s.file_offset + s.size <= l.offset + l.filesz))
+            });
+            if covered[i].is_none() {
+                problems.push(format!(
+                    "{}: no PT_LOAD maps it from its file offset",
+                    s.name
+                ));
+            }
+        }
+        let headers = ElfWriter::layout(image).headers.len() as u64;
+        if let Some(first) = by_offset.first() {
+            if first.file_offset < headers {
+                problems.push(format!(
+                    "{}: file offset {:#x} is inside the file and program headers, which end at {:#x}",
+                    first.name, first.file_offset, headers
+                ));
+            }
+        }
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    let mut text = String::from("output layout check failed (--check-sections):");
+    for p in &problems {
+        text.push_str(&format!("\n  {}", p));
+    }
+    text.push_str(&format!(
+        "\n\n  {:<24} {:>18} {:>10} {:>10} {:>8}",
+        "Section", "Address", "Offset", "Size", "PT_LOAD"
+    ));
+    for (s, load) in placed.iter().zip(&covered) {
+        let offset = if file_backed(s) {
+            format!("{:#x}", s.file_offset)
+        } else {
+            "-".to_string()
+        };
+        text.push_str(&format!(
+            "\n  {:<24} {:>#18x} {:>10} {:>#10x} {:>8}",
+            s.name,
+            s.virtual_address,
+            offset,
+            s.size,
+            load.map_or("-".to_string(), |l| l.to_string())
+        ));
+    }
+    bail!(text)
+}
+
+/// The ELF writer: an executable or shared object with one `PT_LOAD` per run
+/// of sections, plus the dynamic, stack and RELRO program headers.
+pub struct ElfWriter;
+
+/// A program header, encoded as `Elf64_Phdr` or `Elf32_Phdr`.
+struct ProgramHeader {
+    p_type: u32,
+    p_flags: u32,
+    p_offset: u64,
+    p_vaddr: u64,
+    p_paddr: u64,
+    p_filesz: u64,
+    p_memsz: u64,
+    p_align: u64,
+}
+
+impl ProgramHeader {
+    fn encode(&self, is_64: bool, e: Endianness, out: &mut Vec<u8>) {
+        if is_64 {
+            out.extend_from_slice(bytes_of(&ProgramHeader64::<Endianness> {
+                p_type: u32(e, self.p_type),
+                p_flags: u32(e, self.p_flags),
+                p_offset: u64(e, self.p_offset),
+                p_vaddr: u64(e, self.p_vaddr),
+                p_paddr: u64(e, self.p_paddr),
+                p_filesz: u64(e, self.p_filesz),
+                p_memsz: u64(e, self.p_memsz),
+                p_align: u64(e, self.p_align),
+            }));
+        } else {
+            out.extend_from_slice(bytes_of(&ProgramHeader32::<Endianness> {
+                p_type: u32(e, self.p_type),
+                p_offset: u32(e, self.p_offset as u32),
+                p_vaddr: u32(e, self.p_vaddr as u32),
+                p_paddr: u32(e, self.p_paddr as u32),
+                p_filesz: u32(e, self.p_filesz as u32),
+                p_memsz: u32(e, self.p_memsz as u32),
+                p_flags: u32(e, self.p_flags),
+                p_align: u32(e, self.p_align as u32),
+            }));
+        }
+    }
+}
+
+/// A section header, encoded as `Elf64_Shdr` or `Elf32_Shdr`.
+struct SectionHeader {
+    sh_name: u32,
+    sh_type: u32,
+    sh_flags: u64,
+    sh_addr: u64,
+    sh_offset: u64,
+    sh_size: u64,
+    sh_link: u32,
+    sh_info: u32,
+    sh_addralign: u64,
+    sh_entsize: u64,
+}
+
+impl SectionHeader {
+    fn encode(&self, is_64: bool, e: Endianness, out: &mut Vec<u8>) {
+        if is_64 {
+            out.extend_from_slice(bytes_of(&SectionHeader64::<Endianness> {
+                sh_name: u32(e, self.sh_name),
+                sh_type: u32(e, self.sh_type),
+                sh_flags: u64(e, self.sh_flags),
+                sh_addr: u64(e, self.sh_addr),
+                sh_offset: u64(e, self.sh_offset),
+                sh_size: u64(e, self.sh_size),
+                sh_link: u32(e, self.sh_link),
+                sh_info: u32(e, self.sh_info),
+                sh_addralign: u64(e, self.sh_addralign),
+                sh_entsize: u64(e, self.sh_entsize),
+            }));
+        } else {
+            out.extend_from_slice(bytes_of(&SectionHeader32::<Endianness> {
+                sh_name: u32(e, self.sh_name),
+                sh_type: u32(e, self.sh_type),
+                sh_flags: u32(e, self.sh_flags as u32),
+                sh_addr: u32(e, self.sh_addr as u32),
+                sh_offset: u32(e, self.sh_offset as u32),
+                sh_size: u32(e, self.sh_size as u32),
+                sh_link: u32(e, self.sh_link),
+                sh_info: u32(e, self.sh_info),
+                sh_addralign: u32(e, self.sh_addralign as u32),
+                sh_entsize: u32(e, self.sh_entsize as u32),
+            }));
+        }
+    }
+}
+
+/// Sizes of the file, program and section headers of each class.
+const EHDR64: (u16, u16, u16) = (64, 56, 64);
+const EHDR32: (u16, u16, u16) = (52, 32, 40);
+
+/// Where the pieces of an ELF file go.
+///
+/// Only the headers are built in memory; section contents are copied from
+/// the image into place.
+struct ElfLayout {
+    /// File and program headers, written at offset 0.
+    headers: Vec<u8>,
+    /// File offset of each non-allocated section.
+    non_alloc_offsets: Vec<u64>,
+    /// File offset of the section headers.
+    shoff: u64,
+    /// Section headers followed by `.shstrtab`, written at `shoff`.
+    section_headers: Vec<u8>,
+}
+
+impl ElfLayout {
+    /// The pieces of the file, in the order later ones are written over
+    /// earlier ones.
+    fn pieces<'i>(self, image: &Image<'i>) -> Result<Vec<Piece<'i>>> {
+        let mut pieces = vec![(0, Cow::Owned(self.headers))];
+        for segment in image.segments {
+            if !segment.is_nobits() {
+                for (offset, bytes) in segment.pieces(image.objects)? {
+                    pieces.push((segment.file_offset + offset, Cow::Borrowed(bytes)));
+                }
+            }
+        }
+        for (sec, &offset) in image.non_alloc.iter().zip(&self.non_alloc_offsets) {
+            pieces.push((offset, Cow::Borrowed(&sec.data[..])));
+        }
+        pieces.push((self.shoff, Cow::Owned(self.section_headers)));
+        Ok(pieces)
+    }
+}
+
+impl ElfWriter {
+    fn layout(image: &Image) -> ElfLayout {
+        let Image {
+            segments,
+            objects: _,
+            non_alloc,
+            entry_point,
+            e_type,
+            base_addr,
+            os_abi,
+            e_machine,
+            e_flags,
+            is_64,
+            endian: e,
+            z,
+        } = *image;
+        let (ehsize, phentsize, shentsize) = if is_64 { EHDR64 } else { EHDR32 };
+        let word = if is_64 { 8 } else { 4 };
+        let mut buffer = Vec::new();
+        let num_sections = (segments.len() + non_alloc.len()) as u32 + 2;
+        let dynamic = segments.iter().find(|s| s.name == ".dynamic" && s.size > 0);
+        let interp = segments.iter().find(|s| s.name == ".interp" && s.size > 0);
+        let property = segments
+            .iter()
+            .find(|s| s.name == property::SECTION && s.size > 0);
+        let notes = note_runs(segments);
+        // .tdata and .tbss, the TLS initialization image and its zero fill.
+        let tls: Vec<_> = segments
+            .iter()
+            .filter(|s| s.size > 0 && s.is_tls())
+            .collect();
+        // The layout keeps these sections adjacent, so one range covers them.
+        let relro: Vec<_> = segments
+            .iter()
+            .filter(|s| z.relro && s.size > 0 && RELRO_SECTIONS.contains(&s.name.as_str()))
+            .collect();
+        let page_size = z.page_size();
+        let loads = loads(segments, base_addr, page_size);
+        // The interpreter finds a PIE's load address from PT_PHDR, which the
+        // first PT_LOAD maps along with the file header.
+        let phdr = interp.and(loads.first().filter(|l| l.offset == 0));
+        let num_phdrs = loads.len() as u16
+            + 1
+            + phdr.is_some() as u16
+            + dynamic.is_some() as u16
+            + interp.is_some() as u16
+            + notes.len() as u16
+            + property.is_some() as u16
+            + !tls.is_empty() as u16
+            + !relro.is_empty() as u16;
+
+        // ELF file header
+        let e_ident = object::elf::Ident {
+            magic: object::elf::ELFMAG,
+            class: if is_64 {
+                object::elf::ELFCLASS64
             } else {
-                object::elf::SHT_PROGBITS
-            }),
-            sh_flags: u64(match segment.kind {
-                SectionKind::Text => object::elf::SHF_ALLOC | object::elf::SHF_EXECINSTR,
-                SectionKind::Data => object::elf::SHF_ALLOC | object::elf::SHF_WRITE,
-                SectionKind::UninitializedData => object::elf::SHF_ALLOC | object::elf::SHF_WRITE,
-                _ => object::elf::SHF_ALLOC,
-            } as u64),
-            sh_addr: u64(segment.virtual_address),
-            sh_offset: u64(segment.file_offset),
-            sh_size: u64(segment.size),
-            sh_link: u32(0),
-            sh_info: u32(0),
-            sh_addralign: u64(16),
-            sh_entsize: u64(0),
+                object::elf::ELFCLASS32
+            },
+            data: match e {
+                Endianness::Little => object::elf::ELFDATA2LSB,
+                Endianness::Big => object::elf::ELFDATA2MSB,
+            },
+            version: object::elf::EV_CURRENT,
+            os_abi,
+            abi_version: 0,
+            padding: [0; 7],
         };
-        buffer.extend_from_slice(bytes_of(&sec_header));
-    }
-
-    // Section header string table header
-    let shstrtab_header = object::elf::SectionHeader64::<Endianness> {
-        sh_name: u32(section_name_offsets[section_name_offsets.len() - 1] as u32),
-        sh_type: u32(object::elf::SHT_STRTAB),
-        sh_flags: u64(0),
-        sh_addr: u64(0),
-        sh_offset: u64((shoff + (num_sections as usize * 64)) as u64),
-        sh_size: u64(shstrtab.len() as u64),
-        sh_link: u32(0),
-        sh_info: u32(0),
-        sh_addralign: u64(1),
-        sh_entsize: u64(0),
-    };
-    buffer.extend_from_slice(bytes_of(&shstrtab_header));
+        // e_shoff is patched once the section data is placed
+        if is_64 {
+            buffer.extend_from_slice(bytes_of(&FileHeader64::<Endianness> {
+                e_ident,
+                e_type: u16(e, e_type),
+                e_machine: u16(e, e_machine),
+                e_version: u32(e, object::elf::EV_CURRENT as u32),
+                e_entry: u64(e, entry_point),
+                e_phoff: u64(e, ehsize as u64),
+                e_shoff: u64(e, 0),
+                e_flags: u32(e, e_flags),
+                e_ehsize: u16(e, ehsize),
+                e_phentsize: u16(e, phentsize),
+                e_phnum: u16(e, num_phdrs),
+                e_shentsize: u16(e, shentsize),
+                e_shnum: u16(e, num_sections as u16),
+                e_shstrndx: u16(e, num_sections as u16 - 1),
+            }));
+        } else {
+            buffer.extend_from_slice(bytes_of(&FileHeader32::<Endianness> {
+                e_ident,
+                e_type: u16(e, e_type),
+                e_machine: u16(e, e_machine),
+                e_version: u32(e, object::elf::EV_CURRENT as u32),
+                e_entry: u32(e, entry_point as u32),
+                e_phoff: u32(e, ehsize as u32),
+                e_shoff: u32(e, 0),
+                e_flags: u32(e, e_flags),
+                e_ehsize: u16(e, ehsize),
+                e_phentsize: u16(e, phentsize),
+                e_phnum: u16(e, num_phdrs),
+                e_shentsize: u16(e, shentsize),
+                e_shnum: u16(e, num_sections as u16),
+                e_shstrndx: u16(e, num_sections as u16 - 1),
+            }));
+        }
 
-    // String table contents
-    buffer.extend_from_slice(&shstrtab);
+        // PT_PHDR covers the program headers themselves, ahead of every other
+        if let Some(l) = phdr {
+            let size = num_phdrs as u64 * phentsize as u64;
+            let phdr_header = ProgramHeader {
+                p_type: object::elf::PT_PHDR,
+                p_flags: object::elf::PF_R,
+                p_offset: ehsize as u64,
+                p_vaddr: l.vaddr + ehsize as u64,
+                p_paddr: l.paddr + ehsize as u64,
+                p_filesz: size,
+                p_memsz: size,
+                p_align: word,
+            };
+            phdr_header.encode(is_64, e, &mut buffer);
+        }
 
-    // Patch e_shoff in the file header
-    let shoff_bytes = (shoff as u64).to_le_bytes();
-    buffer[40..48].copy_from_slice(&shoff_bytes);
+        // PT_INTERP names the program interpreter and must precede PT_LOAD
+        if let Some(i) = interp {
+            let interp_header = ProgramHeader {
+                p_type: object::elf::PT_INTERP,
+                p_flags: object::elf::PF_R,
+                p_offset: i.file_offset,
+                p_vaddr: i.virtual_address,
+                p_paddr: i.virtual_address,
+                p_filesz: i.size,
+                p_memsz: i.size,
+                p_align: 1,
+            };
+            interp_header.encode(is_64, e, &mut buffer);
+        }
 
-    // Write file
-    std::fs::write(output_path, &buffer)?;
+        for load in &loads {
+            let prog_header = ProgramHeader {
+                p_type: object::elf::PT_LOAD,
+                p_flags: object::elf::PF_R | object::elf::PF_W | object::elf::PF_X,
+                p_offset: load.offset,
+                p_vaddr: load.vaddr,
+                p_paddr: load.paddr,
+                p_filesz: load.filesz,
+                p_memsz: load.memsz,
+                p_align: load.align,
+            };
+            prog_header.encode(is_64, e, &mut buffer);
+        }
 
-    // Make executable
-    let mut perms = std::fs::metadata(output_path)?.permissions();
-    perms.set_mode(0o755);
-    std::fs::set_permissions(output_path, perms)?;
+        // PT_DYNAMIC tells the loader where to find .dynamic
+        if let Some(d) = dynamic {
+            let dyn_header = ProgramHeader {
+                p_type: object::elf::PT_DYNAMIC,
+                p_flags: object::elf::PF_R | object::elf::PF_W,
+                p_offset: d.file_offset,
+                p_vaddr: d.virtual_address,
+                p_paddr: d.virtual_address,
+                p_filesz: d.size,
+                p_memsz: d.size,
+                p_align: 8,
+            };
+            dyn_header.encode(is_64, e, &mut buffer);
+        }
 
-    Ok(())
+        for &(first, size) in &notes {
+            let note_header = ProgramHeader {
+                p_type: object::elf::PT_NOTE,
+                p_flags: object::elf::PF_R,
+                p_offset: first.file_offset,
+                p_vaddr: first.virtual_address,
+                p_paddr: first.virtual_address,
+                p_filesz: size,
+                p_memsz: size,
+                p_align: first.align,
+            };
+            note_header.encode(is_64, e, &mut buffer);
+        }
+
+        // The property note also gets PT_GNU_PROPERTY; its PT_NOTE is for
+        // loaders that predate it.
+        if let Some(p) = property {
+            let property_header = ProgramHeader {
+                p_type: object::elf::PT_GNU_PROPERTY,
+                p_flags: object::elf::PF_R,
+                p_offset: p.file_offset,
+                p_vaddr: p.virtual_address,
+                p_paddr: p.virtual_address,
+                p_filesz: p.size,
+                p_memsz: p.size,
+                p_align: p.align,
+            };
+            property_header.encode(is_64, e, &mut buffer);
+        }
+
+        // PT_TLS is the template each thread's TLS block is copied from.
+        if let (Some(first), Some(last)) = (tls.first(), tls.last()) {
+            let filesz = tls
+                .iter()
+                .filter(|s| !s.is_nobits())
+                .map(|s| s.virtual_address + s.size - first.virtual_address)
+                .max()
+                .unwrap_or(0);
+            let tls_header = ProgramHeader {
+                p_type: object::elf::PT_TLS,
+                p_flags: object::elf::PF_R,
+                p_offset: first.file_offset,
+                p_vaddr: first.virtual_address,
+                p_paddr: first.virtual_address,
+                p_filesz: filesz,
+                p_memsz: last.virtual_address + last.size - first.virtual_address,
+                p_align: tls.iter().map(|s| s.align).max().unwrap_or(1),
+            };
+            tls_header.encode(is_64, e, &mut buffer);
+        }
+
+        // PT_GNU_STACK sets whether the stack is executable, and its size
+        let stack_flags = if z.exec_stack {
+            object::elf::PF_R | object::elf::PF_W | object::elf::PF_X
+        } else {
+            object::elf::PF_R | object::elf::PF_W
+        };
+        let stack_header = ProgramHeader {
+            p_type: object::elf::PT_GNU_STACK,
+            p_flags: stack_flags,
+            p_offset: 0,
+            p_vaddr: 0,
+            p_paddr: 0,
+            p_filesz: 0,
+            p_memsz: z.stack_size.unwrap_or(0),
+            p_align: 16,
+        };
+        stack_header.encode(is_64, e, &mut buffer);
+
+        // PT_GNU_RELRO lets the loader make relocated data read-only; it runs to
+        // the next page so the last partial page is covered too.
+        if let (Some(first), Some(last)) = (relro.first(), relro.last()) {
+            let size =
+                align_up(last.virtual_address + last.size, page_size) - first.virtual_address;
+            let relro_header = ProgramHeader {
+                p_type: object::elf::PT_GNU_RELRO,
+                p_flags: object::elf::PF_R,
+                p_offset: first.file_offset,
+                p_vaddr: first.virtual_address,
+                p_paddr: first.virtual_address,
+                p_filesz: size,
+                p_memsz: size,
+                p_align: 1,
+            };
+            relro_header.encode(is_64, e, &mut buffer);
+        }
+
+        // The headers fill at least the first page; segment data follows at
+        // the offsets the layout gave it.
+        let mut end = (buffer.len() as u64).max(page_size);
+        for segment in segments {
+            if !segment.is_nobits() {
+                end = end.max(segment.file_offset + segment.file_size());
+            }
+        }
+
+        // Non-allocated section data (symbol tables, relocations, ...)
+        let mut non_alloc_offsets = Vec::new();
+        for sec in non_alloc {
+            end = align_up(end, sec.align.max(1));
+            non_alloc_offsets.push(end);
+            end += sec.data.len() as u64;
+        }
+
+        // Output section index of a section by name
+        let section_index = |name: &str| -> u32 {
+            segments
+                .iter()
+                .map(|s| s.name.as_str())
+                .chain(non_alloc.iter().map(|s| s.name.as_str()))
+                .position(|n| n == name)
+                .map_or(0, |i| i as u32 + 1)
+        };
+
+        // Build section header string table
+        let mut shstrtab = Vec::new();
+        shstrtab.push(0);
+        let mut section_name_offsets = Vec::new();
+        section_name_offsets.push(0);
+
+        for name in segments
+            .iter()
+            .map(|s| &s.name)
+            .chain(non_alloc.iter().map(|s| &s.name))
+        {
+            let off = shstrtab.len();
+            section_name_offsets.push(off);
+            shstrtab.extend_from_slice(name.as_bytes());
+            shstrtab.push(0);
+        }
+
+        let shstrtab_offset = shstrtab.len();
+        section_name_offsets.push(shstrtab_offset);
+        shstrtab.extend_from_slice(b".shstrtab\0");
+
+        // Readers require the section headers to be word aligned.
+        let shoff = align_up(end, word);
+        let mut headers = std::mem::take(&mut buffer);
+        if is_64 {
+            headers[40..48].copy_from_slice(bytes_of(&u64(e, shoff)));
+        } else {
+            headers[32..36].copy_from_slice(bytes_of(&u32(e, shoff as u32)));
+        }
+
+        // Null section header
+        let null_sec = SectionHeader {
+            sh_name: 0,
+            sh_type: object::elf::SHT_NULL,
+            sh_flags: 0,
+            sh_addr: 0,
+            sh_offset: 0,
+            sh_size: 0,
+            sh_link: 0,
+            sh_info: 0,
+            sh_addralign: 0,
+            sh_entsize: 0,
+        };
+        null_sec.encode(is_64, e, &mut buffer);
+
+        // Section headers for each segment
+        for (i, segment) in segments.iter().enumerate() {
+            let link = section_link(&segment.name).map_or(0, section_index);
+            let sec_header = SectionHeader {
+                sh_name: section_name_offsets[i + 1] as u32,
+                sh_type: match segment.kind {
+                    SectionKind::UninitializedData | SectionKind::UninitializedTls => {
+                        object::elf::SHT_NOBITS
+                    }
+                    SectionKind::Elf(sh_type) => sh_type,
+                    _ => object::elf::SHT_PROGBITS,
+                },
+                sh_flags: match segment.kind {
+                    SectionKind::Text => object::elf::SHF_ALLOC | object::elf::SHF_EXECINSTR,
+                    SectionKind::Data => object::elf::SHF_ALLOC | object::elf::SHF_WRITE,
+                    SectionKind::UninitializedData => {
+                        object::elf::SHF_ALLOC | object::elf::SHF_WRITE
+                    }
+                    SectionKind::Tls | SectionKind::UninitializedTls => {
+                        object::elf::SHF_ALLOC | object::elf::SHF_WRITE | object::elf::SHF_TLS
+                    }
+                    SectionKind::Elf(
+                        object::elf::SHT_DYNAMIC
+                        | object::elf::SHT_PREINIT_ARRAY
+                        | object::elf::SHT_INIT_ARRAY
+                        | object::elf::SHT_FINI_ARRAY,
+                    ) => object::elf::SHF_ALLOC | object::elf::SHF_WRITE,
+                    _ => object::elf::SHF_ALLOC,
+                } as u64,
+                sh_addr: segment.virtual_address,
+                sh_offset: segment.file_offset,
+                sh_size: segment.size,
+                sh_link: link,
+                sh_info: match segment.name.as_str() {
+                    // Index of the first non-local symbol (all but the null entry)
+                    ".dynsym" => 1,
+                    ".gnu.version_d" => version::verdef_count(&segment.data, e),
+                    ".gnu.version_r" => version::verneed_count(&segment.data, e),
+                    _ => 0,
+                },
+                sh_addralign: segment.align,
+                sh_entsize: entry_size(segment.kind, word),
+            };
+            sec_header.encode(is_64, e, &mut buffer);
+        }
+
+        // Section headers for non-allocated sections
+        for (i, sec) in non_alloc.iter().enumerate() {
+            let sec_header = SectionHeader {
+                sh_name: section_name_offsets[segments.len() + i + 1] as u32,
+                sh_type: sec.sh_type,
+                sh_flags: sec.flags,
+                sh_addr: 0,
+                sh_offset: non_alloc_offsets[i],
+                sh_size: sec.data.len() as u64,
+                sh_link: sec.link.as_deref().map_or(0, section_index),
+                sh_info: sec.info_section.as_deref().map_or(sec.info, section_index),
+                sh_addralign: sec.align,
+                sh_entsize: sec.entsize,
+            };
+            sec_header.encode(is_64, e, &mut buffer);
+        }
+
+        // Section header string table header
+        let shstrtab_header = SectionHeader {
+            sh_name: section_name_offsets[section_name_offsets.len() - 1] as u32,
+            sh_type: object::elf::SHT_STRTAB,
+            sh_flags: 0,
+            sh_addr: 0,
+            sh_offset: shoff + num_sections as u64 * shentsize as u64,
+            sh_size: shstrtab.len() as u64,
+            sh_link: 0,
+            sh_info: 0,
+            sh_addralign: 1,
+            sh_entsize: 0,
+        };
+        shstrtab_header.encode(is_64, e, &mut buffer);
+
+        // String table contents
+        buffer.extend_from_slice(&shstrtab);
+
+        ElfLayout {
+            headers,
+            non_alloc_offsets,
+            shoff,
+            section_headers: buffer,
+        }
+    }
+}
+
+impl ElfWriter {
+    /// The file's pieces, from a single layout of `image`: in file order,
+    /// or one piece holding the whole file if sections are laid over one
+    /// another, since those can only be written in order.
+    fn file_pieces<'i>(image: &Image<'i>) -> Result<Vec<Piece<'i>>> {
+        if !image.is_64 {
+            let end = |s: &Segment| s.virtual_address + s.size;
+            if let Some(s) = image.segments.iter().find(|s| end(s) > 1 << 32) {
+                bail!(
+                    "{}: address {:#x} does not fit in ELFCLASS32 output",
+                    s.name,
+                    end(s)
+                );
+            }
+        }
+        let mut pieces = Self::layout(image).pieces(image)?;
+        let mut spans: Vec<_> = pieces
+            .iter()
+            .map(|(offset, bytes)| (*offset, offset + bytes.len() as u64))
+            .collect();
+        spans.sort();
+        if spans.windows(2).all(|w| w[0].1 <= w[1].0) {
+            pieces.sort_by_key(|(offset, _)| *offset);
+            return Ok(pieces);
+        }
+        let mut file = vec![0; pieces_end(&pieces) as usize];
+        fill(&pieces, &mut file);
+        Ok(vec![(0, Cow::Owned(file))])
+    }
+}
+
+impl OutputFormat for ElfWriter {
+    fn size(&self, image: &Image) -> Result<u64> {
+        Ok(pieces_end(&Self::file_pieces(image)?))
+    }
+
+    fn write(&self, image: &Image, out: &mut [u8]) -> Result<()> {
+        fill(&Self::file_pieces(image)?, out);
+        Ok(())
+    }
+
+    fn pieces<'i>(&self, image: &Image<'i>) -> Result<Option<Vec<Piece<'i>>>> {
+        Self::file_pieces(image).map(Some)
+    }
+
+    fn executable(&self) -> bool {
+        true
+    }
+}
+
+/// Counts the bytes a text format writes, to size the output file.
+struct Count(u64);
+
+impl fmt::Write for Count {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len() as u64;
+        Ok(())
+    }
+}
+
+/// Writes a text format into the mapped output file.
+struct Fill<'a>(&'a mut [u8]);
+
+impl fmt::Write for Fill<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let (head, tail) = std::mem::take(&mut self.0)
+            .split_at_mut_checked(s.len())
+            .ok_or(fmt::Error)?;
+        head.copy_from_slice(s.as_bytes());
+        self.0 = tail;
+        Ok(())
+    }
+}
+
+/// Data bytes per Intel HEX or S-record line, as objcopy writes them.
+const RECORD_LEN: u64 = 16;
+
+/// The file-backed contents of the loaded sections as `(load address,
+/// bytes)` pieces for the record formats.
+///
+/// Pieces are at most [`RECORD_LEN`] bytes and never cross a 64 KiB
+/// boundary, which Intel HEX addressing can't express.
+fn records(image: &Image) -> Result<Vec<(u64, Vec<u8>)>> {
+    let mut records = Vec::new();
+    for s in image
+        .segments
+        .iter()
+        .filter(|s| s.size > 0 && !s.is_nobits())
+    {
+        let mut data = s.contents(image.objects)?;
+        data.resize(s.size as usize, 0);
+        let mut addr = s.load_address;
+        let mut rest = &data[..];
+        while !rest.is_empty() {
+            let len = RECORD_LEN.min(0x10000 - (addr & 0xffff)) as usize;
+            let (chunk, tail) = rest.split_at(len.min(rest.len()));
+            records.push((addr, chunk.to_vec()));
+            addr += chunk.len() as u64;
+            rest = tail;
+        }
+    }
+    Ok(records)
+}
+
+/// Intel HEX records, as `objcopy -O ihex` writes them.
+///
+/// Addresses up to 1 MiB use extended segment address records and higher
+/// ones extended linear address records; the entry point becomes a start
+/// address record of the matching kind.
+pub struct IhexWriter;
+
+impl IhexWriter {
+    fn encode(image: &Image, out: &mut impl fmt::Write) -> Result<()> {
+        fn record(out: &mut impl fmt::Write, addr: u64, kind: u8, data: &[u8]) -> fmt::Result {
+            let mut bytes = vec![data.len() as u8, (addr >> 8) as u8, addr as u8, kind];
+            bytes.extend_from_slice(data);
+            let sum = bytes.iter().fold(0u8, |a, &b| a.wrapping_add(b));
+            bytes.push(sum.wrapping_neg());
+            out.write_char(':')?;
+            for b in bytes {
+                write!(out, "{:02X}", b)?;
+            }
+            out.write_str("\r\n")
+        }
+
+        let (mut segment_base, mut linear_base) = (0u64, 0u64);
+        for (addr, data) in records(image)? {
+            if addr + data.len() as u64 > 1 << 32 {
+                bail!("{:#x}: address does not fit in Intel HEX", addr);
+            }
+            let base = segment_base + linear_base;
+            if addr < base || addr - base > 0xffff {
+                if addr <= 0xfffff {
+                    if linear_base != 0 {
+                        linear_base = 0;
+                        record(out, 0, 4, &[0, 0])?;
+                    }
+                    segment_base = addr & 0xf0000;
+                    record(out, 0, 2, &((segment_base >> 4) as u16).to_be_bytes())?;
+                } else {
+                    if segment_base != 0 {
+                        segment_base = 0;
+                        record(out, 0, 2, &[0, 0])?;
+                    }
+                    linear_base = addr & 0xffff_0000;
+                    record(out, 0, 4, &((linear_base >> 16) as u16).to_be_bytes())?;
+                }
+            }
+            record(out, addr - segment_base - linear_base, 0, &data)?;
+        }
+        if image.entry_point > 0xffff_ffff {
+            bail!(
+                "entry point {:#x} does not fit in Intel HEX",
+                image.entry_point
+            );
+        } else if image.entry_point > 0xfffff {
+            record(out, 0, 5, &(image.entry_point as u32).to_be_bytes())?;
+        } else if image.entry_point != 0 {
+            let cs = ((image.entry_point & 0xf0000) >> 4) as u16;
+            let ip = (image.entry_point & 0xffff) as u16;
+            let mut data = cs.to_be_bytes().to_vec();
+            data.extend_from_slice(&ip.to_be_bytes());
+            record(out, 0, 3, &data)?;
+        }
+        record(out, 0, 1, &[])?;
+        Ok(())
+    }
+}
+
+impl OutputFormat for IhexWriter {
+    fn size(&self, image: &Image) -> Result<u64> {
+        let mut count = Count(0);
+        Self::encode(image, &mut count)?;
+        Ok(count.0)
+    }
+
+    fn write(&self, image: &Image, out: &mut [u8]) -> Result<()> {
+        Self::encode(image, &mut Fill(out))
+    }
+}
+
+/// Motorola S-records, as `objcopy -O srec` writes them.
+///
+/// The address width (S1, S2 or S3 data records, ended by S9, S8 or S7 with
+/// the entry point) is the narrowest that fits every address.
+pub struct SrecWriter {
+    /// Text of the S0 header record; objcopy uses the output file name.
+    pub header: String,
+}
+
+impl SrecWriter {
+    fn encode(&self, image: &Image, out: &mut impl fmt::Write) -> Result<()> {
+        fn record(
+            out: &mut impl fmt::Write,
+            kind: u8,
+            addr: u64,
+            addr_len: usize,
+            data: &[u8],
+        ) -> fmt::Result {
+            let mut bytes = vec![(addr_len + data.len() + 1) as u8];
+            bytes.extend_from_slice(&addr.to_be_bytes()[8 - addr_len..]);
+            bytes.extend_from_slice(data);
+            let sum = bytes.iter().fold(0u8, |a, &b| a.wrapping_add(b));
+            bytes.push(!sum);
+            write!(out, "S{}", kind)?;
+            for b in bytes {
+                write!(out, "{:02X}", b)?;
+            }
+            out.write_str("\r\n")
+        }
+
+        let records = records(image)?;
+        let highest = records
+            .iter()
+            .map(|(addr, data)| addr + data.len() as u64 - 1)
+            .chain([image.entry_point])
+            .max()
+            .unwrap_or(0);
+        let (kind, addr_len) = match highest {
+            0..=0xffff => (1, 2),
+            0x10000..=0xff_ffff => (2, 3),
+            0x100_0000..=0xffff_ffff => (3, 4),
+            _ => bail!("{:#x}: address does not fit in an S-record", highest),
+        };
+
+        record(out, 0, 0, 2, self.header.as_bytes())?;
+        for (addr, data) in &records {
+            record(out, kind, *addr, addr_len, data)?;
+        }
+        record(out, 10 - kind, image.entry_point, addr_len, &[])?;
+        Ok(())
+    }
+}
+
+impl OutputFormat for SrecWriter {
+    fn size(&self, image: &Image) -> Result<u64> {
+        let mut count = Count(0);
+        self.encode(image, &mut count)?;
+        Ok(count.0)
+    }
+
+    fn write(&self, image: &Image, out: &mut [u8]) -> Result<()> {
+        self.encode(image, &mut Fill(out))
+    }
+}
+
+/// The loaded sections as a flat image, as `objcopy -O binary` writes it.
+///
+/// The image starts at the lowest load address, and each section is placed
+/// at its load address. Gaps between sections are zero-filled, and `.bss`
+/// past the last file-backed section is left out.
+pub struct BinaryWriter;
+
+impl BinaryWriter {
+    /// The file-backed sections and the lowest load address among them.
+    fn loaded<'a>(image: &Image<'a>) -> (Vec<&'a Segment>, u64) {
+        let loaded: Vec<&Segment> = image
+            .segments
+            .iter()
+            .filter(|s| s.size > 0 && !s.is_nobits())
+            .collect();
+        let start = loaded.iter().map(|s| s.load_address).min().unwrap_or(0);
+        (loaded, start)
+    }
+}
+
+impl OutputFormat for BinaryWriter {
+    fn size(&self, image: &Image) -> Result<u64> {
+        let (loaded, start) = Self::loaded(image);
+        Ok(loaded
+            .iter()
+            .map(|s| s.load_address + s.size - start)
+            .max()
+            .unwrap_or(0))
+    }
+
+    fn write(&self, image: &Image, out: &mut [u8]) -> Result<()> {
+        let (loaded, start) = Self::loaded(image);
+        for s in loaded {
+            let off = (s.load_address - start) as usize;
+            s.write(image.objects, &mut out[off..off + s.file_size() as usize])?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,27 @@
+# RUN: %as %s -o %t.o
+# RUN: echo '.globl a; a: call b; ret' | %as -o %t.a.o
+# RUN: echo '.globl c; c: mov $42, %eax; ret' | %as -o %t.c.o
+# RUN: echo '.globl b; b: jmp c' | %as -o %t.b.o
+# RUN: rm -f %t.liba.a %t.libb.a && ar rcs %t.liba.a %t.a.o %t.c.o && ar rcs %t.libb.a %t.b.o
+# RUN: not %uld -o %t.nogroup %t.o %t.liba.a %t.libb.a 2>&1 | %filecheck %s --check-prefix=NOGROUP
+# RUN: %uld -o %t.exe %t.o --start-group %t.liba.a %t.libb.a --end-group
+# RUN: %t.exe || echo "Exit: $?" | %filecheck %s
+# RUN: %uld -o %t.short %t.o '-(' %t.liba.a %t.libb.a '-)'
+# RUN: %t.short || echo "Exit: $?" | %filecheck %s
+# RUN: not %uld -o %t.nested %t.o --start-group '-(' %t.liba.a '-)' --end-group 2>&1 | %filecheck %s --check-prefix=NESTED
+
+# liba.a's `a` needs libb.a's `b`, which needs `c` back from liba.a: archives
+# are searched once, in order, unless they are in a group.
+# NOGROUP: undefined symbols:
+# NOGROUP-NEXT: c (referenced by {{.*}}libb.a({{.*}}b.o))
+
+# CHECK: Exit: 42
+
+# NESTED: groups may not be nested
+
+.global _start
+_start:
+    call a
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
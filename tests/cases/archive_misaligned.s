@@ -0,0 +1,20 @@
+# RUN: %as %s -o %t.o
+# RUN: rm -rf %t.dir && mkdir %t.dir && %as %getvalue -o %t.dir/v.o
+# RUN: rm -f %t.a && ar rcS %t.a %t.dir/v.o
+# RUN: od -A d -t x1 -j 68 -N 4 %t.a | %filecheck %s --check-prefix=MEMBER
+# RUN: %uld -o %t.exe %t.o %t.a
+# RUN: %t.exe || echo "Exit: $?" | %filecheck %s
+
+# Without a symbol index or long names, the only member's contents start
+# right after the archive magic and its header, at offset 68: not 8-byte aligned, so the
+# linker parses an aligned copy it keeps for the rest of the link.
+# MEMBER: 0000068 7f 45 4c 46
+
+# CHECK: Exit: 42
+
+.global _start
+_start:
+    call get_value
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
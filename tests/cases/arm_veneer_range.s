@@ -0,0 +1,24 @@
+# RUN: llvm-mc -triple=armv7a-linux-gnueabi -filetype=obj %s -o %t.o
+# RUN: not %uld --section-start=.far=0x9000000 -o %t %t.o 2>&1 | %filecheck %s
+
+# Veneers go at the end of .text, more than the ±16 MiB of a Thumb branch
+# away from the call at its start.
+# CHECK: arm_veneer_range.s.tmp.o:(.text+0x0): branch can't reach its veneer at 0x{{[0-9a-f]+}}: uld places veneers at the end of .text, beyond the branch's range from here
+
+.syntax unified
+.arch armv7-a
+.text
+.thumb
+.global _start
+.type _start, %function
+.thumb_func
+_start:
+    bl far
+    .space 0x1000000
+
+.section .far, "ax", %progbits
+.thumb
+.type far, %function
+.thumb_func
+far:
+    bx lr
@@ -0,0 +1,79 @@
+# RUN: llvm-mc -triple=armv7a-linux-gnueabi -filetype=obj %s -o %t.o
+# RUN: %uld --section-start=.far=0x9000000 -o %t %t.o
+# RUN: %readelf -h %t | %filecheck %s --check-prefix=HEADER
+# RUN: llvm-objdump -d --triple=armv7a --stop-address=0x401014 %t | %filecheck %s --check-prefix=ARM
+# RUN: llvm-objdump -d --triple=thumbv7a --start-address=0x401014 --stop-address=0x401022 %t | %filecheck %s --check-prefix=THUMB
+# RUN: llvm-objdump -d --triple=armv7a --start-address=0x401024 --stop-address=0x401034 %t | %filecheck %s --check-prefix=ARM-VENEER
+# RUN: llvm-objdump -d --triple=thumbv7a --start-address=0x401034 --stop-address=0x401044 %t | %filecheck %s --check-prefix=THUMB-VENEER
+# RUN: %readelf -x .data %t | %filecheck %s --check-prefix=DATA
+
+# HEADER: Class: ELF32
+# HEADER: Machine: ARM
+# HEADER: Flags: 0x5000000, Version5 EABI
+
+# Calls between the instruction sets become BLX; a B to Thumb code and
+# branches out of range go through veneers at the end of .text.
+# ARM: blx 0x401014
+# ARM-NEXT: blx 0x401014
+# ARM-NEXT: b 0x401024
+# ARM-NEXT: bl 0x40102c
+# ARM-NEXT: bx lr
+
+# THUMB: blx 0x401010
+# THUMB-NEXT: b.w 0x401034
+# THUMB-NEXT: bl 0x40103c
+
+# Veneers load their target, with its Thumb bit, into pc.
+# ARM-VENEER: ldr pc, [pc, #-4]
+# ARM-VENEER-NEXT: 05 00 00 09
+# ARM-VENEER-NEXT: ldr pc, [pc, #-4]
+# ARM-VENEER-NEXT: 00 00 00 09
+
+# THUMB-VENEER: ldr.w pc, [pc, #0]
+# THUMB-VENEER-NEXT: 10 10
+# THUMB-VENEER-NEXT: 40 00
+# THUMB-VENEER-NEXT: ldr.w pc, [pc, #0]
+# THUMB-VENEER-NEXT: 00 00
+# THUMB-VENEER-NEXT: 00 09
+
+# Addresses of Thumb functions have bit 0 set.
+# DATA: 15104000 10104000
+
+.syntax unified
+.arch armv7-a
+.text
+.arm
+.global _start
+.type _start, %function
+_start:
+    bl thumb_fn
+    blx thumb_fn
+    b far_thumb
+    bl far_arm
+.type arm_fn, %function
+arm_fn:
+    bx lr
+
+.thumb
+.type thumb_fn, %function
+.thumb_func
+thumb_fn:
+    bl arm_fn
+    b.w arm_fn
+    bl far_arm
+    bx lr
+
+.section .far,"ax",%progbits
+.arm
+.type far_arm, %function
+far_arm:
+    bx lr
+.thumb
+.type far_thumb, %function
+.thumb_func
+far_thumb:
+    bx lr
+
+.data
+    .word thumb_fn
+    .word arm_fn
@@ -0,0 +1,34 @@
+# RUN: rm -rf %t.dir && mkdir -p %t.dir
+# RUN: %as %s -o %t.o
+# RUN: %as %libone -o %t.one.o
+# RUN: %uld -shared -soname libone.so -o %t.dir/libone.so %t.one.o
+# RUN: %as %getvalue -o %t.unused.o
+# RUN: %uld -shared -soname libunused.so -o %t.dir/libunused.so %t.unused.o
+# RUN: %uld -o %t.all %t.o %t.dir/libunused.so %t.dir/libone.so
+# RUN: %readelf -l -d %t.all | %filecheck %s --check-prefix=ALL
+# RUN: %uld -o %t.pruned %t.o --as-needed %t.dir/libunused.so %t.dir/libone.so
+# RUN: %readelf -d %t.pruned | %filecheck %s --check-prefix=PRUNED
+# RUN: %uld -o %t.toggle %t.o --as-needed %t.dir/libone.so --no-as-needed %t.dir/libunused.so
+# RUN: %readelf -d %t.toggle | %filecheck %s --check-prefix=TOGGLE
+# RUN: env LD_LIBRARY_PATH=%t.dir %t.pruned; test $? -eq 1
+
+# ALL: Requesting program interpreter: /lib64/ld-linux-x86-64.so.2
+# ALL: (NEEDED) Shared library: [libunused.so]
+# ALL: (NEEDED) Shared library: [libone.so]
+
+# --as-needed only applies to the libraries that follow it.
+# TOGGLE: (NEEDED) Shared library: [libone.so]
+# TOGGLE: (NEEDED) Shared library: [libunused.so]
+
+# Nothing references libunused.so, so --as-needed drops it.
+# PRUNED-NOT: libunused.so
+# PRUNED: (NEEDED) Shared library: [libone.so]
+# PRUNED-NOT: libunused.so
+
+.text
+.global _start
+_start:
+    call *one@GOTPCREL(%rip)
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
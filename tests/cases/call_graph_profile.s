@@ -0,0 +1,59 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o --print-map | %filecheck %s
+# RUN: %t; test $? -eq 3
+# RUN: %uld -o %t.none %t.o --call-graph-profile-sort=none --print-map | %filecheck %s --check-prefix=NONE
+# RUN: %uld -o %t.nosort %t.o --no-call-graph-profile-sort --print-map | %filecheck %s --check-prefix=NONE
+# RUN: printf '# caller callee weight\n_start cold2 50\n_start nowhere 1\n' > %t.cg
+# RUN: %uld -o %t.file %t.o --call-graph-ordering-file %t.cg --print-map 2>&1 | %filecheck %s --check-prefix=FILE
+# RUN: not %uld -o %t.bad %t.o --call-graph-profile-sort=cdsort 2>&1 | %filecheck %s --check-prefix=BAD
+
+# The profiled call pulls warm up behind _start; the rest keep input order.
+# CHECK: .text._start
+# CHECK-NEXT: .text.warm
+# CHECK-NEXT: .text.cold1
+# CHECK-NEXT: .text.cold2
+
+# NONE: .text._start
+# NONE-NEXT: .text.cold1
+# NONE-NEXT: .text.warm
+# NONE-NEXT: .text.cold2
+
+# An ordering file replaces the profile section.
+# FILE: call graph file: no such symbol: nowhere
+# FILE: .text._start
+# FILE-NEXT: .text.cold2
+# FILE-NEXT: .text.cold1
+# FILE-NEXT: .text.warm
+
+# BAD: --call-graph-profile-sort=cdsort: expected none or hfsort
+
+.section .text._start,"ax"
+.global _start
+_start:
+    call warm
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
+
+.section .text.cold1,"ax"
+.global cold1
+cold1:
+    .fill 64, 1, 0x90
+    ret
+
+.section .text.warm,"ax"
+.global warm
+warm:
+    mov $3, %eax
+    ret
+
+.section .text.cold2,"ax"
+.global cold2
+cold2:
+    ret
+
+# One 100-call edge from _start to warm, in the format clang emits.
+.section .llvm.call-graph-profile,"e",@0x6fff4c09
+.reloc ., R_X86_64_NONE, _start
+.reloc ., R_X86_64_NONE, warm
+.quad 100
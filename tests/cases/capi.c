@@ -0,0 +1,39 @@
+// REQUIRES: capi
+// RUN: %as %start -o %t_start.o
+// RUN: rm -rf %t.dir && mkdir %t.dir && %as %getvalue -o %t.dir/value.o
+// RUN: rm -f %t.dir/libvalue.a && ar rcs %t.dir/libvalue.a %t.dir/value.o
+// RUN: cc -c -ffreestanding -DPROGRAM %s -o %t.program.o
+// RUN: cc -I%include %s -L%libdir -luld_capi -Wl,-rpath,%libdir -o %t.caller
+// RUN: %t.caller -o %t.exe %t_start.o %t.program.o -L%t.dir -lvalue | %filecheck %s
+// RUN: (%t.exe; echo "Exit: $?") | %filecheck %s --check-prefix=EXIT
+// RUN: %t.caller -o %t.bad %t_start.o | %filecheck %s --check-prefix=FAIL
+
+// A C program links through uld_link in libuld_capi.so, and gets the
+// diagnostics of a failed link back as JSON. The command line keeps its
+// order, so -lvalue after the objects resolves their get_value.
+// CHECK: status 0
+// EXIT: Exit: 42
+// FAIL: status 1
+// FAIL: "message":"undefined symbol: main"
+
+#ifdef PROGRAM
+int get_value(void);
+
+int main(void) {
+    return get_value();
+}
+#else
+#include <stdio.h>
+
+#include "uld.h"
+
+// Link with the command line in argv[1..].
+int main(int argc, char **argv) {
+    struct uld_result r = uld_link((const char *const *)argv + 1, argc - 1);
+    printf("status %d\n", r.status);
+    if (r.diagnostics)
+        fputs(r.diagnostics, stdout);
+    uld_free_diagnostics(r.diagnostics);
+    return 0;
+}
+#endif
@@ -0,0 +1,34 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld --check-sections -o %t %t.o
+# RUN: %t
+# RUN: %uld --check-sections -static-pie -o %t.pie %t.o
+# RUN: %t.pie
+# RUN: %uld --check-sections -shared -o %t.so %t.o
+# RUN: %uld --check-sections --section-start=.data=0x500010 -o %t.start %t.o
+# RUN: %t.start
+# RUN: %uld --check-sections --section-lma=.data=0x600000 -o %t.lma %t.o
+# RUN: %uld --check-sections --no-check-sections --oformat=binary -o %t.bin %t.o
+# RUN: %readelf -lW %t.start | %filecheck %s
+# RUN: not %uld --check-sections --section-start=.data=0x500014 -o %t.misaligned %t.o 2>&1 | %filecheck %s --check-prefix=INVALID
+
+# The audit passes for every layout the linker makes: sections pinned to
+# an address still start at a file offset congruent with it.
+# CHECK: LOAD 0x000000 0x0000000000400000
+# CHECK-NEXT: LOAD 0x{{.*}}010 0x0000000000500010
+
+# The written file is then checked as a loader would read it, which catches
+# a section pinned below its own alignment.
+# INVALID: output validation failed (--check-sections):
+# INVALID-NEXT: section .data: address 0x500014 is not aligned to 0x8
+
+.text
+.global _start
+_start:
+    mov value(%rip), %edi
+    mov $60, %eax
+    syscall
+
+.data
+.p2align 3
+value:
+    .long 0
@@ -0,0 +1,45 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym SECOND=1 %s -o %t.second.o
+# RUN: %uld -o %t %t.o %t.second.o
+# RUN: %readelf -p .comment %t | %filecheck %s
+# RUN: %readelf -SW %t | %filecheck %s --check-prefix=HEADER
+# RUN: %t
+# RUN: %uld --no-ident -o %t.noident %t.o %t.second.o
+# RUN: %readelf -p .comment %t.noident | %filecheck %s --check-prefix=NOIDENT
+# RUN: %as --defsym BARE=1 %s -o %t.bare.o
+# RUN: %uld --no-ident -o %t.bare %t.bare.o
+# RUN: %readelf -SW %t.bare | %filecheck %s --check-prefix=NONE
+
+# Each string appears once, in input order, followed by the linker's own.
+# CHECK: String dump of section '.comment':
+# CHECK-NEXT: GCC: (GNU) 12.2.0
+# CHECK-NEXT: clang version 17.0.6
+# CHECK-NEXT: uld {{[0-9]+\.[0-9]+\.[0-9]+}}
+# CHECK-NOT: GCC
+
+# HEADER: .comment PROGBITS {{.*}} 01 MS 0 0 1
+
+# NOIDENT: String dump of section '.comment':
+# NOIDENT-NEXT: GCC: (GNU) 12.2.0
+# NOIDENT-NEXT: clang version 17.0.6
+# NOIDENT-NOT: uld
+
+# Without any strings there is no .comment at all.
+# NONE-NOT: .comment
+
+.ifndef BARE
+.section .comment,"MS",@progbits,1
+    .asciz "GCC: (GNU) 12.2.0"
+.ifdef SECOND
+    .asciz "clang version 17.0.6"
+.endif
+.endif
+
+.ifndef SECOND
+.text
+.global _start
+_start:
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
+.endif
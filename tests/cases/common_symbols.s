@@ -0,0 +1,51 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym OTHER=1 %s -o %t.other.o
+# RUN: %uld -o %t %t.o %t.other.o
+# RUN: %readelf -S %t | %filecheck %s
+# RUN: %t; test $? -eq 42
+# RUN: %uld -o %t.start %t.o %t.other.o --section-start=.bss=0x500010 2>&1 | %filecheck %s --check-prefix=START
+
+# Tentative definitions are allocated in .bss: counter (4 bytes) first, then
+# buf merged to its largest size (64) and alignment (32).
+# CHECK: .bss NOBITS
+# CHECK-NEXT: 0000000000000060 0000000000000000 WA 0 0 32
+
+# .bss takes the alignment of its most aligned common symbol.
+# START: address 0x500010 of section .bss is not a multiple of its alignment (32)
+
+.ifdef OTHER
+.comm buf,64,32
+
+# A real definition wins over the tentative one in the first object.
+.data
+.global init
+init:
+    .long 7
+.else
+
+# Exit with counter (5) + init (7) + the absolute abs_val (30), or 1 if buf
+# is not 32-byte aligned.
+.text
+.global _start
+_start:
+    movl $5, counter(%rip)
+    lea buf(%rip), %rax
+    test $31, %al
+    jnz 1f
+    mov counter(%rip), %edi
+    add init(%rip), %edi
+    add $abs_val, %edi
+    mov $60, %eax
+    syscall
+1:
+    mov $1, %edi
+    mov $60, %eax
+    syscall
+
+.comm counter,4,4
+.comm buf,8,8
+.comm init,4,4
+
+.global abs_val
+.set abs_val, 30
+.endif
@@ -0,0 +1,19 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -m64 -nostartfiles -nodefaultlibs -static -fuse-ld=lld -Wl,--defsym,answer=42 %t.o -o %t
+# RUN: %t; test $? -eq 42
+# RUN: not %uld -m64 -nostartfiles -nodefaultlibs -static -Wl,--no-such-flag %t.o -o %t.bad 2>&1 | %filecheck %s
+# RUN: not env ULD_CC=false %uld -m64 -nostartfiles %t.o -o %t.cc
+# RUN: %uld -o %t.ld %t.o --defsym answer=42
+# RUN: %t.ld; test $? -eq 42
+
+# A compiler driver's line, as rustc sends to `linker = "uld"`, is handed
+# to the compiler with uld standing in for its ld: the -Wl, flags reach
+# the link, -fuse-ld= is dropped, and $ULD_CC picks the compiler.
+# CHECK: unknown flag: --no-such-flag
+
+.text
+.global _start
+_start:
+    mov $answer, %edi
+    mov $60, %eax
+    syscall
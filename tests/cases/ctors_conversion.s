@@ -0,0 +1,60 @@
+# RUN: %as --defsym BEGIN=1 %s -o %t.begin.o
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym END=1 %s -o %t.end.o
+# RUN: %uld -o %t %t.begin.o %t.o %t.end.o
+# RUN: %readelf -S %t | %filecheck %s
+# RUN: %t; test $? -eq 27
+
+# The -1 and 0 sentinels are dropped, so only the three constructors remain.
+# CHECK: .init_array INIT_ARRAY
+# CHECK-NEXT: 0000000000000018 0000000000000008 WA
+# CHECK-NOT: .ctors
+
+# Like crtbegin.o / crtend.o, the outer objects only bracket .ctors.
+.ifdef BEGIN
+.section .ctors,"aw",@progbits
+    .quad -1
+.else
+.ifdef END
+.section .ctors,"aw",@progbits
+    .quad 0
+.else
+
+# _start runs every constructor between __init_array_start and
+# __init_array_end. Each one folds a digit into %ebx, so the exit code
+# encodes the order: .ctors.65435 (priority 100) runs first (1), then the
+# unsuffixed .ctors entries in reverse (2, 3), giving ((1 * 4) + 2) * 4 + 3 = 27.
+.text
+.global _start
+_start:
+    xor %ebx, %ebx
+    lea __init_array_start(%rip), %r12
+    lea __init_array_end(%rip), %r13
+1:
+    cmp %r13, %r12
+    je 2f
+    call *(%r12)
+    add $8, %r12
+    jmp 1b
+2:
+    mov %ebx, %edi
+    mov $60, %eax
+    syscall
+
+ctor_a:
+    lea 3(,%rbx,4), %ebx
+    ret
+ctor_b:
+    lea 2(,%rbx,4), %ebx
+    ret
+ctor_100:
+    lea 1(,%rbx,4), %ebx
+    ret
+
+.section .ctors,"aw",@progbits
+    .quad ctor_a
+    .quad ctor_b
+.section .ctors.65435,"aw",@progbits
+    .quad ctor_100
+.endif
+.endif
@@ -0,0 +1,32 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld --check-sections -shared -z relro -o %t.so %t.o
+# RUN: %readelf -SlW %t.so | %filecheck %s
+
+# .data.rel.ro (vtables and the like) gets its own output section next to
+# the GOT, inside PT_GNU_RELRO, while plain .data stays outside it.
+# CHECK: .data.rel.ro PROGBITS
+# CHECK-NEXT: .got
+# CHECK-NEXT: .got.plt
+# CHECK-NEXT: .dynamic
+# CHECK-NEXT: .data PROGBITS
+# CHECK: GNU_RELRO
+# CHECK: Section to Segment mapping:
+# CHECK: 03 .data.rel.ro .got.plt .dynamic
+
+.text
+.global f
+f:
+    ret
+
+.section .data.rel.ro,"aw"
+.global vtable
+vtable:
+    .quad f
+
+.section .data.rel.ro.local,"aw"
+    .quad f
+
+.data
+.global counter
+counter:
+    .quad 0
@@ -0,0 +1,38 @@
+# RUN: rm -rf %t.root && mkdir -p %t.root/usr/lib %t.root/opt/mylib %t.root/etc/ld.so.conf.d
+# RUN: %as %s -o %t.o
+# RUN: %as %libone -o %t.one.o
+# RUN: %as %getvalue -o %t.value.o
+# RUN: %uld -shared -soname libone.so -o %t.root/usr/lib/libone.so %t.one.o
+# RUN: %uld -shared -soname libvalue.so -o %t.root/opt/mylib/libvalue.so %t.value.o
+# RUN: echo 'include /etc/ld.so.conf.d/*.conf' > %t.root/etc/ld.so.conf
+# RUN: echo '/opt/mylib # site libraries' > %t.root/etc/ld.so.conf.d/site.conf
+# RUN: %uld --sysroot=%t.root -o %t %t.o -lone -lvalue
+# RUN: %readelf -d %t | %filecheck %s
+# RUN: mkdir -p %t.root/usr/lib/i386-linux-gnu %t.root/usr/lib64
+# RUN: echo '.globl thirty; .set thirty, 30' | %as --32 -o %t.thirty32.o
+# RUN: echo '.globl thirty; .set thirty, 30' | %as -o %t.thirty64.o
+# RUN: rm -f %t.root/usr/lib/i386-linux-gnu/libthirty.a %t.root/usr/lib64/libthirty.a
+# RUN: ar rcs %t.root/usr/lib/i386-linux-gnu/libthirty.a %t.thirty32.o
+# RUN: ar rcs %t.root/usr/lib64/libthirty.a %t.thirty64.o
+# RUN: echo '.globl _start; _start: .long thirty' | %as --32 -o %t.main32.o
+# RUN: %uld --sysroot=%t.root -m elf_i386 -o %t.32 %t.main32.o -lthirty
+# RUN: echo '.globl _start; _start: .long thirty' | %as -o %t.main64.o
+# RUN: %uld --sysroot=%t.root -o %t.64 %t.main64.o -lthirty
+# RUN: not %uld --sysroot=%t.root --no-default-libs -o %t.none %t.o -lone 2>&1 | %filecheck %s --check-prefix=NONE
+
+# Built-in directories come first, then those from ld.so.conf.
+# CHECK: (NEEDED) Shared library: [libone.so]
+# CHECK: (NEEDED) Shared library: [libvalue.so]
+
+# NONE: -lone not found
+
+# The built-in directories follow the emulation: -m elf_i386 finds the
+# i386 libthirty.a, not the x86_64 one in /usr/lib64.
+
+.text
+.global _start
+_start:
+    call *one@GOTPCREL(%rip)
+    call *get_value@GOTPCREL(%rip)
+    mov $60, %eax
+    syscall
@@ -0,0 +1,40 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o --defsym magic=0x20 --defsym=handler=add_ten --defsym base=2
+# RUN: %t; test $? -eq 44
+# RUN: %uld -o %t.map %t.o --defsym magic=32 --defsym handler=add_ten --defsym base=2 --print-map | %filecheck %s
+# RUN: %uld -o %t.chain %t.o --defsym magic=outer --defsym outer=inner --defsym inner=0x20 --defsym handler=add_ten --defsym base=2
+# RUN: %t.chain; test $? -eq 44
+# RUN: not %uld -o %t.bad %t.o --defsym magic=base --defsym base=magic --defsym handler=add_ten 2>&1 | %filecheck %s --check-prefix=CYCLE
+# RUN: not %uld -o %t.bad %t.o --defsym handler=nowhere 2>&1 | %filecheck %s --check-prefix=BAD
+# RUN: not %uld -o %t.bad %t.o --defsym magic 2>&1 | %filecheck %s --check-prefix=FORMAT
+
+# CHECK: Symbols
+# CHECK: 0x2 base
+# CHECK: 0x20 magic
+# CHECK: 0x401017 add_ten
+# CHECK-NEXT: 0x401017 handler
+
+# Aliases take their targets' values whatever order they are given in.
+# CYCLE: symbols are defined in terms of each other: base, magic
+
+# BAD: symbol handler is defined as undefined symbol nowhere
+# FORMAT: --defsym magic: expected NAME=VALUE
+
+# magic (32) is only known from the command line, handler is an alias of
+# add_ten, and base replaces the input's definition of 100: 32 + 10 + 2 = 44.
+.text
+.global _start
+_start:
+    mov $magic, %edi
+    call handler
+    add $base, %edi
+    mov $60, %eax
+    syscall
+
+.global add_ten
+add_ten:
+    add $10, %edi
+    ret
+
+.global base
+.set base, 100
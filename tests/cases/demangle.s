@@ -0,0 +1,40 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym REF=1 %s -o %t.ref.o
+# RUN: not %uld -shared -z defs -o %t.so %t.o %t.ref.o 2>&1 | %filecheck %s
+# RUN: not %uld -shared -z defs --no-demangle -o %t.so %t.o %t.ref.o 2>&1 | %filecheck %s --check-prefix=RAW
+# RUN: not %uld -shared -o %t.so %t.o %t.o 2>&1 | %filecheck %s --check-prefix=DUP
+
+# Undefined references are shown demangled, with the definition that was
+# probably meant: a typo, another overload, or a missing extern "C".
+# CHECK: undefined symbols (-z defs):
+# CHECK-NEXT: foo(long) (referenced by {{.*}}ref.o)
+# CHECK-NEXT: did you mean: foo(int) (defined in {{.*}}.tmp.o)
+# CHECK-NEXT: bar (referenced by {{.*}}ref.o)
+# CHECK-NEXT: did you mean to declare bar(int) as extern "C"? (defined in {{.*}}.tmp.o)
+# CHECK-NEXT: counter (referenced by {{.*}}ref.o)
+# CHECK-NEXT: did you mean: Counter (defined in {{.*}}.tmp.o)
+
+# RAW: _Z3fool (referenced by {{.*}}ref.o)
+# RAW-NEXT: did you mean: _Z3fooi
+
+# DUP: multiple definition of `foo(int)`
+
+.ifdef REF
+.data
+    .quad _Z3fool
+    .quad bar
+    .quad counter
+.else
+.text
+.global _Z3fooi
+_Z3fooi:
+    ret
+.global _Z3bari
+_Z3bari:
+    ret
+
+.data
+.global Counter
+Counter:
+    .quad 0
+.endif
@@ -0,0 +1,29 @@
+# RUN: rm -rf %t.dir && mkdir -p %t.dir
+# RUN: %as %s -o %t.dir/main.o
+# RUN: printf '.globl helper\nhelper: ret\n' | %as -o %t.dir/helper.o
+# RUN: ar rcs %t.dir/libhelper.a %t.dir/helper.o
+# RUN: echo 'GROUP ( libhelper.a )' > %t.dir/libstub.a
+# RUN: echo '{ global: _start; local: *; };' > %t.dir/exports.map
+# RUN: echo '%t.dir/main.o' > %t.dir/args.rsp
+# RUN: %uld -o %t.dir/out @%t.dir/args.rsp -L%t.dir -lstub --version-script=%t.dir/exports.map --dependency-file=%t.dir/out.d
+# RUN: %filecheck %s < %t.dir/out.d
+
+# Response files and option files come first, then inputs in the order they
+# were opened: the stub before the archive it names.
+# CHECK: {{.*}}/out: \
+# CHECK-NEXT: {{.*}}/args.rsp \
+# CHECK-NEXT: {{.*}}/exports.map \
+# CHECK-NEXT: {{.*}}/main.o \
+# CHECK-NEXT: {{.*}}/libstub.a \
+# CHECK-NEXT: {{.*}}/libhelper.a
+# CHECK-EMPTY:
+# CHECK-NEXT: {{.*}}/args.rsp:
+# CHECK: {{.*}}/libhelper.a:
+
+.text
+.global _start
+_start:
+    call helper
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
@@ -0,0 +1,24 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym REF=1 %s -o %t.ref.o
+# RUN: not %uld -shared -z defs --diagnostics-format=json -o %t.so %t.o %t.ref.o 2>&1 | %filecheck %s
+# RUN: not %uld -shared --diagnostics-format=json -o %t.so %t.o %t.o 2>&1 | %filecheck %s --check-prefix=DUP
+# RUN: not %uld --diagnostics-format=yaml -o %t.so %t.o 2>&1 | %filecheck %s --check-prefix=BAD
+
+# Each undefined reference is one JSON line, located at its first use.
+# CHECK: {"file":"{{.*}}ref.o","kind":"undefined-symbol","message":"undefined symbol: helper","offset":8,"section":".data","severity":"error","suggestion":"did you mean: Helper (defined in {{.*}}.tmp.o)","symbol":"helper"}
+# CHECK-NEXT: {"file":"{{.*}}ref.o","kind":"undefined-symbol","message":"undefined symbol: missing","offset":0,"section":".data","severity":"error","symbol":"missing"}
+
+# DUP: {"file":"{{.*}}.tmp.o","kind":"multiple-definition","message":"multiple definition of `Helper`: first defined in {{.*}}, again in {{.*}}","severity":"error","symbol":"Helper"}
+
+# BAD: --diagnostics-format: unknown format yaml (expected text or json)
+
+.ifdef REF
+.data
+    .quad missing
+    .quad helper
+.else
+.text
+.global Helper
+Helper:
+    ret
+.endif
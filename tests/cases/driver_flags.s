@@ -0,0 +1,26 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -plugin /nonexistent/liblto_plugin.so -plugin-opt=-pass-through=-lc \
+# RUN:   --build-id --eh-frame-hdr -m elf_x86_64 --hash-style=gnu --as-needed \
+# RUN:   -static -z now -z relro -o %t.exe %t.o --start-group --end-group
+# RUN: %t.exe || echo "Exit: $?" | %filecheck %s
+# RUN: not %uld --no-such-flag -o %t.exe %t.o 2>&1 | %filecheck %s --check-prefix=UNKNOWN
+# RUN: %uld --ignore-unknown-flags --no-such-flag -o %t.exe %t.o
+# RUN: %uld --gc-sections --eh-frame-hdr --build-id -o %t.exe %t.o 2>&1 | %filecheck %s --check-prefix=IGNORED
+# RUN: %uld --gc-sections --no-gc-sections --build-id=none -o %t.exe %t.o 2>&1 | %filecheck %s --check-prefix=OFF --allow-empty
+
+# CHECK: Exit: 42
+# UNKNOWN: unknown flag: --no-such-flag
+
+# Accepted flags that would change the output say they are ignored.
+# IGNORED: --gc-sections: not supported, ignored
+# IGNORED: --eh-frame-hdr: not supported, ignored
+# IGNORED: --build-id: not supported, ignored
+# OFF-NOT: not supported
+
+# Flags commonly passed by compiler drivers are accepted and their values
+# are never mistaken for input files.
+.global _start
+_start:
+    mov $60, %rax
+    mov $42, %rdi
+    syscall
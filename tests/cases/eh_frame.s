@@ -0,0 +1,50 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym OTHER=1 %s -o %t.other.o
+# RUN: %uld -M -o %t %t.o %t.other.o | %filecheck %s
+# RUN: %readelf -SW %t | %filecheck %s --check-prefix=SECTIONS
+
+# Unwind and exception tables get output sections of their own, so the
+# frame descriptions of every input stay together rather than mixed in
+# with read-only data.
+# CHECK: .rodata
+# CHECK-NEXT: .rodata {{.*}}eh_frame.s.tmp.o
+# CHECK-NEXT: .rodata {{.*}}eh_frame.s.tmp.other.o
+# CHECK-NEXT: .eh_frame
+# CHECK-NEXT: .eh_frame {{.*}}eh_frame.s.tmp.o
+# CHECK-NEXT: .eh_frame {{.*}}eh_frame.s.tmp.other.o
+# CHECK-NEXT: .gcc_except_table
+# CHECK-NEXT: .gcc_except_table.main {{.*}}eh_frame.s.tmp.o
+
+# SECTIONS: .rodata
+# SECTIONS-NEXT: .eh_frame
+# SECTIONS-NEXT: .gcc_except_table
+
+.ifdef OTHER
+.text
+.global helper
+.type helper, @function
+helper:
+    .cfi_startproc
+    ret
+    .cfi_endproc
+
+.section .rodata,"a"
+    .quad 2
+.else
+.text
+.global _start
+.type _start, @function
+_start:
+    .cfi_startproc
+    call helper
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
+    .cfi_endproc
+
+.section .rodata,"a"
+    .quad 1
+
+.section .gcc_except_table.main,"a"
+    .byte 0xff
+.endif
@@ -0,0 +1,46 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld --emit-relocs -o %t.exe %t.o
+# RUN: %readelf -S -r %t.exe | %filecheck %s
+# RUN: %t.exe || echo "Exit: $?" | %filecheck %s --check-prefix=EXIT
+# RUN: %as %s -o %t.got.o --defsym GOT=1
+# RUN: %uld --emit-relocs --no-relax -o %t.got.exe %t.got.o
+# RUN: %readelf -r %t.got.exe | %filecheck %s --check-prefix=GOT
+# RUN: %t.got.exe || echo "Exit: $?" | %filecheck %s --check-prefix=EXIT
+
+# CHECK: .rela.text RELA
+# CHECK: .symtab SYMTAB
+# CHECK: Relocation section '.rela.text'
+# CHECK-NEXT: Offset
+# CHECK-NEXT: 000000401001 {{.*}} R_X86_64_PLT32 {{0*}}401014 helper - 4
+# CHECK-NEXT: 000000401007 {{.*}} R_X86_64_PC32 {{0*}}402000 - 4
+
+# EXIT: Exit: 42
+
+# A GOT load of a local keeps referring to the local, not to its GOT slot.
+# GOT: Relocation section '.rela.text'
+# GOT: R_X86_64_REX_GOTP {{0*}}402000 - 4
+
+# Relocations are kept at their output addresses; the local reference to
+# .rodata is rewritten against the output section symbol.
+.section .rodata
+value:
+    .long 40
+
+.text
+.global _start
+_start:
+    call helper
+.ifdef GOT
+    movq value@GOTPCREL(%rip), %rcx
+    add (%rcx), %eax
+.else
+    add value(%rip), %eax
+.endif
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
+
+.global helper
+helper:
+    mov $2, %eax
+    ret
@@ -0,0 +1,49 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym EXTRA=1 %s -o %t.extra.o
+# RUN: %uld -o %t %t.o %t.extra.o
+# RUN: %readelf -S %t | %filecheck %s
+# RUN: %t; test $? -eq 42
+
+# Both objects' my_set sections land in one output section of that name.
+# CHECK: .data PROGBITS
+# CHECK: my_set PROGBITS
+# CHECK-NEXT: 0000000000000018 0000000000000000 WA
+
+.ifdef EXTRA
+.section my_set,"aw",@progbits
+    .quad 12
+.else
+
+# _start sums every entry between __start_my_set and __stop_my_set
+# (10 + 20 + 12), and checks that the weak reference to a section nothing
+# defines stays 0.
+.text
+.global _start
+_start:
+    xor %edi, %edi
+    lea __start_my_set(%rip), %rax
+    lea __stop_my_set(%rip), %rcx
+1:
+    cmp %rcx, %rax
+    je 2f
+    add (%rax), %rdi
+    add $8, %rax
+    jmp 1b
+2:
+    mov $__start_missing, %eax
+    test %rax, %rax
+    jz 3f
+    mov $1, %edi
+3:
+    mov $60, %eax
+    syscall
+
+.weak __start_missing
+
+.data
+    .quad 99
+
+.section my_set,"aw",@progbits
+    .quad 10
+    .quad 20
+.endif
@@ -0,0 +1,21 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o -e other
+# RUN: %t; test $? -eq 7
+# RUN: %uld -o %t.long %t.o --entry=other
+# RUN: %t.long; test $? -eq 7
+# RUN: %uld -o %t.missing %t.o -e nowhere 2>&1 | %filecheck %s
+
+# CHECK: cannot find entry symbol nowhere; not setting start address
+
+.text
+.global _start
+_start:
+    mov $42, %edi
+    mov $60, %eax
+    syscall
+
+.global other
+other:
+    mov $7, %edi
+    mov $60, %eax
+    syscall
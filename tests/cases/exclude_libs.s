@@ -0,0 +1,25 @@
+# RUN: rm -rf %t.dir && mkdir -p %t.dir
+# RUN: %as %s -o %t.o
+# RUN: %as %getvalue -o %t_get_value.o
+# RUN: ar rcs %t.dir/libvalue.a %t_get_value.o
+# RUN: %uld -shared -o %t.so %t.o %t.dir/libvalue.a
+# RUN: %readelf --dyn-syms %t.so | %filecheck %s
+# RUN: %uld -shared --exclude-libs=libvalue.a -o %t.named.so %t.o %t.dir/libvalue.a
+# RUN: %readelf --dyn-syms %t.named.so | %filecheck %s --check-prefix=EXCLUDED
+# RUN: %uld -shared --exclude-libs=libother.a:ALL -o %t.all.so %t.o %t.dir/libvalue.a
+# RUN: %readelf --dyn-syms %t.all.so | %filecheck %s --check-prefix=EXCLUDED
+# RUN: %uld -shared --exclude-libs=libother.a -o %t.other.so %t.o %t.dir/libvalue.a
+# RUN: %readelf --dyn-syms %t.other.so | %filecheck %s
+
+# CHECK: GLOBAL DEFAULT {{[0-9]+}} get_value
+# CHECK: GLOBAL DEFAULT {{[0-9]+}} plugin_init
+
+# Archive members still link in, but their symbols stay out of the ABI.
+# EXCLUDED-NOT: get_value
+# EXCLUDED: GLOBAL DEFAULT {{[0-9]+}} plugin_init
+# EXCLUDED-NOT: get_value
+
+.text
+.global plugin_init
+plugin_init:
+    jmp get_value
@@ -0,0 +1,12 @@
+# RUN: %as %s -o %t.o
+# RUN: not %uld --execute-only -o %t.exe %t.o 2>&1 | %filecheck %s --check-prefix=FLAG
+# RUN: not %uld -z execute-only -o %t.exe %t.o 2>&1 | %filecheck %s
+
+# FLAG: unknown flag: --execute-only
+# CHECK: -z execute-only: not supported, text is always readable
+
+.global _start
+_start:
+    mov $60, %rax
+    mov $0, %rdi
+    syscall
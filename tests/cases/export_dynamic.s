@@ -0,0 +1,41 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o
+# RUN: %readelf -S %t | %filecheck %s --check-prefix=NONE
+# RUN: %uld -E -o %t.all %t.o
+# RUN: %readelf --dyn-syms %t.all | %filecheck %s --check-prefix=ALL
+# RUN: %uld --export-dynamic-symbol='plugin_*' -o %t.glob %t.o
+# RUN: %readelf --dyn-syms %t.glob | %filecheck %s --check-prefix=GLOB
+# RUN: echo '{ /* callbacks */ helper; };' > %t.list
+# RUN: %uld --dynamic-list=%t.list -o %t.list.out %t.o
+# RUN: %readelf --dyn-syms %t.list.out | %filecheck %s --check-prefix=LIST
+# RUN: %t.all; test $? -eq 42
+
+# Executables have no dynamic symbols unless asked for.
+# NONE-NOT: .dynsym
+
+# ALL: GLOBAL DEFAULT {{[0-9]+}} _start
+# ALL: GLOBAL DEFAULT {{[0-9]+}} helper
+# ALL: GLOBAL DEFAULT {{[0-9]+}} plugin_init
+
+# GLOB: Symbol table '.dynsym' contains 2 entries
+# GLOB: GLOBAL DEFAULT {{[0-9]+}} plugin_init
+
+# LIST: Symbol table '.dynsym' contains 2 entries
+# LIST: GLOBAL DEFAULT {{[0-9]+}} helper
+
+.text
+.global _start
+_start:
+    call helper
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
+
+.global helper
+helper:
+    mov $42, %eax
+    ret
+
+.global plugin_init
+plugin_init:
+    ret
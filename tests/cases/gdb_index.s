@@ -0,0 +1,85 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym SECOND=1 %s -o %t.second.o
+# RUN: %uld --gdb-index -o %t %t.o %t.second.o
+# RUN: %readelf --debug-dump=gdb_index %t | %filecheck %s
+# RUN: %t
+# RUN: %uld --gdb-index --no-gdb-index -o %t.none %t.o %t.second.o
+# RUN: %readelf -SW %t.none | %filecheck %s --check-prefix=NONE
+# RUN: %uld --gdb-index -S -o %t.none %t.o %t.second.o
+# RUN: %readelf -SW %t.none | %filecheck %s --check-prefix=NONE
+
+# Each unit is listed with its offset and length in the merged
+# .debug_info, its code range, and the names it defines at the top level.
+# CHECK: Version 7
+# CHECK: CU table:
+# CHECK: [ 0] 0 - 0x{{.*}}
+# CHECK: [ 1] 0x{{.*}}
+# CHECK: Address table:
+# CHECK: 0000000000401000 000000000040100e 0
+# CHECK: 000000000040100e 000000000040100f 1
+# CHECK: Symbol table:
+# CHECK-DAG: _start: 0 [global, function]
+# CHECK-DAG: second: 1 [global, function]
+# CHECK-DAG: counter: 1 [static, variable]
+# CHECK-DAG: int:
+# CHECK-DAG: 0 [static, type]
+# CHECK-DAG: 1 [static, type]
+
+# NONE-NOT: .gdb_index
+
+.ifdef SECOND
+.text
+.global second
+second:
+    ret
+end:
+.else
+.text
+.global _start
+_start:
+    call second
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
+end:
+.endif
+
+.section .debug_abbrev,"",@progbits
+abbrev:
+    .uleb128 1, 0x11, 1         # DW_TAG_compile_unit, has children
+    .uleb128 0x11, 0x01         # DW_AT_low_pc, DW_FORM_addr
+    .uleb128 0x12, 0x07         # DW_AT_high_pc, DW_FORM_data8
+    .uleb128 0, 0
+    .uleb128 2, 0x2e, 0         # DW_TAG_subprogram
+    .uleb128 0x03, 0x08         # DW_AT_name, DW_FORM_string
+    .uleb128 0x3f, 0x19         # DW_AT_external, DW_FORM_flag_present
+    .uleb128 0, 0
+    .uleb128 3, 0x34, 0         # DW_TAG_variable
+    .uleb128 0x03, 0x08         # DW_AT_name, DW_FORM_string
+    .uleb128 0, 0
+    .uleb128 4, 0x24, 0         # DW_TAG_base_type
+    .uleb128 0x03, 0x08         # DW_AT_name, DW_FORM_string
+    .uleb128 0, 0
+    .uleb128 0
+
+.section .debug_info,"",@progbits
+    .long info_end - info_start
+info_start:
+    .short 4
+    .long abbrev
+    .byte 8
+    .uleb128 1
+    .quad .text
+    .quad end - .text
+    .uleb128 2
+.ifdef SECOND
+    .asciz "second"
+    .uleb128 3
+    .asciz "counter"
+.else
+    .asciz "_start"
+.endif
+    .uleb128 4
+    .asciz "int"
+    .byte 0
+info_end:
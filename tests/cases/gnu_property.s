@@ -0,0 +1,58 @@
+# RUN: %as --defsym FEATURES=3 %s -o %t.both.o
+# RUN: %as --defsym FEATURES=1 --defsym HELPER=1 %s -o %t.ibt.o
+# RUN: %as --defsym HELPER=1 %s -o %t.none.o
+# RUN: %uld -o %t %t.both.o
+# RUN: %readelf -lnW %t | %filecheck %s
+# RUN: %t
+# RUN: %uld -o %t.ibt %t.both.o %t.ibt.o
+# RUN: %readelf -n %t.ibt | %filecheck %s --check-prefix=IBT
+# RUN: %uld -o %t.none %t.both.o %t.none.o
+# RUN: %readelf -lnW %t.none | %filecheck %s --check-prefix=NONE
+# RUN: %uld -z shstk -o %t.shstk %t.both.o %t.ibt.o
+# RUN: %readelf -n %t.shstk | %filecheck %s --check-prefix=BOTH
+# RUN: %uld -z force-ibt -o %t.force %t.both.o %t.none.o 2>&1 | %filecheck %s --check-prefix=FORCE
+# RUN: %readelf -n %t.force | %filecheck %s --check-prefix=IBT
+
+# The inputs' notes are merged into one, found through PT_GNU_PROPERTY.
+# CHECK: NOTE {{.*}} R 0x8
+# CHECK-NEXT: GNU_PROPERTY {{.*}} R 0x8
+# CHECK: Properties: x86 feature: IBT, SHSTK
+
+# The output only has the features every input has.
+# IBT: x86 feature: IBT{{$}}
+
+# An input without the note has none of them, and no note is written.
+# NONE-NOT: GNU_PROPERTY
+# NONE-NOT: Properties
+
+# -z shstk and -z force-ibt turn a feature on whatever the inputs say;
+# -z force-ibt names the inputs built without IBT.
+# BOTH: x86 feature: IBT, SHSTK
+# FORCE: {{.*}}gnu_property.s.tmp.none.o: -z force-ibt: file does not have GNU_PROPERTY_X86_FEATURE_1_IBT property
+# FORCE-NOT: tmp.both.o
+
+.text
+.ifdef HELPER
+.global helper
+helper:
+    ret
+.else
+.global _start
+_start:
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
+.endif
+
+.ifdef FEATURES
+.section .note.gnu.property,"a",@note
+.p2align 3
+.long 4
+.long 16
+.long 5             # NT_GNU_PROPERTY_TYPE_0
+.asciz "GNU"
+.long 0xc0000002    # GNU_PROPERTY_X86_FEATURE_1_AND
+.long 4
+.long FEATURES
+.p2align 3
+.endif
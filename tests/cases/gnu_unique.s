@@ -0,0 +1,31 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym COPY=1 %s -o %t.copy.o
+# RUN: %uld -E -o %t.exe %t.o %t.copy.o
+# RUN: %readelf -h %t.exe | %filecheck %s --check-prefix=HEADER
+# RUN: %readelf --dyn-syms %t.exe | %filecheck %s
+# RUN: %t.exe; test $? -eq 5
+
+# Every object with an inline variable carries a copy; the first one wins
+# instead of clashing.
+# CHECK: UNIQUE DEFAULT {{[0-9]+}} counter
+
+# HEADER: OS/ABI: UNIX - GNU
+
+.data
+.type counter, @gnu_unique_object
+.global counter
+counter:
+.ifdef COPY
+    .long 9
+.else
+    .long 5
+.endif
+
+.ifndef COPY
+.text
+.global _start
+_start:
+    mov counter(%rip), %edi
+    mov $60, %eax
+    syscall
+.endif
@@ -0,0 +1,89 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym DESC=1 %s -o %t.desc.o
+# RUN: %as --defsym IFUNC=1 %s -o %t.ifunc.o
+# RUN: %uld -o %t %t.o
+# RUN: %readelf -SW -x .got %t | %filecheck %s
+# RUN: llvm-objdump -d %t | %filecheck %s --check-prefix=BASE
+# RUN: not %uld -o %t.desc %t.desc.o 2>&1 | %filecheck %s --check-prefix=STATICDESC
+# RUN: %uld --check-sections -shared -o %t.desc.so %t.desc.o
+# RUN: %readelf -rW %t.desc.so | %filecheck %s --check-prefix=DESCRIPTOR
+# RUN: not %uld -o %t.ifunc %t.ifunc.o 2>&1 | %filecheck %s --check-prefix=STATICIFUNC
+# RUN: %uld -shared -o %t.ifunc.so %t.ifunc.o
+# RUN: %readelf -SW -rW -x .got.plt %t.ifunc.so | %filecheck %s --check-prefix=SHARED
+# RUN: %as --defsym CALL=1 %s -o %t.call.o
+# RUN: not %uld -shared -o %t.call.so %t.ifunc.o %t.call.o 2>&1 | %filecheck %s --check-prefix=DIRECT
+
+# Addresses and TLS offsets go in .got; .got.plt only holds its three
+# reserved words, and is where _GLOBAL_OFFSET_TABLE_ points.
+# CHECK: .got PROGBITS 0000000000402000 {{[0-9a-f]+}} 000008
+# CHECK-NEXT: .got.plt PROGBITS 0000000000403000 {{[0-9a-f]+}} 000018
+# CHECK-NEXT: .tdata PROGBITS
+
+# `b` is 4 bytes into an 8-byte TLS block that ends at the thread pointer.
+# CHECK: Hex dump of section '.got':
+# CHECK-NEXT: 0x00402000 fcffffff ffffffff
+
+# BASE: leaq {{.*}} # 0x403000
+
+# STATICDESC: TLS descriptor for `b' needs a dynamic output
+
+# A TLS descriptor takes two words of .got and a TLSDESC relocation.
+# DESCRIPTOR: R_X86_64_TLSDESC 4
+
+# STATICIFUNC: GOT entry for ifunc `pick' needs a dynamic output
+
+# In a shared object the loader fills the initial-exec offset, and the ifunc
+# slot after the reserved words of .got.plt, the first of which holds the
+# address of .dynamic.
+# SHARED: .got PROGBITS 0000000000006000
+# SHARED-NEXT: .got.plt PROGBITS 0000000000007000 {{[0-9a-f]+}} 000020
+# SHARED-NEXT: .dynamic DYNAMIC 0000000000008000
+# SHARED: 0000000000006000 {{.*}} R_X86_64_TPOFF64 4
+# SHARED-NEXT: 0000000000007018 {{.*}} R_X86_64_IRELATIVE
+# SHARED: Hex dump of section '.got.plt':
+# SHARED-NEXT: 0x00007000 00800000 00000000 00000000 00000000
+
+# A direct call would reach the resolver rather than what it picks.
+# DIRECT: direct reference to ifunc `pick': uld only supports calling ifuncs through the GOT
+
+.ifdef CALL
+.text
+.global caller
+caller:
+    call pick@PLT
+    ret
+.else
+.ifdef DESC
+.text
+.global _start
+_start:
+    leaq b@tlsdesc(%rip), %rax
+    call *b@tlscall(%rax)
+    ret
+.else
+.text
+.global _start
+_start:
+    movq b@gottpoff(%rip), %rax
+    movl %fs:(%rax), %eax
+    leaq _GLOBAL_OFFSET_TABLE_(%rip), %rcx
+.ifdef IFUNC
+    movq pick@GOTPCREL(%rip), %rdx
+.endif
+    ret
+.endif
+
+.ifdef IFUNC
+.type pick, @gnu_indirect_function
+.global pick
+pick:
+    ret
+.endif
+
+.section .tdata,"awT",@progbits
+.global a, b
+a:
+    .long 1
+b:
+    .long 2
+.endif
@@ -0,0 +1,49 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym GLOBAL=1 %s -o %t.global.o
+# RUN: %uld --no-relax -o %t %t.o %t.global.o
+# RUN: %readelf -x .got %t | %filecheck %s
+# RUN: %t; test $? -eq 42
+
+# Locals get GOT entries of their own, even next to globals of the same
+# name in another file: `value` loads 42, not 7, and the first object's
+# `counter` is at the start of the 8-byte TLS block, 8 bytes below the
+# thread pointer, while the global one is 4 bytes below it.
+# CHECK: Hex dump of section '.got':
+# CHECK-NEXT: 0x{{[0-9a-f]+}} f8ffffff ffffffff 00204000 00000000
+# CHECK-NEXT: 0x{{[0-9a-f]+}} 04204000 00000000 fcffffff ffffffff
+
+.ifdef GLOBAL
+.text
+.global other
+other:
+    movq value@GOTPCREL(%rip), %rax
+    movq counter@gottpoff(%rip), %rax
+    ret
+
+.data
+.global value
+value:
+    .long 7
+
+.section .tdata,"awT",@progbits
+.global counter
+counter:
+    .long 100
+.else
+.text
+.global _start
+_start:
+    movq counter@gottpoff(%rip), %rax
+    movq value@GOTPCREL(%rip), %rbx
+    movl (%rbx), %edi
+    mov $60, %eax
+    syscall
+
+.data
+value:
+    .long 42
+
+.section .tdata,"awT",@progbits
+counter:
+    .long 2
+.endif
@@ -0,0 +1,46 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o
+# RUN: objdump -d --no-show-raw-insn %t | %filecheck %s
+# RUN: %readelf -SW %t | %filecheck %s --check-prefix=GOT
+# RUN: %t; test $? -eq 42
+
+# GOT loads of symbols the output defines become direct references.
+# CHECK: lea {{.*}}(%rip),%rbx
+# CHECK-NEXT: addr32 call
+# CHECK: add {{.*}}(%rip),%rcx
+# CHECK: nop
+# CHECK-NEXT: jmp
+
+# An add through the GOT can't be relaxed, so `value` keeps its entry.
+# GOT: .got PROGBITS {{[0-9a-f]+}} {{[0-9a-f]+}} 000008
+
+.text
+.global _start
+_start:
+    movq value@GOTPCREL(%rip), %rbx
+    call *get@GOTPCREL(%rip)
+    xorl %ecx, %ecx
+    addq value@GOTPCREL(%rip), %rcx
+    cmpq %rbx, %rcx
+    jne 1f
+    jmp *finish@GOTPCREL(%rip)
+1:
+    mov $1, %edi
+    mov $60, %eax
+    syscall
+
+.global get
+get:
+    movl (%rbx), %eax
+    ret
+
+.global finish
+finish:
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
+
+.data
+.global value
+value:
+    .long 42
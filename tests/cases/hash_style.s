@@ -0,0 +1,29 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -shared --hash-style=gnu -o %t.gnu.so %t.o
+# RUN: %readelf -S -d --dyn-syms %t.gnu.so | %filecheck %s --check-prefix=GNU
+# RUN: %uld -shared --hash-style=both -o %t.both.so %t.o
+# RUN: %readelf -d %t.both.so | %filecheck %s --check-prefix=BOTH
+# RUN: not %uld -shared --hash-style=fast -o %t.bad.so %t.o 2>&1 | %filecheck %s --check-prefix=BAD
+
+# GNU-NOT: ] .hash
+# GNU: ] .gnu.hash GNU_HASH
+# GNU-NOT: (HASH)
+# GNU: (GNU_HASH)
+# Undefined symbols precede the hashed, defined ones.
+# GNU: Symbol table '.dynsym' contains 4 entries
+# GNU: 1: {{.*}} UND ext
+
+# BOTH: (HASH)
+# BOTH: (GNU_HASH)
+
+# BAD: --hash-style: unknown style fast
+
+.text
+.global first
+first:
+    call *ext@GOTPCREL(%rip)
+    ret
+
+.global second
+second:
+    ret
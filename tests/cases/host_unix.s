@@ -0,0 +1,22 @@
+# RUN: %as %s -o %t.o
+# RUN: rm -f %t.exe && %uld -o %t.exe %t.o
+# RUN: stat -c %a %t.exe | %filecheck %s --check-prefix=MODE
+# RUN: %as --defsym LTO=1 %s -o %t.lto.o
+# RUN: not %uld -plugin %t.missing.so -o %t.plugin.exe %t.o %t.lto.o 2>&1 | %filecheck %s --check-prefix=PLUGIN
+
+# The paths only Unix hosts build: the output is marked executable (inputs
+# are mapped with a readahead hint on the way), and -plugin loads a library
+# with dlopen once an input holds GCC's LTO IR, whose error is reported.
+# MODE: 755
+# PLUGIN: -plugin {{.*}}host_unix.s.tmp.missing.so: {{.*}}cannot open shared object file
+
+.ifdef LTO
+.section .gnu.lto_.opts, "", @progbits
+.byte 0
+.else
+.global _start
+_start:
+    mov $60, %rax
+    mov $0, %rdi
+    syscall
+.endif
@@ -0,0 +1,61 @@
+# RUN: %as --32 %s -o %t.o
+# RUN: %uld --check-sections -o %t %t.o
+# RUN: %readelf -h %t | %filecheck %s --check-prefix=HEADER
+# RUN: %t; test $? -eq 42
+# RUN: %uld -m elf_i386 --no-relax -o %t.got %t.o
+# RUN: %readelf -SW %t.got | %filecheck %s --check-prefix=GOT
+# RUN: %t.got; test $? -eq 42
+# RUN: not %uld -shared -o %t.so %t.o 2>&1 | %filecheck %s --check-prefix=SHARED
+# RUN: printf '.globl f\nf: ret\n' | %as --32 -o %t.lib.o
+# RUN: ld -m elf_i386 -shared -soname libthirty.so.2 -o %t.lib.so %t.lib.o
+# RUN: not %uld -o %t.dyn %t.o %t.lib.so 2>&1 | %filecheck %s --check-prefix=DYNAMIC
+# RUN: not %uld -m elf_foo -o %t.bad %t.o 2>&1 | %filecheck %s --check-prefix=EMUL
+
+# The target comes from the objects, or from -m.
+# HEADER: Class: ELF32
+# HEADER: Type: EXEC
+# HEADER: Machine: Intel 80386
+
+# Unrelaxed GOT32X loads go through a 4-byte GOT entry.
+# GOT: .got PROGBITS {{[0-9a-f]+}} {{[0-9a-f]+}} 000004 00 WA 0 0 4
+
+# SHARED: only static executables can be linked for 32-bit targets
+# The ELF32 library's DT_SONAME is read with 4-byte tags and values.
+# DYNAMIC: only static executables can be linked for 32-bit targets, not against libthirty.so.2
+# EMUL: -m: unknown emulation elf_foo
+
+# SHT_REL addends live in the relocated fields.
+.text
+.global _start
+_start:
+    call __x86.get_pc_thunk.bx
+    addl $_GLOBAL_OFFSET_TABLE_, %ebx
+    movl value@GOT(%ebx), %eax
+    movl (%eax), %ecx
+    movl ptr@GOTOFF(%ebx), %edx
+    addl (%edx), %ecx
+    call get@PLT
+    addl %eax, %ecx
+    movl %ecx, %ebx
+    movl $1, %eax
+    int $0x80
+
+.global get
+get:
+    movl $2, %eax
+    ret
+
+.section .text.__x86.get_pc_thunk.bx,"axG",@progbits,__x86.get_pc_thunk.bx,comdat
+.global __x86.get_pc_thunk.bx
+.hidden __x86.get_pc_thunk.bx
+__x86.get_pc_thunk.bx:
+    movl (%esp), %ebx
+    ret
+
+.data
+.global value
+value:
+    .long 30
+ptr:
+    .long value + 8
+    .long 10
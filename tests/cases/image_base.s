@@ -0,0 +1,24 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o --image-base=0x10000000 -z max-page-size=0x10000
+# RUN: %readelf -lW %t | %filecheck %s
+# RUN: %t; test $? -eq 42
+# RUN: %uld -shared -o %t.so %t.o --image-base 0x200000
+# RUN: %readelf -lW %t.so | %filecheck %s --check-prefix=SHARED
+# RUN: not %uld -o %t.bad %t.o --image-base=0x401234 2>&1 | %filecheck %s --check-prefix=UNALIGNED
+# RUN: not %uld -o %t.bad %t.o -z max-page-size=0x3000 2>&1 | %filecheck %s --check-prefix=POW2
+
+# With 64 KiB pages the first section starts one page past the base.
+# CHECK: Entry point 0x10010000
+# CHECK: LOAD 0x000000 0x0000000010000000 {{.*}} RWE 0x10000
+
+# SHARED: LOAD 0x000000 0x0000000000200000 {{.*}} RWE 0x1000
+
+# UNALIGNED: --image-base 0x401234: not a multiple of the page size 0x1000
+# POW2: -z max-page-size=0x3000: not a power of two
+
+.text
+.global _start
+_start:
+    mov $42, %edi
+    mov $60, %eax
+    syscall
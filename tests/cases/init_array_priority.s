@@ -0,0 +1,46 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o
+# RUN: %readelf -S %t | %filecheck %s
+# RUN: %t; test $? -eq 27
+
+# CHECK: .init_array INIT_ARRAY
+# CHECK-NEXT: 0000000000000018 0000000000000008 WA
+
+# _start runs every constructor between __init_array_start and
+# __init_array_end. Each one folds a digit into %ebx, so the exit code
+# encodes the order: prio 101 (1), prio 200 (2), then unsuffixed (3)
+# gives ((1 * 4) + 2) * 4 + 3 = 27.
+.text
+.global _start
+_start:
+    xor %ebx, %ebx
+    lea __init_array_start(%rip), %r12
+    lea __init_array_end(%rip), %r13
+1:
+    cmp %r13, %r12
+    je 2f
+    call *(%r12)
+    add $8, %r12
+    jmp 1b
+2:
+    mov %ebx, %edi
+    mov $60, %eax
+    syscall
+
+ctor_default:
+    lea 3(,%rbx,4), %ebx
+    ret
+ctor_200:
+    lea 2(,%rbx,4), %ebx
+    ret
+ctor_101:
+    lea 1(,%rbx,4), %ebx
+    ret
+
+# Deliberately out of priority order in the input.
+.section .init_array.00200,"aw",@init_array
+    .quad ctor_200
+.section .init_array,"aw",@init_array
+    .quad ctor_default
+.section .init_array.00101,"aw",@init_array
+    .quad ctor_101
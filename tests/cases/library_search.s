@@ -0,0 +1,29 @@
+# RUN: rm -rf %t.dir && mkdir -p %t.dir
+# RUN: %as %s -o %t.o
+# RUN: %as %libone -o %t.one.o
+# RUN: ar rcs %t.dir/libone.a %t.one.o
+# RUN: %uld -shared -soname libone.so.1 -o %t.dir/libone.so %t.one.o
+# RUN: cp %t.dir/libone.so %t.dir/libone.so.1
+# RUN: %uld -o %t.dyn %t.o -L%t.dir -lone
+# RUN: %readelf -d %t.dyn | %filecheck %s --check-prefix=DYN
+# RUN: %uld -o %t.static %t.o -L%t.dir -Bstatic -lone
+# RUN: %readelf -l %t.static | %filecheck %s --check-prefix=STATIC
+# RUN: %uld -o %t.toggle %t.o -L%t.dir -static -Bdynamic -lone
+# RUN: %readelf -d %t.toggle | %filecheck %s --check-prefix=DYN
+# RUN: %uld -o %t.exact %t.o -L%t.dir -l:libone.so.1
+# RUN: %readelf -d %t.exact | %filecheck %s --check-prefix=DYN
+# RUN: %t.static; test $? -eq 1
+
+# DYN: (NEEDED) Shared library: [libone.so.1]
+
+# STATIC-NOT: INTERP
+# STATIC-NOT: DYNAMIC
+# STATIC: LOAD
+
+.text
+.global _start
+_start:
+    call *one@GOTPCREL(%rip)
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
@@ -0,0 +1,17 @@
+# REQUIRES: examples
+# RUN: %as %s -o %t.o
+# RUN: %examples/link_in_memory %t.o > %t.exe
+# RUN: %readelf -h %t.exe | %filecheck %s --check-prefix=HEADER
+# RUN: chmod +x %t.exe && %t.exe || echo "Exit: $?" | %filecheck %s
+
+# Linker::link_to_bytes returns the executable without writing a file.
+# HEADER: Type: EXEC (Executable file)
+# HEADER: Entry point address: 0x401000
+
+# CHECK: Exit: 42
+
+.global _start
+_start:
+    mov $60, %rax
+    mov $42, %rdi
+    syscall
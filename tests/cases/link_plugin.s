@@ -0,0 +1,26 @@
+# REQUIRES: examples
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym VETO=1 %s -o %t.veto.o
+# RUN: %examples/link_plugin %t.exe %t.o %t.veto.o 2>&1 | %filecheck %s --check-prefix=HOOKS
+# RUN: %t.exe || echo "Exit: $?" | %filecheck %s
+
+# The plugin leaves out the object defining `answer` as 7 and defines it as
+# 42 itself, seeing each section as it is loaded and the output before it
+# is written.
+# HOOKS: vetoed {{.*}}link_plugin.s.tmp.veto.o
+# HOOKS: loaded .text into .text (12 bytes)
+# HOOKS: writing .text
+# HOOKS-NOT: writing
+
+# CHECK: Exit: 42
+
+.ifdef VETO
+.global answer
+.set answer, 7
+.else
+.global _start
+_start:
+    mov $answer, %edi
+    mov $60, %eax
+    syscall
+.endif
@@ -0,0 +1,60 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o --print-map | %filecheck %s
+# RUN: %t
+
+# Only referenced symbols are defined; `edata` is never used.
+# CHECK: Symbols
+# CHECK: 0x400000 __ehdr_start
+# CHECK-NEXT: 0x401000 _start
+# CHECK-NEXT: etext
+# CHECK-NEXT: _edata
+# CHECK-NEXT: __bss_start
+# CHECK-NEXT: bss_word
+# CHECK-NEXT: _end
+# CHECK-NOT: edata
+
+# Each failed check exits with its own code; success exits 0.
+.text
+.global _start
+_start:
+    mov $1, %edi
+    cmpl $0x464c457f, __ehdr_start(%rip)
+    jne fail
+    inc %edi
+    lea _start(%rip), %rax
+    lea etext(%rip), %rcx
+    cmp %rcx, %rax
+    jae fail
+    inc %edi
+    lea _edata(%rip), %rax
+    cmp %rax, %rcx
+    ja fail
+    lea data_word(%rip), %rcx
+    cmp %rax, %rcx
+    jae fail
+    inc %edi
+    lea __bss_start(%rip), %rcx
+    cmp %rcx, %rax
+    ja fail
+    inc %edi
+    lea bss_word(%rip), %rax
+    cmp %rax, %rcx
+    ja fail
+    inc %edi
+    lea _end(%rip), %rcx
+    add $8, %rax
+    cmp %rcx, %rax
+    ja fail
+    xor %edi, %edi
+fail:
+    mov $60, %eax
+    syscall
+
+.data
+data_word:
+    .quad 1
+
+.bss
+.global bss_word
+bss_word:
+    .quad 0
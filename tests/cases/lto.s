@@ -0,0 +1,47 @@
+# REQUIRES: llvm-lto
+# RUN: %as %s -o %t.o
+# RUN: sed -n 's/^# MAIN: //p' %s | llvm-as -o %t.main.bc
+# RUN: sed -n 's/^# HELPER: //p' %s | llvm-as -o %t.helper.bc
+# RUN: %uld --emit-relocs -o %t %t.main.bc %t.helper.bc %t.o
+# RUN: llvm-objdump -d %t | %filecheck %s
+# RUN: (%t; echo "Exit: $?") | %filecheck %s --check-prefix=EXIT
+# RUN: rm -f %t.a && llvm-ar rcs %t.a %t.helper.bc
+# RUN: %uld -o %t.archive %t.main.bc %t.o %t.a --why-extract=%t.why
+# RUN: (%t.archive; echo "Exit: $?") | %filecheck %s --check-prefix=EXIT
+# RUN: %filecheck %s --check-prefix=EXTRACT < %t.why
+# RUN: not %uld -o %t.bad %t.main.bc %t.o -plugin-opt=O9 2>&1 | %filecheck %s --check-prefix=LEVEL
+
+# Bitcode modules are optimized together: helper() is inlined into _start
+# and folded to a constant.
+# CHECK: <_start>:
+# CHECK: movl $42, %edi
+# CHECK-NEXT: callq {{.*}} <do_exit>
+
+# EXIT: Exit: 42
+
+# A bitcode archive member is extracted for the reference the compiled
+# command-line bitcode leaves, and compiled on its own.
+# EXTRACT: ld-temp.o {{.*}}.a({{.*}}.helper.bc) helper
+
+# LEVEL: -plugin-opt=O9: optimization level must be 0 to 3
+
+# MAIN: target triple = "x86_64-pc-linux-gnu"
+# MAIN: declare i32 @helper(i32)
+# MAIN: declare void @do_exit(i32)
+# MAIN: define void @_start() {
+# MAIN:   %r = call i32 @helper(i32 21)
+# MAIN:   call void @do_exit(i32 %r)
+# MAIN:   unreachable
+# MAIN: }
+
+# HELPER: target triple = "x86_64-pc-linux-gnu"
+# HELPER: define i32 @helper(i32 %x) {
+# HELPER:   %y = mul i32 %x, 2
+# HELPER:   ret i32 %y
+# HELPER: }
+
+.text
+.global do_exit
+do_exit:
+    mov $60, %eax
+    syscall
@@ -0,0 +1,37 @@
+# RUN: sed -n 's/^# MAIN: //p' %s > %t.main.c
+# RUN: sed -n 's/^# HELPER: //p' %s > %t.helper.c
+# RUN: %cc -O2 -flto -c %t.main.c -o %t.main.o
+# RUN: %cc -O2 -flto -c %t.helper.c -o %t.helper.o
+# RUN: rm -rf %t.bin && mkdir -p %t.bin && ln -s %uld %t.bin/ld
+# RUN: %cc -O2 -flto -nostdlib -static -B%t.bin -Wl,--emit-relocs -o %t %t.main.o %t.helper.o
+# RUN: llvm-objdump -d %t | %filecheck %s
+# RUN: (%t; echo "Exit: $?") | %filecheck %s --check-prefix=EXIT
+# RUN: rm -f %t.a && gcc-ar rcs %t.a %t.helper.o
+# RUN: %cc -O2 -flto -nostdlib -static -B%t.bin -o %t.archive %t.main.o %t.a
+# RUN: (%t.archive; echo "Exit: $?") | %filecheck %s --check-prefix=EXIT
+# RUN: not %uld -o %t.slim %t.main.o 2>&1 | %filecheck %s --check-prefix=SLIM
+# RUN: not %cc -O2 -flto -nostdlib -static -B%t.bin -Wl,-plugin-opt=-fno-such-option -o %t.bad %t.main.o %t.helper.o 2>&1 | %filecheck %s --check-prefix=MESSAGE
+
+# The compiler driver passes its LTO plugin with -plugin; the plugin claims
+# both objects, sees that only _start is used outside the IR, and hands back
+# code with helper() inlined and unused() dropped.
+# CHECK: <_start>:
+# CHECK: movl $42, %edi
+# CHECK-NOT: <unused>:
+
+# EXIT: Exit: 42
+
+# The plugin reports failures through message(level, "%s", text).
+# MESSAGE: linker plugin: plugin failed
+# MESSAGE-NEXT: lto-wrapper failed
+
+# SLIM: GCC LTO object without machine code; link it through the compiler's linker plugin
+
+# MAIN: int helper(int);
+# MAIN: void _start(void) {
+# MAIN:     __asm__ volatile("syscall" : : "a"(60), "D"(helper(21)));
+# MAIN:     __builtin_unreachable();
+# MAIN: }
+
+# HELPER: int helper(int x) { return x * 2; }
+# HELPER: int unused(int x) { return x + 1; }
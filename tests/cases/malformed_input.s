@@ -0,0 +1,35 @@
+# RUN: llvm-mc -triple=x86_64 -filetype=obj %s -o %t.o
+# RUN: not %uld -o %t %t.o 2>&1 | %filecheck %s
+# RUN: rm -rf %t.cache
+# RUN: not %uld --object-cache=%t.cache -o %t %t.o 2>&1 | %filecheck %s
+# RUN: not %uld --object-cache=%t.cache -o %t %t.o 2>&1 | %filecheck %s
+# RUN: not %uld --diagnostics-format=json -o %t %t.o 2>&1 | %filecheck %s --check-prefix=JSON
+# RUN: llvm-mc -triple=x86_64 -filetype=obj --defsym RELAX=1 %s -o %t.relax.o
+# RUN: not %uld -o %t %t.relax.o 2>&1 | %filecheck %s --check-prefix=RELAX
+
+# A relocation past the end of its section is reported against the input
+# before anything is laid out, whether or not the object cache has its
+# symbols. (GNU as refuses to write one; llvm-mc doesn't.)
+# CHECK: {{.*}}malformed_input.s.tmp.o: malformed input:
+# CHECK-NEXT: (.text+0x28): relocation lies past the end of its section
+
+# JSON: "kind":"malformed-input","message":"relocation lies past the end of its section","offset":40,"section":".text"
+
+# Relocations `object` gives no size, such as R_X86_64_REX_GOTPCRELX, are
+# checked at the width uld writes them: four bytes at 0x1 overrun the
+# four-byte section.
+# RELAX: (.text+0x1): relocation lies past the end of its section
+
+.text
+.global _start
+_start:
+.ifdef RELAX
+    ret
+    nop
+    nop
+    nop
+.reloc 1, R_X86_64_REX_GOTPCRELX, _start
+.else
+    ret
+.reloc 40, R_X86_64_32, _start
+.endif
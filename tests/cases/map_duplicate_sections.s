@@ -0,0 +1,30 @@
+# RUN: %as %s -o %t.a.o
+# RUN: %as %s -o %t.b.o --defsym SECOND=1
+# RUN: %uld -o %t.exe %t.a.o %t.b.o --print-map --size-report=%t.json | %filecheck %s
+# RUN: %filecheck %s --check-prefix=JSON < %t.json
+# RUN: %uld inspect %t.json | %filecheck %s
+
+# Identically named sections from different inputs must stay distinguishable.
+# CHECK: .text.init {{.*}} {{.*}}.a.o [#0]
+# CHECK: .text.init {{.*}} {{.*}}.b.o [#1]
+
+# The size report records the same provenance, input index included.
+# JSON: "input": 0,
+# JSON: "name": ".text.init",
+# JSON: "path": "{{.*}}.a.o",
+# JSON: "input": 1,
+# JSON: "name": ".text.init",
+# JSON: "path": "{{.*}}.b.o",
+
+.section .text.init,"ax",@progbits
+init:
+    ret
+
+.ifndef SECOND
+.text
+.global _start
+_start:
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
+.endif
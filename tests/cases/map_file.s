@@ -0,0 +1,31 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t.exe %t.o -Map %t.map
+# RUN: %filecheck %s < %t.map
+# RUN: %uld -o %t.exe %t.o --print-map | %filecheck %s
+
+# CHECK: Memory map
+# CHECK: .text
+# CHECK-NEXT: .text {{.*}} 0x10 {{.*}}.o
+# CHECK: .rodata
+# CHECK-NEXT: .rodata {{.*}} 0x4 {{.*}}.o
+# CHECK: Discarded input sections
+# CHECK: .unused.info
+# CHECK: Symbols
+# CHECK: 0x401000 _start
+
+.section .rodata
+value:
+    .long 42
+
+.section .unused.info
+    .asciz "discarded"
+
+.text
+.global _start
+_start:
+    mov value(%rip), %edi
+    mov $60, %eax
+    syscall
+    nop
+    nop
+    nop
@@ -0,0 +1,17 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --32 %s -o %t.32.o
+# RUN: not %uld -o %t %t.o %t.32.o 2>&1 | %filecheck %s
+# RUN: not %uld -m elf_i386 -o %t %t.o 2>&1 | %filecheck %s --check-prefix=EMUL
+# RUN: %uld -m elf_x86_64 -o %t %t.o
+
+# The first object picks the target; every other input must agree.
+# CHECK: mixed architectures: linking for X86_64 (from {{.*}}mixed_arch.s.tmp.o), but these inputs are not:
+# CHECK-NEXT: {{.*}}mixed_arch.s.tmp.32.o: I386
+
+# EMUL: mixed architectures: linking for I386 (from -m), but these inputs are not:
+# EMUL-NEXT: {{.*}}mixed_arch.s.tmp.o: X86_64
+
+.text
+.global _start
+_start:
+    ret
@@ -0,0 +1,34 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld --no-relax -o %t %t.o
+# RUN: objdump -d --no-show-raw-insn %t | %filecheck %s
+# RUN: %t; test $? -eq 42
+# RUN: %uld --no-relax --relax -o %t.relaxed %t.o
+# RUN: objdump -d --no-show-raw-insn %t.relaxed | %filecheck %s --check-prefix=RELAX
+
+# With --no-relax, GOT loads stay loads through the GOT, while PLT32 calls
+# still bind directly to the definition.
+# CHECK: mov {{.*}}(%rip),%rbx
+# CHECK-NEXT: call {{[0-9a-f]+}}
+# CHECK-NOT: addr32
+
+# The last of --relax / --no-relax wins.
+# RELAX: lea {{.*}}(%rip),%rbx
+
+.text
+.global _start
+_start:
+    movq value@GOTPCREL(%rip), %rbx
+    call get@PLT
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
+
+.global get
+get:
+    movl (%rbx), %eax
+    ret
+
+.data
+.global value
+value:
+    .long 42
@@ -0,0 +1,67 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym SECOND=1 %s -o %t.second.o
+# RUN: %uld -o %t %t.o %t.second.o
+# RUN: %readelf -lSW %t | %filecheck %s
+# RUN: %readelf -n %t | %filecheck %s --check-prefix=NOTES
+# RUN: %t
+
+# Loaded notes keep their type and get an output section per name, ahead
+# of the code. Adjacent ones with the same alignment share a PT_NOTE; the
+# 8-byte aligned one needs its own.
+# CHECK: .note.ABI-tag NOTE {{.*}} A 0 0 4
+# CHECK-NEXT: .note.uld.test NOTE {{.*}} A 0 0 4
+# CHECK-NEXT: .note.uld.wide NOTE {{.*}} A 0 0 8
+# CHECK-NEXT: .text PROGBITS
+# CHECK: NOTE 0x001000 0x0000000000401000 0x0000000000401000 0x000048 0x000048 R 0x4
+# CHECK-NEXT: NOTE 0x001048 0x0000000000401048 0x0000000000401048 0x000018 0x000018 R 0x8
+# CHECK: Section to Segment mapping:
+# CHECK: 01 .note.ABI-tag .note.uld.test{{ *$}}
+# CHECK-NEXT: 02 .note.uld.wide{{ *$}}
+
+# Entries of the same note section from both inputs follow each other.
+# NOTES: Displaying notes found in: .note.ABI-tag
+# NOTES-NEXT: Owner Data size Description
+# NOTES-NEXT: GNU 0x00000010 NT_GNU_ABI_TAG (ABI version tag)
+# NOTES-NEXT: OS: Linux, ABI: 3.2.0
+# NOTES: Displaying notes found in: .note.uld.test
+# NOTES-NEXT: Owner Data size Description
+# NOTES-NEXT: uld 0x00000004 {{.*}}0x00001234
+# NOTES-NEXT: description data: 01 00 00 00
+# NOTES-NEXT: uld 0x00000004 {{.*}}0x00001234
+# NOTES-NEXT: description data: 02 00 00 00
+# NOTES: Displaying notes found in: .note.uld.wide
+# NOTES-NEXT: Owner Data size Description
+# NOTES-NEXT: uld 0x00000008 {{.*}}0x00005678
+
+.ifdef SECOND
+.section .note.uld.test,"a",@note
+.balign 4
+    .long 4, 4, 0x1234
+    .asciz "uld"
+    .long 2
+.else
+.text
+.global _start
+_start:
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
+
+.section .note.ABI-tag,"a",@note
+.balign 4
+    .long 4, 16, 1
+    .asciz "GNU"
+    .long 0, 3, 2, 0
+
+.section .note.uld.test,"a",@note
+.balign 4
+    .long 4, 4, 0x1234
+    .asciz "uld"
+    .long 1
+
+.section .note.uld.wide,"a",@note
+.balign 8
+    .long 4, 8, 0x5678
+    .asciz "uld"
+    .quad 3
+.endif
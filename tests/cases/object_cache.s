@@ -0,0 +1,45 @@
+# RUN: rm -rf %t.cache
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym LIB=1 %s -o %t.lib.o
+# RUN: %uld --object-cache=%t.cache -o %t.cold %t.o %t.lib.o
+# RUN: ls %t.cache | %filecheck %s --check-prefix=ENTRIES
+# RUN: %uld --object-cache=%t.cache -o %t.warm %t.o %t.lib.o
+# RUN: %uld -o %t.none %t.o %t.lib.o
+# RUN: cmp %t.cold %t.warm
+# RUN: cmp %t.none %t.warm
+# RUN: %t.warm; test $? -eq 42
+# RUN: for f in %t.cache/*; do echo damaged > $f; done
+# RUN: %uld --object-cache=%t.cache -o %t.damaged %t.o %t.lib.o
+# RUN: cmp %t.none %t.damaged
+
+# The symbols of each object are cached under a hash of its contents. A
+# link reading them back gives the same output as one without the cache,
+# and damaged entries are parsed again.
+# ENTRIES: {{^[0-9a-f]+$}}
+# ENTRIES-NEXT: {{^[0-9a-f]+$}}
+# ENTRIES-NOT: tmp
+
+.ifdef LIB
+.data
+.global value
+value:
+    .long 40
+.weak bonus
+bonus:
+    .long 2
+.global absolute
+.set absolute, 0
+.comm scratch, 16, 8
+.else
+.text
+.global _start
+_start:
+    mov value(%rip), %edi
+    add bonus(%rip), %edi
+    add $absolute, %edi
+    mov %edi, scratch(%rip)
+    lea missing(%rip), %rax
+    mov $60, %eax
+    syscall
+.weak missing
+.endif
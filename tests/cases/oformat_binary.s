@@ -0,0 +1,33 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t.elf %t.o -Ttext 0x7c00
+# RUN: %uld -o %t.bin %t.o -Ttext 0x7c00 --oformat binary
+# RUN: objcopy -O binary %t.elf %t.ref
+# RUN: cmp %t.bin %t.ref
+# RUN: od -A x -t x1 %t.bin | %filecheck %s
+# RUN: not %uld -o %t.bad %t.o --oformat pe 2>&1 | %filecheck %s --check-prefix=BAD
+
+# The image starts with .text, zero-fills up to .rodata and ends with .data,
+# whose pointer holds the linked address of msg; .bss is left out.
+# CHECK: 000000 eb fe 00
+# CHECK: 000400 75 6c 64 21 00
+# CHECK: 001400 00 80 00 00 00 00 00 00
+# CHECK-NEXT: 001408
+
+# BAD: --oformat: unknown format pe (expected elf64-x86-64, binary, ihex or srec)
+
+.text
+.global _start
+_start:
+    jmp _start
+
+.section .rodata
+msg:
+    .ascii "uld!"
+
+.data
+ptr:
+    .quad msg
+
+.bss
+buf:
+    .zero 64
@@ -0,0 +1,42 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t.bin %t.o --oformat binary --section-start .data=0x40fff8
+# RUN: %uld -o %t.hex %t.o --oformat ihex --section-start .data=0x40fff8
+# RUN: %uld -o %t.srec %t.o --oformat srec --section-start .data=0x40fff8
+# RUN: %filecheck %s --check-prefix=IHEX < %t.hex
+# RUN: %filecheck %s --check-prefix=SREC < %t.srec
+# RUN: objcopy -I ihex -O binary %t.hex %t.hex.bin
+# RUN: cmp %t.bin %t.hex.bin
+# RUN: objcopy -I srec -O binary %t.srec %t.srec.bin
+# RUN: cmp %t.bin %t.srec.bin
+# RUN: %uld -o %t.low.hex %t.o --oformat ihex -Ttext 0x7c00
+# RUN: %filecheck %s --check-prefix=LOW < %t.low.hex
+
+# Above 1 MiB, addresses come from extended linear address records; .data
+# straddles 0x410000, so its record is split and the base is moved.
+# IHEX: :020000040040BA
+# IHEX-NEXT: :10100000B8010000000F05000000000000000000{{..}}
+# IHEX: :08FFF800001122334455667725
+# IHEX-NEXT: :020000040041B9
+# IHEX-NEXT: :0800000088990011AABBCCDD{{..}}
+# IHEX-NEXT: :0400000500401000A7
+# IHEX-NEXT: :00000001FF
+
+# SREC: S0{{.*}}
+# SREC-NEXT: S2{{..}}401000B801000000
+# SREC: S20C40FFF80011223344556677E0
+# SREC: S804401000AB
+
+# Below 1 MiB the start address is a CS:IP pair.
+# LOW: :0400000300007C007D
+# LOW-NEXT: :00000001FF
+
+.text
+.global _start
+_start:
+    mov $1, %eax
+    syscall
+    .fill 16, 1, 0
+
+.data
+    .byte 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77
+    .byte 0x88, 0x99, 0x00, 0x11, 0xaa, 0xbb, 0xcc, 0xdd
@@ -0,0 +1,51 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t.exe %t.o
+# RUN: %readelf -SW %t.exe | %filecheck %s
+# RUN: %t.exe; test $? -eq 7
+# RUN: %uld --orphan-handling=warn -o %t.exe %t.o | %filecheck %s --check-prefix=WARN
+# RUN: not %uld --orphan-handling=error -o %t.exe %t.o 2>&1 | %filecheck %s --check-prefix=ERROR
+# RUN: not %uld --orphan-handling=discard -o %t.exe %t.o 2>&1 | %filecheck %s --check-prefix=DISCARDED
+# RUN: %uld --orphan-handling=discard --noinhibit-exec -M -o %t.exe %t.o 2>&1 | %filecheck %s --check-prefix=DISCARD
+
+# Sections no rule describes get an output section of their own after a
+# built-in one with the same flags, instead of being dropped. Notes aren't
+# orphans: they go up front, where PT_NOTE covers them.
+# CHECK: .note.test NOTE {{.*}} A 0 0 4
+# CHECK: .data PROGBITS
+# CHECK-NEXT: my.cfg PROGBITS {{.*}} WA
+# CHECK: .bss NOBITS
+# CHECK-NEXT: .lowbuf NOBITS {{.*}} WA
+
+# WARN: .tmp.o:(my.cfg) is being placed in 'my.cfg'
+# WARN: .tmp.o:(.lowbuf) is being placed in '.lowbuf'
+
+# ERROR: orphan sections (--orphan-handling=error):
+# ERROR-NEXT: .tmp.o:(my.cfg)
+# ERROR-NEXT: .tmp.o:(.lowbuf)
+
+# _start still refers to the discarded section; --noinhibit-exec links anyway.
+# DISCARDED: relocations refer to discarded sections:
+# DISCARDED-NEXT: .tmp.o:(.text+0x2): discarded section `my.cfg' of {{.*}}.tmp.o
+# DISCARD: relocation refers to discarded section
+# DISCARD: Discarded input sections
+# DISCARD: my.cfg
+
+.text
+.global _start
+_start:
+    mov my_cfg(%rip), %edi
+    mov $60, %eax
+    syscall
+
+.section my.cfg,"aw"
+my_cfg:
+    .long 7
+
+.section .note.test,"a",@note
+.balign 4
+    .long 4, 4, 1
+    .asciz "abc"
+    .long 0
+
+.section .lowbuf,"aw",@nobits
+    .zero 64
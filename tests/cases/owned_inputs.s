@@ -0,0 +1,18 @@
+# REQUIRES: examples
+# RUN: %as %s -o %t.o
+# RUN: %as %getvalue -o %t_get_value.o
+# RUN: rm -f %t.a && ar rcs %t.a %t_get_value.o
+# RUN: %examples/owned_inputs %t.exe %t.o %t.a
+# RUN: %t.exe || echo "Exit: $?" | %filecheck %s
+
+# The inputs are read into buffers the linker owns, which outlive the code
+# that loaded them: the link, extracting get_value from the archive, runs
+# on another thread.
+# CHECK: Exit: 42
+
+.global _start
+_start:
+    call get_value
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
@@ -0,0 +1,28 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t '--package-metadata={"type":"deb","name":"hello","version":"1.0%2D1"}' %t.o
+# RUN: %readelf -n %t | %filecheck %s
+# RUN: %readelf -lW %t | %filecheck %s --check-prefix=PHDR
+# RUN: %t
+# RUN: %uld -o %t.none --package-metadata= %t.o
+# RUN: %readelf -SW %t.none | %filecheck %s --check-prefix=NONE
+# RUN: not %uld -o %t.bad '--package-metadata={"name":' %t.o 2>&1 | %filecheck %s --check-prefix=BAD
+
+# The JSON goes in an FDO note, with %xx escapes decoded.
+# CHECK: Displaying notes found in: .note.package
+# CHECK-NEXT: Owner Data size Description
+# CHECK-NEXT: FDO 0x{{.*}} FDO_PACKAGING_METADATA
+# CHECK-NEXT: Packaging Metadata: {"type":"deb","name":"hello","version":"1.0-1"}
+
+# PHDR: NOTE
+# PHDR: .note.package
+
+# NONE-NOT: .note.package
+
+# BAD: --package-metadata: invalid JSON
+
+.text
+.global _start
+_start:
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
@@ -0,0 +1,23 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t.exe %t.o --pad-sections=64+8 --print-map | %filecheck %s
+# RUN: %t.exe || echo "Exit: $?" | %filecheck %s --check-prefix=EXIT
+
+# Each text section is aligned to 64 bytes and then shifted by 8.
+# CHECK: .text
+# CHECK-NEXT: .text {{.*}}0x401008
+# CHECK-NEXT: .text.helper {{.*}}0x401048
+
+# EXIT: Exit: 42
+
+.text
+.global _start
+_start:
+    call helper
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
+
+.section .text.helper,"ax",@progbits
+helper:
+    mov $42, %eax
+    ret
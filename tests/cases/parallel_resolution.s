@@ -0,0 +1,45 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym STRONG=1 %s -o %t.strong.o
+# RUN: %uld -o %t %t.o %t.strong.o --threads 4
+# RUN: %uld -o %t.1 %t.o %t.strong.o --threads 1
+# RUN: cmp %t %t.1
+# RUN: %t; test $? -eq 2
+# RUN: not %uld -o %t.dup %t.o %t.strong.o %t.strong.o --threads 4 2> %t.err4
+# RUN: not %uld -o %t.dup %t.o %t.strong.o %t.strong.o --threads 1 2> %t.err1
+# RUN: cmp %t.err4 %t.err1
+# RUN: %filecheck %s < %t.err4
+
+# Thousands of globals are resolved on several threads, shard by shard. The
+# strong definitions override the weak ones whatever their shard, and the
+# clash reported is the same as with one thread.
+# CHECK: multiple definition of `{{.*}}`: first defined in {{.*}}.strong.o, again in {{.*}}.strong.o
+
+.data
+.irp a,0,1,2,3,4
+.irp b,0,1,2,3,4,5,6,7,8,9
+.irp c,0,1,2,3,4,5,6,7,8,9
+.irp d,0,1,2,3,4,5,6,7,8,9
+.ifdef STRONG
+.global sym\a\b\c\d
+sym\a\b\c\d:
+    .quad 2
+.else
+.weak sym\a\b\c\d
+sym\a\b\c\d:
+    .quad 1
+.endif
+.endr
+.endr
+.endr
+.endr
+
+.ifndef STRONG
+.text
+.global _start
+_start:
+    mov sym0000(%rip), %edi
+    add sym4999(%rip), %edi
+    sub sym2500(%rip), %edi
+    mov $60, %eax
+    syscall
+.endif
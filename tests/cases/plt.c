@@ -0,0 +1,56 @@
+// RUN: cc -fPIC -c %s -o %t.o
+// RUN: %uld -shared -soname libplugin.so -o %t.so %t.o
+// RUN: %readelf -r %t.so | %filecheck %s
+// RUN: %readelf -d %t.so | %filecheck %s --check-prefix=DYNAMIC
+// RUN: cc -DHOST -rdynamic %s -o %t.host -ldl
+// RUN: %t.host %t.so | %filecheck %s --check-prefix=LOADED
+
+// A plugin built with plain -fPIC calls libc and its own exported functions
+// through the PLT; the loader binds both, and the host's `greeting`
+// preempts the plugin's own.
+// CHECK: Relocation section '.rela.plt'
+// CHECK-DAG: R_X86_64_JUMP_SLOT {{.*}} greeting + 0
+// CHECK-DAG: R_X86_64_JUMP_SLOT {{.*}} strlen + 0
+// CHECK-DAG: R_X86_64_JUMP_SLOT {{.*}} puts + 0
+
+// DYNAMIC: (PLTGOT)
+// DYNAMIC-NEXT: (PLTRELSZ) 72 (bytes)
+// DYNAMIC-NEXT: (PLTREL) RELA
+// DYNAMIC-NEXT: (JMPREL)
+// DYNAMIC: (FLAGS) BIND_NOW
+
+// LOADED: hello from the host
+// LOADED: run() = 19
+
+#ifdef HOST
+#include <dlfcn.h>
+#include <stdio.h>
+
+const char *greeting(void) {
+    return "hello from the host";
+}
+
+int main(int argc, char **argv) {
+    void *lib = dlopen(argv[1], RTLD_LAZY);
+    if (!lib) {
+        printf("%s\n", dlerror());
+        return 1;
+    }
+    int (*run)(void) = (int (*)(void))dlsym(lib, "run");
+    printf("run() = %d\n", run());
+    return 0;
+}
+#else
+#include <stdio.h>
+#include <string.h>
+
+const char *greeting(void) {
+    return "hello from the plugin";
+}
+
+int run(void) {
+    const char *s = greeting();
+    puts(s);
+    return strlen(s);
+}
+#endif
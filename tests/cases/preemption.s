@@ -0,0 +1,73 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -shared -soname libpreempt.so -o %t.so %t.o
+# RUN: %readelf -r %t.so | %filecheck %s
+# RUN: sed -n 's/^# HOST: //p' %s > %t.host.c
+# RUN: cc -rdynamic %t.host.c -o %t.host -ldl
+# RUN: %t.host %t.so | %filecheck %s --check-prefix=LOADED
+
+# GOT entries and pointers for the default-visibility `value` are left to
+# the loader, so a definition loaded earlier wins; protected `guarded`
+# binds to the library's own copy, and its GOT load is relaxed away.
+# CHECK: Relocation section '.rela.dyn'
+# CHECK-DAG: R_X86_64_64 {{.*}} value + 0
+# CHECK-DAG: R_X86_64_GLOB_DAT {{.*}} value + 0
+# CHECK-DAG: R_X86_64_RELATIVE
+# CHECK-NOT: guarded
+
+# LOADED: get_value() = 7
+# LOADED: *value_ptr = 7
+# LOADED: get_guarded() = 5
+
+# HOST: #include <dlfcn.h>
+# HOST: #include <stdio.h>
+# HOST: int value = 7;
+# HOST: int guarded = 7;
+# HOST: int main(int argc, char **argv) {
+# HOST:     void *lib = dlopen(argv[1], RTLD_NOW);
+# HOST:     if (!lib) {
+# HOST:         printf("%s\n", dlerror());
+# HOST:         return 1;
+# HOST:     }
+# HOST:     int (*get_value)(void) = (int (*)(void))dlsym(lib, "get_value");
+# HOST:     int (*get_guarded)(void) = (int (*)(void))dlsym(lib, "get_guarded");
+# HOST:     int **value_ptr = dlsym(lib, "value_ptr");
+# HOST:     printf("get_value() = %d\n", get_value());
+# HOST:     printf("*value_ptr = %d\n", **value_ptr);
+# HOST:     printf("get_guarded() = %d\n", get_guarded());
+# HOST:     return 0;
+# HOST: }
+
+.text
+.global get_value
+.type get_value, @function
+get_value:
+    movq value@GOTPCREL(%rip), %rax
+    movl (%rax), %eax
+    ret
+
+.global get_guarded
+.type get_guarded, @function
+get_guarded:
+    movq guarded@GOTPCREL(%rip), %rax
+    movl (%rax), %eax
+    ret
+
+.data
+.global value
+.type value, @object
+value:
+    .long 5
+.size value, 4
+
+.global guarded
+.protected guarded
+.type guarded, @object
+guarded:
+    .long 5
+.size guarded, 4
+
+.global value_ptr
+.type value_ptr, @object
+value_ptr:
+    .quad value
+.size value_ptr, 8
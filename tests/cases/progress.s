@@ -0,0 +1,35 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym LIB=1 %s -o %t.lib.o
+# RUN: rm -f %t.a && ar rcs %t.a %t.lib.o
+# RUN: %uld --progress -o %t %t.o %t.a 2>&1 | %filecheck %s
+# RUN: %t
+# RUN: %uld -o %t.quiet %t.o %t.a 2>&1 | %filecheck %s --check-prefix=QUIET --allow-empty
+
+# Without a terminal, --progress prints a line per milestone.
+# CHECK: progress: inputs added (inputs: 2)
+# CHECK-NEXT: progress: laid out
+# CHECK-NEXT: progress: symbols resolved
+# CHECK-NEXT: progress: relocated (relocations: 2)
+# CHECK-NEXT: progress: written (bytes: {{[0-9]+}})
+
+# QUIET-NOT: progress
+
+.ifdef LIB
+.text
+.global exit_code
+exit_code:
+    xor %edi, %edi
+    ret
+.else
+.text
+.global _start
+_start:
+    call exit_code
+    lea value(%rip), %rax
+    mov $60, %eax
+    syscall
+
+.data
+value:
+    .quad 0
+.endif
@@ -0,0 +1,23 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o
+# RUN: %t; test $? -eq 42
+
+# RELA relocations carry their addend; whatever the field held before is
+# overwritten, not added.
+
+.text
+.global _start
+_start:
+    movl ptr(%rip), %eax
+    movl (%rax), %edi
+    mov $60, %eax
+    syscall
+
+.data
+ptr:
+    .reloc ., R_X86_64_32, value
+    .long 7
+
+.global value
+value:
+    .long 42
@@ -0,0 +1,47 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym DATA=1 %s -o %t.data.o
+# RUN: not %uld --section-start=.data=0x200000000 -o %t %t.o %t.data.o 2>&1 | %filecheck %s
+# RUN: not %uld --image-base=0x100000000 -o %t %t.o %t.data.o 2>&1 | %filecheck %s --check-prefix=ABS
+# RUN: %as --defsym WORD=1 %s -o %t.word.o
+# RUN: not %uld -o %t %t.word.o %t.data.o 2>&1 | %filecheck %s --check-prefix=WORD
+# RUN: %as --32 --defsym WORD=1 %s -o %t.word32.o
+# RUN: %as --32 --defsym DATA=1 %s -o %t.data32.o
+# RUN: not %uld -o %t %t.word32.o %t.data32.o 2>&1 | %filecheck %s --check-prefix=I386
+
+# An overflow names the reference, the target and where it is defined, and
+# suggests what to change.
+# CHECK: .tmp.o:(.text+0x2): relocation R_X86_64_PC32 against `far` (defined in {{.*}}data.o) out of range: 0x{{[0-9a-f]+}} is not in [-2147483648, 2147483647]
+# CHECK-NEXT: recompile with -mcmodel=large, or move `.data` within 2 GiB of `.text` (the address of `.data` is set with --section-start)
+
+# ABS: .tmp.o:(.text+0x7): relocation R_X86_64_32 against `far` (defined in {{.*}}data.o) out of range
+# ABS-NEXT: recompile with -mcmodel=large, or place `.data` below 4 GiB, e.g. with a lower --image-base
+
+# The limit comes from the field's range.
+# WORD: relocation R_X86_64_16 against `far` (defined in {{.*}}data.o) out of range
+# WORD-NEXT: recompile with -mcmodel=large, or place `.data` below 64 KiB, e.g. with a lower --image-base
+
+# -mcmodel=large is x86_64 advice; i386 has no larger code model.
+# I386: relocation R_386_16 against `far` (defined in {{.*}}data32.o) out of range
+# I386-NEXT: {{^}}place `.data` below 64 KiB, e.g. with a lower --image-base
+
+.ifdef DATA
+.data
+.global far
+far:
+    .long 1
+.else
+.ifdef WORD
+.text
+.global _start
+_start:
+    movw $far, %ax
+    ret
+.else
+.text
+.global _start
+_start:
+    movl far(%rip), %eax
+    movl $far, %ebx
+    ret
+.endif
+.endif
@@ -0,0 +1,61 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym UNKNOWN=1 %s -o %t.unknown.o
+# RUN: %uld --defsym small=0x12 -o %t %t.o
+# RUN: %readelf -x .data %t | %filecheck %s
+# RUN: %t; test $? -eq 0
+# RUN: not %uld --defsym small=0x1234 -o %t %t.o 2>&1 | %filecheck %s --check-prefix=BYTE
+# RUN: not %uld -o %t.unknown %t.unknown.o 2>&1 | %filecheck %s --check-prefix=UNKNOWN
+
+# .data is at 0x402000 and .got.plt, where _GLOBAL_OFFSET_TABLE_ points,
+# at 0x404000: PC64 is -0x1000 and GOTOFF64 -0x2000, then the R_X86_64_32,
+# 16 and 8 values.
+# CHECK: 0x00402000 00f0ffff ffffffff 00e0ffff ffffffff
+# CHECK-NEXT: 0x00402010 00204000 120012
+
+# BYTE: relocation R_X86_64_8 against `small` out of range: 0x1234 is not in [-128, 255]
+
+# Relocations the backend doesn't implement are errors, not skipped.
+# UNKNOWN: .tmp.unknown.o:(.text+0x2)
+# UNKNOWN: unsupported relocation R_X86_64_GOT64
+
+.ifdef UNKNOWN
+.data
+.global counter
+counter:
+    .long 0
+
+.text
+.global _start
+_start:
+    movabsq $counter@GOT, %rax
+    ret
+.else
+.text
+.global _start
+_start:
+    # R_X86_64_GOTPC32, 32S, 32 and REX_GOTPCRELX (on an add, which keeps
+    # its GOT entry).
+    leaq _GLOBAL_OFFSET_TABLE_(%rip), %r15
+    movq $target, %rax
+    movl $target, %ebx
+    xorl %ecx, %ecx
+    addq target@GOTPCREL(%rip), %rcx
+    cmpq %rax, %rcx
+    jne 1f
+    # The first word of .got.plt holds _DYNAMIC, 0 without one.
+    cmpq $0, (%r15)
+1:
+    setne %dil
+    movzbl %dil, %edi
+    mov $60, %eax
+    syscall
+
+.data
+.global target
+target:
+    .quad _start - .
+    .quad target@GOTOFF
+    .long target
+    .word small
+    .byte small
+.endif
@@ -0,0 +1,43 @@
+# RUN: rm -rf %t.dir && mkdir -p %t.dir/lib
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym LIB=1 %s -o %t.lib.o
+# RUN: ar rcs %t.dir/lib/libvalue.a %t.lib.o
+# RUN: echo value > %t.dir/order.txt
+# RUN: echo '-L%t.dir/lib -lvalue' > %t.dir/args.rsp
+# RUN: %uld -o %t %t.o @%t.dir/args.rsp --symbol-ordering-file %t.dir/order.txt --reproduce=%t.dir/repro.tar
+# RUN: tar -tf %t.dir/repro.tar | %filecheck %s
+# RUN: cd %t.dir && tar -xf repro.tar
+# RUN: %filecheck %s --check-prefix=RESPONSE < %t.dir/repro/response.txt
+# RUN: cd %t.dir/repro && %uld @response.txt
+# RUN: cmp %t %t.dir/repro/$(basename %t)
+
+# The tarball holds the command line, the version and every file read, at
+# its absolute path. Running the command line in the unpacked directory
+# links the same output from the archived files alone.
+# CHECK: repro/response.txt
+# CHECK-NEXT: repro/version.txt
+# CHECK-NEXT: repro/{{.*}}reproduce.s.tmp.o
+# CHECK-NEXT: repro/{{.*}}/lib/libvalue.a
+# CHECK-NEXT: repro/{{.*}}/args.rsp
+# CHECK-NEXT: repro/{{.*}}/order.txt
+
+# RESPONSE: -o
+# RESPONSE-NEXT: {{^reproduce.s.tmp$}}
+# RESPONSE-NEXT: --symbol-ordering-file={{[^/].*}}/order.txt
+# RESPONSE-NEXT: {{^[^/].*}}reproduce.s.tmp.o
+# RESPONSE-NEXT: {{^[^/].*}}/lib/libvalue.a
+# RESPONSE-NOT: -L
+
+.ifdef LIB
+.data
+.global value
+value:
+    .long 42
+.else
+.text
+.global _start
+_start:
+    mov value(%rip), %edi
+    mov $60, %eax
+    syscall
+.endif
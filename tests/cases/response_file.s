@@ -0,0 +1,14 @@
+# RUN: %as %s -o %t.o
+# RUN: echo "'%t.o'" > %t.inner.rsp
+# RUN: echo "-o \"%t.exe\" @%t.inner.rsp" > %t.rsp
+# RUN: %uld @%t.rsp
+# RUN: %t.exe || echo "Exit: $?" | %filecheck %s
+
+# CHECK: Exit: 42
+
+# Arguments come from a nested response file with shell-style quoting.
+.global _start
+_start:
+    mov $60, %rax
+    mov $42, %rdi
+    syscall
@@ -0,0 +1,20 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -shared -rpath '$ORIGIN/../lib' -R/opt/lib -o %t.so %t.o
+# RUN: %readelf -d %t.so | %filecheck %s --check-prefix=RPATH
+# RUN: %uld -shared --enable-new-dtags -rpath /opt/lib -o %t.new.so %t.o
+# RUN: %readelf -d %t.new.so | %filecheck %s --check-prefix=RUNPATH
+# RUN: %uld -shared --enable-new-dtags --disable-new-dtags -rpath /opt/lib -o %t.old.so %t.o
+# RUN: %readelf -d %t.old.so | %filecheck %s --check-prefix=OLD
+
+# Multiple directories are joined in command-line order.
+# RPATH: (RPATH) Library rpath: [$ORIGIN/../lib:/opt/lib]
+
+# RUNPATH-NOT: (RPATH)
+# RUNPATH: (RUNPATH) Library runpath: [/opt/lib]
+
+# OLD: (RPATH) Library rpath: [/opt/lib]
+
+.text
+.global f
+f:
+    ret
@@ -0,0 +1,23 @@
+// Test: rustc links a hello-world through uld given `-C linker`, the way
+// Cargo does with `linker = "uld"` in .cargo/config.toml. The standard
+// library is a PIE against glibc that calls libc through the PLT.
+// REQUIRES: rustc
+// RUN: rustc -C linker=%uld %s -o %t
+// RUN: (%t; echo "Exit: $?") | %filecheck %s
+// RUN: %readelf -lW %t | %filecheck --check-prefix=PHDR %s
+// RUN: %readelf -rW %t | %filecheck --check-prefix=PLT %s
+
+// CHECK: hello from uld
+// CHECK-NEXT: Exit: 7
+
+// PHDR: DYN (Position-Independent Executable file)
+// PHDR: PHDR
+// PHDR-NEXT: INTERP
+
+// PLT: Relocation section '.rela.plt'
+// PLT: R_X86_64_JUMP_SLOT
+
+fn main() {
+    println!("hello from uld");
+    std::process::exit(7);
+}
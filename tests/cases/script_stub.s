@@ -0,0 +1,30 @@
+# RUN: rm -rf %t.dir && mkdir -p %t.dir
+# RUN: %as %s -o %t.o
+# RUN: %as %libone -o %t.one.o
+# RUN: %as %getvalue -o %t.unused.o
+# RUN: %uld -shared -soname libone.so.1 -o %t.dir/libone.so.1 %t.one.o
+# RUN: %uld -shared -soname libunused.so -o %t.dir/libunused.so %t.unused.o
+# RUN: echo '/* stub */ OUTPUT_FORMAT(elf64-x86-64) GROUP ( libone.so.1 AS_NEEDED ( -lunused ) )' > %t.dir/libone.so
+# RUN: %uld -o %t %t.o -L%t.dir -lone
+# RUN: %readelf -d %t | %filecheck %s
+# RUN: echo 'GROUP ( missing.so )' > %t.dir/libbroken.so
+# RUN: not %uld -o %t.bad %t.o -L%t.dir -lbroken 2>&1 | %filecheck %s --check-prefix=MISSING
+# RUN: echo 'SECTIONS { }' > %t.dir/libsections.so
+# RUN: not %uld -o %t.bad %t.o -L%t.dir -lsections 2>&1 | %filecheck %s --check-prefix=UNSUPPORTED
+
+# The stub is replaced by the library it names; AS_NEEDED entries are
+# dropped when nothing references them.
+# CHECK: (NEEDED) Shared library: [libone.so.1]
+# CHECK-NOT: libunused.so
+
+# MISSING: cannot find missing.so (named by {{.*}}libbroken.so)
+
+# UNSUPPORTED: unsupported linker script command SECTIONS
+
+.text
+.global _start
+_start:
+    call *one@GOTPCREL(%rip)
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
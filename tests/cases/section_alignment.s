@@ -0,0 +1,46 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t.exe %t.o
+# RUN: %readelf -SlW %t.exe | %filecheck %s
+# RUN: %t.exe
+# RUN: %as --defsym BIG=1 %s -o %t.big.o
+# RUN: %uld -o %t.big.exe %t.big.o
+# RUN: %readelf -SlW %t.big.exe | %filecheck %s --check-prefix=BIG
+# RUN: %uld --section-start=.data=0x600008 -o %t.exe %t.o | %filecheck %s --check-prefix=WARN
+
+# sh_addralign is the largest alignment of the input sections.
+# CHECK: .text PROGBITS {{[0-9a-f]+}} {{[0-9a-f]+}} {{[0-9a-f]+}} 00 AX 0 0 16
+# CHECK: .rodata PROGBITS {{[0-9a-f]+}} {{[0-9a-f]+}} {{[0-9a-f]+}} 00 A 0 0 32
+# CHECK: .data PROGBITS {{[0-9a-f]+}} {{[0-9a-f]+}} {{[0-9a-f]+}} 00 WA 0 0 64
+
+# Alignment beyond a page raises the PT_LOAD alignment, with the address and
+# file offset congruent modulo it.
+# BIG: .data PROGBITS 0000000000410000 010000 {{[0-9a-f]+}} 00 WA 0 0 65536
+# BIG: LOAD {{.*}} RWE 0x10000
+
+# WARN: address 0x600008 of section .data is not a multiple of its alignment (64)
+
+.text
+.balign 16
+.global _start
+_start:
+    movaps v(%rip), %xmm0
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
+
+.section .rodata,"a"
+.balign 32
+    .quad 1
+
+.data
+    .byte 1
+.balign 64
+.global v
+v:
+    .fill 32, 1, 2
+
+.ifdef BIG
+.section .data.big,"aw"
+.balign 65536
+    .quad 3
+.endif
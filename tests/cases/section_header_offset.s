@@ -0,0 +1,18 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o
+# RUN: %readelf -S %t | %filecheck %s
+# RUN: %readelf -h %t | sed -n 's/.*Start of section headers: *\([0-9]*\).*/\1/p' > %t.shoff
+# RUN: test $(( $(cat %t.shoff) % 8 )) -eq 0
+
+# The merged .comment ends the file at an odd offset; the section header
+# table after it still starts on an 8-byte boundary, or readers reject it.
+# CHECK: .comment PROGBITS
+
+.global _start
+_start:
+    mov $60, %rax
+    mov $42, %rdi
+    syscall
+
+.section .comment, "MS", @progbits, 1
+.asciz "odd"
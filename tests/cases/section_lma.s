@@ -0,0 +1,47 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o -Tdata 0x800000 --section-lma .data=0x500000
+# RUN: %readelf -lW %t | %filecheck %s
+# RUN: %t; test $? -eq 42
+# RUN: %uld -o %t.bin %t.o -Tdata 0x800000 --section-lma .data=0x500000 --oformat binary
+# RUN: objcopy -O binary %t %t.ref
+# RUN: cmp %t.bin %t.ref
+# RUN: od -A x -t x1 %t.bin | %filecheck %s --check-prefix=IMAGE
+# RUN: not %uld -o %t.bad %t.o --section-lma .data=0x401000 2>&1 | %filecheck %s --check-prefix=OVERLAP
+
+# .data runs at 0x800000 but is stored at 0x500000.
+# CHECK: LOAD 0x000000 0x0000000000400000 0x0000000000400000
+# CHECK-NEXT: LOAD 0x{{[0-9a-f]+}} 0x0000000000800000 0x0000000000500000 0x000008 0x000008
+
+# In the flat image .data sits at its load address, right where startup
+# code would copy it from.
+# IMAGE: 0ff000 2a 00 00 00 00 00 00 00
+
+# OVERLAP: section .text load range [0x401000, 0x{{[0-9a-f]+}}) overlaps .data [0x401000, 0x401008)
+
+.text
+.global _start
+_start:
+    # Exit 42 only if the symbols bracket both copies of .data.
+    mov $1, %edi
+    lea __data_load_start(%rip), %rax
+    cmp $0x500000, %rax
+    jne 1f
+    lea __data_load_end(%rip), %rcx
+    sub %rax, %rcx
+    cmp $8, %rcx
+    jne 1f
+    lea __data_start(%rip), %rax
+    cmp $0x800000, %rax
+    jne 1f
+    lea __data_end(%rip), %rcx
+    sub %rax, %rcx
+    cmp $8, %rcx
+    jne 1f
+    mov value(%rip), %edi
+1:
+    mov $60, %eax
+    syscall
+
+.data
+value:
+    .quad 42
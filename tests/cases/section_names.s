@@ -0,0 +1,38 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -M -o %t.exe %t.o | %filecheck %s
+
+# Input sections are placed by name, as in GNU ld's default script: a
+# read-only `.data.*` section still goes to .data.
+# CHECK: .text
+# CHECK-NEXT: .text {{.*}}
+# CHECK-NEXT: .gnu.linkonce.t.helper {{.*}}
+# CHECK-NEXT: .rodata
+# CHECK-NEXT: .rodata.cst16 {{.*}}
+# CHECK-NEXT: .data.rel.ro
+# CHECK-NEXT: .data.rel.ro.local {{.*}}
+# CHECK-NEXT: .data
+# CHECK-NEXT: .data.table {{.*}}
+# CHECK-NEXT: .bss
+# CHECK-NEXT: .bss.buf {{.*}}
+
+.text
+.global _start
+_start:
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
+
+.section .data.table,"a"
+    .quad 1
+
+.section .rodata.cst16,"aM",@progbits,16
+    .quad 2, 3
+
+.section .gnu.linkonce.t.helper,"ax"
+    ret
+
+.section .data.rel.ro.local,"aw"
+    .quad 4
+
+.section .bss.buf,"aw",@nobits
+    .zero 16
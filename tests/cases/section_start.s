@@ -0,0 +1,38 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o -Ttext 1000000 -Tdata=0x2000000
+# RUN: %readelf -lW %t | %filecheck %s
+# RUN: %t; test $? -eq 42
+# RUN: %uld -o %t.start %t.o --section-start=.data=0x700000 -Tbss 0x800000
+# RUN: %readelf -SW %t.start | %filecheck %s --check-prefix=START
+# RUN: %t.start; test $? -eq 42
+# RUN: not %uld -o %t.bad %t.o -Tdata 401000 2>&1 | %filecheck %s --check-prefix=OVERLAP
+# RUN: not %uld -o %t.bad %t.o --section-start .data 2>&1 | %filecheck %s --check-prefix=FORMAT
+
+# Addresses are hex even without 0x. Each pinned section starts a new
+# PT_LOAD; the first still maps the headers just below .text.
+# CHECK: Entry point 0x1000000
+# CHECK: LOAD 0x000000 0x0000000000fff000 {{.*}} RWE
+# CHECK-NEXT: LOAD 0x002000 0x0000000002000000 {{.*}} 0x000004 0x001004 RWE
+
+# START: .data PROGBITS 0000000000700000
+# START: .bss NOBITS 0000000000800000
+
+# OVERLAP: section .text [0x401000, 0x401016) overlaps .data [0x401000, 0x401004)
+# FORMAT: --section-start .data: expected NAME=ADDRESS
+
+.text
+.global _start
+_start:
+    mov value(%rip), %edi
+    add counter(%rip), %edi
+    add $2, %edi
+    mov $60, %eax
+    syscall
+
+.data
+value:
+    .long 40
+
+.bss
+counter:
+    .long 0
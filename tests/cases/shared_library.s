@@ -0,0 +1,74 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -shared -soname libtest.so -o %t.so %t.o
+# RUN: %readelf -h -d -r --dyn-syms %t.so | %filecheck %s
+# RUN: %as %s -o %t.load.o --defsym LOAD=1
+# RUN: %uld -shared -soname libtest.so -o %t.load.so %t.load.o
+# RUN: sed -n 's/^# HOST: //p' %s > %t.host.c
+# RUN: cc %t.host.c -o %t.host -ldl
+# RUN: %t.host %t.load.so | %filecheck %s --check-prefix=LOAD
+
+# CHECK: Type: DYN (Shared object file)
+# CHECK: (SONAME) Library soname: [libtest.so]
+# CHECK: (HASH)
+# CHECK: (SYMTAB)
+# CHECK: (RELA)
+# CHECK-DAG: R_X86_64_GLOB_DAT {{.*}} ext + 0
+# CHECK-DAG: R_X86_64_64 {{.*}} counter + 0
+# CHECK-DAG: R_X86_64_RELATIVE
+# CHECK: OBJECT GLOBAL DEFAULT {{[0-9]+}} counter
+# CHECK: FUNC GLOBAL DEFAULT {{[0-9]+}} get
+# CHECK: NOTYPE GLOBAL DEFAULT UND ext
+
+# Defined globals are exported with their types; `ext` is bound by the loader
+# through the GOT. The pointer to `counter` is bound by the loader too, since
+# another definition may preempt it, and the one to the local `ptr` is
+# rebased with a RELATIVE relocation.
+
+# The loader maps the library, and the host calls into it through dlsym.
+# LOAD: get() = 5
+# LOAD: counter = 5
+
+# HOST: #include <dlfcn.h>
+# HOST: #include <stdio.h>
+# HOST: int main(int argc, char **argv) {
+# HOST:     void *lib = dlopen(argv[1], RTLD_NOW);
+# HOST:     if (!lib) {
+# HOST:         printf("%s\n", dlerror());
+# HOST:         return 1;
+# HOST:     }
+# HOST:     int (*get)(void) = (int (*)(void))dlsym(lib, "get");
+# HOST:     int *counter = dlsym(lib, "counter");
+# HOST:     printf("get() = %d\n", get());
+# HOST:     printf("counter = %d\n", *counter);
+# HOST:     return 0;
+# HOST: }
+
+.text
+.global get
+.type get, @function
+get:
+    mov ptr(%rip), %rax
+    mov (%rax), %eax
+    ret
+.size get, .-get
+
+.ifndef LOAD
+.global call_ext
+.type call_ext, @function
+call_ext:
+    call *ext@GOTPCREL(%rip)
+    ret
+.size call_ext, .-call_ext
+.endif
+
+.data
+.global counter
+.type counter, @object
+counter:
+    .long 5
+    .long 0
+.size counter, .-counter
+ptr:
+    .quad counter
+self:
+    .quad ptr
@@ -0,0 +1,29 @@
+# RUN: %as %s -o %t.old.o
+# RUN: %as %s -o %t.new.o --defsym GROW=1
+# RUN: %uld -o %t.old.exe %t.old.o --size-report=%t.old.json
+# RUN: %uld -o %t.new.exe %t.new.o --size-report=%t.new.json.zst -Map %t.map.zst
+# RUN: zstd -dc %t.map.zst | %filecheck %s --check-prefix=MAP
+# RUN: %uld size-diff %t.old.json %t.new.json.zst | %filecheck %s
+
+# MAP: Memory map
+
+# CHECK: segments:
+# CHECK-NEXT: +4 {{.*}} .text
+# CHECK: symbols:
+# CHECK-NEXT: +4 9 -> 13 _start
+# CHECK: total: +4
+
+.text
+.global _start
+.type _start, @function
+_start:
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
+.ifdef GROW
+    nop
+    nop
+    nop
+    nop
+.endif
+.size _start, .-_start
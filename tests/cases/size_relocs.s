@@ -0,0 +1,38 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym BUF=1 %s -o %t.buf.o
+# RUN: %uld -o %t %t.o %t.buf.o
+# RUN: %readelf -x .data %t | %filecheck %s
+
+# SIZE64 and SIZE32 hold st_size plus the addend, for symbols from other
+# objects (0x30), common symbols (the merged size, 0x40) and locals (0x8).
+# CHECK: 0x00402000 00000000 00000000 30000000 00000000
+# CHECK-NEXT: 0x00402010 34000000 00000000 40000000 00000000
+# CHECK-NEXT: 0x00402020 08000000 00000000
+
+.ifdef BUF
+.data
+.global buf
+.type buf, @object
+.size buf, 0x30
+buf:
+    .zero 0x30
+.comm shared, 0x40, 8
+.else
+.text
+.global _start
+_start:
+    mov $60, %eax
+    syscall
+
+.data
+    .quad 0
+    .quad buf@SIZE
+    .long buf@SIZE + 4
+    .long 0
+    .quad shared@SIZE
+    .quad local@SIZE
+local:
+    .quad 0
+.size local, 8
+.comm shared, 0x10, 8
+.endif
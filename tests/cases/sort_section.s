@@ -0,0 +1,48 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o --print-map | %filecheck %s
+# RUN: %uld -o %t.align %t.o --sort-section=alignment --print-map | %filecheck %s --check-prefix=ALIGN
+# RUN: %uld -o %t.name %t.o --sort-section name --print-map | %filecheck %s --check-prefix=NAME
+# RUN: %t.align; test $? -eq 0
+# RUN: not %uld -o %t.bad %t.o --sort-section=size 2>&1 | %filecheck %s --check-prefix=BAD
+
+# Input order pads after each byte to reach the next 16-byte boundary.
+# CHECK: .rodata {{.*}} 0x40
+# CHECK-NEXT: .rodata.b
+# CHECK-NEXT: .rodata.wide
+# CHECK-NEXT: .rodata.a
+# CHECK-NEXT: .rodata.wider
+
+# Largest alignment first leaves no gaps.
+# ALIGN: .rodata {{.*}} 0x22
+# ALIGN-NEXT: .rodata.wide
+# ALIGN-NEXT: .rodata.wider
+# ALIGN-NEXT: .rodata.b
+# ALIGN-NEXT: .rodata.a
+
+# NAME: .rodata.a
+# NAME-NEXT: .rodata.b
+# NAME-NEXT: .rodata.wide
+# NAME-NEXT: .rodata.wider
+
+# BAD: --sort-section: unknown sort size (expected name or alignment)
+
+.text
+.global _start
+_start:
+    xor %edi, %edi
+    mov $60, %eax
+    syscall
+
+.section .rodata.b,"a"
+    .byte 2
+
+.section .rodata.wide,"a"
+.balign 16
+    .quad 16, 0
+
+.section .rodata.a,"a"
+    .byte 1
+
+.section .rodata.wider,"a"
+.balign 16
+    .quad 32, 64
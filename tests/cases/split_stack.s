@@ -0,0 +1,35 @@
+# RUN: %as --defsym SPLIT=1 %s -o %t.split.o
+# RUN: %as --defsym SPLIT=1 --defsym CALLEE=1 %s -o %t.callee-split.o
+# RUN: %as --defsym CALLEE=1 %s -o %t.callee.o
+# RUN: %uld -o %t %t.split.o %t.callee-split.o
+# RUN: %t
+# RUN: not %uld -o %t %t.split.o %t.callee.o 2>&1 | %filecheck %s
+
+# Split-stack code may call other split-stack code and __morestack, but not
+# functions that don't check their stack.
+# CHECK: split-stack code calls functions built without -fsplit-stack, which uld cannot adjust for:
+# CHECK-NEXT: helper (defined in {{.*}}callee.o, called from {{.*}}split.o)
+# CHECK-NOT: __morestack
+
+.text
+.ifdef CALLEE
+.global helper
+helper:
+    ret
+
+.global __morestack
+__morestack:
+    ret
+.else
+.global _start
+_start:
+    call __morestack
+    call helper
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
+.endif
+
+.ifdef SPLIT
+.section .note.GNU-split-stack,"",@progbits
+.endif
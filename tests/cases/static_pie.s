@@ -0,0 +1,43 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -static-pie -o %t %t.o
+# RUN: %readelf -hlrdW %t | %filecheck %s
+# RUN: %t; test $? -eq 42
+# RUN: %uld -pie --no-dynamic-linker -o %t.nointerp %t.o
+# RUN: %readelf -hlrdW %t.nointerp | %filecheck %s
+# RUN: %uld --check-sections -pie -o %t.interp %t.o
+# RUN: %readelf -lW %t.interp | %filecheck %s --check-prefix=INTERP
+
+# A static PIE is an ET_DYN executable with no program interpreter, whose
+# startup code finds its relocations through _DYNAMIC.
+# CHECK: Type: DYN
+# CHECK-NOT: INTERP
+# CHECK: DYNAMIC
+# CHECK: (DEBUG) 0x0
+# CHECK-NEXT: (FLAGS_1) Flags: PIE
+# CHECK: R_X86_64_RELATIVE 1000
+
+# INTERP: Requesting program interpreter: /lib64/ld-linux-x86-64.so.2
+
+.text
+.global _start
+_start:
+    # Exit 42 if _DYNAMIC points at a .dynamic entry and there are no
+    # IRELATIVE relocations outside .rela.dyn.
+    leaq _DYNAMIC(%rip), %rax
+    movq (%rax), %rdi
+    testq %rdi, %rdi
+    setne %dil
+    leaq __rela_iplt_start(%rip), %rcx
+    leaq __rela_iplt_end(%rip), %rdx
+    cmpq %rcx, %rdx
+    je 1f
+    xorl %edi, %edi
+1:
+    movzbl %dil, %edi
+    imull $42, %edi
+    mov $60, %eax
+    syscall
+
+.data
+ptr:
+    .quad _start
@@ -0,0 +1,37 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o --stats 2>&1 | %filecheck %s
+# RUN: %uld -o %t %t.o --time-trace
+# RUN: %filecheck %s --check-prefix=TRACE < %t.time-trace
+# RUN: %uld -o %t %t.o --time-trace-file=%t.json
+# RUN: %filecheck %s --check-prefix=TRACE < %t.json
+
+# CHECK: Phase {{.*}} Time (ms)
+# CHECK-NEXT: load
+# CHECK-NEXT: parse
+# CHECK-NEXT: symbols
+# CHECK-NEXT: layout
+# CHECK-NEXT: resolve
+# CHECK-NEXT: relocate
+# CHECK-NEXT: write
+# CHECK-NEXT: total
+# CHECK: objects {{ *}} 1
+# CHECK: symbols {{ *}} 2
+# CHECK: relocations {{ *}} 2
+
+# TRACE: "traceEvents":[{{.*}}"name":"Total"{{.*}}"name":"layout"{{.*}}"name":"write"
+
+.text
+.global _start
+_start:
+    call helper
+    lea value(%rip), %rax
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
+
+.global helper
+helper:
+    ret
+
+.data
+value: .quad 0
@@ -0,0 +1,44 @@
+# RUN: %as -g %s -o %t.o
+# RUN: %as -g --defsym SECOND=1 %s -o %t.second.o
+# RUN: %uld -o %t %t.o %t.second.o
+# RUN: %readelf -SW --debug-dump=info,decodedline %t | %filecheck %s
+# RUN: %t
+# RUN: %uld -S -o %t.stripped %t.o %t.second.o
+# RUN: %readelf -SW %t.stripped | %filecheck %s --check-prefix=STRIP
+# RUN: %uld --strip-all -o %t.stripped %t.o %t.second.o
+# RUN: %readelf -SW %t.stripped | %filecheck %s --check-prefix=STRIP
+# RUN: not %uld -s --emit-relocs -o %t.stripped %t.o 2>&1 | %filecheck %s --check-prefix=EMIT
+
+# Debug sections are merged by name, with addresses relocated and the
+# second unit's offsets into .debug_abbrev and .debug_line moved past the
+# first's.
+# CHECK: .debug_line PROGBITS
+# CHECK: .debug_info PROGBITS
+# CHECK: .debug_abbrev PROGBITS
+# CHECK: strip.s {{.*}} 0x401000
+# CHECK: strip.s {{.*}} 0x40100e
+# CHECK: Abbrev Offset: 0
+# CHECK: DW_AT_stmt_list : {{.*}}0
+# CHECK: DW_AT_low_pc : {{.*}}0x401000
+# CHECK: Abbrev Offset: 0x14
+# CHECK: DW_AT_stmt_list : {{.*}}0x{{[0-9a-f]+}}
+# CHECK: DW_AT_low_pc : {{.*}}0x40100e
+
+# STRIP-NOT: .debug_
+
+# EMIT: --strip-all and --emit-relocs may not be used together
+
+.ifdef SECOND
+.text
+.global second
+second:
+    ret
+.else
+.text
+.global _start
+_start:
+    call second
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
+.endif
@@ -0,0 +1,49 @@
+# RUN: %as %s -o %t.o
+# RUN: printf 'third\n# hot path first\n\nhelper\nabs_val\nmissing\nsecond\nthird\n' > %t.order
+# RUN: %uld -o %t %t.o --symbol-ordering-file %t.order 2>&1 | %filecheck %s --check-prefix=WARN
+# RUN: %uld -o %t %t.o --symbol-ordering-file %t.order --print-map | %filecheck %s
+# RUN: %t; test $? -eq 6
+# RUN: %uld -o %t.quiet %t.o --symbol-ordering-file %t.order --no-warn-symbol-ordering 2>&1 | %filecheck %s --check-prefix=QUIET --allow-empty
+
+# WARN: symbol ordering file: symbol 'third' specified multiple times
+# WARN: symbol ordering file: unable to order absolute symbol: abs_val
+# WARN: symbol ordering file: no such symbol: missing
+
+# QUIET-NOT: symbol ordering file
+
+# Listed sections come first in file order, including the static helper;
+# _start keeps its input position after them.
+# CHECK: .text.third                0x401000
+# CHECK-NEXT: .text.helper               0x401004
+# CHECK-NEXT: .text.second               0x401008
+# CHECK-NEXT: .text._start               0x40100e
+
+.section .text._start,"ax"
+.global _start
+_start:
+    call second
+    call third
+    call helper
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
+
+.section .text.second,"ax"
+.global second
+second:
+    mov $1, %eax
+    ret
+
+.section .text.third,"ax"
+.global third
+third:
+    add $2, %eax
+    ret
+
+.section .text.helper,"ax"
+helper:
+    add $3, %eax
+    ret
+
+.global abs_val
+.set abs_val, 7
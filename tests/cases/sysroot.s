@@ -0,0 +1,24 @@
+# RUN: rm -rf %t.root && mkdir -p %t.root/usr/lib
+# RUN: %as %s -o %t.o
+# RUN: %as %libone -o %t.one.o
+# RUN: %uld -shared -soname libone.so.1 -o %t.root/usr/lib/libone.so.1 %t.one.o
+# RUN: echo 'GROUP ( /usr/lib/libone.so.1 )' > %t.root/usr/lib/libone.so
+# RUN: %uld --sysroot=%t.root -o %t -L=/usr/lib %t.o -lone
+# RUN: %readelf -d %t | %filecheck %s
+# RUN: %uld --sysroot %t.root -o %t.var '-L$SYSROOT/usr/lib' %t.o -lone
+# RUN: %readelf -d %t.var | %filecheck %s
+# RUN: not %uld -o %t.none -L=/usr/lib %t.o -lone 2>&1 | %filecheck %s --check-prefix=NOROOT
+
+# Absolute paths inside a stub found in the sysroot are rebased onto it.
+# CHECK: (NEEDED) Shared library: [libone.so.1]
+
+# Without --sysroot, `=` is dropped and the host /usr/lib has no libone.
+# NOROOT: -lone not found
+
+.text
+.global _start
+_start:
+    call *one@GOTPCREL(%rip)
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
@@ -0,0 +1,76 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o --print-map | %filecheck %s
+# RUN: %t; test $? -eq 7
+# RUN: %uld -o %t.keep %t.o -z keep-text-section-prefix --print-map | %filecheck %s --check-prefix=KEEP
+# RUN: %readelf -SW %t.keep | %filecheck %s --check-prefix=SECTIONS
+# RUN: %t.keep; test $? -eq 7
+
+# Inside .text the tagged groups come first, each contiguous, in GNU ld's
+# order: unlikely, exit, startup, hot, then everything else in input order.
+# CHECK: .text
+# CHECK-NEXT: .text.unlikely.cold
+# CHECK-NEXT: .text.exit
+# CHECK-NEXT: .text.startup.setup
+# CHECK-NEXT: .text.hot.loop
+# CHECK-NEXT: .text.hot
+# CHECK-NEXT: .text._start
+# CHECK-NEXT: .text.hotel
+
+# KEEP: .text.unlikely
+# KEEP-NEXT: .text.unlikely.cold
+# KEEP: .text.exit
+# KEEP-NEXT: .text.exit
+# KEEP: .text.startup
+# KEEP-NEXT: .text.startup.setup
+# KEEP: .text.hot
+# KEEP-NEXT: .text.hot.loop
+# KEEP-NEXT: .text.hot
+# KEEP: .text
+# KEEP-NEXT: .text._start
+# KEEP-NEXT: .text.hotel
+
+# SECTIONS: .text.unlikely PROGBITS
+# SECTIONS: .text.exit PROGBITS
+# SECTIONS: .text.startup PROGBITS
+# SECTIONS: .text.hot PROGBITS
+# SECTIONS: .text PROGBITS
+
+.section .text._start,"ax"
+.global _start
+_start:
+    call setup
+    call loop
+    call hot
+    call hotel
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
+
+.section .text.hot.loop,"ax"
+loop:
+    add $2, %eax
+    ret
+
+.section .text.unlikely.cold,"ax"
+cold:
+    ud2
+
+# Only a whole `.hot` component counts: .text.hotel is plain text.
+.section .text.hotel,"ax"
+hotel:
+    add $1, %eax
+    ret
+
+.section .text.startup.setup,"ax"
+setup:
+    mov $1, %eax
+    ret
+
+.section .text.exit,"ax"
+teardown:
+    ret
+
+.section .text.hot,"ax"
+hot:
+    add $3, %eax
+    ret
@@ -0,0 +1,39 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o --print-map | %filecheck %s
+# RUN: %t; test $? -eq 7
+# RUN: %uld -o %t.align %t.o --sort-section=alignment --print-map | %filecheck %s
+
+# GNU ld's default script places `*(SORT(.text.sorted.*))` after the hot/cold
+# groups and ahead of the rest of .text. The pattern's own sort wins over
+# --sort-section, so b's larger alignment doesn't put it first.
+# CHECK: .text
+# CHECK-NEXT: .text.hot
+# CHECK-NEXT: .text.sorted.a
+# CHECK-NEXT: .text.sorted.b
+# CHECK-NEXT: .text.sorted.c
+# CHECK-NEXT: .text._start
+
+.section .text._start, "ax"
+.global _start
+_start:
+    call a
+    mov $60, %eax
+    syscall
+
+.section .text.sorted.c, "ax"
+c:
+    ret
+
+.section .text.sorted.b, "ax"
+.p2align 6
+b:
+    mov $7, %edi
+    jmp c
+
+.section .text.hot, "ax"
+hot:
+    ret
+
+.section .text.sorted.a, "ax"
+a:
+    jmp b
@@ -0,0 +1,15 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o --threads=1
+# RUN: %t; test $? -eq 42
+# RUN: %uld -o %t.4 %t.o --threads 4
+# RUN: cmp %t %t.4
+# RUN: not %uld -o %t.bad %t.o --threads=0 2>&1 | %filecheck %s
+
+# CHECK: --threads=0: expected a positive number
+
+.text
+.global _start
+_start:
+    mov $42, %edi
+    mov $60, %eax
+    syscall
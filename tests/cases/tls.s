@@ -0,0 +1,72 @@
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o
+# RUN: %readelf -lSW %t | %filecheck %s
+# RUN: llvm-objdump -d --no-show-raw-insn %t | %filecheck %s --check-prefix=RELAXED
+# RUN: %t; test $? -eq 42
+# RUN: %uld -m64 %t.o -Wl,--as-needed -Wl,-Bstatic -Wl,-Bdynamic -fuse-ld=lld -Wl,--eh-frame-hdr -Wl,-z,noexecstack -o %t.rustc -Wl,--gc-sections -static -Wl,-z,relro,-z,now -nodefaultlibs -nostartfiles
+# RUN: %t.rustc; test $? -eq 42
+# RUN: not %uld -shared -o %t.so %t.o 2>&1 | %filecheck %s --check-prefix=SHARED
+
+# The second link uses the compiler-style command line rustc passes.
+
+# .tdata and .tbss make up the TLS block PT_TLS describes.
+# CHECK: .tdata PROGBITS {{.*}} WAT
+# CHECK: .tbss NOBITS {{.*}} WAT
+# CHECK: TLS
+
+# The general- and local-dynamic accesses become local-exec, and their
+# calls to __tls_get_addr (which nothing defines) are gone.
+# RELAXED: movq %fs:0, %rax
+# RELAXED-NEXT: leaq -{{[0-9]+}}(%rax), %rax
+# RELAXED: movq %fs:0, %rax
+# RELAXED-NOT: __tls_get_addr
+
+# SHARED: R_X86_64_TPOFF32 needs a TLS model for shared objects
+
+.section .tdata,"awT",@progbits
+.align 4
+a:
+    .long 0
+
+.section .tbss,"awT",@nobits
+.align 4
+b:
+    .zero 4
+
+.text
+.global _start
+_start:
+    # Point %fs at tcb, whose first word holds its own address, with the
+    # TLS block right below it.
+    lea tcb(%rip), %rsi
+    mov %rsi, (%rsi)
+    mov $0x1002, %edi # ARCH_SET_FS
+    mov $158, %eax # arch_prctl
+    syscall
+
+    # Local-exec
+    movl $30, %fs:a@tpoff
+    movl $12, %fs:b@tpoff
+
+    # General-dynamic, calling through the PLT
+    .byte 0x66
+    leaq a@tlsgd(%rip), %rdi
+    .value 0x6666
+    rex64
+    call __tls_get_addr@PLT
+    mov (%rax), %ebx
+
+    # Local-dynamic, calling through the GOT (-fno-plt)
+    leaq b@tlsld(%rip), %rdi
+    call *__tls_get_addr@GOTPCREL(%rip)
+    add b@dtpoff(%rax), %ebx
+
+    mov %ebx, %edi
+    mov $60, %eax
+    syscall
+
+.bss
+.align 64
+    .zero 16384
+tcb:
+    .quad 0
@@ -0,0 +1,20 @@
+# RUN: %as %s -o %t.o
+# RUN: %as %getvalue -o %t_get_value.o
+# RUN: rm -f %t.a && ar rcs %t.a %t_get_value.o
+# RUN: %uld -t -o %t.exe %t.o %t.a | %filecheck %s
+# RUN: %uld -y get_value --trace-symbol=_start -o %t.exe %t.o %t.a | %filecheck %s --check-prefix=SYM
+
+# Inputs are listed as they are read, extracted archive members included.
+# CHECK: {{.*}}trace.s.tmp.o
+# CHECK-NEXT: {{.*}}trace.s.tmp.a({{.*}}get_value.o)
+
+# SYM: {{.*}}trace.s.tmp.o: definition of _start
+# SYM-NEXT: {{.*}}trace.s.tmp.o: reference to get_value
+# SYM-NEXT: {{.*}}.a({{.*}}get_value.o): definition of get_value
+
+.global _start
+_start:
+    call get_value
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
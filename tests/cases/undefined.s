@@ -0,0 +1,26 @@
+# RUN: %as %s -o %t.o
+# RUN: %as %getvalue -o %t_get_value.o
+# RUN: rm -f %t.a && ar rcs %t.a %t_get_value.o
+# RUN: %uld -o %t.exe %t.o %t.a --why-extract=%t.none
+# RUN: %filecheck %s --check-prefix=NONE < %t.none
+# RUN: %uld -u get_value -o %t.exe %t.o %t.a --why-extract=%t.why
+# RUN: %filecheck %s < %t.why
+# RUN: %uld --undefined=get_value --undefined=not_anywhere -o %t.exe %t.o %t.a
+# RUN: %uld --require-defined=get_value -o %t.exe %t.o %t.a --why-extract=%t.req
+# RUN: %filecheck %s < %t.req
+# RUN: not %uld --require-defined=get_value -o %t.exe %t.o 2>&1 | %filecheck %s --check-prefix=MISSING
+
+# Nothing references get_value, so the member is only pulled in by -u.
+# NONE: reference extracted symbol
+# NONE-NOT: get_value
+
+# CHECK: reference extracted symbol
+# CHECK-NEXT: <command line> {{.*}}.a({{.*}}get_value.o) get_value
+
+# MISSING: required symbols not defined (--require-defined): get_value
+
+.global _start
+_start:
+    mov $0, %edi
+    mov $60, %eax
+    syscall
@@ -0,0 +1,38 @@
+# RUN: %as %s -o %t.o
+# RUN: not %uld -o %t.exe %t.o 2>&1 | %filecheck %s
+# RUN: %uld --unresolved-symbols=ignore-all -o %t.exe %t.o
+# RUN: %as --defsym DIRECT=1 %s -o %t.direct.o
+# RUN: %uld --unresolved-symbols=ignore-all -o %t.direct %t.direct.o
+# RUN: objdump -d --no-show-raw-insn %t.direct | %filecheck %s --check-prefix=IGNORED
+# RUN: %uld --unresolved-symbols=ignore-in-object-files -o %t.direct %t.direct.o
+# RUN: %uld --unresolved-symbols=ignore-in-object-files -o %t.exe %t.o
+# RUN: not %uld --unresolved-symbols=ignore-in-shared-libs -o %t.exe %t.o 2>&1 | %filecheck %s
+# RUN: %uld -shared -o %t.so %t.o
+# RUN: not %uld -shared --no-undefined -o %t.so %t.o 2>&1 | %filecheck %s --check-prefix=DEFS
+# RUN: not %uld -shared --unresolved-symbols=report-all -o %t.so %t.o 2>&1 | %filecheck %s
+# RUN: not %uld --unresolved-symbols=some -o %t.exe %t.o 2>&1 | %filecheck %s --check-prefix=BAD
+
+# Executables reject references nothing defines; shared objects only with
+# -z defs or --no-undefined.
+# CHECK: undefined symbols:
+# CHECK-NEXT: missing (referenced by {{.*}}.tmp.o)
+
+# DEFS: undefined symbols (-z defs):
+# DEFS-NEXT: missing (referenced by {{.*}}.tmp.o)
+
+# Ignored references, direct or through the GOT, resolve to 0.
+# IGNORED: call 0x0
+# IGNORED-NEXT: movabs $0x0,%rax
+
+# BAD: --unresolved-symbols: unknown method some
+
+.text
+.global _start
+_start:
+    call *missing@GOTPCREL(%rip)
+.ifdef DIRECT
+    call missing
+    movabs $missing, %rax
+.endif
+    mov $60, %eax
+    syscall
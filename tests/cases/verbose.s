@@ -0,0 +1,49 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym OTHER=1 %s -o %t.other.o
+# RUN: %as %getvalue -o %t_get_value.o
+# RUN: rm -f %t.a && ar rcs %t.a %t_get_value.o
+# RUN: %uld --verbose -o %t.exe %t.o %t.other.o %t.a | %filecheck %s
+
+# Each global symbol is listed with its final definition and the decisions
+# that led to it.
+# CHECK: Symbol resolution:
+# CHECK: buf: defined in {{.*}}other.o
+# CHECK-NEXT: common in {{.*}}verbose.s.tmp.o
+# CHECK-NEXT: common in {{.*}}other.o merged with common in {{.*}}verbose.s.tmp.o (size 32, alignment 16)
+# CHECK-NEXT: f: defined in {{.*}}other.o
+# CHECK-NEXT: weak definition in {{.*}}verbose.s.tmp.o
+# CHECK-NEXT: definition in {{.*}}other.o overrides weak definition in {{.*}}verbose.s.tmp.o
+# CHECK-NEXT: g: defined in {{.*}}verbose.s.tmp.o
+# CHECK-NEXT: definition in {{.*}}verbose.s.tmp.o
+# CHECK-NEXT: weak definition in {{.*}}other.o ignored: strong definition in {{.*}}verbose.s.tmp.o wins
+# CHECK-NEXT: get_value: defined in {{.*}}.a({{.*}}get_value.o)
+# CHECK-NEXT: referenced by {{.*}}verbose.s.tmp.o, undefined so far
+# CHECK-NEXT: {{.*}}.a({{.*}}get_value.o) extracted to resolve the reference from {{.*}}verbose.s.tmp.o
+# CHECK-NEXT: definition in {{.*}}.a({{.*}}get_value.o)
+
+.text
+.ifdef OTHER
+.global f
+f:
+    ret
+.weak g
+g:
+    ret
+.comm buf,32,16
+.else
+.global _start
+_start:
+    call get_value
+    call f
+    call g
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
+.weak f
+f:
+    ret
+.global g
+g:
+    ret
+.comm buf,8,8
+.endif
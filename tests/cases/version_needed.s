@@ -0,0 +1,55 @@
+# RUN: rm -rf %t.dir && mkdir -p %t.dir
+# RUN: %as --defsym LIB=1 %s -o %t.lib.o
+# RUN: printf 'V1 { global: one; "on?"; local: *; };\nV2 { global: two; extern "C++" { ns::three*; "ns::four()"; }; } V1;\n' > %t.map
+# RUN: %uld -shared -soname libvers.so --version-script %t.map -o %t.dir/libvers.so %t.lib.o
+# RUN: %readelf --dyn-syms -W %t.dir/libvers.so | %filecheck %s --check-prefix=LIB
+# RUN: %as %s -o %t.o
+# RUN: %uld -o %t %t.o %t.dir/libvers.so
+# RUN: %readelf -d -V --dyn-syms -W %t | %filecheck %s
+# RUN: env LD_LIBRARY_PATH=%t.dir %t; test $? -eq 3
+
+# A quoted pattern doesn't glob, and C++ patterns match demangled names.
+# LIB-NOT: onx
+# LIB-DAG: one@@V1
+# LIB-DAG: two@@V2
+# LIB-DAG: _ZN2ns5threeEv@@V2
+# LIB-DAG: _ZN2ns4fourEv@@V2
+# LIB-NOT: onx
+
+# The plain reference binds to the default version, and `two@V2` names its own.
+# CHECK: (VERNEED)
+# CHECK: (VERNEEDNUM) 1
+# CHECK-DAG: one@V1 (2)
+# CHECK-DAG: two@V2 (3)
+# CHECK: Version needs section '.gnu.version_r' contains 1 entry:
+# CHECK: File: libvers.so  Cnt: 2
+# CHECK: Name: V1  Flags: none  Version: 2
+# CHECK: Name: V2  Flags: none  Version: 3
+
+.ifdef LIB
+.text
+.global one, onx, two, _ZN2ns5threeEv, _ZN2ns4fourEv
+one:
+    mov $1, %eax
+    ret
+onx:
+    ret
+two:
+    mov $2, %eax
+    ret
+_ZN2ns5threeEv:
+    ret
+_ZN2ns4fourEv:
+    ret
+.else
+.symver two_ref, two@V2
+.text
+.global _start
+_start:
+    call *one@GOTPCREL(%rip)
+    mov %eax, %ebx
+    call *two_ref@GOTPCREL(%rip)
+    lea (%rax,%rbx), %edi
+    mov $60, %eax
+    syscall
+.endif
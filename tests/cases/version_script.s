@@ -0,0 +1,32 @@
+# RUN: %as %s -o %t.o
+# RUN: echo 'VERS_1 { global: foo; bar*; local: *; }; VERS_2 { global: baz; } VERS_1;' > %t.map
+# RUN: %uld -shared -soname libv.so --version-script %t.map -o %t.so %t.o
+# RUN: %readelf -d -V --dyn-syms %t.so | %filecheck %s
+# RUN: echo 'VERS_1 { foo; } MISSING;' > %t.bad
+# RUN: not %uld -shared --version-script %t.bad -o %t.bad.so %t.o 2>&1 | %filecheck %s --check-prefix=BAD
+
+# CHECK: (VERSYM)
+# CHECK: (VERDEF)
+# CHECK: (VERDEFNUM) 3
+# CHECK: Symbol table '.dynsym' contains 4 entries
+# CHECK: bar1@@VERS_1
+# CHECK: baz@@VERS_2
+# CHECK: foo@@VERS_1
+# CHECK-NOT: hidden
+# CHECK: Flags: BASE Index: 1 Cnt: 1 Name: libv.so
+# CHECK: Index: 2 Cnt: 1 Name: VERS_1
+# CHECK: Index: 3 Cnt: 2 Name: VERS_2
+# CHECK: Parent 1: VERS_1
+
+# BAD: version VERS_1 depends on unknown version MISSING
+
+.text
+.global foo, bar1, baz, hidden
+foo:
+    ret
+bar1:
+    ret
+baz:
+    ret
+hidden:
+    ret
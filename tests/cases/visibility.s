@@ -0,0 +1,61 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym HIDE=1 %s -o %t.hide.o
+# RUN: %as %libone -o %t.one.o
+# RUN: %uld -shared -soname libone.so -o %t.one.so %t.one.o
+# RUN: %uld -shared -o %t.so %t.o
+# RUN: %readelf --dyn-syms %t.so | %filecheck %s
+# RUN: %readelf --dyn-syms %t.so | %filecheck %s --check-prefix=PROTECTED
+# RUN: %uld -shared -o %t.merged.so %t.o %t.hide.o %t.one.o
+# RUN: %readelf --dyn-syms %t.merged.so | %filecheck %s --check-prefix=MERGED
+# RUN: not %uld -shared -o %t.bad.so %t.o %t.hide.o 2>&1 | %filecheck %s --check-prefix=UNDEF
+# RUN: not %uld -shared -o %t.bad.so %t.o %t.hide.o %t.one.so 2>&1 | %filecheck %s --check-prefix=IMPORT
+
+# Hidden and internal definitions stay out of .dynsym.
+# CHECK: Symbol table '.dynsym' contains 4 entries
+# CHECK-NOT: hidden_fn
+# CHECK-NOT: internal_fn
+
+# Protected symbols are exported, but bind to the output's own definition.
+# PROTECTED: GLOBAL PROTECTED {{[0-9]+}} guarded
+
+# A hidden reference from another object hides the definition too.
+# MERGED: Symbol table '.dynsym' contains 4 entries
+# MERGED-NOT: exported
+# MERGED-NOT: one
+
+# Hidden references can't be bound at runtime.
+# UNDEF: hidden symbol one is not defined
+# IMPORT: hidden symbol one is not defined (only in shared library libone.so)
+
+.text
+.ifdef HIDE
+.hidden exported
+.hidden one
+.global use_hidden
+use_hidden:
+    call exported
+    call *one@GOTPCREL(%rip)
+    ret
+.else
+.global exported
+exported:
+    call *one@GOTPCREL(%rip)
+    ret
+
+.global guarded
+.protected guarded
+guarded:
+    call hidden_fn
+    call internal_fn
+    ret
+
+.global hidden_fn
+.hidden hidden_fn
+hidden_fn:
+    ret
+
+.global internal_fn
+.internal internal_fn
+internal_fn:
+    ret
+.endif
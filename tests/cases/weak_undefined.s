@@ -0,0 +1,40 @@
+# RUN: rm -rf %t.dir && mkdir -p %t.dir
+# RUN: %as %s -o %t.o
+# RUN: %as %libone -o %t.one.o
+# RUN: %uld -shared -soname libone.so -o %t.dir/libone.so %t.one.o
+# RUN: %uld -o %t.static %t.o
+# RUN: %t.static; test $? -eq 7
+# RUN: %uld -o %t.dynamic %t.o %t.dir/libone.so
+# RUN: %readelf -r --dyn-syms %t.dynamic | %filecheck %s --check-prefix=DYNAMIC
+# RUN: env LD_LIBRARY_PATH=%t.dir %t.dynamic; test $? -eq 1
+# RUN: %uld -shared -o %t.so %t.o
+# RUN: %readelf -r --dyn-syms %t.so | %filecheck %s --check-prefix=SHARED
+
+# Without a definition, weak references are 0 in a static link...
+# ...but are bound by the loader when a shared library may define them.
+# DYNAMIC: R_X86_64_GLOB_DAT {{.*}} one + 0
+# DYNAMIC: WEAK DEFAULT UND one
+
+# SHARED: R_X86_64_64 {{.*}} missing + 0
+# SHARED: WEAK DEFAULT UND missing
+
+.text
+.global _start
+_start:
+    mov one@GOTPCREL(%rip), %rax
+    test %rax, %rax
+    jz 1f
+    call *%rax
+    mov %eax, %edi
+    jmp 2f
+1:
+    mov $7, %edi
+2:
+    mov $60, %eax
+    syscall
+
+.weak one
+.weak missing
+
+.data
+.quad missing
@@ -0,0 +1,18 @@
+# RUN: %as %s -o %t.o
+# RUN: %as %getvalue -o %t_get_value.o
+# RUN: rm -f %t.a && ar rcs %t.a %t_get_value.o
+# RUN: %uld -o %t.exe %t.o %t.a --why-extract=%t.why
+# RUN: %filecheck %s < %t.why
+# RUN: %t.exe || echo "Exit: $?" | %filecheck %s --check-prefix=EXIT
+
+# CHECK: reference extracted symbol
+# CHECK-NEXT: {{.*}}why_extract.s.tmp.o {{.*}}.a({{.*}}get_value.o) get_value
+
+# EXIT: Exit: 42
+
+.global _start
+_start:
+    call get_value
+    mov %eax, %edi
+    mov $60, %eax
+    syscall
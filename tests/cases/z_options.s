@@ -0,0 +1,60 @@
+# RUN: %as %s -o %t.o
+# RUN: %as --defsym UNDEF=1 %s -o %t.undef.o
+# RUN: %uld -shared -z relro -z lazy -z now -o %t.so %t.o
+# RUN: %readelf -lW -d %t.so | %filecheck %s
+# RUN: %readelf -S %t.so | %filecheck %s --check-prefix=ORDER
+# RUN: %uld -shared -z execstack -z norelro -o %t.exec.so %t.o
+# RUN: %readelf -lW %t.exec.so | %filecheck %s --check-prefix=EXECSTACK
+# RUN: %uld -shared -z bogus -o %t.so %t.o 2>&1 | %filecheck %s --check-prefix=UNKNOWN
+# RUN: %uld -shared -o %t.undef.so %t.undef.o
+# RUN: not %uld -shared -z defs -o %t.undef.so %t.undef.o 2>&1 | %filecheck %s --check-prefix=DEFS
+# RUN: not %uld -shared -o %t.dup.so %t.o %t.o 2>&1 | %filecheck %s --check-prefix=MULDEFS
+# RUN: %uld -shared -z muldefs -o %t.dup.so %t.o %t.o
+# RUN: %uld -shared -z stack-size=0x800000 -o %t.stack.so %t.o
+# RUN: %readelf -lW %t.stack.so | %filecheck %s --check-prefix=STACKSIZE
+# RUN: not %uld -shared -z stack-size=big -o %t.stack.so %t.o 2>&1 | %filecheck %s --check-prefix=BADSIZE
+
+# The stack is non-executable unless asked, and the relocated data is covered
+# by PT_GNU_RELRO. The last of -z lazy / -z now wins.
+# CHECK: GNU_STACK
+# CHECK-SAME: RW 0x10
+# CHECK: GNU_RELRO
+# CHECK: (FLAGS) BIND_NOW
+# CHECK: (FLAGS_1) Flags: NOW
+
+# .data moves after the RELRO sections so they stay contiguous.
+# ORDER: .got
+# ORDER: .dynamic
+# ORDER: .data
+
+# EXECSTACK: GNU_STACK
+# EXECSTACK-SAME: RWE 0x10
+# EXECSTACK-NOT: GNU_RELRO
+
+# -z stack-size is recorded as PT_GNU_STACK's memory size.
+# STACKSIZE: GNU_STACK 0x000000 0x0000000000000000 0x0000000000000000 0x000000 0x800000 RW 0x10
+# BADSIZE: -z stack-size=big
+
+# UNKNOWN: -z bogus: unknown keyword, ignored
+
+# DEFS: undefined symbols (-z defs):
+# DEFS-NEXT: missing (referenced by {{.*}}undef.o)
+
+# MULDEFS: multiple definition of `f`: first defined in {{.*}}.o, again in {{.*}}.o
+
+.ifdef UNDEF
+.data
+.global ref
+ref:
+    .quad missing
+.else
+.text
+.global f
+f:
+    ret
+
+.data
+.global table
+table:
+    .quad f
+.endif
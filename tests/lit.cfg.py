@@ -1,10 +1,11 @@
 import lit.formats
 import os
+import shutil
 
 config.name = 'uld'
 config.test_format = lit.formats.ShTest(True)
 
-config.suffixes = ['.c', '.s']
+config.suffixes = ['.c', '.rs', '.s']
 
 # Source directory - only look in cases/
 config.test_source_root = os.path.join(os.path.dirname(__file__), 'cases')
@@ -26,11 +27,37 @@ if not os.path.exists(uld_path):
 support_dir = os.path.join(os.path.dirname(__file__), 'support')
 
 config.substitutions.append(('%uld', uld_path))
+
+# The C API library of the uld-capi crate and its header; its tests need
+# `cargo build --workspace`.
+lib_dir = os.path.dirname(uld_path)
+if os.path.exists(os.path.join(lib_dir, 'libuld_capi.so')):
+    config.available_features.add('capi')
+config.substitutions.append(('%libdir', lib_dir))
+config.substitutions.append(('%include', os.path.join(project_root, 'include')))
+
+# Programs in examples/ that link through the Rust library; their tests need
+# `cargo build --examples`.
+examples_dir = os.path.join(lib_dir, 'examples')
+if os.path.isdir(examples_dir):
+    config.available_features.add('examples')
+config.substitutions.append(('%examples', examples_dir))
+
+# LLVM bitcode tests need the LLVM tools, which uld also runs for LTO.
+if all(shutil.which(t) for t in ('llvm-as', 'llvm-ar', 'llvm-objdump', 'llvm-lto', 'llvm-nm')):
+    config.available_features.add('llvm-lto')
+# Rust programs linked with `-C linker=uld`, as Cargo does given
+# `linker = "uld"`.
+if shutil.which('rustc'):
+    config.available_features.add('rustc')
 config.substitutions.append(('%cc', 'musl-gcc'))
 config.substitutions.append(('%as', 'as'))
 config.substitutions.append(('%start', os.path.join(support_dir, 'start.s')))
 config.substitutions.append(('%helper', os.path.join(support_dir, 'c_helper.c')))
+config.substitutions.append(('%getvalue', os.path.join(support_dir, 'get_value.s')))
+config.substitutions.append(('%libone', os.path.join(support_dir, 'libone.s')))
 config.substitutions.append(('%filecheck', 'filecheck'))
+config.substitutions.append(('%readelf', 'readelf'))
 
 # musl libc CRT files for static linking
 # Try common paths: Fedora uses lib64, Ubuntu uses lib
@@ -0,0 +1,7 @@
+# Archive member for why_extract.s
+# This is not a test itself - just provides get_value()
+
+.global get_value
+get_value:
+    mov $42, %eax
+    ret
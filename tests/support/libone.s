@@ -0,0 +1,11 @@
+# Tiny shared library used by as_needed.s.
+.text
+.global one
+one:
+    mov $1, %eax
+    ret
+
+.global two
+two:
+    mov $2, %eax
+    ret